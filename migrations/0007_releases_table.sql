@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS releases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    distiller TEXT NOT NULL,
+    expected_date TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS release_regions (
+    release_id INTEGER NOT NULL,
+    region TEXT NOT NULL,
+    PRIMARY KEY (release_id, region)
+);
+CREATE TABLE IF NOT EXISTS release_watchers (
+    release_id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    notified INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (release_id, user_id)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS spirit_anomalies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    spirit_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    resolved INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS spirit_types (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+INSERT INTO spirit_types(name)
+VALUES ('Bourbon'),
+    ('Scotch'),
+    ('Rye'),
+    ('Irish Whiskey'),
+    ('American Whiskey'),
+    ('American Single Malt'),
+    ('Japanese Whisky'),
+    ('Canadian Whisky'),
+    ('Tennessee Whiskey'),
+    ('World Whiskey'),
+    ('Flavored Whiskey'),
+    ('Rum'),
+    ('Gin'),
+    ('Vodka'),
+    ('Tequila'),
+    ('Mezcal'),
+    ('Brandy'),
+    ('Cognac'),
+    ('Liqueur');
+ALTER TABLE spirits
+ADD COLUMN type_id INTEGER REFERENCES spirit_types(id);
+UPDATE spirits
+SET type_id = (
+        SELECT t.id
+        FROM spirit_types t
+        WHERE t.name = spirits.type
+            OR t.name LIKE spirits.type || ' Whisk%'
+        ORDER BY t.id
+        LIMIT 1
+    );
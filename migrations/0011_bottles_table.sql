@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS bottles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    label TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'held',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS bottle_provenance (
+    bottle_id INTEGER PRIMARY KEY NOT NULL REFERENCES bottles(id),
+    acquired_from TEXT NOT NULL,
+    acquired_on TEXT,
+    price REAL,
+    condition_notes TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bottle_transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bottle_id INTEGER NOT NULL REFERENCES bottles(id),
+    kind TEXT NOT NULL,
+    counterpart TEXT NOT NULL,
+    counterpart_user_id TEXT,
+    transferred_on TEXT NOT NULL,
+    price REAL,
+    notes TEXT NOT NULL
+);
@@ -0,0 +1,39 @@
+CREATE TABLE IF NOT EXISTS swap_offers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    volume_ml INTEGER NOT NULL CHECK (volume_ml BETWEEN 30 AND 50),
+    notes TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS swap_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    volume_ml INTEGER NOT NULL CHECK (volume_ml BETWEEN 30 AND 50),
+    notes TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS swap_matches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    offer_id INTEGER NOT NULL REFERENCES swap_offers(id),
+    request_id INTEGER NOT NULL REFERENCES swap_requests(id),
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS swap_confirmations (
+    match_id INTEGER NOT NULL REFERENCES swap_matches(id),
+    user_id TEXT NOT NULL,
+    rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (match_id, user_id)
+);
+CREATE TABLE IF NOT EXISTS swap_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    match_id INTEGER NOT NULL REFERENCES swap_matches(id),
+    user_id TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
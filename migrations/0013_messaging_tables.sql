@@ -0,0 +1,59 @@
+CREATE TABLE IF NOT EXISTS conversations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    swap_match_id INTEGER UNIQUE REFERENCES swap_matches(id),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS conversation_participants (
+    conversation_id INTEGER NOT NULL REFERENCES conversations(id),
+    user_id TEXT NOT NULL,
+    last_read_message_id INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (conversation_id, user_id)
+);
+CREATE TABLE IF NOT EXISTS messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    conversation_id INTEGER NOT NULL REFERENCES conversations(id),
+    sender_id TEXT NOT NULL,
+    body TEXT NOT NULL,
+    hidden INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS user_blocks (
+    blocker_id TEXT NOT NULL,
+    blocked_id TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (blocker_id, blocked_id)
+);
+CREATE TABLE IF NOT EXISTS message_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    reporter_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    resolved INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+-- Swap match threads become regular conversations.
+INSERT INTO conversations(swap_match_id, created_at)
+SELECT id,
+    created_at
+FROM swap_matches;
+INSERT INTO conversation_participants(conversation_id, user_id)
+SELECT c.id,
+    o.user_id
+FROM conversations c
+    JOIN swap_matches m ON m.id = c.swap_match_id
+    JOIN swap_offers o ON o.id = m.offer_id
+UNION
+SELECT c.id,
+    r.user_id
+FROM conversations c
+    JOIN swap_matches m ON m.id = c.swap_match_id
+    JOIN swap_requests r ON r.id = m.request_id;
+INSERT INTO messages(conversation_id, sender_id, body, created_at)
+SELECT c.id,
+    sm.user_id,
+    sm.body,
+    sm.created_at
+FROM swap_messages sm
+    JOIN conversations c ON c.swap_match_id = sm.match_id
+ORDER BY sm.id;
+DROP TABLE swap_messages;
@@ -0,0 +1,98 @@
+CREATE TABLE IF NOT EXISTS countries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS regions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    country_id INTEGER NOT NULL REFERENCES countries(id),
+    name TEXT NOT NULL,
+    UNIQUE (country_id, name)
+);
+CREATE TABLE IF NOT EXISTS distillers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    region_id INTEGER REFERENCES regions(id)
+);
+INSERT INTO countries(code, name)
+VALUES ('GB-SCT', 'Scotland'),
+    ('IE', 'Ireland'),
+    ('US', 'United States'),
+    ('CA', 'Canada'),
+    ('JP', 'Japan'),
+    ('MX', 'Mexico'),
+    ('FR', 'France'),
+    ('TW', 'Taiwan'),
+    ('IN', 'India'),
+    ('AU', 'Australia');
+INSERT INTO regions(country_id, name)
+SELECT c.id,
+    r.name
+FROM (
+        SELECT 'Scotland' AS country,
+            'Islay' AS name
+        UNION ALL
+        SELECT 'Scotland',
+            'Speyside'
+        UNION ALL
+        SELECT 'Scotland',
+            'Highlands'
+        UNION ALL
+        SELECT 'Scotland',
+            'Lowlands'
+        UNION ALL
+        SELECT 'Scotland',
+            'Campbeltown'
+        UNION ALL
+        SELECT 'Scotland',
+            'Islands'
+        UNION ALL
+        SELECT 'Ireland',
+            'Cork'
+        UNION ALL
+        SELECT 'Ireland',
+            'Antrim'
+        UNION ALL
+        SELECT 'United States',
+            'Kentucky'
+        UNION ALL
+        SELECT 'United States',
+            'Tennessee'
+        UNION ALL
+        SELECT 'United States',
+            'Indiana'
+        UNION ALL
+        SELECT 'United States',
+            'Texas'
+        UNION ALL
+        SELECT 'United States',
+            'New York'
+        UNION ALL
+        SELECT 'Canada',
+            'Ontario'
+        UNION ALL
+        SELECT 'Japan',
+            'Hokkaido'
+        UNION ALL
+        SELECT 'Japan',
+            'Osaka'
+        UNION ALL
+        SELECT 'Mexico',
+            'Jalisco'
+        UNION ALL
+        SELECT 'Mexico',
+            'Oaxaca'
+        UNION ALL
+        SELECT 'France',
+            'Cognac'
+        UNION ALL
+        SELECT 'France',
+            'Armagnac'
+    ) r
+    JOIN countries c ON c.name = r.country;
+INSERT INTO distillers(name)
+SELECT DISTINCT distiller
+FROM spirits
+WHERE distiller != '';
+ALTER TABLE spirits
+ADD COLUMN region_id INTEGER REFERENCES regions(id);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS spirit_submissions (
+    spirit_id TEXT PRIMARY KEY NOT NULL REFERENCES spirits(uuid),
+    user_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reviewed_by TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS spirits_normalized_name ON spirits(lower(trim(name)), lower(trim(distiller)));
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS ratings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    score INTEGER NOT NULL CHECK (score BETWEEN 0 AND 100),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, spirit_id)
+);
+CREATE INDEX IF NOT EXISTS ratings_spirit_id ON ratings(spirit_id);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS image_uploads (
+    id TEXT PRIMARY KEY NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    user_id TEXT NOT NULL,
+    total_bytes INTEGER NOT NULL,
+    received_bytes INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
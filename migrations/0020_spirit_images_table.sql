@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS spirit_images (
+    spirit_id TEXT PRIMARY KEY NOT NULL REFERENCES spirits(uuid),
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS flavor_votes (
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    dimension TEXT NOT NULL,
+    score INTEGER NOT NULL CHECK (score BETWEEN 0 AND 10),
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, spirit_id, dimension)
+);
+CREATE INDEX IF NOT EXISTS flavor_votes_spirit_id ON flavor_votes(spirit_id);
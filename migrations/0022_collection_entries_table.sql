@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS collection_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    status TEXT NOT NULL,
+    purchase_date TEXT,
+    price_paid REAL,
+    opened INTEGER NOT NULL DEFAULT 0,
+    fill_level INTEGER CHECK (fill_level BETWEEN 0 AND 100),
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS collection_entries_user_id ON collection_entries(user_id);
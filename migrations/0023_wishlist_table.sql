@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS wishlist_entries (
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    notify INTEGER NOT NULL DEFAULT 1,
+    note TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, spirit_id)
+);
+CREATE INDEX IF NOT EXISTS wishlist_entries_spirit_id ON wishlist_entries(spirit_id);
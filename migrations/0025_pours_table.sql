@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS pours (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    poured_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    amount_ml REAL NOT NULL CHECK (amount_ml > 0),
+    occasion TEXT NOT NULL DEFAULT '',
+    note TEXT NOT NULL DEFAULT ''
+);
+CREATE INDEX IF NOT EXISTS pours_user_id_poured_at ON pours(user_id, poured_at);
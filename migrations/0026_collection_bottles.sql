@@ -0,0 +1,11 @@
+ALTER TABLE bottles
+ADD COLUMN collection_entry_id INTEGER REFERENCES collection_entries(id) ON DELETE SET NULL;
+ALTER TABLE bottles
+ADD COLUMN volume_ml REAL;
+ALTER TABLE bottles
+ADD COLUMN remaining_ml REAL;
+ALTER TABLE bottles
+ADD COLUMN opened_on TEXT;
+CREATE INDEX IF NOT EXISTS bottles_collection_entry_id ON bottles(collection_entry_id);
+ALTER TABLE pours
+ADD COLUMN bottle_id INTEGER REFERENCES bottles(id) ON DELETE SET NULL;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS barcodes (
+    code TEXT PRIMARY KEY NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid) ON DELETE CASCADE,
+    added_by TEXT NOT NULL REFERENCES users(user_id),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS barcodes_spirit_id ON barcodes(spirit_id);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS spirit_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- No foreign key so the history outlives the spirit.
+    spirit_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    snapshot TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS spirit_revisions_spirit_id ON spirit_revisions(spirit_id, id);
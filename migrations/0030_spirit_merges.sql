@@ -0,0 +1,2 @@
+ALTER TABLE spirits ADD COLUMN deleted_at TEXT;
+ALTER TABLE spirits ADD COLUMN merged_into TEXT REFERENCES spirits(uuid);
@@ -0,0 +1,43 @@
+-- Spirits can have several images. Existing images keep their spirit id as their image id,
+-- which is also the name of their file on disk.
+CREATE TABLE IF NOT EXISTS spirit_images_gallery (
+    id TEXT PRIMARY KEY NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    uploaded_by TEXT,
+    caption TEXT NOT NULL DEFAULT '',
+    position INTEGER NOT NULL DEFAULT 0,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO spirit_images_gallery(
+        id,
+        spirit_id,
+        is_primary,
+        content_type,
+        size_bytes,
+        width,
+        height,
+        sha256,
+        created_at
+    )
+SELECT spirit_id,
+    spirit_id,
+    TRUE,
+    content_type,
+    size_bytes,
+    width,
+    height,
+    sha256,
+    created_at
+FROM spirit_images;
+DROP TABLE spirit_images;
+ALTER TABLE spirit_images_gallery
+    RENAME TO spirit_images;
+CREATE INDEX IF NOT EXISTS spirit_images_spirit_id ON spirit_images(spirit_id, position);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_images_primary ON spirit_images(spirit_id)
+WHERE is_primary;
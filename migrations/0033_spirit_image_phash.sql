@@ -0,0 +1,4 @@
+-- A perceptual hash of each image, used to spot near-identical uploads for the same spirit.
+-- Images stored before this column existed get one when they are backfilled.
+ALTER TABLE spirit_images
+ADD COLUMN phash TEXT;
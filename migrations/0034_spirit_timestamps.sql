@@ -0,0 +1,19 @@
+-- When each spirit was added and last edited. Spirits that predate these columns take the
+-- times from their revision history, and stay NULL when they have none.
+ALTER TABLE spirits
+ADD COLUMN created_at TEXT;
+ALTER TABLE spirits
+ADD COLUMN updated_at TEXT;
+UPDATE spirits
+SET created_at = (
+        SELECT MIN(created_at)
+        FROM spirit_revisions
+        WHERE spirit_id = spirits.uuid
+    ),
+    updated_at = (
+        SELECT MAX(created_at)
+        FROM spirit_revisions
+        WHERE spirit_id = spirits.uuid
+    );
+CREATE INDEX IF NOT EXISTS spirits_created_at ON spirits(created_at);
+CREATE INDEX IF NOT EXISTS spirits_updated_at ON spirits(updated_at);
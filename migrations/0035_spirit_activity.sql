@@ -0,0 +1,10 @@
+-- Daily counts of how often each spirit is viewed, rated and added to collections, used to
+-- rank trending spirits.
+CREATE TABLE IF NOT EXISTS spirit_activity (
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    day TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (spirit_id, day, kind)
+);
+CREATE INDEX IF NOT EXISTS spirit_activity_day ON spirit_activity(day);
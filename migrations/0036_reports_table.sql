@@ -0,0 +1,29 @@
+-- One moderation queue for everything users can report. Review reports move over from
+-- review_reports; a reporter can only have one open report per piece of content.
+CREATE TABLE IF NOT EXISTS reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    target_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    reporter_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL DEFAULT 'open',
+    resolved_by TEXT,
+    resolution_note TEXT NOT NULL DEFAULT '',
+    resolved_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS reports_status ON reports(status, created_at);
+CREATE INDEX IF NOT EXISTS reports_target ON reports(target_type, target_id);
+CREATE UNIQUE INDEX IF NOT EXISTS reports_open_per_reporter ON reports(target_type, target_id, reporter_id)
+WHERE status = 'open';
+INSERT OR IGNORE INTO reports(target_type, target_id, reporter_id, reason, detail, status, created_at)
+SELECT 'review',
+    CAST(review_id AS TEXT),
+    reporter_id,
+    'other',
+    reason,
+    CASE WHEN resolved THEN 'hidden' ELSE 'open' END,
+    created_at
+FROM review_reports;
+DROP TABLE review_reports;
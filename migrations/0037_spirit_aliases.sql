@@ -0,0 +1,10 @@
+-- Other names a spirit is sold or known under, searched alongside its name.
+CREATE TABLE IF NOT EXISTS spirit_aliases (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    alias TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (spirit_id, alias COLLATE NOCASE)
+);
+CREATE VIRTUAL TABLE spirit_aliases_fts USING fts5(alias_id UNINDEXED, spirit_id UNINDEXED, alias);
@@ -0,0 +1,15 @@
+-- Links between spirits that are editions of the same expression, such as numbered batches
+-- or bottlings at different proofs. Links are undirected, so each pair is stored once.
+CREATE TABLE IF NOT EXISTS spirit_relations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    related_id TEXT NOT NULL REFERENCES spirits(uuid),
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (spirit_id <> related_id)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_relations_pair ON spirit_relations(
+    min(spirit_id, related_id),
+    max(spirit_id, related_id)
+);
+CREATE INDEX IF NOT EXISTS spirit_relations_related_id ON spirit_relations(related_id);
@@ -0,0 +1,12 @@
+-- Prices users have seen a spirit selling for.
+CREATE TABLE IF NOT EXISTS price_points (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    user_id TEXT NOT NULL,
+    amount REAL NOT NULL,
+    currency TEXT NOT NULL,
+    store TEXT NOT NULL DEFAULT '',
+    observed_on TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS price_points_spirit_id ON price_points(spirit_id, observed_on);
@@ -0,0 +1,4 @@
+-- How obtainable a bottle is: available, allocated, limited or discontinued.
+ALTER TABLE spirits
+ADD COLUMN availability TEXT NOT NULL DEFAULT 'available';
+CREATE INDEX IF NOT EXISTS spirits_availability ON spirits(availability);
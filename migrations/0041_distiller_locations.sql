@@ -0,0 +1,6 @@
+-- Where each distillery is, for plotting them on a map.
+ALTER TABLE distillers
+ADD COLUMN latitude REAL;
+ALTER TABLE distillers
+ADD COLUMN longitude REAL;
+CREATE INDEX IF NOT EXISTS distillers_location ON distillers(latitude, longitude);
@@ -0,0 +1,21 @@
+-- Curated, ordered tastings of a handful of spirits that users can share and copy.
+CREATE TABLE IF NOT EXISTS flights (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    public INTEGER NOT NULL DEFAULT 0,
+    cloned_from INTEGER REFERENCES flights(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS flights_user_id ON flights(user_id);
+CREATE INDEX IF NOT EXISTS flights_public ON flights(public, created_at);
+CREATE TABLE IF NOT EXISTS flight_spirits (
+    flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    PRIMARY KEY (flight_id, position),
+    UNIQUE (flight_id, spirit_id)
+);
+CREATE INDEX IF NOT EXISTS flight_spirits_spirit_id ON flight_spirits(spirit_id);
@@ -0,0 +1,25 @@
+-- Cocktail recipes. An ingredient is a specific spirit from the catalog, any spirit of a
+-- type such as "Bourbon", or something outside the catalog like lemon juice.
+CREATE TABLE IF NOT EXISTS cocktails (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    instructions TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS cocktail_ingredients (
+    cocktail_id INTEGER NOT NULL REFERENCES cocktails(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    spirit_id TEXT REFERENCES spirits(uuid),
+    spirit_type_id INTEGER REFERENCES spirit_types(id),
+    name TEXT NOT NULL DEFAULT '',
+    amount TEXT NOT NULL DEFAULT '',
+    optional INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (cocktail_id, position),
+    CHECK (
+        (spirit_id IS NOT NULL) + (spirit_type_id IS NOT NULL) + (name <> '') = 1
+    )
+);
+CREATE INDEX IF NOT EXISTS cocktail_ingredients_spirit_id ON cocktail_ingredients(spirit_id);
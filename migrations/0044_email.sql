@@ -0,0 +1,24 @@
+-- Which kinds of email each user wants. Users without a row get the defaults below.
+CREATE TABLE IF NOT EXISTS email_preferences (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    moderation INTEGER NOT NULL DEFAULT 1,
+    price_alerts INTEGER NOT NULL DEFAULT 1,
+    weekly_digest INTEGER NOT NULL DEFAULT 0,
+    last_digest_at TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+-- Rendered emails waiting to be handed to the SMTP server by the background sender.
+CREATE TABLE IF NOT EXISTS email_outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    to_address TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    sent_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS email_outbox_pending ON email_outbox(next_attempt_at)
+WHERE sent_at IS NULL;
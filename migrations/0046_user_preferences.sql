@@ -0,0 +1,9 @@
+-- Display settings. Users without a row get the defaults below.
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    volume_unit TEXT NOT NULL DEFAULT 'ml',
+    strength_unit TEXT NOT NULL DEFAULT 'abv',
+    locale TEXT NOT NULL DEFAULT 'en-US',
+    theme TEXT NOT NULL DEFAULT 'system',
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
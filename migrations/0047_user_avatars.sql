@@ -0,0 +1,2 @@
+-- The SHA-256 of the user's avatar file, which doubles as its ETag. NULL without an avatar.
+ALTER TABLE users ADD COLUMN avatar_sha256 TEXT;
@@ -0,0 +1,4 @@
+-- Who can see a user's collection on their profile, and whether their recent ratings and
+-- reviews are listed there.
+ALTER TABLE user_preferences ADD COLUMN collection_visibility TEXT NOT NULL DEFAULT 'private';
+ALTER TABLE user_preferences ADD COLUMN show_activity INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,7 @@
+-- Badges a user has earned. Badge definitions live in code, keyed by `badge`.
+CREATE TABLE IF NOT EXISTS user_badges (
+    user_id TEXT NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    badge TEXT NOT NULL,
+    awarded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, badge)
+);
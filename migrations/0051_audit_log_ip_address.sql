@@ -0,0 +1,3 @@
+-- The client's address, seen through any trusted proxies. NULL for background jobs and for
+-- entries recorded before it was kept.
+ALTER TABLE audit_log ADD COLUMN ip_address TEXT;
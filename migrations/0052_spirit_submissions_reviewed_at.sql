@@ -0,0 +1,3 @@
+-- When a moderator approved or rejected the submission. NULL while it's pending and for
+-- submissions reviewed before it was kept.
+ALTER TABLE spirit_submissions ADD COLUMN reviewed_at TEXT;
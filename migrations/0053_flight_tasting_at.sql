@@ -0,0 +1,4 @@
+-- When the flight is being tasted, in UTC, turning it into an event on the owner's calendar.
+-- NULL for flights that aren't scheduled.
+ALTER TABLE flights ADD COLUMN tasting_at TEXT;
+CREATE INDEX IF NOT EXISTS flights_user_id_tasting_at ON flights(user_id, tasting_at);
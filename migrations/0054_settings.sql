@@ -0,0 +1,11 @@
+-- Site-wide settings admins change at runtime. There is only ever the one row.
+CREATE TABLE IF NOT EXISTS settings (
+    id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+    registration_open INTEGER NOT NULL DEFAULT 1,
+    moderation_required INTEGER NOT NULL DEFAULT 1,
+    default_page_size INTEGER NOT NULL DEFAULT 20,
+    maintenance_banner TEXT,
+    updated_by TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT OR IGNORE INTO settings(id) VALUES (1);
@@ -0,0 +1,2 @@
+-- When the user last signed in, in UTC. NULL for users who haven't signed in since it was kept.
+ALTER TABLE users ADD COLUMN last_login_at TEXT;
@@ -0,0 +1,7 @@
+-- Each user's latest Keycloak refresh token, used to check their role with Keycloak when the
+-- app's own tokens are refreshed. Only usable together with the client secret.
+CREATE TABLE IF NOT EXISTS identity_provider_tokens (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    refresh_token TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
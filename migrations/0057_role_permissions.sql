@@ -0,0 +1,17 @@
+-- What each app role may do. Permission names are defined in src/permissions.rs, and a role
+-- without rows here can do nothing that needs one.
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role TEXT NOT NULL,
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+INSERT INTO role_permissions(role, permission)
+VALUES ('user', 'spirits:write'),
+    ('user', 'images:upload'),
+    ('moderator', 'spirits:write'),
+    ('moderator', 'images:upload'),
+    ('moderator', 'moderation:review'),
+    ('admin', 'spirits:write'),
+    ('admin', 'images:upload'),
+    ('admin', 'images:manage'),
+    ('admin', 'moderation:review');
@@ -0,0 +1,13 @@
+-- Who added the spirit, who may edit or delete it and add images to it along with anyone
+-- allowed to manage every spirit. Filled in from the moderation submissions for spirits added
+-- before it was kept; NULL for older spirits without one, which only those users can change.
+ALTER TABLE spirits ADD COLUMN created_by TEXT REFERENCES users(user_id);
+UPDATE spirits
+SET created_by = (
+        SELECT ss.user_id
+        FROM spirit_submissions ss
+            JOIN users u ON u.user_id = ss.user_id
+        WHERE ss.spirit_id = spirits.uuid
+    );
+INSERT INTO role_permissions(role, permission)
+VALUES ('admin', 'spirits:manage');
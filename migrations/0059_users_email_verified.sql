@@ -0,0 +1,3 @@
+-- Whether Keycloak has verified the user's email, as of their last sign in or token refresh.
+-- Existing users count as unverified until then.
+ALTER TABLE users ADD COLUMN email_verified INTEGER NOT NULL DEFAULT 0;
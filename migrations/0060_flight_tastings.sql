@@ -0,0 +1,11 @@
+-- Live tastings of a flight: how many samples the owner has revealed so far, in tasting order,
+-- and the score each attendee gave each sample.
+ALTER TABLE flights ADD COLUMN revealed_count INTEGER NOT NULL DEFAULT 0;
+CREATE TABLE IF NOT EXISTS flight_scores (
+    flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    score INTEGER NOT NULL CHECK (score BETWEEN 0 AND 100),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (flight_id, position, user_id)
+);
@@ -0,0 +1,9 @@
+-- Whether other users found a review helpful. The share of helpful votes a user's reviews get
+-- counts towards their reputation.
+CREATE TABLE IF NOT EXISTS review_votes (
+    review_id INTEGER NOT NULL REFERENCES reviews(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    helpful INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (review_id, user_id)
+);
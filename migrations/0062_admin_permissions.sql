@@ -0,0 +1,13 @@
+-- Permissions for what used to be checked against the admin role directly.
+INSERT INTO role_permissions(role, permission)
+VALUES ('admin', 'images:delete'),
+    ('admin', 'users:manage'),
+    ('admin', 'distillers:manage'),
+    ('admin', 'releases:manage'),
+    ('admin', 'catalog:import'),
+    ('admin', 'catalog:export'),
+    ('admin', 'webhooks:manage'),
+    ('admin', 'jobs:manage'),
+    ('admin', 'backups:create'),
+    ('admin', 'settings:manage'),
+    ('admin', 'system:monitor');
@@ -0,0 +1,18 @@
+-- The PostgreSQL schema only covers what the ported repositories read and write so far. It is
+-- the SQLite schema as of 0059, with timestamps kept as UTC `YYYY-MM-DD HH:MM:SS` text so both
+-- backends hand the same strings to the app.
+CREATE TABLE IF NOT EXISTS users (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    preferred_username TEXT NOT NULL,
+    email TEXT NOT NULL,
+    refresh_token_version BIGINT NOT NULL,
+    role TEXT NOT NULL,
+    avatar_sha256 TEXT,
+    last_login_at TEXT,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE TABLE IF NOT EXISTS identity_provider_tokens (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    refresh_token TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
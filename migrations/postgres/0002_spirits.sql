@@ -0,0 +1,109 @@
+-- Spirits and what their details and search read. Search uses a generated `tsvector` in place
+-- of SQLite's FTS5 tables, so there's no separate index to keep in step.
+CREATE TABLE IF NOT EXISTS countries (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS regions (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    country_id BIGINT NOT NULL REFERENCES countries(id),
+    name TEXT NOT NULL,
+    UNIQUE (country_id, name)
+);
+CREATE TABLE IF NOT EXISTS distillers (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    region_id BIGINT REFERENCES regions(id),
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION
+);
+CREATE INDEX IF NOT EXISTS distillers_location ON distillers(latitude, longitude);
+CREATE TABLE IF NOT EXISTS spirit_types (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    name TEXT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_types_name ON spirit_types(lower(name));
+CREATE TABLE IF NOT EXISTS spirits (
+    uuid TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    distiller TEXT NOT NULL,
+    bottler TEXT NOT NULL,
+    type TEXT NOT NULL,
+    abv DOUBLE PRECISION NOT NULL,
+    age TEXT NOT NULL,
+    type_id BIGINT REFERENCES spirit_types(id),
+    region_id BIGINT REFERENCES regions(id),
+    version BIGINT NOT NULL DEFAULT 1,
+    deleted_at TEXT,
+    merged_into TEXT REFERENCES spirits(uuid),
+    created_at TEXT,
+    updated_at TEXT,
+    availability TEXT NOT NULL DEFAULT 'available',
+    created_by TEXT REFERENCES users(user_id),
+    search tsvector GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED
+);
+CREATE INDEX IF NOT EXISTS spirits_normalized_name ON spirits(lower(trim(name)), lower(trim(distiller)));
+CREATE INDEX IF NOT EXISTS spirits_created_at ON spirits(created_at);
+CREATE INDEX IF NOT EXISTS spirits_updated_at ON spirits(updated_at);
+CREATE INDEX IF NOT EXISTS spirits_availability ON spirits(availability);
+CREATE INDEX IF NOT EXISTS spirits_search ON spirits USING GIN (search);
+CREATE TABLE IF NOT EXISTS spirit_submissions (
+    spirit_id TEXT PRIMARY KEY NOT NULL REFERENCES spirits(uuid),
+    user_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reviewed_by TEXT,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    reviewed_at TEXT
+);
+CREATE TABLE IF NOT EXISTS ratings (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    score BIGINT NOT NULL CHECK (score BETWEEN 0 AND 100),
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE (user_id, spirit_id)
+);
+CREATE INDEX IF NOT EXISTS ratings_spirit_id ON ratings(spirit_id);
+CREATE TABLE IF NOT EXISTS spirit_images (
+    id TEXT PRIMARY KEY NOT NULL,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    uploaded_by TEXT,
+    caption TEXT NOT NULL DEFAULT '',
+    position BIGINT NOT NULL DEFAULT 0,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    width BIGINT NOT NULL,
+    height BIGINT NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    phash TEXT
+);
+CREATE INDEX IF NOT EXISTS spirit_images_spirit_id ON spirit_images(spirit_id, position);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_images_primary ON spirit_images(spirit_id)
+WHERE is_primary;
+CREATE TABLE IF NOT EXISTS spirit_relations (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    related_id TEXT NOT NULL REFERENCES spirits(uuid),
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    CHECK (spirit_id <> related_id)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_relations_pair ON spirit_relations(
+    least(spirit_id, related_id),
+    greatest(spirit_id, related_id)
+);
+CREATE INDEX IF NOT EXISTS spirit_relations_related_id ON spirit_relations(related_id);
+CREATE TABLE IF NOT EXISTS spirit_aliases (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    spirit_id TEXT NOT NULL REFERENCES spirits(uuid),
+    alias TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    search tsvector GENERATED ALWAYS AS (to_tsvector('simple', alias)) STORED
+);
+CREATE UNIQUE INDEX IF NOT EXISTS spirit_aliases_alias ON spirit_aliases(spirit_id, lower(alias));
+CREATE INDEX IF NOT EXISTS spirit_aliases_search ON spirit_aliases USING GIN (search);
@@ -0,0 +1,18 @@
+UPDATE jobs
+SET status = 'running',
+    attempts = attempts + 1,
+    started_at = CURRENT_TIMESTAMP
+WHERE status = 'queued'
+    AND id = (
+        SELECT id
+        FROM jobs
+        WHERE status = 'queued'
+            AND run_at <= CURRENT_TIMESTAMP
+        ORDER BY run_at,
+            id
+        LIMIT 1
+    )
+RETURNING id AS 'id!',
+    kind,
+    payload,
+    attempts;
@@ -0,0 +1,4 @@
+UPDATE spirit_images
+SET is_primary = FALSE
+WHERE spirit_id = $1
+    AND is_primary;
@@ -0,0 +1,2 @@
+DELETE FROM barcodes
+WHERE code = $1;
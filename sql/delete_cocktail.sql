@@ -0,0 +1,2 @@
+DELETE FROM cocktails
+WHERE id = $1;
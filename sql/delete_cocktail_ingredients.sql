@@ -0,0 +1,2 @@
+DELETE FROM cocktail_ingredients
+WHERE cocktail_id = $1;
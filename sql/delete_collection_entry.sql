@@ -0,0 +1,3 @@
+DELETE FROM collection_entries
+WHERE id = $1
+    AND user_id = $2;
@@ -0,0 +1,2 @@
+DELETE FROM store_entries
+WHERE expires_at <= $1;
@@ -0,0 +1,2 @@
+DELETE FROM flights
+WHERE id = $1;
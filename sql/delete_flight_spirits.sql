@@ -0,0 +1,2 @@
+DELETE FROM flight_spirits
+WHERE flight_id = $1;
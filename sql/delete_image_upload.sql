@@ -0,0 +1,2 @@
+DELETE FROM image_uploads
+WHERE id = $1;
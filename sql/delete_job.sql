@@ -0,0 +1,2 @@
+DELETE FROM jobs
+WHERE id = $1;
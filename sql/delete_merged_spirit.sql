@@ -0,0 +1,5 @@
+UPDATE spirits
+SET deleted_at = CURRENT_TIMESTAMP,
+    merged_into = $1
+WHERE uuid = $2
+    AND deleted_at IS NULL;
@@ -0,0 +1,3 @@
+DELETE FROM ratings
+WHERE user_id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,3 @@
+DELETE FROM release_watchers
+WHERE release_id = $1
+    AND user_id = $2;
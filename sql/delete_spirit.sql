@@ -0,0 +1,4 @@
+UPDATE spirits
+SET deleted_at = CURRENT_TIMESTAMP
+WHERE uuid = $1
+    AND deleted_at IS NULL;
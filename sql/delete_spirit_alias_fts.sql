@@ -0,0 +1,2 @@
+DELETE FROM spirit_aliases_fts
+WHERE alias_id = $1;
@@ -0,0 +1,2 @@
+DELETE FROM spirits_fts
+WHERE uuid = $1;
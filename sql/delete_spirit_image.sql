@@ -0,0 +1,3 @@
+DELETE FROM spirit_images
+WHERE id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,9 @@
+DELETE FROM spirit_relations
+WHERE (
+        spirit_id = $1
+        AND related_id = $2
+    )
+    OR (
+        spirit_id = $2
+        AND related_id = $1
+    );
@@ -0,0 +1,3 @@
+DELETE FROM image_uploads
+WHERE created_at < datetime(CURRENT_TIMESTAMP, '-' || $1 || ' seconds')
+RETURNING id;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DELETE FROM store_entries
+WHERE namespace = $1
+    AND key = $2;
@@ -0,0 +1,3 @@
+DELETE FROM wishlist_entries
+WHERE user_id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,2 @@
+INSERT INTO audit_log(user_id, ip_address, action, detail)
+VALUES ($1, $2, $3, $4);
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO barcodes(code, spirit_id, added_by)
+VALUES ($1, $2, $3);
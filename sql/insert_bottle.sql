@@ -0,0 +1,3 @@
+INSERT INTO bottles(user_id, spirit_id, label)
+VALUES ($1, $2, $3)
+RETURNING id AS 'id!';
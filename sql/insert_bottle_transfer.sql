@@ -0,0 +1,11 @@
+INSERT INTO bottle_transfers(
+        bottle_id,
+        kind,
+        counterpart,
+        counterpart_user_id,
+        transferred_on,
+        price,
+        notes
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id AS 'id!';
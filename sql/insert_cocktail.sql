@@ -0,0 +1,3 @@
+INSERT INTO cocktails(user_id, name, description, instructions)
+VALUES ($1, $2, $3, $4)
+RETURNING id AS 'id!';
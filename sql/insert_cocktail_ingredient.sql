@@ -0,0 +1,10 @@
+INSERT INTO cocktail_ingredients(
+        cocktail_id,
+        position,
+        spirit_id,
+        spirit_type_id,
+        name,
+        amount,
+        optional
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7);
@@ -0,0 +1,11 @@
+INSERT INTO bottles(
+        user_id,
+        spirit_id,
+        label,
+        collection_entry_id,
+        volume_ml,
+        remaining_ml,
+        opened_on
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id AS 'id!';
@@ -0,0 +1,12 @@
+INSERT INTO collection_entries(
+        user_id,
+        spirit_id,
+        status,
+        purchase_date,
+        price_paid,
+        opened,
+        fill_level,
+        notes
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+RETURNING id AS 'id!';
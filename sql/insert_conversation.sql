@@ -0,0 +1,3 @@
+INSERT INTO conversations(swap_match_id)
+VALUES ($1)
+RETURNING id AS 'id!';
@@ -0,0 +1,2 @@
+INSERT INTO conversation_participants(conversation_id, user_id)
+VALUES ($1, $2) ON CONFLICT(conversation_id, user_id) DO NOTHING;
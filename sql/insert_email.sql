@@ -0,0 +1,15 @@
+INSERT INTO email_outbox(user_id, to_address, subject, body)
+SELECT u.user_id,
+    u.email,
+    $3,
+    $4
+FROM users u
+    LEFT JOIN email_preferences p ON p.user_id = u.user_id
+WHERE u.user_id = $1
+    AND u.email != ''
+    AND CASE
+        $2
+        WHEN 'moderation' THEN COALESCE(p.moderation, 1)
+        WHEN 'price_alerts' THEN COALESCE(p.price_alerts, 1)
+        WHEN 'weekly_digest' THEN COALESCE(p.weekly_digest, 0)
+    END = 1;
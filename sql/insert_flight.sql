@@ -0,0 +1,3 @@
+INSERT INTO flights(user_id, title, description, public, cloned_from, tasting_at)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING id AS 'id!';
@@ -0,0 +1,2 @@
+INSERT INTO flight_spirits(flight_id, position, spirit_id)
+VALUES ($1, $2, $3);
@@ -0,0 +1,2 @@
+INSERT INTO image_uploads (id, spirit_id, user_id, total_bytes)
+VALUES ($1, $2, $3, $4);
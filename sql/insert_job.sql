@@ -0,0 +1,2 @@
+INSERT INTO jobs(kind, payload)
+VALUES ($1, $2);
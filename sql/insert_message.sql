@@ -0,0 +1,4 @@
+INSERT INTO messages(conversation_id, sender_id, body)
+VALUES ($1, $2, $3)
+RETURNING id AS 'id!',
+    created_at;
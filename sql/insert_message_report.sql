@@ -0,0 +1,2 @@
+INSERT INTO message_reports(message_id, reporter_id, reason)
+VALUES ($1, $2, $3);
@@ -0,0 +1,2 @@
+INSERT INTO notifications(user_id, message)
+VALUES ($1, $2);
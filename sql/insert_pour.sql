@@ -0,0 +1,11 @@
+INSERT INTO pours(
+        user_id,
+        spirit_id,
+        poured_at,
+        amount_ml,
+        occasion,
+        note,
+        bottle_id
+    )
+VALUES ($1, $2, COALESCE($3, CURRENT_TIMESTAMP), $4, $5, $6, $7)
+RETURNING id AS 'id!';
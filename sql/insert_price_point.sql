@@ -0,0 +1,10 @@
+INSERT INTO price_points(
+        spirit_id,
+        user_id,
+        amount,
+        currency,
+        store,
+        observed_on
+    )
+VALUES ($1, $2, $3, $4, $5, COALESCE($6, DATE('now')))
+RETURNING id AS 'id!';
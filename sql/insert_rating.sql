@@ -0,0 +1,2 @@
+INSERT INTO ratings(user_id, spirit_id, score)
+VALUES ($1, $2, $3);
@@ -0,0 +1,3 @@
+INSERT INTO releases(name, distiller, expected_date, description)
+VALUES ($1, $2, $3, $4)
+RETURNING id;
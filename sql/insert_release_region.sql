@@ -0,0 +1,2 @@
+INSERT INTO release_regions(release_id, region)
+VALUES ($1, $2) ON CONFLICT(release_id, region) DO NOTHING;
@@ -0,0 +1,2 @@
+INSERT INTO release_watchers(release_id, user_id)
+VALUES ($1, $2) ON CONFLICT(release_id, user_id) DO NOTHING;
@@ -0,0 +1,3 @@
+INSERT INTO reports(target_type, target_id, reporter_id, reason, detail)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id;
@@ -0,0 +1,3 @@
+INSERT INTO reviews(user_id, spirit_id, body)
+VALUES ($1, $2, $3)
+RETURNING id AS 'id!';
@@ -0,0 +1,5 @@
+INSERT INTO distillers(name, region_id)
+SELECT $1,
+    r.id
+FROM regions r
+WHERE r.name = $2 ON CONFLICT(name) DO NOTHING;
@@ -1,2 +1,30 @@
-INSERT INTO spirits(uuid, name, description, distiller, abv)
-VALUES ($1, $2, $3, $4, $5) ON CONFLICT(uuid) DO NOTHING;
\ No newline at end of file
+INSERT INTO spirits(
+        uuid,
+        name,
+        description,
+        distiller,
+        bottler,
+        type,
+        type_id,
+        region_id,
+        abv,
+        age,
+        created_at,
+        updated_at,
+        created_by
+    )
+VALUES (
+        $1,
+        $2,
+        $3,
+        $4,
+        '',
+        $5,
+        $6,
+        $7,
+        $8,
+        '',
+        CURRENT_TIMESTAMP,
+        CURRENT_TIMESTAMP,
+        $9
+    ) ON CONFLICT(uuid) DO NOTHING;
@@ -0,0 +1,3 @@
+INSERT INTO spirit_aliases(spirit_id, alias, created_by)
+VALUES ($1, $2, $3)
+RETURNING id;
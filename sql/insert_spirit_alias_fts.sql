@@ -0,0 +1,2 @@
+INSERT INTO spirit_aliases_fts(alias_id, spirit_id, alias)
+VALUES ($1, $2, $3);
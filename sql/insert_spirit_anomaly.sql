@@ -0,0 +1,11 @@
+INSERT INTO spirit_anomalies(spirit_id, field, reason)
+SELECT $1,
+    $2,
+    $3
+WHERE NOT EXISTS (
+        SELECT 1
+        FROM spirit_anomalies
+        WHERE spirit_id = $1
+            AND field = $2
+            AND reason = $3
+    );
\ No newline at end of file
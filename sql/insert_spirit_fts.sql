@@ -0,0 +1,2 @@
+INSERT INTO spirits_fts(uuid, name, distiller, bottler, type)
+VALUES ($1, $2, $3, '', $4);
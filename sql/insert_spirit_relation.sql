@@ -0,0 +1,2 @@
+INSERT INTO spirit_relations(spirit_id, related_id, created_by)
+VALUES ($1, $2, $3);
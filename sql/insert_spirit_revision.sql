@@ -0,0 +1,2 @@
+INSERT INTO spirit_revisions(spirit_id, user_id, action, changes, snapshot)
+VALUES ($1, $2, $3, $4, $5);
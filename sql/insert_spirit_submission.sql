@@ -0,0 +1,2 @@
+INSERT INTO spirit_submissions(spirit_id, user_id, status)
+VALUES ($1, $2, $3);
@@ -0,0 +1,4 @@
+INSERT INTO swap_confirmations(match_id, user_id, rating)
+VALUES ($1, $2, $3) ON CONFLICT(match_id, user_id) DO
+UPDATE
+SET rating = excluded.rating;
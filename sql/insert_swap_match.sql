@@ -0,0 +1,3 @@
+INSERT INTO swap_matches(offer_id, request_id)
+VALUES ($1, $2)
+RETURNING id AS 'id!';
@@ -0,0 +1,3 @@
+INSERT INTO swap_offers(user_id, spirit_id, volume_ml, notes)
+VALUES ($1, $2, $3, $4)
+RETURNING id AS 'id!';
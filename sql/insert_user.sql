@@ -3,6 +3,7 @@ INSERT INTO users (
         preferred_username,
         email,
         refresh_token_version,
-        role
+        role,
+        email_verified
     )
-VALUES ($1, $2, $3, $4, $5) ON CONFLICT(user_id) DO NOTHING;
\ No newline at end of file
+VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(user_id) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,3 @@
+INSERT
+    OR IGNORE INTO user_badges(user_id, badge)
+VALUES ($1, $2);
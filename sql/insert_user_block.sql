@@ -0,0 +1,2 @@
+INSERT INTO user_blocks(blocker_id, blocked_id)
+VALUES ($1, $2) ON CONFLICT(blocker_id, blocked_id) DO NOTHING;
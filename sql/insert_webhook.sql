@@ -0,0 +1,3 @@
+INSERT INTO webhooks(url, secret, events, description, created_by)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id AS 'id!';
@@ -0,0 +1,10 @@
+INSERT INTO webhook_deliveries(webhook_id, event, payload)
+SELECT w.id,
+    $1,
+    $2
+FROM webhooks w
+WHERE EXISTS (
+        SELECT 1
+        FROM json_each(w.events) e
+        WHERE e.value = $1
+    );
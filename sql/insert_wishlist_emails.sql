@@ -0,0 +1,13 @@
+INSERT INTO email_outbox(user_id, to_address, subject, body)
+SELECT u.user_id,
+    u.email,
+    $3,
+    $4
+FROM wishlist_entries w
+    JOIN users u ON u.user_id = w.user_id
+    LEFT JOIN email_preferences p ON p.user_id = u.user_id
+WHERE w.spirit_id = $1
+    AND w.notify = 1
+    AND w.user_id != $2
+    AND u.email != ''
+    AND COALESCE(p.price_alerts, 1) = 1;
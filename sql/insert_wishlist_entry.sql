@@ -0,0 +1,2 @@
+INSERT INTO wishlist_entries(user_id, spirit_id, notify, note)
+VALUES ($1, $2, $3, $4);
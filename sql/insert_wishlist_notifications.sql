@@ -0,0 +1,8 @@
+INSERT INTO notifications(user_id, message)
+SELECT w.user_id,
+    s.name || ': ' || $3
+FROM wishlist_entries w
+    JOIN spirits s ON s.uuid = w.spirit_id
+WHERE w.spirit_id = $1
+    AND w.notify = 1
+    AND w.user_id != $2;
@@ -0,0 +1,8 @@
+UPDATE spirit_aliases_fts
+SET spirit_id = $1
+WHERE spirit_id = $2
+    AND alias_id IN (
+        SELECT id
+        FROM spirit_aliases
+        WHERE spirit_id = $1
+    );
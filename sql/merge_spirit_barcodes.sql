@@ -0,0 +1,3 @@
+UPDATE barcodes
+SET spirit_id = $1
+WHERE spirit_id = $2;
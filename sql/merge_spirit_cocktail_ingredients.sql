@@ -0,0 +1,3 @@
+UPDATE cocktail_ingredients
+SET spirit_id = $1
+WHERE spirit_id = $2;
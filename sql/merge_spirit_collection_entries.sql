@@ -0,0 +1,3 @@
+UPDATE collection_entries
+SET spirit_id = $1
+WHERE spirit_id = $2;
@@ -0,0 +1,3 @@
+UPDATE OR IGNORE flavor_votes
+SET spirit_id = $1
+WHERE spirit_id = $2;
@@ -0,0 +1,3 @@
+UPDATE OR IGNORE flight_spirits
+SET spirit_id = $1
+WHERE spirit_id = $2;
@@ -0,0 +1,10 @@
+UPDATE spirit_images
+SET spirit_id = $1,
+    is_primary = is_primary
+    AND NOT EXISTS (
+        SELECT 1
+        FROM spirit_images
+        WHERE spirit_id = $1
+            AND is_primary
+    )
+WHERE spirit_id = $2;
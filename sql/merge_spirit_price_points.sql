@@ -0,0 +1,3 @@
+UPDATE price_points
+SET spirit_id = $1
+WHERE spirit_id = $2;
@@ -0,0 +1,8 @@
+UPDATE OR IGNORE spirit_relations
+SET spirit_id = CASE WHEN spirit_id = $2 THEN $1 ELSE spirit_id END,
+    related_id = CASE WHEN related_id = $2 THEN $1 ELSE related_id END
+WHERE (
+        spirit_id = $2
+        OR related_id = $2
+    )
+    AND $1 NOT IN (spirit_id, related_id);
@@ -0,0 +1,3 @@
+UPDATE OR IGNORE reviews
+SET spirit_id = $1
+WHERE spirit_id = $2;
@@ -0,0 +1,3 @@
+UPDATE swap_offers
+SET spirit_id = $1
+WHERE spirit_id = $2;
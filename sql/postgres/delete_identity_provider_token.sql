@@ -0,0 +1,2 @@
+DELETE FROM identity_provider_tokens
+WHERE user_id = $1;
\ No newline at end of file
@@ -0,0 +1,9 @@
+INSERT INTO users (
+        user_id,
+        preferred_username,
+        email,
+        refresh_token_version,
+        role,
+        email_verified
+    )
+VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(user_id) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,33 @@
+SELECT s.uuid,
+    s.name,
+    s.distiller,
+    s.bottler,
+    s.type AS typ,
+    AVG(rt.score)::DOUBLE PRECISION AS average_rating,
+    COUNT(rt.id) AS rating_count
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+    LEFT JOIN ratings rt ON rt.spirit_id = s.uuid
+WHERE s.uuid IN (
+        SELECT uuid
+        FROM spirits
+        WHERE search @@ plainto_tsquery('simple', $1)
+        UNION
+        SELECT spirit_id
+        FROM spirit_aliases
+        WHERE search @@ plainto_tsquery('simple', $1)
+    )
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+    AND (
+        $2::BIGINT IS NULL
+        OR COALESCE(s.region_id, d.region_id) = $2
+    )
+    AND (
+        $3::TEXT IS NULL
+        OR s.availability = $3
+    )
+GROUP BY s.uuid
+ORDER BY s.name DESC
+LIMIT 20;
@@ -0,0 +1,3 @@
+SELECT refresh_token
+FROM identity_provider_tokens
+WHERE user_id = $1;
\ No newline at end of file
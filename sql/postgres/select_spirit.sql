@@ -0,0 +1,77 @@
+SELECT s.uuid,
+    s.name,
+    s.description,
+    s.distiller,
+    s.bottler,
+    s.type AS typ,
+    s.abv,
+    s.age,
+    s.version,
+    s.availability,
+    r.name AS region,
+    (
+        SELECT AVG(score)::DOUBLE PRECISION
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS average_rating,
+    (
+        SELECT COUNT(*)
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS rating_count,
+    (
+        SELECT score
+        FROM ratings
+        WHERE spirit_id = s.uuid
+            AND user_id = $2
+    ) AS my_rating,
+    (
+        SELECT COALESCE(
+                json_agg(
+                    json_build_object(
+                        'id',
+                        i.id,
+                        'url',
+                        '/api/spirit/' || s.uuid || '/images/' || i.id,
+                        'caption',
+                        i.caption,
+                        'primary',
+                        i.is_primary
+                    )
+                    ORDER BY i.position,
+                        i.created_at
+                ),
+                '[]'
+            )
+        FROM spirit_images i
+        WHERE i.spirit_id = s.uuid
+    ) AS images,
+    (
+        SELECT COALESCE(
+                json_agg(
+                    json_build_object('id', rs.uuid, 'name', rs.name, 'abv', rs.abv)
+                    ORDER BY rs.name
+                ),
+                '[]'
+            )
+        FROM spirit_relations sr
+            JOIN spirits rs ON rs.uuid = CASE
+                WHEN sr.spirit_id = s.uuid THEN sr.related_id
+                ELSE sr.spirit_id
+            END
+            LEFT JOIN spirit_submissions rss ON rss.spirit_id = rs.uuid
+        WHERE (
+                sr.spirit_id = s.uuid
+                OR sr.related_id = s.uuid
+            )
+            AND COALESCE(rss.status, 'approved') = 'approved'
+            AND rs.deleted_at IS NULL
+    ) AS related_releases,
+    COALESCE(ss.status, 'approved') AS status,
+    ss.user_id AS submitted_by
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+    LEFT JOIN regions r ON r.id = COALESCE(s.region_id, d.region_id)
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.uuid = $1
+    AND s.deleted_at IS NULL;
@@ -0,0 +1,6 @@
+SELECT uuid AS id
+FROM spirits
+WHERE lower(name) = lower($1)
+    AND lower(distiller) = lower($2)
+    AND deleted_at IS NULL
+LIMIT 1;
@@ -0,0 +1,8 @@
+SELECT user_id,
+    preferred_username,
+    email,
+    refresh_token_version,
+    role,
+    email_verified
+FROM users
+WHERE user_id = $1;
@@ -0,0 +1,3 @@
+UPDATE users
+SET email_verified = $2
+WHERE user_id = $1;
\ No newline at end of file
@@ -0,0 +1,24 @@
+INSERT INTO users (
+        user_id,
+        preferred_username,
+        email,
+        refresh_token_version,
+        role,
+        email_verified,
+        last_login_at
+    )
+VALUES (
+        $1,
+        $2,
+        $3,
+        $4,
+        $5,
+        $6,
+        to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+    ) ON CONFLICT(user_id) DO
+UPDATE
+SET preferred_username = excluded.preferred_username,
+    email = excluded.email,
+    role = excluded.role,
+    email_verified = excluded.email_verified,
+    last_login_at = excluded.last_login_at;
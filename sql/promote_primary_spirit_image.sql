@@ -0,0 +1,16 @@
+UPDATE spirit_images
+SET is_primary = TRUE
+WHERE id = (
+        SELECT id
+        FROM spirit_images
+        WHERE spirit_id = $1
+        ORDER BY position,
+            created_at
+        LIMIT 1
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM spirit_images
+        WHERE spirit_id = $1
+            AND is_primary
+    );
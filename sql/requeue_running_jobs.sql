@@ -0,0 +1,3 @@
+UPDATE jobs
+SET status = 'queued'
+WHERE status = 'running';
@@ -0,0 +1,4 @@
+UPDATE spirits
+SET deleted_at = NULL
+WHERE uuid = $1
+    AND deleted_at IS NOT NULL;
\ No newline at end of file
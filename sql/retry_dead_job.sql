@@ -0,0 +1,6 @@
+UPDATE jobs
+SET status = 'queued',
+    attempts = 0,
+    run_at = CURRENT_TIMESTAMP
+WHERE id = $1
+    AND status = 'dead';
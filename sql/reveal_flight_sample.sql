@@ -0,0 +1,9 @@
+UPDATE flights
+SET revealed_count = revealed_count + 1
+WHERE id = $1
+    AND revealed_count < (
+        SELECT COUNT(*)
+        FROM flight_spirits
+        WHERE flight_id = $1
+    )
+RETURNING revealed_count AS 'revealed_count!';
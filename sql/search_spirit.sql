@@ -1,9 +1,33 @@
-SELECT uuid AS 'uuid: String',
-    name AS 'name: String',
-    distiller AS 'distiller: String',
-    bottler AS 'bottler: String',
-    type AS 'typ: String'
-FROM spirits_fts
-WHERE name MATCH $1
-ORDER BY name DESC
-LIMIT 20;
\ No newline at end of file
+SELECT s.uuid AS 'uuid?: String',
+    s.name AS 'name?: String',
+    s.distiller AS 'distiller?: String',
+    s.bottler AS 'bottler?: String',
+    s.type AS 'typ?: String',
+    AVG(rt.score) AS 'average_rating?: f64',
+    COUNT(rt.id) AS 'rating_count?: i64'
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+    LEFT JOIN ratings rt ON rt.spirit_id = s.uuid
+WHERE s.uuid IN (
+        SELECT uuid
+        FROM spirits_fts
+        WHERE name MATCH $1
+        UNION
+        SELECT spirit_id
+        FROM spirit_aliases_fts
+        WHERE alias MATCH $1
+    )
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+    AND (
+        $2 IS NULL
+        OR COALESCE(s.region_id, d.region_id) = $2
+    )
+    AND (
+        $3 IS NULL
+        OR s.availability = $3
+    )
+GROUP BY s.uuid
+ORDER BY s.name DESC
+LIMIT 20;
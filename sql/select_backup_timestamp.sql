@@ -0,0 +1 @@
+SELECT strftime('%Y%m%d-%H%M%S', 'now') AS 'timestamp!: String';
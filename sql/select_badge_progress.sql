@@ -0,0 +1,55 @@
+WITH tried AS (
+    SELECT spirit_id
+    FROM ratings
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM reviews
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM pours
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM collection_entries
+    WHERE user_id = $1
+        AND (
+            status = 'finished'
+            OR opened = 1
+        )
+),
+tried_spirits AS (
+    SELECT s.distiller,
+        r.name AS region
+    FROM tried t
+        JOIN spirits s ON s.uuid = t.spirit_id
+        LEFT JOIN regions r ON r.id = s.region_id
+    WHERE s.deleted_at IS NULL
+)
+SELECT (
+        SELECT COUNT(*)
+        FROM ratings
+        WHERE user_id = $1
+    ) AS 'ratings!: i64',
+    (
+        SELECT COUNT(*)
+        FROM reviews
+        WHERE user_id = $1
+            AND hidden = 0
+    ) AS 'reviews!: i64',
+    (
+        SELECT COUNT(*)
+        FROM pours
+        WHERE user_id = $1
+    ) AS 'pours!: i64',
+    (
+        SELECT COUNT(*)
+        FROM tried_spirits
+        WHERE region = 'Islay'
+    ) AS 'islay_spirits!: i64',
+    (
+        SELECT COUNT(DISTINCT lower(trim(distiller)))
+        FROM tried_spirits
+        WHERE trim(distiller) != ''
+    ) AS 'distilleries!: i64';
@@ -0,0 +1,3 @@
+SELECT spirit_id
+FROM barcodes
+WHERE code = $1;
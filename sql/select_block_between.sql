@@ -0,0 +1,11 @@
+SELECT blocker_id
+FROM user_blocks
+WHERE (
+        blocker_id = $1
+        AND blocked_id = $2
+    )
+    OR (
+        blocker_id = $2
+        AND blocked_id = $1
+    )
+LIMIT 1;
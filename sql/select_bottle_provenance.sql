@@ -0,0 +1,6 @@
+SELECT acquired_from,
+    acquired_on,
+    price,
+    condition_notes
+FROM bottle_provenance
+WHERE bottle_id = $1;
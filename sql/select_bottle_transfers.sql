@@ -0,0 +1,11 @@
+SELECT id AS 'id!',
+    kind,
+    counterpart,
+    counterpart_user_id,
+    transferred_on,
+    price,
+    notes
+FROM bottle_transfers
+WHERE bottle_id = $1
+ORDER BY transferred_on ASC,
+    id ASC;
@@ -0,0 +1,10 @@
+SELECT b.id,
+    b.spirit_id,
+    s.name AS spirit_name,
+    b.label,
+    b.status,
+    b.created_at
+FROM bottles b
+    JOIN spirits s ON s.uuid = b.spirit_id
+WHERE b.user_id = $1
+ORDER BY b.created_at DESC;
@@ -0,0 +1,40 @@
+SELECT c.id AS 'id!',
+    c.user_id,
+    c.name,
+    c.description,
+    c.instructions,
+    (
+        SELECT json_group_array(
+                json_object(
+                    'position',
+                    o.position,
+                    'spirit_id',
+                    o.spirit_id,
+                    'spirit_type',
+                    o.spirit_type,
+                    'name',
+                    o.name,
+                    'amount',
+                    o.amount,
+                    'optional',
+                    json(CASE WHEN o.optional THEN 'true' ELSE 'false' END)
+                )
+            )
+        FROM (
+                SELECT ci.position,
+                    ci.spirit_id,
+                    st.name AS spirit_type,
+                    COALESCE(s.name, st.name, ci.name) AS name,
+                    ci.amount,
+                    ci.optional
+                FROM cocktail_ingredients ci
+                    LEFT JOIN spirits s ON s.uuid = ci.spirit_id
+                    LEFT JOIN spirit_types st ON st.id = ci.spirit_type_id
+                WHERE ci.cocktail_id = c.id
+                ORDER BY ci.position
+            ) o
+    ) AS 'ingredients!: sqlx::types::Json<Vec<CocktailIngredient>>',
+    c.created_at,
+    c.updated_at
+FROM cocktails c
+WHERE c.id = $1;
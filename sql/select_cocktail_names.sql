@@ -0,0 +1,5 @@
+SELECT id AS 'id!',
+    name
+FROM cocktails
+ORDER BY name COLLATE NOCASE ASC,
+    id ASC;
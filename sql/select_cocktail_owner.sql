@@ -0,0 +1,3 @@
+SELECT user_id
+FROM cocktails
+WHERE id = $1;
@@ -0,0 +1,9 @@
+SELECT id AS 'id!',
+    user_id,
+    name,
+    description,
+    created_at
+FROM cocktails
+ORDER BY name COLLATE NOCASE ASC,
+    id ASC
+LIMIT $1 OFFSET $2;
@@ -0,0 +1,16 @@
+SELECT b.id AS 'id!',
+    b.label,
+    b.status,
+    b.volume_ml,
+    b.remaining_ml,
+    b.opened_on,
+    p.acquired_from AS 'acquired_from?: String',
+    p.acquired_on AS 'acquired_on?: String',
+    p.price AS 'price?: f64',
+    b.created_at
+FROM bottles b
+    LEFT JOIN bottle_provenance p ON p.bottle_id = b.id
+WHERE b.collection_entry_id = $1
+    AND b.user_id = $2
+ORDER BY b.created_at ASC,
+    b.id ASC;
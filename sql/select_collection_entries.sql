@@ -0,0 +1,28 @@
+SELECT c.id AS 'id!',
+    c.spirit_id,
+    s.name AS spirit_name,
+    c.status,
+    c.purchase_date,
+    c.price_paid,
+    c.opened AS 'opened: bool',
+    c.fill_level,
+    c.notes,
+    c.created_at,
+    c.updated_at
+FROM collection_entries c
+    JOIN spirits s ON s.uuid = c.spirit_id
+WHERE c.user_id = $1
+    AND (
+        $2 IS NULL
+        OR c.status = $2
+    )
+    AND (
+        $3 IS NULL
+        OR c.opened = $3
+    )
+    AND (
+        $4 IS NULL
+        OR c.spirit_id = $4
+    )
+ORDER BY c.created_at DESC,
+    c.id DESC;
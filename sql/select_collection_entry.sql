@@ -0,0 +1,15 @@
+SELECT c.id AS 'id!',
+    c.spirit_id,
+    s.name AS spirit_name,
+    c.status,
+    c.purchase_date,
+    c.price_paid,
+    c.opened AS 'opened: bool',
+    c.fill_level,
+    c.notes,
+    c.created_at,
+    c.updated_at
+FROM collection_entries c
+    JOIN spirits s ON s.uuid = c.spirit_id
+WHERE c.id = $1
+    AND c.user_id = $2;
@@ -0,0 +1,4 @@
+SELECT spirit_id
+FROM collection_entries
+WHERE id = $1
+    AND user_id = $2;
@@ -0,0 +1,3 @@
+SELECT user_id
+FROM conversation_participants
+WHERE conversation_id = $1;
@@ -0,0 +1,22 @@
+SELECT c.id AS 'id!',
+    c.swap_match_id,
+    c.created_at,
+    (
+        SELECT group_concat(u.preferred_username, ',')
+        FROM conversation_participants op
+            JOIN users u ON u.user_id = op.user_id
+        WHERE op.conversation_id = c.id
+            AND op.user_id != p.user_id
+    ) AS 'participants?: String',
+    (
+        SELECT COUNT(*)
+        FROM messages m
+        WHERE m.conversation_id = c.id
+            AND m.id > p.last_read_message_id
+            AND m.sender_id != p.user_id
+            AND m.hidden = 0
+    ) AS 'unread_count!: i64'
+FROM conversations c
+    JOIN conversation_participants p ON p.conversation_id = c.id
+WHERE p.user_id = $1
+ORDER BY c.created_at DESC;
@@ -0,0 +1,5 @@
+SELECT id AS 'id!',
+    code,
+    name
+FROM countries
+ORDER BY name ASC;
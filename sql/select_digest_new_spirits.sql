@@ -0,0 +1,10 @@
+SELECT s.name
+FROM spirit_revisions r
+    JOIN spirits s ON s.uuid = r.spirit_id
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE r.action = 'create'
+    AND r.created_at > datetime(CURRENT_TIMESTAMP, '-7 days')
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+ORDER BY r.created_at DESC
+LIMIT $1;
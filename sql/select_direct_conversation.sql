@@ -0,0 +1,8 @@
+SELECT c.id AS 'id!'
+FROM conversations c
+    JOIN conversation_participants a ON a.conversation_id = c.id
+    AND a.user_id = $1
+    JOIN conversation_participants b ON b.conversation_id = c.id
+    AND b.user_id = $2
+WHERE c.swap_match_id IS NULL
+LIMIT 1;
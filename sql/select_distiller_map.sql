@@ -0,0 +1,36 @@
+SELECT d.id AS 'id!',
+    d.name,
+    r.name AS 'region?: String',
+    d.latitude AS 'latitude!: f64',
+    d.longitude AS 'longitude!: f64',
+    (
+        SELECT COUNT(*)
+        FROM spirits s
+            LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+        WHERE s.distiller = d.name
+            AND COALESCE(ss.status, 'approved') = 'approved'
+            AND s.deleted_at IS NULL
+    ) AS 'spirit_count!: i64'
+FROM distillers d
+    LEFT JOIN regions r ON r.id = d.region_id
+WHERE d.latitude IS NOT NULL
+    AND d.longitude IS NOT NULL
+    AND (
+        $1 IS NULL
+        OR d.latitude BETWEEN $2 AND $4
+    )
+    AND (
+        $1 IS NULL
+        OR (
+            $1 <= $3
+            AND d.longitude BETWEEN $1 AND $3
+        )
+        OR (
+            $1 > $3
+            AND (
+                d.longitude >= $1
+                OR d.longitude <= $3
+            )
+        )
+    )
+ORDER BY d.name ASC;
@@ -0,0 +1,9 @@
+SELECT d.id AS 'id!',
+    d.name,
+    d.region_id,
+    r.name AS 'region?: String',
+    d.latitude,
+    d.longitude
+FROM distillers d
+    LEFT JOIN regions r ON r.id = d.region_id
+ORDER BY d.name ASC;
@@ -0,0 +1,10 @@
+SELECT u.user_id,
+    u.preferred_username
+FROM email_preferences p
+    JOIN users u ON u.user_id = p.user_id
+WHERE p.weekly_digest = 1
+    AND u.email != ''
+    AND (
+        p.last_digest_at IS NULL
+        OR p.last_digest_at <= datetime(CURRENT_TIMESTAMP, '-7 days')
+    );
@@ -0,0 +1,8 @@
+SELECT rw.release_id,
+    rw.user_id,
+    r.name,
+    r.expected_date
+FROM release_watchers rw
+    JOIN releases r ON r.id = rw.release_id
+WHERE rw.notified = 0
+    AND r.expected_date BETWEEN date('now') AND date('now', '+' || $1 || ' days');
@@ -0,0 +1,7 @@
+SELECT s.uuid AS 'id!',
+    s.name,
+    s.distiller
+FROM spirits_fts f
+    JOIN spirits s ON s.uuid = f.uuid
+WHERE f.name MATCH $1
+LIMIT 200;
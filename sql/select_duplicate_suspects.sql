@@ -0,0 +1,9 @@
+SELECT a.uuid AS first_id,
+    a.name AS first_name,
+    b.uuid AS second_id,
+    b.name AS second_name
+FROM spirits a
+    JOIN spirits b ON lower(trim(a.name)) = lower(trim(b.name))
+    AND lower(trim(a.distiller)) = lower(trim(b.distiller))
+    AND a.uuid < b.uuid
+ORDER BY a.name ASC;
@@ -0,0 +1,6 @@
+SELECT COALESCE(p.moderation, 1) AS 'moderation!: bool',
+    COALESCE(p.price_alerts, 1) AS 'price_alerts!: bool',
+    COALESCE(p.weekly_digest, 0) AS 'weekly_digest!: bool'
+FROM users u
+    LEFT JOIN email_preferences p ON p.user_id = u.user_id
+WHERE u.user_id = $1;
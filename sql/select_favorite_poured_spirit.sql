@@ -0,0 +1,10 @@
+SELECT p.spirit_id,
+    s.name AS spirit_name,
+    COUNT(*) AS 'pours!: i64'
+FROM pours p
+    JOIN spirits s ON s.uuid = p.spirit_id
+WHERE p.user_id = $1
+GROUP BY p.spirit_id
+ORDER BY COUNT(*) DESC,
+    MAX(p.poured_at) DESC
+LIMIT 1;
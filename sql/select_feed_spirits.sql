@@ -0,0 +1,16 @@
+SELECT s.uuid AS id,
+    s.name,
+    s.distiller,
+    s.description,
+    strftime(
+        '%Y-%m-%dT%H:%M:%SZ',
+        COALESCE(ss.reviewed_at, ss.created_at, s.created_at)
+    ) AS 'approved_at!: String'
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+    AND s.created_at IS NOT NULL
+    AND COALESCE(ss.status, 'approved') = 'approved'
+ORDER BY COALESCE(ss.reviewed_at, ss.created_at, s.created_at) DESC,
+    s.uuid
+LIMIT $1;
@@ -0,0 +1,6 @@
+SELECT dimension,
+    AVG(score) AS 'average!: f64',
+    COUNT(*) AS 'votes!: i64'
+FROM flavor_votes
+WHERE spirit_id = $1
+GROUP BY dimension;
@@ -0,0 +1,3 @@
+SELECT COUNT(DISTINCT user_id) AS 'count!: i64'
+FROM flavor_votes
+WHERE spirit_id = $1;
@@ -0,0 +1,40 @@
+SELECT f.id AS 'id!',
+    f.user_id,
+    f.title,
+    f.description,
+    f.public AS 'public!: bool',
+    f.cloned_from,
+    (
+        SELECT json_group_array(
+                json_object(
+                    'position',
+                    o.position,
+                    'id',
+                    o.uuid,
+                    'name',
+                    o.name,
+                    'distiller',
+                    o.distiller,
+                    'abv',
+                    o.abv
+                )
+            )
+        FROM (
+                SELECT fs.position,
+                    s.uuid,
+                    s.name,
+                    s.distiller,
+                    s.abv
+                FROM flight_spirits fs
+                    JOIN spirits s ON s.uuid = fs.spirit_id
+                WHERE fs.flight_id = f.id
+                    AND s.deleted_at IS NULL
+                ORDER BY fs.position
+            ) o
+    ) AS 'spirits!: sqlx::types::Json<Vec<FlightSpirit>>',
+    f.tasting_at,
+    f.revealed_count,
+    f.created_at,
+    f.updated_at
+FROM flights f
+WHERE f.id = $1;
@@ -0,0 +1,6 @@
+SELECT COUNT(*) AS count
+FROM flights f
+WHERE CASE
+        WHEN $2 THEN f.user_id = $1
+        ELSE f.public = 1
+    END;
@@ -0,0 +1,4 @@
+SELECT user_id,
+    public AS 'public!: bool'
+FROM flights
+WHERE id = $1;
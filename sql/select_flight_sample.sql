@@ -0,0 +1,9 @@
+SELECT fs.position,
+    s.uuid AS id,
+    s.name,
+    s.distiller,
+    s.abv
+FROM flight_spirits fs
+    JOIN spirits s ON s.uuid = fs.spirit_id
+WHERE fs.flight_id = $1
+    AND fs.position = $2;
@@ -0,0 +1,4 @@
+SELECT spirit_id
+FROM flight_spirits
+WHERE flight_id = $1
+ORDER BY position;
@@ -0,0 +1,20 @@
+SELECT f.id AS 'id!',
+    f.user_id,
+    f.title,
+    f.description,
+    f.public AS 'public!: bool',
+    (
+        SELECT COUNT(*)
+        FROM flight_spirits fs
+        WHERE fs.flight_id = f.id
+    ) AS 'spirit_count!: i64',
+    f.tasting_at,
+    f.created_at
+FROM flights f
+WHERE CASE
+        WHEN $2 THEN f.user_id = $1
+        ELSE f.public = 1
+    END
+ORDER BY f.created_at DESC,
+    f.id DESC
+LIMIT $3 OFFSET $4;
@@ -0,0 +1,3 @@
+SELECT id AS 'id!', total_bytes, received_bytes
+FROM image_uploads
+WHERE id = $1 AND spirit_id = $2 AND user_id = $3;
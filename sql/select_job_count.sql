@@ -0,0 +1,6 @@
+SELECT COUNT(*) AS count
+FROM jobs
+WHERE (
+        $1 IS NULL
+        OR status = $1
+    );
@@ -0,0 +1,16 @@
+SELECT id AS 'id!',
+    kind,
+    payload,
+    status,
+    attempts,
+    last_error,
+    run_at,
+    started_at,
+    created_at
+FROM jobs
+WHERE (
+        $1 IS NULL
+        OR status = $1
+    )
+ORDER BY id DESC
+LIMIT $2 OFFSET $3;
@@ -0,0 +1,6 @@
+SELECT m.id AS 'id!',
+    m.conversation_id
+FROM messages m
+    JOIN conversation_participants p ON p.conversation_id = m.conversation_id
+WHERE m.id = $1
+    AND p.user_id = $2;
@@ -0,0 +1,11 @@
+SELECT r.id AS 'id!',
+    r.message_id,
+    m.sender_id,
+    m.body,
+    r.reporter_id,
+    r.reason,
+    r.created_at
+FROM message_reports r
+    JOIN messages m ON m.id = r.message_id
+WHERE r.resolved = 0
+ORDER BY r.created_at ASC;
@@ -0,0 +1,10 @@
+SELECT m.id AS 'id!',
+    m.sender_id,
+    u.preferred_username AS 'sender_username?: String',
+    m.body,
+    m.created_at
+FROM messages m
+    LEFT JOIN users u ON u.user_id = m.sender_id
+WHERE m.conversation_id = $1
+    AND m.hidden = 0
+ORDER BY m.id ASC;
@@ -0,0 +1,34 @@
+WITH owned AS (
+    SELECT DISTINCT s.uuid,
+        s.type_id
+    FROM collection_entries ce
+        JOIN spirits s ON s.uuid = ce.spirit_id
+    WHERE ce.user_id = $1
+        AND ce.status = 'owned'
+        AND s.deleted_at IS NULL
+)
+SELECT ci.cocktail_id,
+    COALESCE(s.name, st.name) AS 'ingredient!: String'
+FROM cocktail_ingredients ci
+    LEFT JOIN spirits s ON s.uuid = ci.spirit_id
+    LEFT JOIN spirit_types st ON st.id = ci.spirit_type_id
+WHERE ci.optional = 0
+    AND (
+        (
+            ci.spirit_id IS NOT NULL
+            AND ci.spirit_id NOT IN (
+                SELECT uuid
+                FROM owned
+            )
+        )
+        OR (
+            ci.spirit_type_id IS NOT NULL
+            AND ci.spirit_type_id NOT IN (
+                SELECT type_id
+                FROM owned
+                WHERE type_id IS NOT NULL
+            )
+        )
+    )
+ORDER BY ci.cocktail_id,
+    ci.position;
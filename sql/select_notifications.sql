@@ -0,0 +1,8 @@
+SELECT id,
+    message,
+    is_read,
+    created_at
+FROM notifications
+WHERE user_id = $1
+ORDER BY created_at DESC
+LIMIT 50;
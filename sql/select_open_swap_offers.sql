@@ -0,0 +1,14 @@
+SELECT o.id AS 'id!',
+    o.user_id,
+    u.preferred_username AS 'username?: String',
+    o.spirit_id,
+    s.name AS spirit_name,
+    o.volume_ml,
+    o.notes,
+    o.created_at
+FROM swap_offers o
+    JOIN spirits s ON s.uuid = o.spirit_id
+    LEFT JOIN users u ON u.user_id = o.user_id
+WHERE o.status = 'open'
+    AND ($1 IS NULL OR o.spirit_id = $1)
+ORDER BY o.created_at DESC;
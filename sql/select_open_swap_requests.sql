@@ -0,0 +1,14 @@
+SELECT r.id AS 'id!',
+    r.user_id,
+    u.preferred_username AS 'username?: String',
+    r.spirit_id,
+    s.name AS spirit_name,
+    r.volume_ml,
+    r.notes,
+    r.created_at
+FROM swap_requests r
+    JOIN spirits s ON s.uuid = r.spirit_id
+    LEFT JOIN users u ON u.user_id = r.user_id
+WHERE r.status = 'open'
+    AND ($1 IS NULL OR r.spirit_id = $1)
+ORDER BY r.created_at DESC;
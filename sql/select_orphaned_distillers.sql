@@ -0,0 +1,7 @@
+SELECT s.distiller,
+    COUNT(*) AS 'spirit_count!: i64'
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+WHERE d.id IS NULL
+GROUP BY s.distiller
+ORDER BY COUNT(*) DESC;
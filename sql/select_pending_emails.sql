@@ -0,0 +1,12 @@
+SELECT id AS 'id!',
+    to_address,
+    subject,
+    body,
+    attempts
+FROM email_outbox
+WHERE sent_at IS NULL
+    AND attempts < $1
+    AND next_attempt_at <= CURRENT_TIMESTAMP
+ORDER BY next_attempt_at,
+    id
+LIMIT $2;
@@ -0,0 +1,13 @@
+SELECT ss.spirit_id,
+    s.name,
+    s.distiller,
+    s.type AS typ,
+    s.abv,
+    ss.user_id,
+    u.preferred_username AS 'username?: String',
+    ss.created_at
+FROM spirit_submissions ss
+    JOIN spirits s ON s.uuid = ss.spirit_id
+    LEFT JOIN users u ON u.user_id = ss.user_id
+WHERE ss.status = 'pending'
+ORDER BY ss.created_at ASC;
@@ -0,0 +1,13 @@
+SELECT d.id AS 'id!',
+    d.event,
+    d.payload,
+    d.attempts,
+    w.url,
+    w.secret
+FROM webhook_deliveries d
+    JOIN webhooks w ON w.id = d.webhook_id
+WHERE d.status = 'pending'
+    AND d.next_attempt_at <= CURRENT_TIMESTAMP
+ORDER BY d.next_attempt_at,
+    d.id
+LIMIT $1;
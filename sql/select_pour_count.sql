@@ -0,0 +1,7 @@
+SELECT COUNT(*) AS 'count!: i64'
+FROM pours
+WHERE user_id = $1
+    AND (
+        $2 IS NULL
+        OR spirit_id = $2
+    );
@@ -0,0 +1,18 @@
+SELECT p.id AS 'id!',
+    p.spirit_id,
+    s.name AS spirit_name,
+    p.poured_at,
+    p.amount_ml,
+    p.occasion,
+    p.note,
+    p.bottle_id
+FROM pours p
+    JOIN spirits s ON s.uuid = p.spirit_id
+WHERE p.user_id = $1
+    AND (
+        $2 IS NULL
+        OR p.spirit_id = $2
+    )
+ORDER BY p.poured_at DESC,
+    p.id DESC
+LIMIT $3 OFFSET $4;
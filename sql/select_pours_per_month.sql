@@ -0,0 +1,7 @@
+SELECT strftime('%Y-%m', poured_at) AS 'month!: String',
+    COUNT(*) AS 'pours!: i64',
+    SUM(amount_ml) AS 'total_ml!: f64'
+FROM pours
+WHERE user_id = $1
+GROUP BY strftime('%Y-%m', poured_at)
+ORDER BY strftime('%Y-%m', poured_at) DESC;
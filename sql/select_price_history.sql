@@ -0,0 +1,16 @@
+SELECT strftime('%Y-%m', observed_on) AS 'month!: String',
+    currency,
+    MIN(amount) AS 'min!: f64',
+    AVG(amount) AS 'avg!: f64',
+    MAX(amount) AS 'max!: f64',
+    COUNT(*) AS 'count!: i64'
+FROM price_points
+WHERE spirit_id = $1
+    AND (
+        $2 IS NULL
+        OR currency = $2
+    )
+GROUP BY strftime('%Y-%m', observed_on),
+    currency
+ORDER BY strftime('%Y-%m', observed_on) ASC,
+    currency ASC;
@@ -0,0 +1,7 @@
+SELECT COUNT(*) AS count
+FROM price_points
+WHERE spirit_id = $1
+    AND (
+        $2 IS NULL
+        OR currency = $2
+    );
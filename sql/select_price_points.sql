@@ -0,0 +1,16 @@
+SELECT id AS 'id!',
+    user_id,
+    amount,
+    currency,
+    store,
+    observed_on,
+    created_at
+FROM price_points
+WHERE spirit_id = $1
+    AND (
+        $2 IS NULL
+        OR currency = $2
+    )
+ORDER BY observed_on DESC,
+    id DESC
+LIMIT $3 OFFSET $4;
@@ -0,0 +1,6 @@
+SELECT id,
+    content_type,
+    sha256
+FROM spirit_images
+WHERE spirit_id = $1
+    AND is_primary;
@@ -0,0 +1,28 @@
+SELECT 'rating' AS 'kind!: String',
+    rt.spirit_id AS 'spirit_id!: String',
+    s.name AS 'spirit_name!: String',
+    rt.score AS 'score?: i64',
+    NULL AS 'body?: String',
+    rt.created_at AS 'created_at!: String'
+FROM ratings rt
+    JOIN spirits s ON s.uuid = rt.spirit_id
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE rt.user_id = $1
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+UNION ALL
+SELECT 'review',
+    r.spirit_id,
+    s.name,
+    NULL,
+    r.body,
+    r.created_at
+FROM reviews r
+    JOIN spirits s ON s.uuid = r.spirit_id
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE r.user_id = $1
+    AND r.hidden = 0
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+ORDER BY 6 DESC
+LIMIT $2;
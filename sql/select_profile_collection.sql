@@ -0,0 +1,13 @@
+SELECT c.spirit_id,
+    s.name AS spirit_name,
+    c.status,
+    c.opened AS 'opened: bool'
+FROM collection_entries c
+    JOIN spirits s ON s.uuid = c.spirit_id
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE c.user_id = $1
+    AND c.status != 'wishlist'
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL
+ORDER BY c.created_at DESC,
+    c.id DESC;
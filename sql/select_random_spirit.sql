@@ -0,0 +1,11 @@
+SELECT s.uuid
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND (
+        $1 IS NULL
+        OR s.type_id = $1
+    )
+ORDER BY RANDOM()
+LIMIT 1;
@@ -0,0 +1,6 @@
+SELECT COUNT(*) AS 'count!: i64'
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+    AND s.created_at IS NOT NULL
+    AND COALESCE(ss.status, 'approved') = 'approved';
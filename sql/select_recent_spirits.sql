@@ -0,0 +1,17 @@
+SELECT s.uuid AS id,
+    s.name,
+    s.distiller,
+    s.type AS typ,
+    s.created_at AS 'created_at!: String',
+    COALESCE(s.updated_at, s.created_at) AS 'updated_at!: String'
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+    AND s.created_at IS NOT NULL
+    AND COALESCE(ss.status, 'approved') = 'approved'
+ORDER BY CASE
+        WHEN $1 = 'updated' THEN COALESCE(s.updated_at, s.created_at)
+        ELSE s.created_at
+    END DESC,
+    s.uuid
+LIMIT $2 OFFSET $3;
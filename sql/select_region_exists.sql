@@ -0,0 +1,3 @@
+SELECT id
+FROM regions
+WHERE id = $1;
@@ -0,0 +1,5 @@
+SELECT id AS 'id!'
+FROM regions
+WHERE name = $1
+ORDER BY id
+LIMIT 1;
@@ -0,0 +1,10 @@
+SELECT r.id AS 'id!',
+    r.name,
+    c.id AS 'country_id!',
+    c.name AS country
+FROM regions r
+    JOIN countries c ON c.id = r.country_id
+WHERE $1 IS NULL
+    OR r.country_id = $1
+ORDER BY c.name ASC,
+    r.name ASC;
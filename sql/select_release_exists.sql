@@ -0,0 +1,3 @@
+SELECT id
+FROM releases
+WHERE id = $1;
@@ -0,0 +1,13 @@
+SELECT r.id,
+    r.name,
+    r.distiller,
+    r.expected_date,
+    r.description,
+    (
+        SELECT json_group_array(rr.region)
+        FROM release_regions rr
+        WHERE rr.release_id = r.id
+    ) AS 'regions!: sqlx::types::Json<Vec<String>>'
+FROM releases r
+WHERE strftime('%Y-%m', r.expected_date) = COALESCE($1, strftime('%Y-%m', 'now'))
+ORDER BY r.expected_date ASC;
@@ -0,0 +1,4 @@
+SELECT COUNT(*) AS count
+FROM reports
+WHERE status = $1
+    AND ($2 IS NULL OR target_type = $2);
@@ -0,0 +1,5 @@
+SELECT target_type,
+    target_id,
+    status
+FROM reports
+WHERE id = $1;
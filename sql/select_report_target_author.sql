@@ -0,0 +1,18 @@
+SELECT CASE
+        $1
+        WHEN 'spirit' THEN (
+            SELECT user_id
+            FROM spirit_submissions
+            WHERE spirit_id = $2
+        )
+        WHEN 'review' THEN (
+            SELECT user_id
+            FROM reviews
+            WHERE id = CAST($2 AS INTEGER)
+        )
+        WHEN 'image' THEN (
+            SELECT uploaded_by
+            FROM spirit_images
+            WHERE id = $2
+        )
+    END AS 'author_id?: String';
@@ -0,0 +1,16 @@
+SELECT id AS 'id!',
+    target_type,
+    target_id,
+    reporter_id,
+    reason,
+    detail,
+    status,
+    resolved_by,
+    resolution_note,
+    resolved_at,
+    created_at
+FROM reports
+WHERE status = $1
+    AND ($2 IS NULL OR target_type = $2)
+ORDER BY created_at ASC, id ASC
+LIMIT $3 OFFSET $4;
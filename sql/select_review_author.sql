@@ -0,0 +1,4 @@
+SELECT user_id
+FROM reviews
+WHERE id = $1
+    AND hidden = 0;
@@ -0,0 +1,4 @@
+SELECT body
+FROM reviews
+WHERE spirit_id = $1
+    AND hidden = 0;
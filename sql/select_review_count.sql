@@ -0,0 +1,4 @@
+SELECT COUNT(*) AS 'count!: i64'
+FROM reviews
+WHERE spirit_id = $1
+    AND hidden = 0;
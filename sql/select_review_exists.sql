@@ -0,0 +1,4 @@
+SELECT id
+FROM reviews
+WHERE id = $1
+    AND hidden = 0;
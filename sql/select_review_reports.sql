@@ -0,0 +1,14 @@
+SELECT rp.id AS 'id!',
+    r.id AS review_id,
+    r.spirit_id,
+    r.user_id,
+    r.body,
+    rp.reporter_id,
+    rp.reason,
+    rp.detail,
+    rp.created_at
+FROM reports rp
+    JOIN reviews r ON r.id = CAST(rp.target_id AS INTEGER)
+WHERE rp.target_type = 'review'
+    AND rp.status = 'open'
+ORDER BY rp.created_at ASC;
@@ -0,0 +1,30 @@
+SELECT r.id AS 'id!',
+    r.user_id,
+    u.preferred_username AS 'username?: String',
+    CASE
+        WHEN u.avatar_sha256 IS NOT NULL THEN '/api/user/' || u.user_id || '/avatar'
+    END AS 'avatar_url?: String',
+    rt.score AS 'score?: i64',
+    r.body,
+    (
+        SELECT COUNT(*)
+        FROM review_votes
+        WHERE review_id = r.id
+            AND helpful = 1
+    ) AS 'helpful_votes!: i64',
+    (
+        SELECT COUNT(*)
+        FROM review_votes
+        WHERE review_id = r.id
+    ) AS 'votes!: i64',
+    r.created_at,
+    r.updated_at
+FROM reviews r
+    LEFT JOIN users u ON u.user_id = r.user_id
+    LEFT JOIN ratings rt ON rt.user_id = r.user_id
+    AND rt.spirit_id = r.spirit_id
+WHERE r.spirit_id = $1
+    AND r.hidden = 0
+ORDER BY r.created_at DESC,
+    r.id DESC
+LIMIT $2 OFFSET $3;
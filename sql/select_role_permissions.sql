@@ -0,0 +1,3 @@
+SELECT permission
+FROM role_permissions
+WHERE role = $1;
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT registration_open AS 'registration_open!: bool',
+    moderation_required AS 'moderation_required!: bool',
+    default_page_size,
+    maintenance_banner
+FROM settings
+WHERE id = 1;
@@ -0,0 +1,24 @@
+SELECT s.uuid AS id,
+    s.name,
+    s.description,
+    s.distiller,
+    s.type AS typ,
+    s.abv,
+    r.name AS 'region?: String',
+    (
+        SELECT AVG(score)
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS 'average_rating?: f64',
+    (
+        SELECT COUNT(*)
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS 'rating_count!: i64'
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+    LEFT JOIN regions r ON r.id = COALESCE(s.region_id, d.region_id)
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.uuid = $1
+    AND s.deleted_at IS NULL
+    AND COALESCE(ss.status, 'approved') = 'approved';
@@ -0,0 +1,6 @@
+SELECT uuid,
+    name,
+    length(trim(description)) AS 'description_length!: i64'
+FROM spirits
+WHERE length(trim(description)) < $1
+ORDER BY name ASC;
@@ -0,0 +1,62 @@
+WITH target AS (
+    SELECT s.uuid,
+        s.type_id,
+        s.distiller,
+        COALESCE(s.region_id, d.region_id) AS region_id
+    FROM spirits s
+        LEFT JOIN distillers d ON d.name = s.distiller
+    WHERE s.uuid = $1
+),
+prominent_flavors AS (
+    SELECT spirit_id,
+        dimension
+    FROM flavor_votes
+    GROUP BY spirit_id,
+        dimension
+    HAVING AVG(score) >= $2
+),
+candidates AS (
+    SELECT s.uuid AS id,
+        s.name,
+        s.distiller,
+        s.type AS typ,
+        COALESCE(s.type_id = t.type_id, FALSE) AS same_type,
+        s.distiller = t.distiller AS same_distiller,
+        COALESCE(COALESCE(s.region_id, d.region_id) = t.region_id, FALSE) AS same_region,
+        (
+            SELECT COUNT(*)
+            FROM prominent_flavors pf
+                JOIN prominent_flavors tf ON tf.dimension = pf.dimension
+                AND tf.spirit_id = t.uuid
+            WHERE pf.spirit_id = s.uuid
+        ) AS shared_flavors
+    FROM spirits s
+        CROSS JOIN target t
+        LEFT JOIN distillers d ON d.name = s.distiller
+        LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+    WHERE s.uuid != t.uuid
+        AND s.deleted_at IS NULL
+        AND COALESCE(ss.status, 'approved') = 'approved'
+),
+scored AS (
+    SELECT *,
+        same_type * $3 + same_distiller * $4 + same_region * $5 + shared_flavors * $6 AS score
+    FROM candidates
+    WHERE same_type
+        OR same_distiller
+        OR same_region
+        OR shared_flavors > 0
+)
+SELECT id AS 'id!: String',
+    name AS 'name!: String',
+    distiller AS 'distiller!: String',
+    typ AS 'typ!: String',
+    same_type AS 'same_type!: bool',
+    same_distiller AS 'same_distiller!: bool',
+    same_region AS 'same_region!: bool',
+    shared_flavors AS 'shared_flavors!: i64',
+    score AS 'score!: i64'
+FROM scored
+ORDER BY score DESC,
+    name
+LIMIT $7;
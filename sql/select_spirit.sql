@@ -0,0 +1,79 @@
+SELECT s.uuid,
+    s.name,
+    s.description,
+    s.distiller,
+    s.bottler,
+    s.type AS typ,
+    s.abv,
+    s.age,
+    s.version,
+    s.availability,
+    r.name AS 'region?: String',
+    (
+        SELECT AVG(score)
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS 'average_rating?: f64',
+    (
+        SELECT COUNT(*)
+        FROM ratings
+        WHERE spirit_id = s.uuid
+    ) AS 'rating_count!: i64',
+    (
+        SELECT score
+        FROM ratings
+        WHERE spirit_id = s.uuid
+            AND user_id = $2
+    ) AS 'my_rating?: i64',
+    (
+        SELECT json_group_array(
+                json_object(
+                    'id',
+                    i.id,
+                    'url',
+                    '/api/spirit/' || s.uuid || '/images/' || i.id,
+                    'caption',
+                    i.caption,
+                    'primary',
+                    json(CASE WHEN i.is_primary THEN 'true' ELSE 'false' END)
+                )
+            )
+        FROM (
+                SELECT *
+                FROM spirit_images
+                WHERE spirit_id = s.uuid
+                ORDER BY position,
+                    created_at
+            ) i
+    ) AS 'images!: sqlx::types::Json<Vec<SpiritImageSummary>>',
+    (
+        SELECT json_group_array(
+                json_object('id', o.uuid, 'name', o.name, 'abv', o.abv)
+            )
+        FROM (
+                SELECT rs.uuid,
+                    rs.name,
+                    rs.abv
+                FROM spirit_relations sr
+                    JOIN spirits rs ON rs.uuid = CASE
+                        WHEN sr.spirit_id = s.uuid THEN sr.related_id
+                        ELSE sr.spirit_id
+                    END
+                    LEFT JOIN spirit_submissions rss ON rss.spirit_id = rs.uuid
+                WHERE (
+                        sr.spirit_id = s.uuid
+                        OR sr.related_id = s.uuid
+                    )
+                    AND COALESCE(rss.status, 'approved') = 'approved'
+                    AND rs.deleted_at IS NULL
+                ORDER BY rs.name
+            ) o
+    ) AS 'related_releases!: sqlx::types::Json<Vec<RelatedRelease>>',
+    COALESCE(ss.status, 'approved') AS 'status!: String',
+    ss.user_id AS 'submitted_by?: String'
+FROM spirits s
+    LEFT JOIN distillers d ON d.name = s.distiller
+    LEFT JOIN regions r ON r.id = COALESCE(s.region_id, d.region_id)
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.uuid = $1
+    AND s.deleted_at IS NULL;
@@ -0,0 +1,13 @@
+SELECT a.spirit_id,
+    s.name,
+    s.distiller,
+    s.type AS typ,
+    CAST(julianday(date('now')) - julianday(a.day) AS INTEGER) AS 'age_days!: i64',
+    a.kind,
+    a.count
+FROM spirit_activity a
+    JOIN spirits s ON s.uuid = a.spirit_id
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE a.day > date('now', '-' || $1 || ' days')
+    AND s.deleted_at IS NULL
+    AND COALESCE(ss.status, 'approved') = 'approved';
@@ -0,0 +1,6 @@
+SELECT id AS 'id!',
+    alias,
+    created_at
+FROM spirit_aliases
+WHERE spirit_id = $1
+ORDER BY alias COLLATE NOCASE ASC;
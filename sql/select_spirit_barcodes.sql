@@ -0,0 +1,5 @@
+SELECT code,
+    created_at
+FROM barcodes
+WHERE spirit_id = $1
+ORDER BY created_at;
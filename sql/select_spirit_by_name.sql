@@ -0,0 +1,6 @@
+SELECT uuid AS 'id!'
+FROM spirits
+WHERE name = $1 COLLATE NOCASE
+    AND distiller = $2 COLLATE NOCASE
+    AND deleted_at IS NULL
+LIMIT 1;
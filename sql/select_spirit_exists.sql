@@ -0,0 +1,4 @@
+SELECT uuid
+FROM spirits
+WHERE uuid = $1
+    AND deleted_at IS NULL;
@@ -0,0 +1,15 @@
+SELECT s.uuid AS 'id!',
+    s.name,
+    s.description,
+    s.distiller,
+    s.bottler,
+    s.type AS typ,
+    s.abv,
+    s.age,
+    s.region_id,
+    COALESCE(ss.status, 'approved') AS 'status!: String'
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+ORDER BY s.name,
+    s.uuid;
@@ -0,0 +1,4 @@
+SELECT uuid,
+    name
+FROM spirits
+ORDER BY name ASC;
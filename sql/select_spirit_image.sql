@@ -0,0 +1,5 @@
+SELECT content_type,
+    sha256
+FROM spirit_images
+WHERE id = $1
+    AND spirit_id = $2;
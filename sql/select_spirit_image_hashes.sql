@@ -0,0 +1,6 @@
+SELECT id,
+    phash AS "phash!"
+FROM spirit_images
+WHERE spirit_id = $1
+    AND phash IS NOT NULL
+ORDER BY position;
@@ -0,0 +1,4 @@
+SELECT id,
+    spirit_id,
+    phash IS NOT NULL AS "has_phash!: bool"
+FROM spirit_images;
@@ -0,0 +1,4 @@
+SELECT uploaded_by
+FROM spirit_images
+WHERE id = $1
+    AND spirit_id = $2;
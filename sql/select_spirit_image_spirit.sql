@@ -0,0 +1,3 @@
+SELECT spirit_id
+FROM spirit_images
+WHERE id = $1;
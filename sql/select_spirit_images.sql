@@ -0,0 +1,12 @@
+SELECT id,
+    caption,
+    position,
+    is_primary AS 'is_primary: bool',
+    content_type,
+    width,
+    height,
+    created_at
+FROM spirit_images
+WHERE spirit_id = $1
+ORDER BY position,
+    created_at;
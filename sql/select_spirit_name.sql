@@ -0,0 +1,4 @@
+SELECT name
+FROM spirits
+WHERE uuid = $1
+    AND deleted_at IS NULL;
@@ -0,0 +1,7 @@
+SELECT s.uuid
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+    AND COALESCE(ss.status, 'approved') = 'approved'
+ORDER BY s.uuid
+LIMIT 1 OFFSET $1;
@@ -0,0 +1,4 @@
+SELECT created_by
+FROM spirits
+WHERE uuid = $1
+    AND deleted_at IS NULL;
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT COUNT(*) AS 'count!: i64'
+FROM spirit_revisions
+WHERE spirit_id = $1;
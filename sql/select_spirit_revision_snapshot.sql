@@ -0,0 +1,4 @@
+SELECT snapshot
+FROM spirit_revisions
+WHERE id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,11 @@
+SELECT sr.id AS 'id!',
+    sr.user_id,
+    u.preferred_username AS 'username?: String',
+    sr.action,
+    sr.changes,
+    sr.created_at
+FROM spirit_revisions sr
+    LEFT JOIN users u ON u.user_id = sr.user_id
+WHERE sr.spirit_id = $1
+ORDER BY sr.id DESC
+LIMIT $2 OFFSET $3;
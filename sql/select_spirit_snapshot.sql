@@ -0,0 +1,9 @@
+SELECT name,
+    description,
+    distiller,
+    type AS typ,
+    region_id,
+    abv,
+    deleted_at IS NOT NULL AS 'deleted!: bool'
+FROM spirits
+WHERE uuid = $1;
\ No newline at end of file
@@ -0,0 +1,4 @@
+SELECT id AS 'id!',
+    name
+FROM spirit_types
+WHERE name = $1;
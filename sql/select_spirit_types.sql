@@ -0,0 +1,4 @@
+SELECT id AS 'id!',
+    name
+FROM spirit_types
+ORDER BY name ASC;
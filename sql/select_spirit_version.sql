@@ -0,0 +1,3 @@
+SELECT version
+FROM spirits
+WHERE uuid = $1;
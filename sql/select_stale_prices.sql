@@ -0,0 +1,9 @@
+SELECT s.uuid AS 'uuid!',
+    s.name AS 'name!',
+    MAX(p.observed_on) AS 'last_observed_on!: String'
+FROM spirits s
+    JOIN price_points p ON p.spirit_id = s.uuid
+WHERE s.deleted_at IS NULL
+GROUP BY s.uuid
+HAVING MAX(p.observed_on) < date('now', '-' || $1 || ' days')
+ORDER BY MAX(p.observed_on) ASC;
@@ -0,0 +1,8 @@
+SELECT value
+FROM store_entries
+WHERE namespace = $1
+    AND key = $2
+    AND (
+        expires_at IS NULL
+        OR expires_at > $3
+    );
@@ -0,0 +1,3 @@
+SELECT COUNT(*) AS 'count!: i64'
+FROM swap_confirmations
+WHERE match_id = $1;
@@ -0,0 +1,10 @@
+SELECT m.id AS 'id!',
+    m.offer_id,
+    m.request_id,
+    m.status,
+    o.user_id AS offerer_id,
+    r.user_id AS requester_id
+FROM swap_matches m
+    JOIN swap_offers o ON o.id = m.offer_id
+    JOIN swap_requests r ON r.id = m.request_id
+WHERE m.id = $1;
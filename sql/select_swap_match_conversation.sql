@@ -0,0 +1,3 @@
+SELECT id AS 'id!'
+FROM conversations
+WHERE swap_match_id = $1;
@@ -0,0 +1,16 @@
+SELECT r.id AS 'id!',
+    r.user_id,
+    u.preferred_username AS 'username?: String',
+    r.spirit_id,
+    s.name AS spirit_name,
+    r.volume_ml,
+    r.notes,
+    r.created_at
+FROM swap_offers o
+    JOIN swap_requests r ON r.spirit_id = o.spirit_id
+    JOIN spirits s ON s.uuid = r.spirit_id
+    LEFT JOIN users u ON u.user_id = r.user_id
+WHERE o.id = $1
+    AND r.status = 'open'
+    AND r.user_id != o.user_id
+ORDER BY r.created_at ASC;
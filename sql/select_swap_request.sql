@@ -0,0 +1,6 @@
+SELECT id AS 'id!',
+    user_id,
+    spirit_id,
+    status
+FROM swap_requests
+WHERE id = $1;
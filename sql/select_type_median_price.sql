@@ -0,0 +1,25 @@
+WITH prices AS (
+    SELECT p.amount,
+        ROW_NUMBER() OVER (
+            ORDER BY p.amount
+        ) AS position,
+        COUNT(*) OVER () AS total
+    FROM price_points p
+        JOIN spirits s ON s.uuid = p.spirit_id
+    WHERE s.type = (
+            SELECT type
+            FROM spirits
+            WHERE uuid = $1
+        )
+        AND s.deleted_at IS NULL
+        AND p.currency = $2
+)
+SELECT (
+        SELECT type
+        FROM spirits
+        WHERE uuid = $1
+    ) AS 'typ!: String',
+    AVG(amount) AS 'median?: f64',
+    COALESCE(MAX(total), 0) AS 'count!: i64'
+FROM prices
+WHERE position IN ((total + 1) / 2, (total + 2) / 2);
@@ -0,0 +1,7 @@
+SELECT COUNT(*) AS 'unread_count!: i64'
+FROM messages m
+    JOIN conversation_participants p ON p.conversation_id = m.conversation_id
+WHERE p.user_id = $1
+    AND m.id > p.last_read_message_id
+    AND m.sender_id != p.user_id
+    AND m.hidden = 0;
@@ -0,0 +1,4 @@
+SELECT COUNT(*) AS count
+FROM notifications
+WHERE user_id = $1
+    AND is_read = 0;
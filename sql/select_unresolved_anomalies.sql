@@ -0,0 +1,10 @@
+SELECT a.id,
+    a.spirit_id,
+    s.name AS 'spirit_name?: String',
+    a.field,
+    a.reason,
+    a.created_at
+FROM spirit_anomalies a
+    LEFT JOIN spirits s ON s.uuid = a.spirit_id
+WHERE a.resolved = 0
+ORDER BY a.created_at ASC;
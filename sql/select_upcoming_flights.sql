@@ -0,0 +1,21 @@
+SELECT f.id AS 'id!',
+    f.title,
+    f.description,
+    (
+        SELECT group_concat(o.name, ', ')
+        FROM (
+                SELECT s.name
+                FROM flight_spirits fs
+                    JOIN spirits s ON s.uuid = fs.spirit_id
+                WHERE fs.flight_id = f.id
+                    AND s.deleted_at IS NULL
+                ORDER BY fs.position
+            ) o
+    ) AS 'spirits?: String',
+    strftime('%Y%m%dT%H%M%SZ', f.tasting_at) AS 'starts_at!: String',
+    strftime('%Y%m%dT%H%M%SZ', f.updated_at) AS 'updated_at!: String'
+FROM flights f
+WHERE f.user_id = $1
+    AND f.tasting_at >= datetime(CURRENT_TIMESTAMP, '-1 day')
+ORDER BY f.tasting_at,
+    f.id;
@@ -2,6 +2,7 @@ SELECT user_id,
     preferred_username,
     email,
     refresh_token_version,
-    role
+    role,
+    email_verified AS 'email_verified: bool'
 FROM users
 WHERE user_id = ?;
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT avatar_sha256
+FROM users
+WHERE user_id = $1;
@@ -0,0 +1,4 @@
+SELECT badge,
+    awarded_at
+FROM user_badges
+WHERE user_id = $1;
@@ -0,0 +1,7 @@
+SELECT b.blocked_id,
+    u.preferred_username AS 'username?: String',
+    b.created_at
+FROM user_blocks b
+    LEFT JOIN users u ON u.user_id = b.blocked_id
+WHERE b.blocker_id = $1
+ORDER BY b.created_at DESC;
@@ -0,0 +1,9 @@
+SELECT COALESCE(p.volume_unit, 'ml') AS 'volume_unit!: String',
+    COALESCE(p.strength_unit, 'abv') AS 'strength_unit!: String',
+    COALESCE(p.locale, 'en-US') AS 'locale!: String',
+    COALESCE(p.theme, 'system') AS 'theme!: String',
+    COALESCE(p.collection_visibility, 'private') AS 'collection_visibility!: String',
+    COALESCE(p.show_activity, 1) AS 'show_activity!: bool'
+FROM users u
+    LEFT JOIN user_preferences p ON p.user_id = u.user_id
+WHERE u.user_id = $1;
@@ -0,0 +1,7 @@
+SELECT u.user_id,
+    u.preferred_username AS username,
+    CASE
+        WHEN u.avatar_sha256 IS NOT NULL THEN '/api/user/' || u.user_id || '/avatar'
+    END AS 'avatar_url?: String'
+FROM users u
+WHERE u.user_id = $1;
@@ -0,0 +1,60 @@
+SELECT (
+        SELECT COUNT(*)
+        FROM swap_matches m
+            JOIN swap_offers o ON o.id = m.offer_id
+            JOIN swap_requests r ON r.id = m.request_id
+        WHERE m.status = 'completed'
+            AND (
+                o.user_id = $1
+                OR r.user_id = $1
+            )
+    ) AS 'completed_swaps!: i64',
+    (
+        SELECT AVG(c.rating)
+        FROM swap_confirmations c
+            JOIN swap_matches m ON m.id = c.match_id
+            JOIN swap_offers o ON o.id = m.offer_id
+            JOIN swap_requests r ON r.id = m.request_id
+        WHERE m.status = 'completed'
+            AND c.user_id != $1
+            AND (
+                o.user_id = $1
+                OR r.user_id = $1
+            )
+    ) AS 'average_swap_rating?: f64',
+    (
+        SELECT COUNT(*)
+        FROM spirit_submissions
+        WHERE user_id = $1
+            AND status = 'approved'
+    ) AS 'approved_submissions!: i64',
+    (
+        SELECT COUNT(*)
+        FROM spirit_submissions
+        WHERE user_id = $1
+            AND status = 'rejected'
+    ) AS 'rejected_submissions!: i64',
+    (
+        SELECT COUNT(*)
+        FROM messages
+        WHERE sender_id = $1
+            AND hidden = 1
+    ) AS 'hidden_messages!: i64',
+    (
+        SELECT COUNT(*)
+        FROM reviews
+        WHERE user_id = $1
+            AND hidden = 1
+    ) AS 'hidden_reviews!: i64',
+    (
+        SELECT COUNT(*)
+        FROM review_votes v
+            JOIN reviews r ON r.id = v.review_id
+        WHERE r.user_id = $1
+    ) AS 'review_votes!: i64',
+    (
+        SELECT AVG(v.helpful)
+        FROM review_votes v
+            JOIN reviews r ON r.id = v.review_id
+        WHERE r.user_id = $1
+    ) AS 'helpful_ratio?: f64';
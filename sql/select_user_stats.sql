@@ -0,0 +1,59 @@
+WITH tried AS (
+    SELECT spirit_id
+    FROM ratings
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM reviews
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM pours
+    WHERE user_id = $1
+    UNION
+    SELECT spirit_id
+    FROM collection_entries
+    WHERE user_id = $1
+        AND (
+            status = 'finished'
+            OR opened = 1
+        )
+),
+tried_spirits AS (
+    SELECT s.distiller,
+        s.region_id
+    FROM tried t
+        JOIN spirits s ON s.uuid = t.spirit_id
+    WHERE s.deleted_at IS NULL
+)
+SELECT (
+        SELECT COUNT(*)
+        FROM collection_entries
+        WHERE user_id = $1
+            AND status = 'owned'
+    ) AS 'bottles_owned!: i64',
+    (
+        SELECT COUNT(*)
+        FROM collection_entries
+        WHERE user_id = $1
+            AND status = 'finished'
+    ) AS 'bottles_finished!: i64',
+    (
+        SELECT COUNT(DISTINCT lower(trim(distiller)))
+        FROM tried_spirits
+        WHERE trim(distiller) != ''
+    ) AS 'distilleries_tried!: i64',
+    (
+        SELECT COUNT(DISTINCT region_id)
+        FROM tried_spirits
+    ) AS 'regions_tried!: i64',
+    (
+        SELECT COUNT(*)
+        FROM ratings
+        WHERE user_id = $1
+    ) AS 'ratings_given!: i64',
+    (
+        SELECT AVG(score)
+        FROM ratings
+        WHERE user_id = $1
+    ) AS 'average_rating?: f64';
@@ -0,0 +1,18 @@
+SELECT id AS 'id!',
+    event,
+    payload,
+    status,
+    attempts,
+    response_status,
+    last_error,
+    next_attempt_at,
+    delivered_at,
+    created_at
+FROM webhook_deliveries
+WHERE webhook_id = $1
+    AND (
+        $2 IS NULL
+        OR status = $2
+    )
+ORDER BY id DESC
+LIMIT $3 OFFSET $4;
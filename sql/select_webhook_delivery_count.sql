@@ -0,0 +1,7 @@
+SELECT COUNT(*) AS count
+FROM webhook_deliveries
+WHERE webhook_id = $1
+    AND (
+        $2 IS NULL
+        OR status = $2
+    );
@@ -0,0 +1,3 @@
+SELECT id
+FROM webhooks
+WHERE id = $1;
@@ -0,0 +1,14 @@
+SELECT s.uuid AS id,
+    s.name,
+    s.description,
+    s.distiller,
+    s.type AS typ,
+    s.region_id,
+    s.abv,
+    s.availability,
+    s.version
+FROM spirits s
+    LEFT JOIN spirit_submissions ss ON ss.spirit_id = s.uuid
+WHERE s.uuid = $1
+    AND COALESCE(ss.status, 'approved') = 'approved'
+    AND s.deleted_at IS NULL;
@@ -0,0 +1,14 @@
+SELECT w.id AS 'id!',
+    w.url,
+    w.events AS 'events!: sqlx::types::Json<Vec<String>>',
+    w.description,
+    w.created_by,
+    w.created_at,
+    (
+        SELECT COUNT(*)
+        FROM webhook_deliveries d
+        WHERE d.webhook_id = w.id
+            AND d.status = 'failed'
+    ) AS 'failed_deliveries!: i64'
+FROM webhooks w
+ORDER BY w.id;
@@ -0,0 +1,9 @@
+SELECT w.spirit_id,
+    s.name AS spirit_name,
+    w.notify AS 'notify: bool',
+    w.note,
+    w.created_at
+FROM wishlist_entries w
+    JOIN spirits s ON s.uuid = w.spirit_id
+WHERE w.user_id = $1
+ORDER BY w.created_at DESC;
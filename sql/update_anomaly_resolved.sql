@@ -0,0 +1,3 @@
+UPDATE spirit_anomalies
+SET resolved = 1
+WHERE id = $1;
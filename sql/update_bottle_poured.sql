@@ -0,0 +1,7 @@
+UPDATE bottles
+SET remaining_ml = MAX(remaining_ml - $4, 0),
+    opened_on = COALESCE(opened_on, date(COALESCE($5, CURRENT_TIMESTAMP)))
+WHERE id = $1
+    AND user_id = $2
+    AND spirit_id = $3
+    AND status = 'held';
@@ -0,0 +1,3 @@
+UPDATE bottles
+SET status = $2
+WHERE id = $1;
@@ -0,0 +1,6 @@
+UPDATE cocktails
+SET name = $2,
+    description = $3,
+    instructions = $4,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = $1;
@@ -0,0 +1,8 @@
+UPDATE bottles
+SET label = $4,
+    volume_ml = $5,
+    remaining_ml = $6,
+    opened_on = $7
+WHERE id = $1
+    AND collection_entry_id = $2
+    AND user_id = $3;
@@ -0,0 +1,10 @@
+UPDATE collection_entries
+SET status = $3,
+    purchase_date = $4,
+    price_paid = $5,
+    opened = $6,
+    fill_level = $7,
+    notes = $8,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = $1
+    AND user_id = $2;
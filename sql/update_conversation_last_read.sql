@@ -0,0 +1,8 @@
+UPDATE conversation_participants
+SET last_read_message_id = (
+        SELECT COALESCE(MAX(id), 0)
+        FROM messages
+        WHERE conversation_id = $1
+    )
+WHERE conversation_id = $1
+    AND user_id = $2;
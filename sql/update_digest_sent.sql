@@ -0,0 +1,3 @@
+UPDATE email_preferences
+SET last_digest_at = CURRENT_TIMESTAMP
+WHERE user_id = $1;
@@ -0,0 +1,4 @@
+UPDATE distillers
+SET latitude = $2,
+    longitude = $3
+WHERE id = $1;
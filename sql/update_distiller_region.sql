@@ -0,0 +1,3 @@
+UPDATE distillers
+SET region_id = $2
+WHERE id = $1;
@@ -0,0 +1,5 @@
+UPDATE email_outbox
+SET attempts = attempts + 1,
+    last_error = $2,
+    next_attempt_at = datetime(CURRENT_TIMESTAMP, '+' || $3 || ' seconds')
+WHERE id = $1;
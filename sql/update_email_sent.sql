@@ -0,0 +1,5 @@
+UPDATE email_outbox
+SET sent_at = CURRENT_TIMESTAMP,
+    attempts = attempts + 1,
+    last_error = NULL
+WHERE id = $1;
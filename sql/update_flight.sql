@@ -0,0 +1,7 @@
+UPDATE flights
+SET title = $2,
+    description = $3,
+    public = $4,
+    tasting_at = $5,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = $1;
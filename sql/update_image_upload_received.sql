@@ -0,0 +1,3 @@
+UPDATE image_uploads
+SET received_bytes = $3
+WHERE id = $1 AND received_bytes = $2;
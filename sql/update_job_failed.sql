@@ -0,0 +1,8 @@
+UPDATE jobs
+SET status = CASE
+        WHEN attempts >= $3 THEN 'dead'
+        ELSE 'queued'
+    END,
+    last_error = $2,
+    run_at = datetime(CURRENT_TIMESTAMP, '+' || $4 || ' seconds')
+WHERE id = $1;
@@ -0,0 +1,3 @@
+UPDATE messages
+SET hidden = 1
+WHERE id = $1;
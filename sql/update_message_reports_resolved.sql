@@ -0,0 +1,3 @@
+UPDATE message_reports
+SET resolved = 1
+WHERE message_id = $1;
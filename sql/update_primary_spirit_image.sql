@@ -0,0 +1,4 @@
+UPDATE spirit_images
+SET is_primary = TRUE
+WHERE id = $1
+    AND spirit_id = $2;
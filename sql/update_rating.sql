@@ -0,0 +1,4 @@
+UPDATE ratings
+SET score = $3
+WHERE user_id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,5 @@
+UPDATE users
+SET refresh_token_version = refresh_token_version + 1
+WHERE user_id = $1
+    AND refresh_token_version = $2
+RETURNING refresh_token_version;
@@ -0,0 +1,4 @@
+UPDATE release_watchers
+SET notified = 1
+WHERE release_id = $1
+    AND user_id = $2;
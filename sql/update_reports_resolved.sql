@@ -0,0 +1,8 @@
+UPDATE reports
+SET status = $3,
+    resolved_by = $4,
+    resolution_note = $5,
+    resolved_at = CURRENT_TIMESTAMP
+WHERE target_type = $1
+    AND target_id = $2
+    AND status = 'open';
@@ -0,0 +1,5 @@
+UPDATE reviews
+SET body = $3,
+    updated_at = CURRENT_TIMESTAMP
+WHERE user_id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,4 @@
+UPDATE reviews
+SET hidden = 1
+WHERE id = $1
+RETURNING spirit_id;
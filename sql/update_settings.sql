@@ -0,0 +1,8 @@
+UPDATE settings
+SET registration_open = $1,
+    moderation_required = $2,
+    default_page_size = $3,
+    maintenance_banner = $4,
+    updated_by = $5,
+    updated_at = CURRENT_TIMESTAMP
+WHERE id = 1;
@@ -0,0 +1,14 @@
+UPDATE spirits
+SET name = $2,
+    description = $3,
+    distiller = $4,
+    type = $5,
+    type_id = $6,
+    region_id = $7,
+    abv = $8,
+    version = version + 1,
+    updated_at = CURRENT_TIMESTAMP
+WHERE uuid = $1
+    AND version = $9
+    AND deleted_at IS NULL
+RETURNING version;
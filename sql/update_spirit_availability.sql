@@ -0,0 +1,6 @@
+UPDATE spirits
+SET availability = $2,
+    updated_at = CURRENT_TIMESTAMP
+WHERE uuid = $1
+    AND deleted_at IS NULL
+    AND availability <> $2;
@@ -0,0 +1,5 @@
+UPDATE spirits_fts
+SET name = $2,
+    distiller = $3,
+    type = $4
+WHERE uuid = $1;
@@ -0,0 +1,5 @@
+UPDATE spirit_images
+SET caption = COALESCE($3, caption),
+    position = COALESCE($4, position)
+WHERE id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,12 @@
+UPDATE spirit_submissions
+SET status = $2,
+    reviewed_by = $3,
+    reviewed_at = CURRENT_TIMESTAMP
+WHERE spirit_id = $1
+    AND status = 'pending'
+RETURNING user_id,
+    (
+        SELECT name
+        FROM spirits
+        WHERE uuid = spirit_id
+    ) AS 'name!: String';
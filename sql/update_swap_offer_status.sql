@@ -0,0 +1,3 @@
+UPDATE swap_offers
+SET status = $2
+WHERE id = $1;
@@ -0,0 +1,3 @@
+UPDATE swap_requests
+SET status = $2
+WHERE id = $1;
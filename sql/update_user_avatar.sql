@@ -0,0 +1,3 @@
+UPDATE users
+SET avatar_sha256 = $2
+WHERE user_id = $1;
@@ -0,0 +1,7 @@
+UPDATE webhook_deliveries
+SET status = 'delivered',
+    attempts = attempts + 1,
+    response_status = $2,
+    last_error = NULL,
+    delivered_at = CURRENT_TIMESTAMP
+WHERE id = $1;
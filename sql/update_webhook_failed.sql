@@ -0,0 +1,10 @@
+UPDATE webhook_deliveries
+SET attempts = attempts + 1,
+    status = CASE
+        WHEN attempts + 1 >= $4 THEN 'failed'
+        ELSE 'pending'
+    END,
+    response_status = $2,
+    last_error = $3,
+    next_attempt_at = datetime(CURRENT_TIMESTAMP, '+' || $5 || ' seconds')
+WHERE id = $1;
@@ -0,0 +1,5 @@
+UPDATE wishlist_entries
+SET notify = $3,
+    note = $4
+WHERE user_id = $1
+    AND spirit_id = $2;
@@ -0,0 +1,13 @@
+INSERT INTO bottle_provenance(
+        bottle_id,
+        acquired_from,
+        acquired_on,
+        price,
+        condition_notes
+    )
+VALUES ($1, $2, $3, $4, $5) ON CONFLICT(bottle_id) DO
+UPDATE
+SET acquired_from = excluded.acquired_from,
+    acquired_on = excluded.acquired_on,
+    price = excluded.price,
+    condition_notes = excluded.condition_notes;
@@ -0,0 +1,7 @@
+INSERT INTO email_preferences(user_id, moderation, price_alerts, weekly_digest)
+VALUES ($1, $2, $3, $4) ON CONFLICT(user_id) DO
+UPDATE
+SET moderation = excluded.moderation,
+    price_alerts = excluded.price_alerts,
+    weekly_digest = excluded.weekly_digest,
+    updated_at = CURRENT_TIMESTAMP;
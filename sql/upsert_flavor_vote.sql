@@ -0,0 +1,5 @@
+INSERT INTO flavor_votes(user_id, spirit_id, dimension, score)
+VALUES ($1, $2, $3, $4) ON CONFLICT(user_id, spirit_id, dimension) DO
+UPDATE
+SET score = excluded.score,
+    updated_at = CURRENT_TIMESTAMP;
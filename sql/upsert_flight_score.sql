@@ -0,0 +1,5 @@
+INSERT INTO flight_scores(flight_id, position, user_id, score)
+VALUES ($1, $2, $3, $4) ON CONFLICT(flight_id, position, user_id) DO
+UPDATE
+SET score = excluded.score,
+    created_at = CURRENT_TIMESTAMP;
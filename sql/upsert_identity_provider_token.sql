@@ -0,0 +1,5 @@
+INSERT INTO identity_provider_tokens (user_id, refresh_token)
+VALUES ($1, $2) ON CONFLICT(user_id) DO
+UPDATE
+SET refresh_token = excluded.refresh_token,
+    updated_at = CURRENT_TIMESTAMP;
\ No newline at end of file
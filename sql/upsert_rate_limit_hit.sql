@@ -0,0 +1,9 @@
+INSERT INTO rate_limit_windows(key, window_start, hits)
+VALUES ($1, $2, 1) ON CONFLICT(key) DO
+UPDATE
+SET hits = CASE
+        WHEN window_start = excluded.window_start THEN hits + 1
+        ELSE 1
+    END,
+    window_start = excluded.window_start
+RETURNING hits;
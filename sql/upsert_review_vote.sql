@@ -0,0 +1,5 @@
+INSERT INTO review_votes(review_id, user_id, helpful)
+VALUES ($1, $2, $3) ON CONFLICT(review_id, user_id) DO
+UPDATE
+SET helpful = excluded.helpful,
+    created_at = CURRENT_TIMESTAMP;
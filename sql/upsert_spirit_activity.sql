@@ -0,0 +1,4 @@
+INSERT INTO spirit_activity(spirit_id, day, kind, count)
+VALUES ($1, date('now'), $2, 1) ON CONFLICT(spirit_id, day, kind) DO
+UPDATE
+SET count = count + 1;
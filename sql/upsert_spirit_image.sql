@@ -0,0 +1,44 @@
+INSERT INTO spirit_images(
+        id,
+        spirit_id,
+        uploaded_by,
+        caption,
+        position,
+        is_primary,
+        content_type,
+        size_bytes,
+        width,
+        height,
+        sha256,
+        phash
+    )
+VALUES (
+        $1,
+        $2,
+        $3,
+        $4,
+        (
+            SELECT COALESCE(MAX(position) + 1, 0)
+            FROM spirit_images
+            WHERE spirit_id = $2
+        ),
+        NOT EXISTS (
+            SELECT 1
+            FROM spirit_images
+            WHERE spirit_id = $2
+                AND is_primary
+        ),
+        $5,
+        $6,
+        $7,
+        $8,
+        $9,
+        $10
+    ) ON CONFLICT(id) DO
+UPDATE
+SET content_type = excluded.content_type,
+    size_bytes = excluded.size_bytes,
+    width = excluded.width,
+    height = excluded.height,
+    sha256 = excluded.sha256,
+    phash = excluded.phash;
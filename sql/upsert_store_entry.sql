@@ -0,0 +1,5 @@
+INSERT INTO store_entries(namespace, key, value, expires_at)
+VALUES ($1, $2, $3, $4) ON CONFLICT(namespace, key) DO
+UPDATE
+SET value = excluded.value,
+    expires_at = excluded.expires_at;
@@ -0,0 +1,16 @@
+INSERT INTO users (
+        user_id,
+        preferred_username,
+        email,
+        refresh_token_version,
+        role,
+        email_verified,
+        last_login_at
+    )
+VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP) ON CONFLICT(user_id) DO
+UPDATE
+SET preferred_username = excluded.preferred_username,
+    email = excluded.email,
+    role = excluded.role,
+    email_verified = excluded.email_verified,
+    last_login_at = excluded.last_login_at;
\ No newline at end of file
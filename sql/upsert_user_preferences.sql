@@ -0,0 +1,18 @@
+INSERT INTO user_preferences(
+        user_id,
+        volume_unit,
+        strength_unit,
+        locale,
+        theme,
+        collection_visibility,
+        show_activity
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT(user_id) DO
+UPDATE
+SET volume_unit = excluded.volume_unit,
+    strength_unit = excluded.strength_unit,
+    locale = excluded.locale,
+    theme = excluded.theme,
+    collection_visibility = excluded.collection_visibility,
+    show_activity = excluded.show_activity,
+    updated_at = CURRENT_TIMESTAMP;
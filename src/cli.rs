@@ -0,0 +1,179 @@
+//! Command line interface. Without a subcommand the binary serves the site; the other
+//! subcommands run an admin operation against the configured database and exit.
+
+use std::io;
+
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+#[cfg(feature = "postgres")]
+use sqlx::PgPool;
+use sqlx::SqlitePool;
+use tokio::{
+    io::{stdout, AsyncWriteExt},
+    process,
+};
+use uuid::Uuid;
+
+#[cfg(feature = "postgres")]
+use crate::repositories::PostgresRepositories;
+use crate::{
+    config::{AppConfig, DatabaseBackend},
+    infra::ImageStore,
+    repositories::{Repositories, UserRepository},
+    seed,
+    services::{
+        spirit_export, sweep_orphaned_images, ExportFormat, StrengthUnit, WebError, WebResult,
+        APP_ADMIN_ROLE,
+    },
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the site. The default when no subcommand is given.
+    Serve,
+    /// Apply any pending database migrations.
+    Migrate,
+    /// Load sample users, distillers and spirits for development and demos.
+    Seed,
+    /// Give the admin role to a user who has signed in at least once.
+    CreateAdmin {
+        /// The user's subject, as issued by the identity provider.
+        sub: String,
+    },
+    /// Generate new token signing secrets. Once they're configured, everyone has to sign in
+    /// again.
+    RotateSecrets,
+    /// Delete image files nothing refers to and image records whose file is missing.
+    PruneImages,
+    /// Write the full spirit catalog to stdout.
+    Export {
+        #[arg(long, value_enum, default_value = "json")]
+        format: ExportFormat,
+    },
+    /// Regenerate the offline query data in `.sqlx` against the configured database, after
+    /// changing a query or migration. Runs `cargo sqlx prepare` in the source tree this binary
+    /// was built from, so it needs sqlx-cli installed.
+    Prepare,
+}
+
+async fn create_admin(users: &dyn UserRepository, sub: &str) -> WebResult<()> {
+    // The user row is only created on first sign in, along with the username and email.
+    if !users.set_role(sub, APP_ADMIN_ROLE).await? {
+        return Err(WebError::InvalidInput(format!(
+            "No user '{}'. They need to sign in once first.",
+            sub
+        )));
+    }
+    tracing::info!("{} is now an admin", sub);
+    Ok(())
+}
+
+/// Runs an admin subcommand. Migrations have already been applied by the time this is called.
+pub async fn run(command: Command, config: &AppConfig, database: &SqlitePool) -> WebResult<()> {
+    match command {
+        Command::Serve => unreachable!("serving is handled by main"),
+        Command::Migrate => tracing::info!("Migrations are up to date"),
+        Command::Seed => {
+            let report = seed::seed(database).await?;
+            tracing::info!("Seeded {:?}", report);
+        }
+        Command::CreateAdmin { sub } => {
+            create_admin(&*Repositories::sqlite(database).users, &sub).await?;
+        }
+        Command::RotateSecrets => {
+            // The secrets come from the config file or environment, which this can't rewrite,
+            // so the operator sets the new values and restarts.
+            let mut stdout = stdout();
+            for name in ["ACCESS_TOKEN_HMAC_SECRET", "REFRESH_TOKEN_HMAC_SECRET"] {
+                let secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+                stdout
+                    .write_all(format!("{}={}\n", name, secret).as_bytes())
+                    .await?;
+            }
+            stdout.flush().await?;
+        }
+        Command::PruneImages => {
+            let images = ImageStore::new(&config.storage.images_path);
+            let sweep = sweep_orphaned_images(database, &images).await?;
+            tracing::info!(
+                "Removed {} files and {} records",
+                sweep.removed_files.len(),
+                sweep.removed_records.len()
+            );
+        }
+        Command::Export { format } => {
+            let body = spirit_export(database.clone(), format, StrengthUnit::default());
+            let mut chunks = body.into_data_stream();
+            let mut stdout = stdout();
+            while let Some(chunk) = chunks.next().await {
+                let chunk = chunk.map_err(io::Error::other)?;
+                stdout.write_all(&chunk).await?;
+            }
+            stdout.flush().await?;
+        }
+        Command::Prepare => {
+            let Some(DatabaseBackend::Sqlite(path)) = config.database.backend() else {
+                unreachable!("only SQLite commands are run here");
+            };
+            // Every target and the test factories, so queries only they use are kept too.
+            let status = process::Command::new("cargo")
+                .args([
+                    "sqlx",
+                    "prepare",
+                    "--",
+                    "--all-targets",
+                    "--features",
+                    "testing",
+                ])
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .env(
+                    "DATABASE_URL",
+                    format!("sqlite://{}", path.display()),
+                )
+                .env_remove("SQLX_OFFLINE")
+                .status()
+                .await?;
+            if !status.success() {
+                return Err(WebError::InvalidInput(format!(
+                    "cargo sqlx prepare failed ({}). Is sqlx-cli installed?",
+                    status
+                )));
+            }
+            tracing::info!("Offline query data is up to date");
+        }
+    }
+    Ok(())
+}
+
+/// Runs an admin subcommand against PostgreSQL, applying its migrations first. Only the user
+/// and spirit repositories have been ported, so only the commands needing nothing else run.
+#[cfg(feature = "postgres")]
+pub async fn run_postgres(command: Command, url: &str) -> WebResult<()> {
+    let database = PgPool::connect(url).await?;
+    sqlx::migrate!("./migrations/postgres")
+        .run(&database)
+        .await
+        .map_err(sqlx::Error::from)?;
+
+    match command {
+        Command::Migrate => tracing::info!("Migrations are up to date"),
+        Command::CreateAdmin { sub } => {
+            create_admin(&*PostgresRepositories::new(&database).users, &sub).await?;
+        }
+        _ => {
+            return Err(WebError::InvalidInput(
+                "Only the migrate and create-admin commands run against PostgreSQL so far. \
+                 Point database.url at SQLite for everything else."
+                    .into(),
+            ))
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+//! The current time, behind a trait so token expiry and refresh can be exercised without
+//! waiting for real time to pass.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub trait Clock: Send + Sync {
+    /// Time since the Unix epoch.
+    fn now(&self) -> Duration;
+}
+
+/// The system's wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        // Only a system clock set before 1970 fails, and treating that as the epoch is fine.
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
@@ -1,9 +1,22 @@
+use std::time::Duration;
+
 use tower_cookies::{cookie::SameSite, Cookie};
 
-pub fn create_token_cookie<'a>(key: &'a str, token: String) -> Cookie<'a> {
+/// Without a `max_age` the cookie only lasts until the browser is closed.
+pub fn create_token_cookie<'a>(
+    key: &'a str,
+    token: String,
+    secure: bool,
+    max_age: Option<Duration>,
+) -> Cookie<'a> {
     let mut cookie = Cookie::new(key, token);
     cookie.set_path("/");
-    cookie.set_secure(true);
+    if let Some(max_age) = max_age {
+        cookie.set_max_age(tower_cookies::cookie::time::Duration::seconds(
+            max_age.as_secs() as i64,
+        ));
+    }
+    cookie.set_secure(secure);
     cookie.set_same_site(SameSite::Lax);
     cookie.set_http_only(true);
     cookie
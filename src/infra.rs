@@ -0,0 +1,120 @@
+mod images;
+mod memory;
+mod redis;
+mod session;
+mod sqlite;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use thiserror::Error;
+
+pub use images::ImageStore;
+pub use session::SessionStoreAdapter;
+
+use crate::config::{StorageBackendKind, StorageConfig};
+
+use self::{memory::MemoryStore, redis::RedisStore, sqlite::SqliteStore};
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Error querying database")]
+    Database(#[from] sqlx::Error),
+    #[error("Error querying redis")]
+    Redis(#[from] ::redis::RedisError),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Persists serialized login sessions.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn load(&self, id: &str) -> StoreResult<Option<Vec<u8>>>;
+    async fn save(&self, id: &str, data: &[u8], ttl: Duration) -> StoreResult<()>;
+    async fn delete(&self, id: &str) -> StoreResult<()>;
+    /// Drops expired sessions that were never touched again, returning how many went.
+    /// Backends that share one table for everything drop expired cache entries too.
+    async fn remove_expired(&self) -> StoreResult<u64>;
+}
+
+/// Short-lived cached values that can always be recomputed.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>>;
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()>;
+    async fn delete(&self, key: &str) -> StoreResult<()>;
+}
+
+/// The state of a rate limit window after a hit.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitWindow {
+    /// Hits in the current window, including this one.
+    pub hits: u64,
+    /// How long until the window ends and the count starts over.
+    pub resets_in: Duration,
+}
+
+/// Fixed-window hit counters.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a hit against `key` in the current window.
+    async fn hit(&self, key: &str, window: Duration) -> StoreResult<RateLimitWindow>;
+}
+
+/// Where sessions, cache entries and rate limit counters live, chosen with `storage.backend`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Per-process storage. The default, and only suitable for a single instance.
+    Memory,
+    /// The app database, so state survives restarts.
+    Sqlite,
+    /// A shared redis at `storage.redis_url`, for running several instances.
+    Redis(String),
+}
+
+impl StorageBackend {
+    pub fn from_config(storage: &StorageConfig) -> Self {
+        match storage.backend {
+            StorageBackendKind::Memory => Self::Memory,
+            StorageBackendKind::Sqlite => Self::Sqlite,
+            // Checked when the configuration is loaded.
+            StorageBackendKind::Redis => Self::Redis(storage.redis_url.clone().unwrap_or_default()),
+        }
+    }
+}
+
+const SESSION_NAMESPACE: &str = "session";
+const CACHE_NAMESPACE: &str = "cache";
+
+#[derive(Clone)]
+pub struct Stores {
+    pub sessions: Arc<dyn SessionStore>,
+    pub cache: Arc<dyn CacheStore>,
+    pub rate_limits: Arc<dyn RateLimitStore>,
+}
+
+impl Stores {
+    pub async fn new(backend: &StorageBackend, database: &SqlitePool) -> StoreResult<Self> {
+        Ok(match backend {
+            StorageBackend::Memory => Self {
+                sessions: Arc::new(MemoryStore::default()),
+                cache: Arc::new(MemoryStore::default()),
+                rate_limits: Arc::new(MemoryStore::default()),
+            },
+            StorageBackend::Sqlite => Self {
+                sessions: Arc::new(SqliteStore::new(database.clone(), SESSION_NAMESPACE)),
+                cache: Arc::new(SqliteStore::new(database.clone(), CACHE_NAMESPACE)),
+                rate_limits: Arc::new(SqliteStore::new(database.clone(), "")),
+            },
+            StorageBackend::Redis(url) => {
+                let connection = RedisStore::connect(url).await?;
+                Self {
+                    sessions: Arc::new(connection.with_namespace(SESSION_NAMESPACE)),
+                    cache: Arc::new(connection.with_namespace(CACHE_NAMESPACE)),
+                    rate_limits: Arc::new(connection.with_namespace("rate_limit")),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tokio::fs;
+
+/// A file found by [`ImageStore::list`].
+pub struct StoredFile {
+    /// The file's key, to hand back to the store.
+    pub key: String,
+    /// The file's name without its extension.
+    pub stem: String,
+    pub modified: Option<SystemTime>,
+}
+
+/// Where spirit images, their variants and avatars are kept, under `storage.images_path`.
+/// Files are addressed by keys relative to that directory, such as `thumbnails/<id>.webp`.
+#[derive(Debug, Clone)]
+pub struct ImageStore {
+    root: PathBuf,
+}
+
+impl ImageStore {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_owned(),
+        }
+    }
+
+    /// The file on disk for `key`, for streaming it or handing it to the decoder.
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    /// Writes `data` next to its final name and renames it into place, so a request never
+    /// sees half a file.
+    pub async fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let partial_path = self.path(&format!("{}.part", key));
+        fs::write(&partial_path, data).await?;
+        fs::rename(&partial_path, &path).await
+    }
+
+    /// Moves a received file into the store. `source` must be on the same filesystem.
+    pub async fn put_file(&self, key: &str, source: &Path) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(source, &path).await
+    }
+
+    pub async fn exists(&self, key: &str) -> io::Result<bool> {
+        fs::try_exists(self.path(key)).await
+    }
+
+    /// The file's size in bytes, or `None` if there isn't one.
+    pub async fn size(&self, key: &str) -> io::Result<Option<u64>> {
+        match fs::metadata(self.path(key)).await {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn remove(&self, key: &str) -> io::Result<()> {
+        fs::remove_file(self.path(key)).await
+    }
+
+    /// Removes a file, logging rather than failing since it only leaves clutter behind.
+    pub async fn discard(&self, key: &str) {
+        if let Err(e) = self.remove(key).await {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::warn!("Could not remove {}: {}", key, e);
+            }
+        }
+    }
+
+    /// The files directly inside `directory`, or the store's top level without one. A missing
+    /// directory has no files.
+    pub async fn list(&self, directory: Option<&str>) -> io::Result<Vec<StoredFile>> {
+        let path = directory.map_or_else(|| self.root.clone(), |directory| self.path(directory));
+        let mut entries = match fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(stem) = Path::new(&name).file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            files.push(StoredFile {
+                key: match directory {
+                    Some(directory) => format!("{}/{}", directory, name),
+                    None => name.clone(),
+                },
+                stem: stem.to_owned(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        Ok(files)
+    }
+}
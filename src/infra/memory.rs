@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{CacheStore, RateLimitStore, RateLimitWindow, SessionStore, StoreResult};
+
+/// Keeps everything in process memory. Expired entries are dropped when next touched.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+    windows: Mutex<HashMap<String, (Instant, u64)>>,
+}
+
+impl MemoryStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((value, expires_at)) if *expires_at > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_owned(), (value.to_vec(), Instant::now() + ttl));
+    }
+
+    fn delete(&self, key: &str) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+    }
+
+    fn remove_expired(&self) -> u64 {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let before = entries.len();
+        let now = Instant::now();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        (before - entries.len()) as u64
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, id: &str) -> StoreResult<Option<Vec<u8>>> {
+        Ok(self.get(id))
+    }
+
+    async fn save(&self, id: &str, data: &[u8], ttl: Duration) -> StoreResult<()> {
+        self.set(id, data, ttl);
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> StoreResult<()> {
+        MemoryStore::delete(self, id);
+        Ok(())
+    }
+
+    async fn remove_expired(&self) -> StoreResult<u64> {
+        Ok(MemoryStore::remove_expired(self))
+    }
+}
+
+#[async_trait]
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        Ok(MemoryStore::get(self, key))
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()> {
+        MemoryStore::set(self, key, value, ttl);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        MemoryStore::delete(self, key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn hit(&self, key: &str, window: Duration) -> StoreResult<RateLimitWindow> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let (started_at, hits) = windows.entry(key.to_owned()).or_insert((now, 0));
+        if now.duration_since(*started_at) >= window {
+            *started_at = now;
+            *hits = 0;
+        }
+        *hits += 1;
+        Ok(RateLimitWindow {
+            hits: *hits,
+            resets_in: window.saturating_sub(now.duration_since(*started_at)),
+        })
+    }
+}
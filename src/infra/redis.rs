@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands, Client};
+
+use super::{CacheStore, RateLimitStore, RateLimitWindow, SessionStore, StoreResult};
+
+/// Stores entries in redis under `wol:<namespace>:<key>`, letting redis handle expiry.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    namespace: &'static str,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str) -> StoreResult<Self> {
+        let connection = ConnectionManager::new(Client::open(url)?).await?;
+        Ok(Self {
+            connection,
+            namespace: "",
+        })
+    }
+
+    pub fn with_namespace(&self, namespace: &'static str) -> Self {
+        Self {
+            connection: self.connection.clone(),
+            namespace,
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("wol:{}:{}", self.namespace, key)
+    }
+
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        Ok(self.connection.clone().get(self.key(key)).await?)
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()> {
+        self.connection
+            .clone()
+            .set_ex::<_, _, ()>(self.key(key), value, ttl.as_secs().max(1))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        self.connection.clone().del::<_, ()>(self.key(key)).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisStore {
+    async fn load(&self, id: &str) -> StoreResult<Option<Vec<u8>>> {
+        self.get(id).await
+    }
+
+    async fn save(&self, id: &str, data: &[u8], ttl: Duration) -> StoreResult<()> {
+        self.set(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str) -> StoreResult<()> {
+        RedisStore::delete(self, id).await
+    }
+
+    /// Redis expires keys itself.
+    async fn remove_expired(&self) -> StoreResult<u64> {
+        Ok(0)
+    }
+}
+
+#[async_trait]
+impl CacheStore for RedisStore {
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        RedisStore::get(self, key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()> {
+        RedisStore::set(self, key, value, ttl).await
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        RedisStore::delete(self, key).await
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn hit(&self, key: &str, window: Duration) -> StoreResult<RateLimitWindow> {
+        let key = self.key(key);
+        // NX keeps the expiry from sliding forward on every hit.
+        let (hits, _, ttl): (u64, bool, i64) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(window.as_secs().max(1))
+            .arg("NX")
+            .ttl(&key)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(RateLimitWindow {
+            hits,
+            // Negative when the key has no expiry, which the EXPIRE above rules out.
+            resets_in: Duration::from_secs(ttl.max(0) as u64),
+        })
+    }
+}
@@ -0,0 +1,52 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tower_sessions::{
+    cookie::time::OffsetDateTime,
+    session::{Id, Record},
+    session_store::{self, Error},
+};
+
+use super::SessionStore;
+
+/// Lets `tower_sessions` keep its sessions in whichever [`SessionStore`] is configured.
+#[derive(Clone)]
+pub struct SessionStoreAdapter(pub Arc<dyn SessionStore>);
+
+impl fmt::Debug for SessionStoreAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionStoreAdapter")
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl session_store::SessionStore for SessionStoreAdapter {
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_vec(record).map_err(|e| Error::Encode(e.to_string()))?;
+        let ttl = (record.expiry_date - OffsetDateTime::now_utc())
+            .whole_seconds()
+            .max(0) as u64;
+        self.0
+            .save(&record.id.to_string(), &data, Duration::from_secs(ttl))
+            .await
+            .map_err(|e| Error::Backend(e.to_string()))
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let data = self
+            .0
+            .load(&session_id.to_string())
+            .await
+            .map_err(|e| Error::Backend(e.to_string()))?;
+        data.map(|data| serde_json::from_slice(&data).map_err(|e| Error::Decode(e.to_string())))
+            .transpose()
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        self.0
+            .delete(&session_id.to_string())
+            .await
+            .map_err(|e| Error::Backend(e.to_string()))
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+
+use super::{CacheStore, RateLimitStore, RateLimitWindow, SessionStore, StoreResult};
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Stores entries in the app database, keeping sessions and cache entries apart by namespace.
+pub struct SqliteStore {
+    database: SqlitePool,
+    namespace: &'static str,
+}
+
+impl SqliteStore {
+    pub fn new(database: SqlitePool, namespace: &'static str) -> Self {
+        Self {
+            database,
+            namespace,
+        }
+    }
+
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        let now = unix_now();
+        Ok(
+            sqlx::query_file!("sql/select_store_entry.sql", self.namespace, key, now)
+                .fetch_optional(&self.database)
+                .await?
+                .map(|row| row.value),
+        )
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()> {
+        let expires_at = unix_now() + ttl.as_secs() as i64;
+        sqlx::query_file!(
+            "sql/upsert_store_entry.sql",
+            self.namespace,
+            key,
+            value,
+            expires_at
+        )
+        .execute(&self.database)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        sqlx::query_file!("sql/delete_store_entry.sql", self.namespace, key)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqliteStore {
+    async fn load(&self, id: &str) -> StoreResult<Option<Vec<u8>>> {
+        self.get(id).await
+    }
+
+    async fn save(&self, id: &str, data: &[u8], ttl: Duration) -> StoreResult<()> {
+        self.set(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str) -> StoreResult<()> {
+        SqliteStore::delete(self, id).await
+    }
+
+    async fn remove_expired(&self) -> StoreResult<u64> {
+        let now = unix_now();
+        Ok(
+            sqlx::query_file!("sql/delete_expired_store_entries.sql", now)
+                .execute(&self.database)
+                .await?
+                .rows_affected(),
+        )
+    }
+}
+
+#[async_trait]
+impl CacheStore for SqliteStore {
+    async fn get(&self, key: &str) -> StoreResult<Option<Vec<u8>>> {
+        SqliteStore::get(self, key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> StoreResult<()> {
+        SqliteStore::set(self, key, value, ttl).await
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        SqliteStore::delete(self, key).await
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for SqliteStore {
+    async fn hit(&self, key: &str, window: Duration) -> StoreResult<RateLimitWindow> {
+        let window_secs = window.as_secs().max(1) as i64;
+        let now = unix_now();
+        let window_start = now / window_secs * window_secs;
+        let hits = sqlx::query_file!("sql/upsert_rate_limit_hit.sql", key, window_start)
+            .fetch_one(&self.database)
+            .await?
+            .hits;
+        Ok(RateLimitWindow {
+            hits: hits as u64,
+            resets_in: Duration::from_secs((window_start + window_secs - now) as u64),
+        })
+    }
+}
@@ -1,5 +1,13 @@
 mod jwt;
 mod jwk;
+mod signed_url;
 
 pub use jwk::{JWKCertificate, KeycloakIDClaims, verify_jwt};
-pub use jwt::{TokenState, User, generate_access_and_refresh_tokens, verify_tokens};
\ No newline at end of file
+pub use jwt::{
+    AuthContext, TokenExpiry, TokenState, User, generate_access_and_refresh_tokens,
+    refresh_token_version, verify_tokens,
+};
+pub use signed_url::{
+    sign_calendar_token, sign_slug, sign_url, verify_calendar_token, verify_signed_url,
+    verify_slug,
+};
\ No newline at end of file
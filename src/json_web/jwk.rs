@@ -21,7 +21,7 @@ pub struct KeycloakIDClaims {
     acr: String,           // Authentication context class
     sid: String,           // Session ID
     at_hash: String,       // Access Token's hash
-    email_verified: bool,
+    pub email_verified: bool,
     name: String,
     pub preferred_username: String,
     given_name: String,
@@ -84,7 +84,7 @@ fn parse_header(jwt: &str) -> VerificationResult<jsonwebtoken::Header> {
     };
 
     Ok(serde_json::from_slice::<jsonwebtoken::Header>(
-        &general_purpose::STANDARD_NO_PAD.decode(header_b64).unwrap(),
+        &general_purpose::STANDARD_NO_PAD.decode(header_b64)?,
     )?)
 }
 
@@ -97,8 +97,11 @@ where
         let decoding_key = DecodingKey::from_secret(hmac.as_bytes());
         let mut validation = Validation::new(header.alg);
         validation.set_audience(&[audience]);
+        // jsonwebtoken only knows the system time, so callers check `exp` against the app's
+        // clock. It still has to be present.
+        validation.validate_exp = false;
         // Decode the token and get the claims
-        Ok(decode::<T>(&jwt, &decoding_key, &validation)?)
+        Ok(decode::<T>(jwt, &decoding_key, &validation)?)
     } else {
         Err(VerificationError::UnknownAlgorithm)
     }
@@ -123,5 +126,5 @@ where
     validation.set_audience(&[audience]);
 
     // Decode the token and get the claims
-    Ok(decode::<T>(&jwt, &decoding_key, &validation)?)
+    Ok(decode::<T>(jwt, &decoding_key, &validation)?)
 }
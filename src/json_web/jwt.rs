@@ -1,15 +1,32 @@
-use std::time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH};
+use std::time::Duration;
 
 use jsonwebtoken::{EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
-use crate::WaterOfLifeState;
+use crate::{
+    clock::Clock,
+    config::TokenConfig,
+    permissions::Permissions,
+    security::{SecurityEvent, SecurityEventKind},
+    WaterOfLifeState,
+};
 
 use super::jwk::verfy_jwt_hmac;
 
+/// Tokens are still accepted this long after they expire, to allow for clock skew. The same as
+/// jsonwebtoken's default.
+const EXPIRY_LEEWAY: Duration = Duration::from_secs(60);
+
 trait Claim {
-    fn new(aud: &str, sub: &str, role: &str, expiration: JWTExpiration<usize>) -> Self;
+    fn new(
+        aud: &str,
+        sub: &str,
+        role: &str,
+        version: i64,
+        remember_me: bool,
+        expiration: JWTExpiration<usize>,
+    ) -> Self;
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,20 +48,39 @@ impl CommonClaims {
             sub: sub.into(),
         }
     }
+
+    /// Whether the token had expired at `now`, allowing for [`EXPIRY_LEEWAY`].
+    fn is_expired(&self, now: Duration) -> bool {
+        (self.exp as u64).saturating_add(EXPIRY_LEEWAY.as_secs()) < now.as_secs()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshTokenClaims {
     #[serde(flatten)]
     common: CommonClaims,
+    /// The user's refresh token version when it was issued. Moving the stored version on
+    /// revokes every refresh token issued before.
     version: i64,
+    /// Carried over to the tokens issued on refresh, so they last as long as the first ones.
+    /// Missing from tokens issued before it was added.
+    #[serde(default)]
+    remember_me: bool,
 }
 
 impl Claim for RefreshTokenClaims {
-    fn new(aud: &str, sub: &str, _role: &str, expiration: JWTExpiration<usize>) -> Self {
+    fn new(
+        aud: &str,
+        sub: &str,
+        _role: &str,
+        version: i64,
+        remember_me: bool,
+        expiration: JWTExpiration<usize>,
+    ) -> Self {
         Self {
             common: CommonClaims::new(aud, sub, expiration),
-            version: 1,
+            version,
+            remember_me,
         }
     }
 }
@@ -58,7 +94,14 @@ pub struct AccessTokenClaims {
 }
 
 impl Claim for AccessTokenClaims {
-    fn new(aud: &str, sub: &str, role: &str, expiration: JWTExpiration<usize>) -> Self {
+    fn new(
+        aud: &str,
+        sub: &str,
+        role: &str,
+        _version: i64,
+        _remember_me: bool,
+        expiration: JWTExpiration<usize>,
+    ) -> Self {
         Self {
             common: CommonClaims::new(aud, sub, expiration),
             role: role.to_owned(),
@@ -68,9 +111,11 @@ impl Claim for AccessTokenClaims {
 }
 
 pub enum TokenState {
-    Valid(String),
+    Valid(String, TokenExpiry),
     Invalid,
-    RequiresRefresh(String, User),
+    /// The access token has expired but the refresh token is good. Also says whether the user
+    /// asked to be remembered when they signed in.
+    RequiresRefresh(String, User, bool),
 }
 
 #[derive(Debug, FromRow, Clone)]
@@ -80,6 +125,38 @@ pub struct User {
     pub email: String,
     pub refresh_token_version: i64,
     pub role: String,
+    /// Whether Keycloak has verified `email`.
+    pub email_verified: bool,
+}
+
+/// When a request's tokens expire, as Unix timestamps. The session lapses once the refresh
+/// token expires.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TokenExpiry {
+    pub access_token_expires_at: u64,
+    pub refresh_token_expires_at: u64,
+}
+
+impl TokenExpiry {
+    /// The expiry of tokens issued now by [`generate_access_and_refresh_tokens`].
+    pub fn issued(clock: &dyn Clock, tokens: &TokenConfig, remember_me: bool) -> Self {
+        let expires_at = |lifetime| calculate_expiration(clock, lifetime).expires_at as u64;
+        Self {
+            access_token_expires_at: expires_at(tokens.access_token_lifetime()),
+            refresh_token_expires_at: expires_at(tokens.refresh_token_lifetime(remember_me)),
+        }
+    }
+}
+
+/// Set on requests that got through authentication, next to the [`User`], for handlers that
+/// need to know about the session as well as who it belongs to.
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub user: User,
+    /// Whether the access token had expired and new tokens were set on this response.
+    pub refreshed: bool,
+    pub expiry: TokenExpiry,
+    pub permissions: Permissions,
 }
 
 pub async fn verify_tokens(
@@ -87,80 +164,119 @@ pub async fn verify_tokens(
     refresh_token: &str,
     state: &WaterOfLifeState,
 ) -> TokenState {
-    if let Ok(access_token_claims) = verfy_jwt_hmac::<AccessTokenClaims>(
+    let now = state.clock.now();
+    if let Some(access_token_claims) = verfy_jwt_hmac::<AccessTokenClaims>(
         access_token,
-        &state.client_id,
-        &state.access_token_hmac_secret,
-    ) {
-        return TokenState::Valid(access_token_claims.claims.common.sub);
+        &state.config.oidc.client_id,
+        &state.config.tokens.access_token_hmac_secret,
+    )
+    .ok()
+    .filter(|token| !token.claims.common.is_expired(now))
+    {
+        let access_token_expires_at = access_token_claims.claims.common.exp as u64;
+        // Only read for its expiry. It's checked properly when it's used to refresh, and if it
+        // couldn't be the session ends with the access token.
+        let refresh_token_expires_at = verfy_jwt_hmac::<RefreshTokenClaims>(
+            refresh_token,
+            &state.config.oidc.client_id,
+            &state.config.tokens.refresh_token_hmac_secret,
+        )
+        .map_or(access_token_expires_at, |token| token.claims.common.exp as u64);
+        return TokenState::Valid(
+            access_token_claims.claims.common.sub,
+            TokenExpiry {
+                access_token_expires_at,
+                refresh_token_expires_at,
+            },
+        );
     }
 
-    if let Ok(refresh_token_claims) = verfy_jwt_hmac::<RefreshTokenClaims>(
+    if let Some(refresh_token_claims) = verfy_jwt_hmac::<RefreshTokenClaims>(
         refresh_token,
-        &state.client_id,
-        &state.refresh_token_hmac_secret,
-    ) {
-        let maybe_user = sqlx::query_file_as!(
-            User,
-            "sql/select_user.sql",
-            refresh_token_claims.claims.common.sub
-        )
-        .fetch_one(&state.database)
-        .await;
-
-        if maybe_user.is_err() {
+        &state.config.oidc.client_id,
+        &state.config.tokens.refresh_token_hmac_secret,
+    )
+    .ok()
+    .filter(|token| !token.claims.common.is_expired(now))
+    {
+        let subject = &refresh_token_claims.claims.common.sub;
+        let Ok(Some(user)) = state.repositories.users.find(subject).await else {
             return TokenState::Invalid;
-        }
-
-        let user = maybe_user.unwrap();
+        };
         if refresh_token_claims.claims.version == user.refresh_token_version {
-            return TokenState::RequiresRefresh(refresh_token_claims.claims.common.sub, user);
+            return TokenState::RequiresRefresh(
+                refresh_token_claims.claims.common.sub,
+                user,
+                refresh_token_claims.claims.remember_me,
+            );
         }
+
+        // A correctly signed refresh token from an older version has been revoked, so
+        // someone is replaying it.
+        state.security.emit(SecurityEvent::new(
+            SecurityEventKind::RefreshTokenReuse,
+            Some(&user.user_id),
+            format!(
+                "Refresh token version {} presented, current version is {}",
+                refresh_token_claims.claims.version, user.refresh_token_version
+            ),
+        ));
     }
 
     TokenState::Invalid
 }
 
-fn generate_token<T>(
-    secret: &str,
-    client_id: &str,
-    subject: &str,
-    role: &str,
-    expires_in: Duration,
-) -> Option<String>
-where
-    T: Claim + Serialize,
-{
+/// The subject and version of a correctly signed refresh token, whether or not it has expired.
+pub fn refresh_token_version(
+    refresh_token: &str,
+    state: &WaterOfLifeState,
+) -> Option<(String, i64)> {
+    let token = verfy_jwt_hmac::<RefreshTokenClaims>(
+        refresh_token,
+        &state.config.oidc.client_id,
+        &state.config.tokens.refresh_token_hmac_secret,
+    )
+    .ok()?;
+    Some((token.claims.common.sub, token.claims.version))
+}
+
+fn encode_token<T: Serialize>(secret: &str, claims: &T) -> Option<String> {
     let token_encoding_key = EncodingKey::from_secret(secret.as_bytes());
-    let token_expiration = calculate_expiration(expires_in).ok()?;
-    let token_claims = T::new(client_id, subject, role, token_expiration.clone());
-    jsonwebtoken::encode(&Header::default(), &token_claims, &token_encoding_key).ok()
+    jsonwebtoken::encode(&Header::default(), claims, &token_encoding_key).ok()
 }
 
+/// The refresh token carries `refresh_token_version`, the user's current one. `remember_me`
+/// picks the longer refresh token lifetime.
 pub fn generate_access_and_refresh_tokens(
-    access_token_secret: &str,
-    refresh_token_secret: &str,
+    clock: &dyn Clock,
+    tokens: &TokenConfig,
     client_id: &str,
     subject: &str,
-    role: &str
+    role: &str,
+    refresh_token_version: i64,
+    remember_me: bool,
 ) -> Option<(String, String)> {
-    let access_token = generate_token::<AccessTokenClaims>(
-        access_token_secret,
+    let access_token_claims = AccessTokenClaims::new(
         client_id,
         subject,
         role,
-        Duration::from_secs(60 * 30),
-    )?;
-    tracing::debug!("Generated access token: {}", access_token);
+        refresh_token_version,
+        remember_me,
+        calculate_expiration(clock, tokens.access_token_lifetime()),
+    );
+    let access_token = encode_token(&tokens.access_token_hmac_secret, &access_token_claims)?;
+    tracing::debug!("Generated access token for {}", subject);
 
-    let refresh_token = generate_token::<RefreshTokenClaims>(
-        refresh_token_secret,
+    let refresh_token_claims = RefreshTokenClaims::new(
         client_id,
         subject,
         role,
-        Duration::from_secs(60 * 60 * 24 * 30),
-    )?;
-    tracing::debug!("Generated refresh token: {}", refresh_token);
+        refresh_token_version,
+        remember_me,
+        calculate_expiration(clock, tokens.refresh_token_lifetime(remember_me)),
+    );
+    let refresh_token = encode_token(&tokens.refresh_token_hmac_secret, &refresh_token_claims)?;
+    tracing::debug!("Generated refresh token for {}", subject);
 
     Some((access_token, refresh_token))
 }
@@ -171,16 +287,195 @@ pub struct JWTExpiration<T> {
     pub expires_at: T,
 }
 
-/// Returns a tuple of (iat, exp).
-pub fn calculate_expiration(expires_in: Duration) -> Result<JWTExpiration<usize>, SystemTimeError> {
-    let time_since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+/// When a token issued now by `clock` and lasting `expires_in` was issued and expires.
+pub fn calculate_expiration(clock: &dyn Clock, expires_in: Duration) -> JWTExpiration<usize> {
+    let time_since_epoch = clock.now();
     let exp = time_since_epoch
         .checked_add(expires_in)
-        .unwrap_or_else(|| time_since_epoch)
+        .unwrap_or(time_since_epoch)
         .as_secs() as usize;
 
-    Ok(JWTExpiration {
+    JWTExpiration {
         issued_at: time_since_epoch.as_secs() as usize,
         expires_at: exp,
-    })
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{
+            header::{COOKIE, SET_COOKIE},
+            Request, StatusCode,
+        },
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        services::APP_USER_ROLE,
+        testing::{self, TestApp},
+    };
+
+    use super::*;
+
+    /// A user's tokens, issued on a whole second so their expiry lands exactly on a boundary.
+    struct SignedIn {
+        app: TestApp,
+        issued_at: Duration,
+        access_token: String,
+        refresh_token: String,
+    }
+
+    impl SignedIn {
+        async fn new() -> Self {
+            let app = testing::app().await;
+            let issued_at = Duration::from_secs(app.clock.now().as_secs());
+            app.clock.set(issued_at);
+            let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+            let (access_token, refresh_token) = testing::signed_tokens(&app.state, &user);
+            Self {
+                app,
+                issued_at,
+                access_token,
+                refresh_token,
+            }
+        }
+
+        /// Moves the clock to `seconds` past the access token's expiry.
+        fn after_access_expiry(&self, seconds: u64) {
+            let tokens = &self.app.state.config.tokens;
+            let expires_at = self.issued_at + tokens.access_token_lifetime();
+            self.app.clock.set(expires_at + Duration::from_secs(seconds));
+        }
+
+        /// Moves the clock to `seconds` past the refresh token's expiry.
+        fn after_refresh_expiry(&self, seconds: u64) {
+            let tokens = &self.app.state.config.tokens;
+            let expires_at = self.issued_at + tokens.refresh_token_lifetime(false);
+            self.app.clock.set(expires_at + Duration::from_secs(seconds));
+        }
+
+        async fn verify(&self) -> TokenState {
+            verify_tokens(&self.access_token, &self.refresh_token, &self.app.state).await
+        }
+
+        async fn get_user_info(&self) -> axum::response::Response {
+            let request = Request::get("/api/user_info")
+                .header(
+                    COOKIE,
+                    format!("wl_id={}; wl_rid={}", self.access_token, self.refresh_token),
+                )
+                .body(Body::empty())
+                .unwrap();
+            self.app.router.clone().oneshot(request).await.unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn access_token_is_accepted_until_the_leeway_runs_out() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_access_expiry(EXPIRY_LEEWAY.as_secs());
+
+        assert!(matches!(signed_in.verify().await, TokenState::Valid(..)));
+    }
+
+    #[tokio::test]
+    async fn access_token_needs_refreshing_once_the_leeway_runs_out() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_access_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+
+        assert!(matches!(
+            signed_in.verify().await,
+            TokenState::RequiresRefresh(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_accepted_until_the_leeway_runs_out() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_refresh_expiry(EXPIRY_LEEWAY.as_secs());
+
+        assert!(matches!(
+            signed_in.verify().await,
+            TokenState::RequiresRefresh(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_rejected_once_the_leeway_runs_out() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_refresh_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+
+        assert!(matches!(signed_in.verify().await, TokenState::Invalid));
+    }
+
+    #[tokio::test]
+    async fn request_after_access_expiry_is_refreshed() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_access_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+
+        let response = signed_in.get_user_info().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let set_cookies = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| value.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert!(set_cookies.iter().any(|cookie| cookie.starts_with("wl_id=")));
+        assert!(set_cookies.iter().any(|cookie| cookie.starts_with("wl_rid=")));
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["session"]["refreshed"], true);
+        let now = signed_in.app.clock.now().as_secs();
+        let access_token_lifetime = signed_in.app.state.config.tokens.access_token_lifetime_seconds;
+        assert_eq!(
+            body["session"]["access_token_expires_at"],
+            now + access_token_lifetime
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshing_revokes_the_refresh_token_used() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_access_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+
+        let response = signed_in.get_user_info().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        // Replaying the tokens the refresh was made with is refused.
+        assert!(matches!(signed_in.verify().await, TokenState::Invalid));
+        let response = signed_in.get_user_info().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn logging_out_revokes_the_refresh_token() {
+        let signed_in = SignedIn::new().await;
+        let request = Request::get("/oidc/logout")
+            .header(
+                COOKIE,
+                format!(
+                    "wl_id={}; wl_rid={}",
+                    signed_in.access_token, signed_in.refresh_token
+                ),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response = signed_in.app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        signed_in.after_access_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+        assert!(matches!(signed_in.verify().await, TokenState::Invalid));
+    }
+
+    #[tokio::test]
+    async fn request_after_refresh_expiry_is_unauthorized() {
+        let signed_in = SignedIn::new().await;
+        signed_in.after_refresh_expiry(EXPIRY_LEEWAY.as_secs() + 1);
+
+        let response = signed_in.get_user_info().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
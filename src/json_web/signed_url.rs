@@ -0,0 +1,111 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::http::Uri;
+use base64::{engine::general_purpose, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const SIGNATURE_PARAMETER: &str = "&signature=";
+/// Keeps slug signatures from ever matching a URL signature made with the same secret.
+const SLUG_PREFIX: &str = "slug:";
+/// Keeps calendar tokens from doubling as share slugs, and the other way around.
+const CALENDAR_PREFIX: &str = "calendar:";
+
+fn url_mac(secret: &str, unsigned_url: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(unsigned_url.as_bytes());
+    mac
+}
+
+/// Appends an expiry and an HMAC signature to `url`, a path with an optional query string. The
+/// signature covers the whole URL, so none of it can be changed without invalidating the link.
+/// Returns the signed URL and when it expires, in seconds since the epoch.
+pub fn sign_url(secret: &str, url: &str, expires_in: Duration) -> (String, u64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let expires = now.saturating_add(expires_in).as_secs();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let unsigned_url = format!("{}{}expires={}", url, separator, expires);
+
+    let signature = general_purpose::URL_SAFE_NO_PAD
+        .encode(url_mac(secret, &unsigned_url).finalize().into_bytes());
+    (
+        format!("{}{}{}", unsigned_url, SIGNATURE_PARAMETER, signature),
+        expires,
+    )
+}
+
+/// Checks that a request URI was produced by [`sign_url`] with `secret` and hasn't expired.
+/// The signature has to be the last query parameter so nothing can be added after signing.
+pub fn verify_signed_url(secret: &str, uri: &Uri) -> bool {
+    let Some(url) = uri.path_and_query().map(|url| url.as_str()) else {
+        return false;
+    };
+    let Some((unsigned_url, signature)) = url.rsplit_once(SIGNATURE_PARAMETER) else {
+        return false;
+    };
+    let Ok(signature) = general_purpose::URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+
+    let expires = unsigned_url
+        .rsplit_once('?')
+        .into_iter()
+        .flat_map(|(_, query)| query.split('&'))
+        .find_map(|parameter| parameter.strip_prefix("expires="))
+        .and_then(|expires| expires.parse::<u64>().ok());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if expires.is_none_or(|expires| expires < now) {
+        return false;
+    }
+
+    url_mac(secret, unsigned_url)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+fn sign_prefixed(secret: &str, prefix: &str, value: &str) -> String {
+    let signature = general_purpose::URL_SAFE_NO_PAD.encode(
+        url_mac(secret, &format!("{}{}", prefix, value))
+            .finalize()
+            .into_bytes(),
+    );
+    format!("{}.{}", value, signature)
+}
+
+fn verify_prefixed<'a>(secret: &str, prefix: &str, signed: &'a str) -> Option<&'a str> {
+    // The signature never contains a `.`, so the last one is always the separator.
+    let (value, signature) = signed.rsplit_once('.')?;
+    let signature = general_purpose::URL_SAFE_NO_PAD.decode(signature).ok()?;
+    url_mac(secret, &format!("{}{}", prefix, value))
+        .verify_slice(&signature)
+        .ok()?;
+    Some(value)
+}
+
+/// Signs `value` into a slug of the form `value.signature` that doesn't expire.
+pub fn sign_slug(secret: &str, value: &str) -> String {
+    sign_prefixed(secret, SLUG_PREFIX, value)
+}
+
+/// Returns the value signed into a slug by [`sign_slug`], if the signature is valid.
+pub fn verify_slug<'a>(secret: &str, slug: &'a str) -> Option<&'a str> {
+    verify_prefixed(secret, SLUG_PREFIX, slug)
+}
+
+/// Signs a user id into a token for their calendar feed. Calendar apps can't sign in, so the
+/// token stands in for the user and doesn't expire.
+pub fn sign_calendar_token(secret: &str, user_id: &str) -> String {
+    sign_prefixed(secret, CALENDAR_PREFIX, user_id)
+}
+
+/// Returns the user id signed into a token by [`sign_calendar_token`], if the signature is
+/// valid.
+pub fn verify_calendar_token<'a>(secret: &str, token: &'a str) -> Option<&'a str> {
+    verify_prefixed(secret, CALENDAR_PREFIX, token)
+}
@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use super::EmailKind;
+
+const FOOTER: &str =
+    "\n\n--\nYou can choose which emails Water of Life sends you in your account settings.\n";
+
+/// Every email the app sends, rendered to a plain text subject and body.
+pub enum EmailTemplate<'a> {
+    /// An admin approved or rejected a spirit the user submitted.
+    SubmissionReviewed { name: &'a str, status: &'a str },
+    /// An admin upheld a report about something the user wrote or uploaded.
+    ContentWarning { target_type: &'a str, note: &'a str },
+    /// A new price was reported for a spirit on the user's wishlist.
+    PriceAlert {
+        spirit: &'a str,
+        amount: f64,
+        currency: &'a str,
+        store: &'a str,
+    },
+    WeeklyDigest {
+        username: &'a str,
+        new_spirits: &'a [String],
+        unread_notifications: i64,
+    },
+}
+
+impl EmailTemplate<'_> {
+    pub fn kind(&self) -> EmailKind {
+        match self {
+            Self::SubmissionReviewed { .. } | Self::ContentWarning { .. } => EmailKind::Moderation,
+            Self::PriceAlert { .. } => EmailKind::PriceAlerts,
+            Self::WeeklyDigest { .. } => EmailKind::WeeklyDigest,
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        match self {
+            Self::SubmissionReviewed { name, status } => {
+                format!("Your submission '{}' was {}", name, status)
+            }
+            Self::ContentWarning { target_type, .. } => {
+                format!("A report about your {} was upheld", target_type)
+            }
+            Self::PriceAlert { spirit, .. } => format!("New price reported for {}", spirit),
+            Self::WeeklyDigest { .. } => "Your week on Water of Life".to_owned(),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        let mut body = match self {
+            Self::SubmissionReviewed { name, status } => format!(
+                "An admin reviewed your submission '{}' and it was {}.",
+                name, status
+            ),
+            Self::ContentWarning { target_type, note } => {
+                let mut body = format!(
+                    "An admin reviewed a report about your {} and decided to warn you.",
+                    target_type
+                );
+                if !note.is_empty() {
+                    let _ = write!(body, "\n\nThey added: {}", note);
+                }
+                body
+            }
+            Self::PriceAlert {
+                spirit,
+                amount,
+                currency,
+                store,
+            } => {
+                let mut body = format!(
+                    "{}, which is on your wishlist, was seen for {:.2} {}",
+                    spirit, amount, currency
+                );
+                if !store.is_empty() {
+                    let _ = write!(body, " at {}", store);
+                }
+                body.push('.');
+                body
+            }
+            Self::WeeklyDigest {
+                username,
+                new_spirits,
+                unread_notifications,
+            } => {
+                let mut body = format!("Hi {},\n", username);
+                if !new_spirits.is_empty() {
+                    body.push_str("\nNew in the catalog this week:\n");
+                    for spirit in new_spirits.iter() {
+                        let _ = writeln!(body, "  - {}", spirit);
+                    }
+                }
+                if *unread_notifications > 0 {
+                    let _ = write!(
+                        body,
+                        "\nYou have {} unread notification{}.",
+                        unread_notifications,
+                        if *unread_notifications == 1 { "" } else { "s" }
+                    );
+                }
+                body
+            }
+        };
+        body.push_str(FOOTER);
+        body
+    }
+}
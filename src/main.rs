@@ -1,116 +1,583 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::{env, fs};
+use std::{fs, net::SocketAddr, process, time::Duration};
+use std::sync::{Arc, RwLock};
 
+use axum::extract::DefaultBodyLimit;
 use axum::handler::HandlerWithoutStateExt;
-use axum::routing::{post, put, MethodRouter};
+use axum::routing::{delete, patch, post, put, MethodRouter};
 use axum::{routing::get, Router};
+use clap::Parser;
+use cli::{Cli, Command};
+use clock::{Clock, SystemClock};
+use config::{AppConfig, DatabaseBackend};
 use json_web::JWKCertificate;
+use log::LevelFilter;
+use mailer::Mailer;
 use reqwest::Client;
-use services::{get_jwks, get_well_known_configuration, OpenidConfiguration};
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use infra::{ImageStore, StorageBackend, Stores};
+use repositories::Repositories;
+use security::SecurityMonitor;
+use services::{
+    get_jwks, get_well_known_configuration, IdentityProviderHealth, MessageEvent,
+    OpenidConfiguration, RoleCheckLocks, SchedulerStatus, SettingsService, TastingEvent,
+};
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, SqlitePool};
 use tokio::net::TcpListener;
+use tokio::sync::broadcast;
 use tower_cookies::CookieManagerLayer;
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
+use telemetry::SlowOperations;
 
+mod cli;
+mod clock;
+mod config;
 mod cookie;
+mod infra;
 mod json_web;
+mod mailer;
 mod middleware;
+mod permissions;
+mod proxy;
+mod rate_limit;
+mod repositories;
+mod security;
+mod seed;
 mod services;
+mod telemetry;
+#[cfg(feature = "testing")]
+mod testing;
+mod tls;
 
 #[derive(Clone)]
 struct WaterOfLifeState {
     client: reqwest::Client,
     database: SqlitePool,
     oidc_configuration: OpenidConfiguration,
-    images_path: PathBuf,
-    client_id: String,
-    client_secret: String,
-    access_token_hmac_secret: String,
-    refresh_token_hmac_secret: String,
-    jwks: HashMap<String, JWKCertificate>,
+    config: Arc<AppConfig>,
+    /// Refreshed by the scheduler so keys Keycloak rotates in are picked up.
+    jwks: Arc<RwLock<HashMap<String, JWKCertificate>>>,
+    message_events: broadcast::Sender<MessageEvent>,
+    tasting_events: broadcast::Sender<TastingEvent>,
+    stores: Stores,
+    repositories: Repositories,
+    security: SecurityMonitor,
+    idp_health: IdentityProviderHealth,
+    role_checks: RoleCheckLocks,
+    scheduler: SchedulerStatus,
+    settings: SettingsService,
+    images: ImageStore,
+    /// Delivers the outbox on the scheduler's `emails` task.
+    mailer: Mailer,
+    slow_operations: SlowOperations,
+    /// Read when issuing and checking tokens, so tests can move time forward.
+    clock: Arc<dyn Clock>,
 }
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
     dotenv::dotenv().ok();
 
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .init();
+    let config = AppConfig::load().unwrap_or_else(|e| panic!("{}", e));
+    let slow_operations = SlowOperations::default();
+    let _telemetry = telemetry::init(&config.telemetry, &slow_operations);
 
+    // Logged as warnings, which the telemetry layer also counts.
+    let (slow_query_level, slow_query_threshold) = match config.telemetry.slow_query_ms {
+        0 => (LevelFilter::Off, Duration::MAX),
+        slow_query_ms => (LevelFilter::Warn, Duration::from_millis(slow_query_ms)),
+    };
+    let database_path = match config.database.backend() {
+        #[cfg(feature = "postgres")]
+        Some(DatabaseBackend::Postgres(url)) => {
+            if let Err(e) = cli::run_postgres(cli.command.unwrap_or(Command::Serve), &url).await {
+                tracing::error!("{:?}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Some(DatabaseBackend::Sqlite(path)) => path,
+        _ => {
+            tracing::error!("database.url doesn't name a database this build can serve");
+            process::exit(1);
+        }
+    };
     let database = SqlitePool::connect_with(
         SqliteConnectOptions::new()
-            .filename("test.db")
-            .create_if_missing(true),
+            .filename(&database_path)
+            .create_if_missing(true)
+            .log_slow_statements(slow_query_level, slow_query_threshold),
     )
     .await
     .unwrap();
 
     sqlx::migrate!("./migrations").run(&database).await.unwrap();
 
-    let client_id =
-        env::var("CLIENT_ID").expect("Expected the 'CLIENT_ID' environment variable to be set.");
-    let client_secret = env::var("CLIENT_SECRET")
-        .expect("Expected the 'CLIENT_SECRET' environment variable to be set.");
-    let access_token_hmac_secret = env::var("ACCESS_TOKEN_HMAC_SECRET")
-        .expect("Expected the 'ACCESS_TOKEN_HMAC_SECRET' environment variable to be set.");
-    let refresh_token_hmac_secret = env::var("REFRESH_TOKEN_HMAC_SECRET")
-        .expect("Expected the 'REFRESH_TOKEN_HMAC_SECRET' environment variable to be set.");
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config, database, slow_operations).await,
+        command => {
+            if let Err(e) = cli::run(command, &config, &database).await {
+                tracing::error!("{:?}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
 
+async fn serve(config: AppConfig, database: SqlitePool, slow_operations: SlowOperations) {
     let client = Client::new();
+    let stores = Stores::new(&StorageBackend::from_config(&config.storage), &database)
+        .await
+        .unwrap();
+    let security = SecurityMonitor::new(
+        client.clone(),
+        config.security.webhook_url.clone(),
+        stores.rate_limits.clone(),
+    );
 
-    let oidc_configuration = get_well_known_configuration(&client).await.unwrap();
+    let oidc_configuration = get_well_known_configuration(&client, &config.oidc.realm_url)
+        .await
+        .unwrap();
     let jwks = get_jwks(&oidc_configuration.jwks_uri, &client)
         .await
         .unwrap();
 
-    let images_path = PathBuf::new().join("./spirit_images");
-    fs::create_dir_all(&images_path).unwrap();
+    fs::create_dir_all(&config.storage.images_path).unwrap();
+    fs::create_dir_all(&config.storage.uploads_path).unwrap();
+
+    let (message_events, _) = broadcast::channel(256);
+    let (tasting_events, _) = broadcast::channel(256);
+    let repositories = Repositories::sqlite(&database);
+    let settings = SettingsService::new(database.clone());
+    let mailer = Mailer::new(&config.mail);
+    let images = ImageStore::new(&config.storage.images_path);
 
+    let bind_address = config.server.bind_address;
+    let tls_paths = config
+        .server
+        .tls_paths()
+        .map(|(cert_path, key_path)| (cert_path.to_owned(), key_path.to_owned()));
     let state = WaterOfLifeState {
         client,
         database,
         oidc_configuration,
-        images_path,
-        client_id,
-        client_secret,
-        access_token_hmac_secret,
-        refresh_token_hmac_secret,
-        jwks,
+        config: Arc::new(config),
+        jwks: Arc::new(RwLock::new(jwks)),
+        message_events,
+        tasting_events,
+        stores,
+        repositories,
+        security,
+        idp_health: IdentityProviderHealth::default(),
+        role_checks: RoleCheckLocks::default(),
+        scheduler: SchedulerStatus::default(),
+        settings,
+        images,
+        mailer,
+        slow_operations,
+        clock: Arc::new(SystemClock),
     };
 
-    let app = Router::new()
+    tokio::spawn(services::scheduler(state.clone()));
+    tokio::spawn(services::webhook_sender(state.clone()));
+    tokio::spawn(services::job_workers(state.clone()));
+
+    let app = router(state);
+
+    if let Some((cert_path, key_path)) = tls_paths {
+        tls::serve(bind_address, app, &cert_path, &key_path).await.unwrap();
+        return;
+    }
+    let listener = TcpListener::bind(bind_address).await.unwrap();
+    tracing::debug!("listening on {}", listener.local_addr().unwrap());
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
+}
+
+/// The built frontend, with cache headers suited to SvelteKit's output.
+fn frontend() -> Router {
+    Router::new()
+        .fallback_service(
+            ServeDir::new("./frontend/build")
+                .not_found_service(middleware::handle_error.into_service()),
+        )
+        .layer(axum::middleware::from_fn(middleware::static_cache_control))
+}
+
+/// Lets one route accept a larger body than the default. A `Content-Length` over the limit is
+/// refused before the handler runs, and a streamed body is cut off once it passes the limit.
+fn with_body_limit(
+    route: MethodRouter<WaterOfLifeState>,
+    max_bytes: usize,
+) -> MethodRouter<WaterOfLifeState> {
+    let route: MethodRouter<WaterOfLifeState> = route.layer(DefaultBodyLimit::max(max_bytes));
+    route.layer(RequestBodyLimitLayer::new(max_bytes))
+}
+
+/// Counts one route against `rate_limits.uploads_per_day` as well as the request quota.
+fn with_upload_quota(
+    route: MethodRouter<WaterOfLifeState>,
+    state: &WaterOfLifeState,
+) -> MethodRouter<WaterOfLifeState> {
+    route.layer(axum::middleware::from_fn_with_state(
+        state.clone(),
+        rate_limit::upload_quota,
+    ))
+}
+
+/// Builds every route on top of `state`. Shared with the `testing` helpers so tests exercise
+/// the same router the server runs.
+fn router(state: WaterOfLifeState) -> Router {
+    let max_image_bytes = state.config.limits.max_image_upload_bytes;
+    let max_import_bytes = state.config.limits.max_import_body_bytes;
+    let max_body_bytes = state.config.limits.max_body_bytes;
+    // Images can also be fetched with a signed URL instead of cookies.
+    let images = Router::new()
+        .route("/api/spirit/:id/image", get(services::get_spirit_image))
+        .route("/api/spirit/:id/images/:image_id", get(services::get_spirit_image_by_id))
+        .route("/api/user/:id/avatar", get(services::get_avatar))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::signed_url_authentication,
+        ));
+
+    Router::new()
         .route("/api/spirit", post(services::add_spirit))
+        .route(
+            "/api/spirit/batch",
+            with_body_limit(post(services::add_spirits), max_import_bytes),
+        )
         .route("/api/spirit/search", get(services::search_spirit))
+        .route("/api/spirit/types", get(services::list_spirit_types))
+        .route("/api/spirit/random", get(services::random_spirit))
+        .route("/api/spirit/of_the_day", get(services::spirit_of_the_day))
+        .route("/api/spirit/recent", get(services::recent_spirits))
+        .route("/api/spirit/trending", get(services::trending_spirits))
+        .route("/api/spirit/compare", get(services::compare_spirits))
+        .route("/api/spirit/export", get(services::export_spirits))
+        .route(
+            "/api/spirit/by_barcode/:code",
+            get(services::get_spirit_by_barcode),
+        )
+        .route("/api/spirit/:id", get(services::get_spirit))
         .route("/api/spirit/:id", put(services::edit_spirit))
-        .route("/api/spirit/:id/image", put(services::upload_spirit_image))
-        .route("/api/spirit/:id/image", get(services::get_spirit_image))
+        .route("/api/spirit/:id", delete(services::delete_spirit))
+        .route("/api/spirit/:id/history", get(services::spirit_history))
+        .route("/api/spirit/:id/similar", get(services::similar_spirits))
+        .route("/api/spirit/:id/share", post(services::share_spirit))
+        .route(
+            "/api/admin/spirit/:id/revisions/:revision_id/revert",
+            post(services::revert_spirit),
+        )
+        .route(
+            "/api/admin/spirit/:keep_id/merge/:dup_id",
+            post(services::merge_spirits),
+        )
+        .route("/api/spirit/:id/rating", post(services::add_rating))
+        .route("/api/spirit/:id/rating", put(services::edit_rating))
+        .route("/api/spirit/:id/rating", delete(services::delete_rating))
+        .route("/api/spirit/:id/reviews", get(services::list_reviews))
+        .route("/api/spirit/:id/flavors", get(services::get_flavor_cloud))
+        .route("/api/spirit/:id/flavor_profile", get(services::get_flavor_profile))
+        .route("/api/spirit/:id/flavor_votes", put(services::set_flavor_votes))
+        .route("/api/spirit/:id/flavor_votes", delete(services::delete_flavor_votes))
+        .route("/api/spirit/:id/review", post(services::add_review))
+        .route("/api/spirit/:id/review", put(services::edit_review))
+        .route("/api/spirit/:id/review", delete(services::delete_review))
+        .route("/api/reviews/:id/report", post(services::report_review))
+        .route("/api/reviews/:id/helpful", post(services::vote_review_helpful))
+        .route("/api/admin/review_reports", get(services::list_review_reports))
+        .route("/api/admin/reviews/:id/hide", put(services::hide_review))
+        .route("/api/spirit/:id/report", post(services::report_spirit))
+        .route(
+            "/api/spirit/:id/images/:image_id/report",
+            post(services::report_spirit_image),
+        )
+        .route("/api/admin/reports", get(services::list_reports))
+        .route("/api/admin/reports/:id/resolve", put(services::resolve_report))
+        .route("/api/spirit/:id/barcodes", get(services::list_spirit_barcodes))
+        .route("/api/spirit/:id/barcodes", post(services::add_spirit_barcode))
+        .route("/api/spirit/:id/aliases", get(services::list_spirit_aliases))
+        .route("/api/spirit/:id/aliases", post(services::add_spirit_alias))
+        .route(
+            "/api/spirit/:id/aliases/:alias_id",
+            delete(services::delete_spirit_alias),
+        )
+        .route(
+            "/api/spirit/:id/related/:related_id",
+            post(services::add_spirit_relation),
+        )
+        .route(
+            "/api/spirit/:id/related/:related_id",
+            delete(services::delete_spirit_relation),
+        )
+        .route("/api/spirit/:id/prices", get(services::list_price_points))
+        .route("/api/spirit/:id/prices", post(services::add_price_point))
+        .route("/api/spirit/:id/price_history", get(services::price_history))
+        .route(
+            "/api/spirit/:id/availability",
+            put(services::set_spirit_availability),
+        )
+        .route("/api/admin/barcodes/:code", delete(services::delete_barcode))
+        .route(
+            "/api/spirit/:id/image",
+            with_body_limit(
+                with_upload_quota(put(services::upload_spirit_image), &state),
+                max_image_bytes,
+            ),
+        )
+        .route("/api/spirit/:id/image", delete(services::delete_primary_spirit_image))
+        .route("/api/spirit/:id/image_url", get(services::get_spirit_image_url))
+        .route("/api/spirit/:id/images", get(services::list_spirit_images))
+        .route("/api/spirit/:id/images/:image_id", patch(services::edit_spirit_image))
+        .route("/api/spirit/:id/images/:image_id", delete(services::delete_spirit_image))
+        .route(
+            "/api/spirit/:id/image/uploads",
+            with_upload_quota(post(services::start_image_upload), &state),
+        )
+        .route("/api/spirit/:id/image/uploads/:upload_id", get(services::image_upload_status))
+        .route(
+            "/api/spirit/:id/image/uploads/:upload_id",
+            with_body_limit(patch(services::upload_image_chunk), max_image_bytes),
+        )
+        .route("/api/spirit/:id/image/uploads/:upload_id", delete(services::cancel_image_upload))
         .route("/api/user_info", get(services::user_info))
+        .route(
+            "/api/admin/data-quality",
+            get(services::data_quality_report),
+        )
+        .route("/api/admin/images/backfill", post(services::backfill_images))
+        .route(
+            "/api/admin/spirit/import",
+            with_body_limit(post(services::import_spirits), max_import_bytes),
+        )
+        .route("/api/admin/webhooks", get(services::list_webhooks))
+        .route("/api/admin/webhooks", post(services::add_webhook))
+        .route("/api/admin/webhooks/:id", delete(services::delete_webhook))
+        .route(
+            "/api/admin/webhooks/:id/deliveries",
+            get(services::list_webhook_deliveries),
+        )
+        .route("/api/admin/jobs", get(services::list_jobs))
+        .route("/api/admin/jobs/:id/retry", post(services::retry_job))
+        .route("/api/admin/scheduler", get(services::scheduler_status))
+        .route("/api/admin/backup", post(services::backup_database))
+        .route("/api/admin/settings", get(services::get_settings))
+        .route("/api/admin/settings", put(services::set_settings))
+        .route("/api/admin/slow", get(services::slow_routes))
+        .route("/api/admin/anomalies", get(services::list_anomalies))
+        .route(
+            "/api/admin/anomalies/:id/resolve",
+            put(services::resolve_anomaly),
+        )
+        .route(
+            "/api/user/avatar",
+            with_body_limit(with_upload_quota(put(services::set_avatar), &state), max_image_bytes),
+        )
+        .route("/api/user/avatar", delete(services::delete_avatar))
+        .route("/api/user/stats", get(services::user_stats))
+        .route("/api/user/calendar", get(services::calendar_url))
+        .route("/api/user/badges", get(services::list_badges))
+        .route("/api/user/preferences", get(services::get_preferences))
+        .route("/api/user/preferences", put(services::set_preferences))
+        .route("/api/user/email_preferences", get(services::get_email_preferences))
+        .route("/api/user/email_preferences", put(services::set_email_preferences))
+        .route("/api/user/collection", get(services::list_collection))
+        .route("/api/user/collection", post(services::add_collection_entry))
+        .route("/api/user/collection/export", get(services::export_collection))
+        .route("/api/user/collection/:id", get(services::get_collection_entry))
+        .route("/api/user/collection/:id", put(services::edit_collection_entry))
+        .route("/api/user/collection/:id", delete(services::delete_collection_entry))
+        .route(
+            "/api/user/collection/:id/bottles",
+            get(services::list_collection_bottles),
+        )
+        .route(
+            "/api/user/collection/:id/bottles",
+            post(services::add_collection_bottle),
+        )
+        .route(
+            "/api/user/collection/:id/bottles/:bottle_id",
+            put(services::edit_collection_bottle),
+        )
+        .route("/api/user/pours", get(services::list_pours))
+        .route("/api/user/pours", post(services::add_pour))
+        .route("/api/user/pours/stats", get(services::pour_stats))
+        .route("/api/user/pours/:id", delete(services::delete_pour))
+        .route("/api/user/wishlist", get(services::list_wishlist))
+        .route("/api/user/wishlist", post(services::add_wishlist_entry))
+        .route("/api/user/wishlist/:spirit_id", put(services::edit_wishlist_entry))
+        .route("/api/user/wishlist/:spirit_id", delete(services::delete_wishlist_entry))
+        .route("/api/user/bottles", get(services::list_bottles))
+        .route("/api/user/bottles", post(services::add_bottle))
+        .route(
+            "/api/user/bottles/:id/provenance",
+            put(services::set_bottle_provenance),
+        )
+        .route(
+            "/api/user/bottles/:id/transfers",
+            post(services::add_bottle_transfer),
+        )
+        .route(
+            "/api/user/bottles/:id/custody",
+            get(services::bottle_custody),
+        )
+        .route("/api/swaps/offers", get(services::list_swap_offers))
+        .route("/api/swaps/offers", post(services::add_swap_offer))
+        .route("/api/swaps/offers/:id", delete(services::close_swap_offer))
+        .route(
+            "/api/swaps/offers/:id/matches",
+            get(services::swap_offer_matches),
+        )
+        .route("/api/swaps/requests", get(services::list_swap_requests))
+        .route("/api/swaps/requests", post(services::add_swap_request))
+        .route(
+            "/api/swaps/requests/:id",
+            delete(services::close_swap_request),
+        )
+        .route("/api/swaps/matches", post(services::add_swap_match))
+        .route(
+            "/api/swaps/matches/:id/messages",
+            get(services::list_swap_messages),
+        )
+        .route(
+            "/api/swaps/matches/:id/messages",
+            post(services::add_swap_message),
+        )
+        .route(
+            "/api/swaps/matches/:id/confirm",
+            post(services::confirm_swap_match),
+        )
+        .route("/api/users/:id/profile", get(services::get_profile))
+        .route("/api/users/:id/reputation", get(services::get_reputation))
+        .route(
+            "/api/admin/submissions",
+            get(services::list_pending_submissions),
+        )
+        .route(
+            "/api/admin/submissions/:id/approve",
+            put(services::approve_submission),
+        )
+        .route(
+            "/api/admin/submissions/:id/reject",
+            put(services::reject_submission),
+        )
+        .route("/api/conversations", get(services::list_conversations))
+        .route("/api/conversations", post(services::start_conversation))
+        .route(
+            "/api/conversations/unread",
+            get(services::unread_message_count),
+        )
+        .route("/api/conversations/events", get(services::message_events))
+        .route(
+            "/api/conversations/:id/messages",
+            get(services::list_messages),
+        )
+        .route(
+            "/api/conversations/:id/messages",
+            post(services::add_message),
+        )
+        .route("/api/messages/:id/report", post(services::report_message))
+        .route(
+            "/api/admin/message_reports",
+            get(services::list_message_reports),
+        )
+        .route("/api/admin/messages/:id/hide", put(services::hide_message))
+        .route("/api/user/blocks", get(services::list_blocks))
+        .route("/api/user/blocks/:id", put(services::block_user))
+        .route("/api/user/blocks/:id", delete(services::unblock_user))
+        .route("/api/user/notifications", get(services::list_notifications))
+        .route("/api/countries", get(services::list_countries))
+        .route("/api/regions", get(services::list_regions))
+        .route("/api/distillers", get(services::list_distillers))
+        .route("/api/distillers/map", get(services::distiller_map))
+        .route(
+            "/api/distillers/:id/region",
+            put(services::set_distiller_region),
+        )
+        .route(
+            "/api/distillers/:id/location",
+            put(services::set_distiller_location),
+        )
+        .route("/api/flights", get(services::list_flights))
+        .route("/api/flights", post(services::add_flight))
+        .route("/api/flights/:id", get(services::get_flight))
+        .route("/api/flights/:id", put(services::edit_flight))
+        .route("/api/flights/:id", delete(services::delete_flight))
+        .route("/api/flights/:id/clone", post(services::clone_flight))
+        .route("/api/flights/:id/reveal", post(services::reveal_flight_sample))
+        .route("/api/flights/:id/scores", post(services::score_flight_sample))
+        .route("/api/flights/:id/live", get(services::tasting_events))
+        .route("/api/cocktails", get(services::list_cocktails))
+        .route("/api/cocktails", post(services::add_cocktail))
+        .route("/api/cocktails/makeable", get(services::makeable_cocktails))
+        .route("/api/cocktails/:id", get(services::get_cocktail))
+        .route("/api/cocktails/:id", put(services::edit_cocktail))
+        .route("/api/cocktails/:id", delete(services::delete_cocktail))
+        .route("/api/releases", get(services::list_releases))
+        .route("/api/releases", post(services::add_release))
+        .route(
+            "/api/releases/import",
+            with_body_limit(post(services::import_releases), max_import_bytes),
+        )
+        .route("/api/releases/:id/watch", put(services::watch_release))
+        .route("/api/releases/:id/watch", delete(services::unwatch_release))
+        // Inside authentication, which it needs to know whose quota to count against.
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::request_quota,
+        ))
         .route_layer(axum::middleware::from_fn_with_state(
             state.clone(),
             middleware::authentication,
         ))
+        .merge(images)
+        .route("/api/status", get(services::status))
+        .route("/share/:slug", get(services::get_shared_spirit))
+        .route("/feeds/spirits.atom", get(services::spirits_feed))
+        .route("/feeds/events.ics", get(services::events_feed))
         .route("/oidc/login", get(services::login))
         .route("/oidc/logout", get(services::logout))
         .route("/oidc/token", get(services::token))
+        // Set before the layers below so the frontend and unknown paths get them too.
+        .fallback_service(frontend())
+        // Routes that take more set their own limit with `with_body_limit`.
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .layer(axum::middleware::from_fn(middleware::payload_too_large_errors))
+        // Inside the trace layer so slow requests are logged in the request's span.
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::log_slow_requests,
+        ))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(middleware::create_span)
                 .on_failure(()),
         )
-        .layer(middleware::session_layer())
+        .layer(axum::middleware::from_fn(middleware::request_id_in_errors))
+        .layer(PropagateRequestIdLayer::new(middleware::REQUEST_ID_HEADER))
+        .layer(SetRequestIdLayer::new(middleware::REQUEST_ID_HEADER, MakeRequestUuid))
+        .layer(middleware::session_layer(
+            state.stores.sessions.clone(),
+            &state.config.cookies,
+        ))
         .layer(CookieManagerLayer::new())
-        .fallback_service(
-            ServeDir::new("./frontend/build")
-                .not_found_service(middleware::handle_error.into_service()),
-        )
+        .layer(CompressionLayer::new())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::strict_transport_security,
+        ))
+        // Outside the trace layer so the request's span can include the client's address.
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            proxy::resolve_client,
+        ))
         // TODO: Make some authentication middleware
         // https://docs.rs/axum/latest/axum/middleware/index.html#passing-state-from-middleware-to-handlers
-        .with_state(state);
-
-    let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+        .with_state(state)
 }
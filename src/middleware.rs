@@ -1,23 +1,55 @@
+use std::{sync::Arc, time::Instant};
+
 use axum::{
+    body::Body,
     extract::{MatchedPath, Request, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, STRICT_TRANSPORT_SECURITY},
+        HeaderName, HeaderValue,
+    },
     middleware::Next,
-    response::Response, Extension,
+    response::{IntoResponse, Response},
 };
 use reqwest::StatusCode;
 use tower_cookies::{
     cookie::{time::Duration, SameSite},
-    Cookie, Cookies,
+    Cookies,
 };
-use tower_sessions::{MemoryStore, SessionManagerLayer};
+use tower_sessions::SessionManagerLayer;
 
 use crate::{
-    json_web::{self, generate_access_and_refresh_tokens, verify_tokens, TokenState, User},
+    config::CookieConfig,
+    cookie::create_token_cookie,
+    infra::{SessionStore, SessionStoreAdapter},
+    json_web::{
+        generate_access_and_refresh_tokens, verify_signed_url, verify_tokens, AuthContext,
+        TokenExpiry, TokenState,
+    },
+    permissions::{role_permissions, ModerationReview},
+    proxy::{ClientIp, Scheme},
+    services::{check_role, error_response, RoleCheck, WebError, WebResult},
     WaterOfLifeState,
 };
 
+/// Set on every request, or kept from the client, and echoed back in the response.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+fn request_id(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
 pub fn create_span(request: &Request) -> tracing::Span {
     let method = request.method();
     let uri = request.uri();
+    let request_id = request_id(request).unwrap_or("<unknown>");
+    let client_ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .and_then(|ClientIp(client_ip)| *client_ip)
+        .map_or_else(|| "<unknown>".to_owned(), |client_ip| client_ip.to_string());
 
     let matched_path = request
         .extensions()
@@ -25,21 +57,161 @@ pub fn create_span(request: &Request) -> tracing::Span {
         .map(|matched_path| matched_path.as_str())
         .unwrap_or("<unknown>");
 
-    tracing::debug_span!("request", %method, %uri, matched_path)
+    tracing::debug_span!(
+        "request",
+        %method,
+        %uri,
+        matched_path,
+        request_id,
+        client_ip,
+        user_id = tracing::field::Empty,
+        user_role = tracing::field::Empty,
+    )
 }
 
-pub fn session_layer() -> SessionManagerLayer<MemoryStore> {
-    // Probably fine to store nonces in memory for now since theyre 32 bytes each
-    SessionManagerLayer::new(MemoryStore::default())
+/// SvelteKit puts build output with content-hashed file names under this path, so a changed
+/// file always gets a new URL.
+const IMMUTABLE_ASSETS_PATH: &str = "/_app/immutable/";
+
+/// Lets browsers keep hashed assets for a year, and makes them revalidate everything else,
+/// `index.html` included, so a deploy is picked up on the next load.
+pub async fn static_cache_control(request: Request, next: Next) -> Response {
+    let immutable = request.uri().path().starts_with(IMMUTABLE_ASSETS_PATH);
+    let mut response = next.run(request).await;
+    if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+        let cache_control = if immutable {
+            "public, max-age=31536000, immutable"
+        } else {
+            "no-cache"
+        };
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    }
+    response
+}
+
+/// Tells browsers to keep using HTTPS for a year. Only sent when the server terminates TLS
+/// itself, since behind a plain HTTP listener it would be ignored at best.
+pub async fn strict_transport_security(
+    State(state): State<WaterOfLifeState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if state.config.server.tls_enabled() {
+        response.headers_mut().insert(
+            STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000"),
+        );
+    }
+    response
+}
+
+pub fn session_layer(
+    store: Arc<dyn SessionStore>,
+    cookies: &CookieConfig,
+) -> SessionManagerLayer<SessionStoreAdapter> {
+    SessionManagerLayer::new(SessionStoreAdapter(store))
         .with_same_site(SameSite::Lax)
-        // FIXME: This should be removed once the web server is running HTTPS
-        .with_secure(false)
-        .with_expiry(tower_sessions::Expiry::OnInactivity(Duration::minutes(2)))
+        // Always secure when serving HTTPS, see `AppConfig::load`.
+        .with_secure(cookies.secure_session)
+        .with_expiry(tower_sessions::Expiry::OnInactivity(Duration::minutes(
+            cookies.session_inactivity_minutes,
+        )))
 }
 
 #[allow(clippy::unused_async)]
-pub async fn handle_error() -> (StatusCode, &'static str) {
-    (StatusCode::NOT_FOUND, "That endpoint does not exist.")
+pub async fn handle_error() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "not_found",
+        "That endpoint does not exist.".into(),
+        None,
+    )
+}
+
+/// Body limits are enforced by layers and extractors that answer in plain text, so their 413s
+/// are replaced with the usual error body.
+pub async fn payload_too_large_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE || is_json {
+        return response;
+    }
+    error_response(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "payload_too_large",
+        "The request body is larger than this endpoint accepts.".into(),
+        None,
+    )
+}
+
+/// Logs requests that take at least `telemetry.slow_request_ms` to start responding, and counts
+/// them against their route. Logged inside the request's span, so the line carries the matched
+/// path and user id.
+pub async fn log_slow_requests(
+    State(state): State<WaterOfLifeState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let threshold = state.config.telemetry.slow_request_ms;
+    if threshold == 0 {
+        return next.run(request).await;
+    }
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "<unknown>".to_owned(), |path| path.as_str().to_owned());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed();
+    if elapsed >= std::time::Duration::from_millis(threshold) {
+        tracing::warn!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            status = response.status().as_u16(),
+            "slow request"
+        );
+        state.slow_operations.record_request(&route, elapsed);
+    }
+    response
+}
+
+/// Adds the request id to JSON error bodies, so a user reporting a failure can quote it and
+/// it can be matched to the request's logs.
+pub async fn request_id_in_errors(request: Request, next: Next) -> Response {
+    let request_id = request_id(&request).map(str::to_owned);
+    let response = next.run(request).await;
+
+    let is_error = response.status().is_client_error() || response.status().is_server_error();
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    let Some(request_id) = request_id.filter(|_| is_error && is_json) else {
+        return response;
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Could not read error body: {}", e);
+            return parts.status.into_response();
+        }
+    };
+    let body = match serde_json::from_slice(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert("request_id".into(), request_id.into());
+            parts.headers.remove(CONTENT_LENGTH);
+            Body::from(serde_json::Value::Object(object).to_string())
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
 }
 
 async fn validate_cookies(
@@ -57,58 +229,18 @@ async fn validate_cookies(
     Ok(verify_tokens(
         access_token_cookie.value(),
         refresh_token_cookie.value(),
-        &state,
+        state,
     )
     .await)
 }
 
-fn create_token_cookie<'a>(key: &'a str, token: String) -> Cookie<'a> {
-    let mut cookie = Cookie::new(key, token);
-    cookie.set_path("/");
-    cookie.set_secure(true);
-    cookie.set_same_site(SameSite::Lax);
-    cookie.set_http_only(true);
-    cookie
-}
-#[macro_export]
-macro_rules! requires_role {
-    ($role:literal, [$($endpoint:literal),*]) => {
-        axum::middleware::from_fn(|user: axum::Extension<crate::json_web::User>, request: axum::extract::Request, next: axum::middleware::Next| async move {
-            tracing::info!("Test: {:#?}", user);
-            tracing::info!("Role: {}", $role);
-            next.run(request).await
-        })
-    };
-}
-
-#[macro_export]
-macro_rules! requires_scopes {
-    ( $($scope:literal),*, [$($endpoint:literal),*]) => {
-        axum::middleware::from_fn(|user: axum::Extension<crate::json_web::User>, request: axum::extract::Request, next: axum::middleware::Next| async move {
-            tracing::info!("Test: {:#?}", user);
-            $(
-                tracing::info!("Scope: {}", $scope);
-            )*
-            next.run(request).await
-        })
-    };
-}
-
-pub async fn test(
-    Extension(user): Extension<User>,
-    mut request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    tracing::info!("Test: {:#?}", user);
-    Ok(next.run(request).await)
-}
-
 pub async fn authentication(
     State(state): State<WaterOfLifeState>,
     cookies: Cookies,
+    scheme: Scheme,
     mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> WebResult<Response> {
     // tracing::debug!("{}", request.uri().path());
     // if !request.uri().path().starts_with("/api") {
     //     return Ok(next.run(request).await);
@@ -118,34 +250,153 @@ pub async fn authentication(
         Ok(is_token_valid) => is_token_valid,
         Err(e) => {
             tracing::debug!("{}", e);
-            return Err(StatusCode::UNAUTHORIZED);
+            return Err(WebError::Unauthorized);
         }
     };
 
-    let user_id = match is_token_valid {
-        TokenState::Valid(user_id) => user_id,
-        TokenState::RequiresRefresh(user_id, user) => {
-            if let Some((access_token, refresh_token)) = generate_access_and_refresh_tokens(
-                &state.access_token_hmac_secret,
-                &state.refresh_token_hmac_secret,
-                &state.client_id,
+    let (user_id, refreshed, expiry) = match is_token_valid {
+        TokenState::Valid(user_id, expiry) => (user_id, false, expiry),
+        TokenState::RequiresRefresh(user_id, user, remember_me) => {
+            let permissions = role_permissions(&state.database, &user.role).await?;
+            let role = match check_role(&state, &user).await {
+                Ok(RoleCheck::Current(role)) => role,
+                // A privileged role that Keycloak can no longer vouch for isn't kept. Signing
+                // in again reads it afresh.
+                Ok(RoleCheck::SessionEnded) if permissions.has::<ModerationReview>() => {
+                    tracing::info!("Keycloak session ended for {} {}", user.role, user_id);
+                    return Err(WebError::Unauthorized);
+                }
+                Ok(RoleCheck::SessionEnded) => user.role.clone(),
+                // Keep the stored role rather than signing everyone out while Keycloak is down.
+                Err(e) => {
+                    tracing::warn!("Could not check {}'s role with Keycloak: {}", user_id, e);
+                    user.role.clone()
+                }
+            };
+            // Moving the version on revokes the refresh token just used, so it can't be
+            // replayed. Losing the race to another request refreshing with it ends the session.
+            let Some(refresh_token_version) = state
+                .repositories
+                .users
+                .advance_refresh_token_version(&user_id, user.refresh_token_version)
+                .await?
+            else {
+                return Err(WebError::Unauthorized);
+            };
+            // Without new tokens the session can't go on, and the expiry reported to handlers
+            // would be wrong.
+            let Some((access_token, refresh_token)) = generate_access_and_refresh_tokens(
+                &*state.clock,
+                &state.config.tokens,
+                &state.config.oidc.client_id,
                 &user_id,
-                &user.role,
-            ) {
-                cookies.add(create_token_cookie("wl_id", access_token));
-                cookies.add(create_token_cookie("wl_rid", refresh_token));
-            }
-            user_id
+                &role,
+                refresh_token_version,
+                remember_me,
+            ) else {
+                tracing::warn!("Could not generate tokens for {}", user_id);
+                return Err(WebError::Unauthorized);
+            };
+            let secure = state.config.cookies.secure || scheme.https;
+            let max_age = remember_me.then(|| state.config.tokens.refresh_token_lifetime(true));
+            cookies.add(create_token_cookie("wl_id", access_token, secure, max_age));
+            cookies.add(create_token_cookie("wl_rid", refresh_token, secure, max_age));
+            let expiry = TokenExpiry::issued(&*state.clock, &state.config.tokens, remember_me);
+            (user_id, true, expiry)
+        }
+        TokenState::Invalid => {
+            state.security.record_jwt_failure().await;
+            return Err(WebError::Unauthorized);
         }
-        TokenState::Invalid => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    let user = sqlx::query_file_as!(json_web::User, "sql/select_user.sql", user_id)
-        .fetch_one(&state.database)
-        .await
-        .unwrap();
+    let user = state
+        .repositories
+        .users
+        .find(&user_id)
+        .await?
+        .ok_or(WebError::Unauthorized)?;
 
     // tracing::info!("Got user: {:#?}", user);
+    tracing::Span::current()
+        .record("user_id", &user.user_id)
+        .record("user_role", &user.role);
+    // Reads stay open so the user can still browse and see that they need to verify.
+    if state.config.oidc.require_verified_email
+        && !user.email_verified
+        && !request.method().is_safe()
+    {
+        return Err(WebError::EmailUnverified);
+    }
+
+    let path = request.uri().path().to_owned();
+    let user_id = user.user_id.clone();
+    let permissions = role_permissions(&state.database, &user.role).await?;
+    request.extensions_mut().insert(AuthContext {
+        user: user.clone(),
+        refreshed,
+        expiry,
+        permissions,
+    });
     request.extensions_mut().insert(user);
+    let response = next.run(request).await;
+    if response.status() == StatusCode::FORBIDDEN {
+        state.security.record_forbidden(&user_id, &path).await;
+    }
+    Ok(response)
+}
+
+/// Lets a request through without cookies when it carries a valid signed URL, so images can be
+/// fetched through a CDN or object store. A bad or expired signature is refused, and requests
+/// without one are authenticated as usual.
+pub async fn signed_url_authentication(
+    State(state): State<WaterOfLifeState>,
+    cookies: Cookies,
+    scheme: Scheme,
+    request: Request,
+    next: Next,
+) -> WebResult<Response> {
+    let is_signed = request
+        .uri()
+        .query()
+        .is_some_and(|query| query.contains("signature="));
+    if !is_signed {
+        return authentication(State(state), cookies, scheme, request, next).await;
+    }
+
+    if !verify_signed_url(&state.config.tokens.access_token_hmac_secret, request.uri()) {
+        return Err(WebError::Forbidden);
+    }
     Ok(next.run(request).await)
 }
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::http::header::COOKIE;
+    use tower::ServiceExt;
+
+    use crate::testing;
+
+    use super::*;
+
+    async fn get_user_info(cookie: &str) -> Response {
+        let app = testing::app().await;
+        let request = Request::get("/api/user_info")
+            .header(COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        app.router.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn garbage_tokens_are_unauthorized() {
+        let response = get_user_info("wl_id=garbage; wl_rid=garbage").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn missing_refresh_token_is_unauthorized() {
+        let response = get_user_info("wl_id=garbage").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
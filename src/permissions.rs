@@ -0,0 +1,212 @@
+//! What signed in users may do. Roles are granted permissions in the `role_permissions` table,
+//! the authentication middleware loads the user's into their [`AuthContext`], and handlers ask
+//! for one with the [`RequirePermission`] extractor.
+
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use async_trait::async_trait;
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+
+use crate::{
+    json_web::{AuthContext, User},
+    services::WebError,
+};
+
+/// A permission a handler can require, named as it is in `role_permissions`.
+pub trait Permission: Send + Sync {
+    const NAME: &'static str;
+}
+
+macro_rules! permissions {
+    ($($(#[$doc:meta])* $permission:ident = $name:literal;)*) => {
+        $(
+            $(#[$doc])*
+            pub struct $permission;
+
+            impl Permission for $permission {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+permissions! {
+    /// Add spirits, and edit, delete and upload images of the ones the user added.
+    SpiritsWrite = "spirits:write";
+    /// Edit and delete any spirit and upload images of it, not just ones the user added.
+    SpiritsManage = "spirits:manage";
+    /// Upload images of spirits.
+    ImagesUpload = "images:upload";
+    /// Change any spirit image, not just ones the user uploaded, and regenerate their variants.
+    ImagesManage = "images:manage";
+    /// Delete any spirit image, not just ones the user uploaded.
+    ImagesDelete = "images:delete";
+    /// Review submissions and act on reported spirits, reviews and messages.
+    ModerationReview = "moderation:review";
+    /// See every user's profile, including private ones.
+    UsersManage = "users:manage";
+    /// Set where distillers are.
+    DistillersManage = "distillers:manage";
+    /// Add and import spirit releases.
+    ReleasesManage = "releases:manage";
+    /// Import spirits in bulk.
+    CatalogImport = "catalog:import";
+    /// Export the whole spirit catalog.
+    CatalogExport = "catalog:export";
+    /// Register, remove and inspect webhooks.
+    WebhooksManage = "webhooks:manage";
+    /// List and retry background jobs.
+    JobsManage = "jobs:manage";
+    /// Take database backups.
+    BackupsCreate = "backups:create";
+    /// Read and change the site settings.
+    SettingsManage = "settings:manage";
+    /// See slow routes and the scheduler's status.
+    SystemMonitor = "system:monitor";
+}
+
+/// The permissions granted to a user's role.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Permissions(BTreeSet<String>);
+
+impl Permissions {
+    pub fn has<P: Permission>(&self) -> bool {
+        self.0.contains(P::NAME)
+    }
+}
+
+pub async fn role_permissions<'e, E>(executor: E, role: &str) -> sqlx::Result<Permissions>
+where
+    E: SqliteExecutor<'e>,
+{
+    let rows = sqlx::query_file!("sql/select_role_permissions.sql", role)
+        .fetch_all(executor)
+        .await?;
+    Ok(Permissions(
+        rows.into_iter().map(|row| row.permission).collect(),
+    ))
+}
+
+/// The signed in user, extracted only when their role has the permission `P`. Requests without
+/// it are refused with 403.
+pub struct RequirePermission<P>(pub User, pub PhantomData<P>);
+
+#[async_trait]
+impl<P: Permission, S: Send + Sync> FromRequestParts<S> for RequirePermission<P> {
+    type Rejection = WebError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let context = parts
+            .extensions
+            .get::<AuthContext>()
+            .ok_or(WebError::Unauthorized)?;
+        if !context.permissions.has::<P>() {
+            return Err(WebError::Forbidden);
+        }
+        Ok(Self(context.user.clone(), PhantomData))
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        services::{APP_ADMIN_ROLE, APP_MODERATOR_ROLE, APP_USER_ROLE},
+        testing,
+    };
+
+    #[tokio::test]
+    async fn only_admins_can_delete_any_image_or_manage_users() {
+        let database = testing::database().await;
+
+        let admin = role_permissions(&database, APP_ADMIN_ROLE).await.unwrap();
+        assert!(admin.has::<ImagesDelete>());
+        assert!(admin.has::<UsersManage>());
+
+        let moderator = role_permissions(&database, APP_MODERATOR_ROLE)
+            .await
+            .unwrap();
+        assert!(!moderator.has::<ImagesDelete>());
+        assert!(!moderator.has::<UsersManage>());
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints_need_their_permission() {
+        let app = testing::app().await;
+        let moderator = testing::create_moderator(&app.state.database).await;
+        let admin = testing::create_admin(&app.state.database).await;
+
+        for (user, expected) in [(moderator, StatusCode::FORBIDDEN), (admin, StatusCode::OK)] {
+            let request = Request::get("/api/admin/webhooks")
+                .header(header::COOKIE, testing::auth_cookie(&app.state, &user))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_spirits_are_visible_to_their_submitter_and_moderators() {
+        let app = testing::app().await;
+        let submitter = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let other = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let moderator = testing::create_moderator(&app.state.database).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Pending Bourbon").await;
+        sqlx::query(
+            "INSERT INTO spirit_submissions (spirit_id, user_id, status) VALUES (?, ?, 'pending')",
+        )
+        .bind(&spirit_id)
+        .bind(&submitter.user_id)
+        .execute(&app.state.database)
+        .await
+        .unwrap();
+
+        for (user, expected) in [
+            (other, StatusCode::NOT_FOUND),
+            (submitter, StatusCode::OK),
+            (moderator, StatusCode::OK),
+        ] {
+            let request = Request::get(format!("/api/spirit/{}", spirit_id))
+                .header(header::COOKIE, testing::auth_cookie(&app.state, &user))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn unowned_images_need_the_delete_permission() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let moderator = testing::create_moderator(&app.state.database).await;
+        let admin = testing::create_admin(&app.state.database).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        // Stored before uploads were recorded, so nobody owns it.
+        let path = app.state.config.storage.images_path.join(&spirit_id);
+        std::fs::write(&path, b"GIF89a").unwrap();
+
+        for (user, expected) in [
+            (user, StatusCode::FORBIDDEN),
+            (moderator, StatusCode::FORBIDDEN),
+            (admin, StatusCode::OK),
+        ] {
+            let request = Request::delete(format!("/api/spirit/{}/image", spirit_id))
+                .header(header::COOKIE, testing::auth_cookie(&app.state, &user))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected);
+        }
+        assert!(!path.exists());
+    }
+}
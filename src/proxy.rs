@@ -0,0 +1,119 @@
+//! The client's address and the scheme it connected with, seen through any trusted reverse
+//! proxies. Forwarding headers are only believed when the connection comes from an address in
+//! `server.trusted_proxies`, since anyone else can set them to whatever they like.
+
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{config::ServerConfig, WaterOfLifeState};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// The address of the client that made the request. Unset when the server wasn't told who
+/// connected, as when requests are handed straight to the router in tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+/// Whether the client connected over HTTPS, either to this server or to a trusted proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scheme {
+    pub https: bool,
+}
+
+/// Walks `X-Forwarded-For` back from the nearest hop, skipping trusted proxies. The first
+/// address that isn't one is the client; anything further left was supplied by the client and
+/// can't be trusted.
+fn forwarded_client(headers: &HeaderMap, peer: IpAddr, config: &ServerConfig) -> IpAddr {
+    let hops = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        if !config.trusted_proxies.contains(&client) {
+            break;
+        }
+        match hop {
+            Some(hop) => client = hop,
+            // Garbage in the chain means nothing before it can be relied on.
+            None => break,
+        }
+    }
+    client
+}
+
+fn resolve(headers: &HeaderMap, peer: Option<IpAddr>, config: &ServerConfig) -> (ClientIp, Scheme) {
+    let trusted = peer.is_some_and(|peer| config.trusted_proxies.contains(&peer));
+    let client_ip = peer.map(|peer| forwarded_client(headers, peer, config));
+
+    let forwarded_https = headers
+        .get(X_FORWARDED_PROTO)
+        .and_then(|value| value.to_str().ok())
+        // Proxies chaining the header append to it, so the first entry is the client's.
+        .and_then(|value| value.split(',').next())
+        .map(|proto| proto.trim().eq_ignore_ascii_case("https"));
+    let https = match forwarded_https {
+        Some(https) if trusted => https,
+        _ => config.tls_enabled(),
+    };
+
+    (ClientIp(client_ip), Scheme { https })
+}
+
+/// Works out the client's address and scheme once per request, for handlers to extract and
+/// for the request's span.
+pub async fn resolve_client(
+    State(state): State<WaterOfLifeState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+    let (client_ip, scheme) = resolve(request.headers(), peer, &state.config.server);
+    request.extensions_mut().insert(client_ip);
+    request.extensions_mut().insert(scheme);
+    next.run(request).await
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .unwrap_or(Self(None)))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Scheme {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .unwrap_or(Self { https: false }))
+    }
+}
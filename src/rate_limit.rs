@@ -0,0 +1,131 @@
+//! Quotas for signed in users, on top of the body limits every route has. Hits are counted in
+//! the shared rate limit store under the user's id, so a user's quota is the same whichever
+//! instance serves them. Each response says how much of the quota is left in
+//! `X-RateLimit-*` headers, and once it's used up requests are refused with 429 until the
+//! window resets.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    config::RateLimitConfig, infra::RateLimitWindow, json_web::User, services::WebError,
+    WaterOfLifeState,
+};
+
+const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+/// Seconds until the window resets, not a timestamp, so clients don't need a synced clock.
+const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+#[derive(Debug, Clone, Copy)]
+enum Quota {
+    Requests,
+    Uploads,
+}
+
+impl Quota {
+    fn key(&self, user_id: &str) -> String {
+        match self {
+            Self::Requests => format!("quota:requests:{}", user_id),
+            Self::Uploads => format!("quota:uploads:{}", user_id),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        match self {
+            Self::Requests => Duration::from_secs(60 * 60),
+            Self::Uploads => Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// The role's quota, or `None` when it has none.
+    fn limit(&self, config: &RateLimitConfig, role: &str) -> Option<u64> {
+        let limit = match self {
+            Self::Requests => config.requests_per_hour(role),
+            Self::Uploads => config.uploads_per_day(role),
+        };
+        (limit > 0).then_some(limit)
+    }
+
+    fn exceeded_message(&self) -> &'static str {
+        match self {
+            Self::Requests => "You've made too many requests. Try again later.",
+            Self::Uploads => "You've uploaded too many images today. Try again later.",
+        }
+    }
+}
+
+/// Sets the `X-RateLimit-*` headers, leaving any already there alone so the tighter, more
+/// specific quota checked further in is the one reported.
+fn set_headers(headers: &mut HeaderMap, limit: u64, window: &RateLimitWindow) {
+    let values = [
+        (X_RATELIMIT_LIMIT, limit),
+        (X_RATELIMIT_REMAINING, limit.saturating_sub(window.hits)),
+        (X_RATELIMIT_RESET, window.resets_in.as_secs()),
+    ];
+    for (name, value) in values {
+        if !headers.contains_key(&name) {
+            headers.insert(name, HeaderValue::from(value));
+        }
+    }
+}
+
+/// Counts the request against the user's quota and refuses it once the quota is used up. If the
+/// store can't be reached the request goes through uncounted, rather than every request failing.
+async fn enforce(state: &WaterOfLifeState, quota: Quota, request: Request, next: Next) -> Response {
+    let Some(user) = request.extensions().get::<User>() else {
+        return next.run(request).await;
+    };
+    let Some(limit) = quota.limit(&state.config.rate_limits, &user.role) else {
+        return next.run(request).await;
+    };
+    let window = match state
+        .stores
+        .rate_limits
+        .hit(&quota.key(&user.user_id), quota.window())
+        .await
+    {
+        Ok(window) => window,
+        Err(e) => {
+            tracing::warn!("enforce: {}", e);
+            return next.run(request).await;
+        }
+    };
+
+    let mut response = if window.hits > limit {
+        let mut response =
+            WebError::TooManyRequests(quota.exceeded_message().into()).into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(window.resets_in.as_secs()));
+        response
+    } else {
+        next.run(request).await
+    };
+    set_headers(response.headers_mut(), limit, &window);
+    response
+}
+
+/// `rate_limits.requests_per_hour`, for every route behind authentication.
+pub async fn request_quota(
+    State(state): State<WaterOfLifeState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    enforce(&state, Quota::Requests, request, next).await
+}
+
+/// `rate_limits.uploads_per_day`, for the routes that start an image upload.
+pub async fn upload_quota(
+    State(state): State<WaterOfLifeState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    enforce(&state, Quota::Uploads, request, next).await
+}
@@ -0,0 +1,443 @@
+//! Data access behind traits, so handlers don't depend on how records are stored. Every
+//! repository has a SQLite implementation, and handlers not yet moved over still query the pool
+//! directly. Users and spirits also have PostgreSQL ones behind the `postgres` feature.
+//!
+//! Handlers that make several writes take a [`Transaction`] from [`Repositories::begin`] and
+//! pass it to each repository call and query, so either every write lands or none do.
+//!
+//! The queries are checked at compile time unless the `runtime-queries` feature is on.
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "runtime-queries")]
+mod runtime;
+#[cfg(not(feature = "runtime-queries"))]
+mod sqlite;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+#[cfg(feature = "postgres")]
+use sqlx::PgPool;
+use sqlx::{Sqlite, SqlitePool};
+use thiserror::Error;
+
+use crate::{
+    json_web::User,
+    services::{SearchResponse, SpiritDetailResponse},
+};
+
+#[cfg(feature = "postgres")]
+use self::postgres::{PostgresSpiritRepository, PostgresUserRepository};
+#[cfg(feature = "runtime-queries")]
+use self::runtime::{SqliteReviewRepository, SqliteSpiritRepository, SqliteUserRepository};
+#[cfg(not(feature = "runtime-queries"))]
+use self::sqlite::{SqliteReviewRepository, SqliteSpiritRepository, SqliteUserRepository};
+
+#[derive(Error, Debug)]
+pub enum RepositoryError {
+    #[error("Error querying database")]
+    Database(#[from] sqlx::Error),
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+/// Writes made through a transaction are only kept once it's committed. Dropping it, say by
+/// returning early with `?`, rolls them back.
+pub type Transaction = sqlx::Transaction<'static, Sqlite>;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find(&self, user_id: &str) -> RepositoryResult<Option<User>>;
+    async fn exists(&self, user_id: &str) -> RepositoryResult<bool>;
+    /// Adds a user. Existing users are left as they are.
+    async fn insert(&self, user: &User) -> RepositoryResult<()>;
+    /// Adds the user, or brings their username, email, role and email verification up to date
+    /// with `user`, and records the login. Their refresh token version is kept.
+    async fn record_login(&self, user: &User) -> RepositoryResult<()>;
+    /// Moves the user's refresh token version on from `version`, revoking the refresh tokens
+    /// issued with it, and returns the new one. `None` when the user is gone or their version
+    /// has already moved on.
+    async fn advance_refresh_token_version(
+        &self,
+        user_id: &str,
+        version: i64,
+    ) -> RepositoryResult<Option<i64>>;
+    /// Changes an existing user's role. Returns whether the user was found.
+    async fn set_role(&self, user_id: &str, role: &str) -> RepositoryResult<bool>;
+    /// Records whether Keycloak has verified the user's email.
+    async fn set_email_verified(&self, user_id: &str, email_verified: bool)
+        -> RepositoryResult<()>;
+    /// The user's latest Keycloak refresh token, if one is kept.
+    async fn identity_provider_token(&self, user_id: &str) -> RepositoryResult<Option<String>>;
+    /// Keeps `refresh_token` as the user's latest Keycloak refresh token, or forgets it when
+    /// `None`.
+    async fn set_identity_provider_token(
+        &self,
+        user_id: &str,
+        refresh_token: Option<&str>,
+    ) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait SpiritRepository: Send + Sync {
+    /// A spirit that hasn't been deleted, whatever its moderation status, along with
+    /// `viewer_id`'s rating of it.
+    async fn find(
+        &self,
+        spirit_id: &str,
+        viewer_id: &str,
+    ) -> RepositoryResult<Option<SpiritDetailResponse>>;
+    /// The id of the spirit with this name and distiller, ignoring case.
+    async fn find_by_name(&self, name: &str, distiller: &str) -> RepositoryResult<Option<String>>;
+    /// Visible spirits matching `name`, optionally narrowed to a region and availability.
+    async fn search(
+        &self,
+        name: &str,
+        region: Option<i64>,
+        availability: Option<&str>,
+    ) -> RepositoryResult<Vec<SearchResponse>>;
+}
+
+#[async_trait]
+pub trait ReviewRepository: Send + Sync {
+    /// Adds `user_id`'s review of a spirit and returns its id.
+    async fn insert(
+        &self,
+        transaction: &mut Transaction,
+        user_id: &str,
+        spirit_id: &str,
+        body: &str,
+    ) -> RepositoryResult<i64>;
+}
+
+#[derive(Clone)]
+pub struct Repositories {
+    database: SqlitePool,
+    pub users: Arc<dyn UserRepository>,
+    pub spirits: Arc<dyn SpiritRepository>,
+    pub reviews: Arc<dyn ReviewRepository>,
+}
+
+impl Repositories {
+    pub fn sqlite(database: &SqlitePool) -> Self {
+        Self {
+            database: database.clone(),
+            users: Arc::new(SqliteUserRepository::new(database.clone())),
+            spirits: Arc::new(SqliteSpiritRepository::new(database.clone())),
+            reviews: Arc::new(SqliteReviewRepository),
+        }
+    }
+
+    /// Starts a transaction for a handler that makes more than one write.
+    pub async fn begin(&self) -> RepositoryResult<Transaction> {
+        Ok(self.database.begin().await?)
+    }
+}
+
+/// The repositories ported to PostgreSQL so far. Until the rest are, only the admin commands
+/// that need nothing else run against it.
+#[cfg(feature = "postgres")]
+#[derive(Clone)]
+pub struct PostgresRepositories {
+    pub users: Arc<dyn UserRepository>,
+    /// Only the tests read this until the spirit handlers can be served from PostgreSQL.
+    #[allow(unused)]
+    pub spirits: Arc<dyn SpiritRepository>,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresRepositories {
+    pub fn new(database: &PgPool) -> Self {
+        Self {
+            users: Arc::new(PostgresUserRepository::new(database.clone())),
+            spirits: Arc::new(PostgresSpiritRepository::new(database.clone())),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use crate::{
+        services::{APP_ADMIN_ROLE, APP_USER_ROLE},
+        testing,
+    };
+
+    use super::*;
+
+    async fn repositories() -> (SqlitePool, Repositories) {
+        let database = testing::database().await;
+        let repositories = Repositories::sqlite(&database);
+        (database, repositories)
+    }
+
+    fn user(user_id: &str) -> User {
+        User {
+            user_id: user_id.to_owned(),
+            preferred_username: format!("{}-name", user_id),
+            email: format!("{}@example.com", user_id),
+            refresh_token_version: 1,
+            role: APP_USER_ROLE.to_owned(),
+            email_verified: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn inserted_user_can_be_found() {
+        let (_, repositories) = repositories().await;
+        let user = user("alice");
+        repositories.users.insert(&user).await.unwrap();
+
+        let found = repositories.users.find("alice").await.unwrap().unwrap();
+        assert_eq!(found.preferred_username, user.preferred_username);
+        assert_eq!(found.email, user.email);
+        assert_eq!(found.role, user.role);
+        assert_eq!(found.refresh_token_version, 1);
+        assert!(!found.email_verified);
+        assert!(repositories.users.exists("alice").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn unknown_user_is_not_found() {
+        let (_, repositories) = repositories().await;
+
+        assert!(repositories.users.find("nobody").await.unwrap().is_none());
+        assert!(!repositories.users.exists("nobody").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn insert_leaves_an_existing_user_alone() {
+        let (_, repositories) = repositories().await;
+        repositories.users.insert(&user("alice")).await.unwrap();
+        let mut changed = user("alice");
+        changed.email = "changed@example.com".to_owned();
+        repositories.users.insert(&changed).await.unwrap();
+
+        let found = repositories.users.find("alice").await.unwrap().unwrap();
+        assert_eq!(found.email, "alice@example.com");
+    }
+
+    #[tokio::test]
+    async fn record_login_adds_a_new_user() {
+        let (_, repositories) = repositories().await;
+        repositories
+            .users
+            .record_login(&user("alice"))
+            .await
+            .unwrap();
+
+        assert!(repositories.users.exists("alice").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn record_login_updates_the_profile_but_keeps_the_refresh_token_version() {
+        let (database, repositories) = repositories().await;
+        repositories.users.insert(&user("alice")).await.unwrap();
+        sqlx::query("UPDATE users SET refresh_token_version = 3 WHERE user_id = 'alice'")
+            .execute(&database)
+            .await
+            .unwrap();
+
+        let mut login = user("alice");
+        login.preferred_username = "alice-renamed".to_owned();
+        login.email = "renamed@example.com".to_owned();
+        login.role = APP_ADMIN_ROLE.to_owned();
+        login.email_verified = true;
+        repositories.users.record_login(&login).await.unwrap();
+
+        let found = repositories.users.find("alice").await.unwrap().unwrap();
+        assert_eq!(found.preferred_username, "alice-renamed");
+        assert_eq!(found.email, "renamed@example.com");
+        assert_eq!(found.role, APP_ADMIN_ROLE);
+        assert!(found.email_verified);
+        assert_eq!(found.refresh_token_version, 3);
+    }
+
+    #[tokio::test]
+    async fn set_role_changes_the_role_of_existing_users_only() {
+        let (_, repositories) = repositories().await;
+        repositories.users.insert(&user("alice")).await.unwrap();
+
+        assert!(repositories
+            .users
+            .set_role("alice", APP_ADMIN_ROLE)
+            .await
+            .unwrap());
+        assert!(!repositories
+            .users
+            .set_role("nobody", APP_ADMIN_ROLE)
+            .await
+            .unwrap());
+        let found = repositories.users.find("alice").await.unwrap().unwrap();
+        assert_eq!(found.role, APP_ADMIN_ROLE);
+    }
+
+    #[tokio::test]
+    async fn set_email_verified_is_stored() {
+        let (_, repositories) = repositories().await;
+        repositories.users.insert(&user("alice")).await.unwrap();
+
+        repositories
+            .users
+            .set_email_verified("alice", true)
+            .await
+            .unwrap();
+        assert!(
+            repositories
+                .users
+                .find("alice")
+                .await
+                .unwrap()
+                .unwrap()
+                .email_verified
+        );
+        repositories
+            .users
+            .set_email_verified("alice", false)
+            .await
+            .unwrap();
+        assert!(
+            !repositories
+                .users
+                .find("alice")
+                .await
+                .unwrap()
+                .unwrap()
+                .email_verified
+        );
+    }
+
+    #[tokio::test]
+    async fn identity_provider_token_is_kept_replaced_and_forgotten() {
+        let (_, repositories) = repositories().await;
+        let users = &repositories.users;
+        users.insert(&user("alice")).await.unwrap();
+        assert_eq!(users.identity_provider_token("alice").await.unwrap(), None);
+
+        users
+            .set_identity_provider_token("alice", Some("first"))
+            .await
+            .unwrap();
+        assert_eq!(
+            users
+                .identity_provider_token("alice")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("first")
+        );
+        users
+            .set_identity_provider_token("alice", Some("second"))
+            .await
+            .unwrap();
+        assert_eq!(
+            users
+                .identity_provider_token("alice")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("second")
+        );
+        users
+            .set_identity_provider_token("alice", None)
+            .await
+            .unwrap();
+        assert_eq!(users.identity_provider_token("alice").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn spirit_can_be_found_until_it_is_deleted() {
+        let (database, repositories) = repositories().await;
+        let spirit_id = testing::create_spirit(&database, "Harbor Light").await;
+
+        let found = repositories
+            .spirits
+            .find(&spirit_id, "viewer")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.name, "Harbor Light");
+        assert!(found.my_rating.is_none());
+
+        sqlx::query("UPDATE spirits SET deleted_at = CURRENT_TIMESTAMP WHERE uuid = ?")
+            .bind(&spirit_id)
+            .execute(&database)
+            .await
+            .unwrap();
+        assert!(repositories
+            .spirits
+            .find(&spirit_id, "viewer")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn find_by_name_ignores_case() {
+        let (database, repositories) = repositories().await;
+        let spirit_id = testing::create_spirit(&database, "Harbor Light").await;
+
+        let found = repositories
+            .spirits
+            .find_by_name("harbor light", "TEST DISTILLERY")
+            .await
+            .unwrap();
+        assert_eq!(found, Some(spirit_id));
+        let missing = repositories
+            .spirits
+            .find_by_name("Harbor Light", "Another Distillery")
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn search_matches_names() {
+        let (database, repositories) = repositories().await;
+        let spirit_id = testing::create_spirit(&database, "Harbor Light").await;
+        testing::create_spirit(&database, "Mountain Rye").await;
+
+        let results = repositories
+            .spirits
+            .search("harbor", None, None)
+            .await
+            .unwrap();
+        let ids = results
+            .into_iter()
+            .filter_map(|result| result.uuid)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![spirit_id]);
+    }
+
+    #[tokio::test]
+    async fn review_is_only_kept_once_the_transaction_commits() {
+        let (database, repositories) = repositories().await;
+        let user = testing::create_user(&database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&database, "Harbor Light").await;
+        let count_reviews = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM reviews")
+                .fetch_one(&database)
+                .await
+                .unwrap()
+        };
+
+        let mut transaction = repositories.begin().await.unwrap();
+        repositories
+            .reviews
+            .insert(&mut transaction, &user.user_id, &spirit_id, "Rolled back")
+            .await
+            .unwrap();
+        drop(transaction);
+        assert_eq!(count_reviews().await, 0);
+
+        let mut transaction = repositories.begin().await.unwrap();
+        let review_id = repositories
+            .reviews
+            .insert(&mut transaction, &user.user_id, &spirit_id, "Kept")
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+        assert_eq!(count_reviews().await, 1);
+        assert!(review_id > 0);
+    }
+}
@@ -0,0 +1,462 @@
+//! The user and spirit repositories on PostgreSQL, built with the `postgres` feature. They
+//! read the ports in `sql/postgres` and are checked when they run, since the offline query data
+//! only covers SQLite.
+
+use async_trait::async_trait;
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::{
+    json_web::User,
+    services::{SearchResponse, SpiritDetailResponse},
+};
+
+use super::{RepositoryResult, SpiritRepository, UserRepository};
+
+fn user(row: &PgRow) -> sqlx::Result<User> {
+    Ok(User {
+        user_id: row.try_get("user_id")?,
+        preferred_username: row.try_get("preferred_username")?,
+        email: row.try_get("email")?,
+        refresh_token_version: row.try_get("refresh_token_version")?,
+        role: row.try_get("role")?,
+        email_verified: row.try_get("email_verified")?,
+    })
+}
+
+fn spirit_detail(row: &PgRow) -> sqlx::Result<SpiritDetailResponse> {
+    Ok(SpiritDetailResponse {
+        uuid: row.try_get("uuid")?,
+        name: row.try_get("name")?,
+        description: row.try_get("description")?,
+        distiller: row.try_get("distiller")?,
+        bottler: row.try_get("bottler")?,
+        typ: row.try_get("typ")?,
+        abv: row.try_get("abv")?,
+        age: row.try_get("age")?,
+        version: row.try_get("version")?,
+        availability: row.try_get("availability")?,
+        region: row.try_get("region")?,
+        average_rating: row.try_get("average_rating")?,
+        rating_count: row.try_get("rating_count")?,
+        my_rating: row.try_get("my_rating")?,
+        images: row.try_get("images")?,
+        related_releases: row.try_get("related_releases")?,
+        status: row.try_get("status")?,
+        submitted_by: row.try_get("submitted_by")?,
+    })
+}
+
+fn search_result(row: &PgRow) -> sqlx::Result<SearchResponse> {
+    Ok(SearchResponse {
+        uuid: row.try_get("uuid")?,
+        name: row.try_get("name")?,
+        distiller: row.try_get("distiller")?,
+        bottler: row.try_get("bottler")?,
+        typ: row.try_get("typ")?,
+        average_rating: row.try_get("average_rating")?,
+        rating_count: row.try_get("rating_count")?,
+    })
+}
+
+pub struct PostgresUserRepository {
+    database: PgPool,
+}
+
+impl PostgresUserRepository {
+    pub fn new(database: PgPool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PostgresUserRepository {
+    async fn find(&self, user_id: &str) -> RepositoryResult<Option<User>> {
+        let row = sqlx::query(include_str!("../../sql/postgres/select_user.sql"))
+            .bind(user_id)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.as_ref().map(user).transpose()?)
+    }
+
+    async fn exists(&self, user_id: &str) -> RepositoryResult<bool> {
+        Ok(
+            sqlx::query(include_str!("../../sql/postgres/select_user_exists.sql"))
+                .bind(user_id)
+                .fetch_optional(&self.database)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn insert(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query(include_str!("../../sql/postgres/insert_user.sql"))
+            .bind(&user.user_id)
+            .bind(&user.preferred_username)
+            .bind(&user.email)
+            .bind(user.refresh_token_version)
+            .bind(&user.role)
+            .bind(user.email_verified)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_login(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query(include_str!("../../sql/postgres/upsert_user_login.sql"))
+            .bind(&user.user_id)
+            .bind(&user.preferred_username)
+            .bind(&user.email)
+            .bind(user.refresh_token_version)
+            .bind(&user.role)
+            .bind(user.email_verified)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    async fn advance_refresh_token_version(
+        &self,
+        user_id: &str,
+        version: i64,
+    ) -> RepositoryResult<Option<i64>> {
+        let row = sqlx::query(include_str!(
+            "../../sql/postgres/update_refresh_token_version.sql"
+        ))
+        .bind(user_id)
+        .bind(version)
+        .fetch_optional(&self.database)
+        .await?;
+        Ok(row.map(|row| row.try_get("refresh_token_version")).transpose()?)
+    }
+
+    async fn set_role(&self, user_id: &str, role: &str) -> RepositoryResult<bool> {
+        let result = sqlx::query(include_str!("../../sql/postgres/update_user_role.sql"))
+            .bind(user_id)
+            .bind(role)
+            .execute(&self.database)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &str,
+        email_verified: bool,
+    ) -> RepositoryResult<()> {
+        sqlx::query(include_str!(
+            "../../sql/postgres/update_user_email_verified.sql"
+        ))
+        .bind(user_id)
+        .bind(email_verified)
+        .execute(&self.database)
+        .await?;
+        Ok(())
+    }
+
+    async fn identity_provider_token(&self, user_id: &str) -> RepositoryResult<Option<String>> {
+        let row = sqlx::query(include_str!(
+            "../../sql/postgres/select_identity_provider_token.sql"
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.database)
+        .await?;
+        Ok(row.map(|row| row.try_get("refresh_token")).transpose()?)
+    }
+
+    async fn set_identity_provider_token(
+        &self,
+        user_id: &str,
+        refresh_token: Option<&str>,
+    ) -> RepositoryResult<()> {
+        let query = match refresh_token {
+            Some(refresh_token) => sqlx::query(include_str!(
+                "../../sql/postgres/upsert_identity_provider_token.sql"
+            ))
+            .bind(user_id)
+            .bind(refresh_token),
+            None => sqlx::query(include_str!(
+                "../../sql/postgres/delete_identity_provider_token.sql"
+            ))
+            .bind(user_id),
+        };
+        query.execute(&self.database).await?;
+        Ok(())
+    }
+}
+
+pub struct PostgresSpiritRepository {
+    database: PgPool,
+}
+
+impl PostgresSpiritRepository {
+    pub fn new(database: PgPool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl SpiritRepository for PostgresSpiritRepository {
+    async fn find(
+        &self,
+        spirit_id: &str,
+        viewer_id: &str,
+    ) -> RepositoryResult<Option<SpiritDetailResponse>> {
+        let row = sqlx::query(include_str!("../../sql/postgres/select_spirit.sql"))
+            .bind(spirit_id)
+            .bind(viewer_id)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.as_ref().map(spirit_detail).transpose()?)
+    }
+
+    async fn find_by_name(&self, name: &str, distiller: &str) -> RepositoryResult<Option<String>> {
+        let row = sqlx::query(include_str!("../../sql/postgres/select_spirit_by_name.sql"))
+            .bind(name)
+            .bind(distiller)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.map(|row| row.try_get("id")).transpose()?)
+    }
+
+    /// Matches whole words of `name`, as PostgreSQL's `plainto_tsquery` reads it, rather than
+    /// FTS5's query syntax.
+    async fn search(
+        &self,
+        name: &str,
+        region: Option<i64>,
+        availability: Option<&str>,
+    ) -> RepositoryResult<Vec<SearchResponse>> {
+        let rows = sqlx::query(include_str!("../../sql/postgres/search_spirit.sql"))
+            .bind(name)
+            .bind(region)
+            .bind(availability)
+            .fetch_all(&self.database)
+            .await?;
+        Ok(rows.iter().map(search_result).collect::<Result<_, _>>()?)
+    }
+}
+
+/// Run with `TEST_POSTGRES_URL` pointing at a database these may create schemas in, and
+/// `cargo test --features postgres -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use std::{env, str::FromStr};
+
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use uuid::Uuid;
+
+    use crate::services::{APP_ADMIN_ROLE, APP_USER_ROLE};
+
+    use super::*;
+
+    /// A pool on a new, migrated schema of its own, so tests don't see each other's rows.
+    async fn database() -> PgPool {
+        let url = env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL must be set");
+        let schema = format!("test_{}", Uuid::new_v4().simple());
+        let admin = PgPool::connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        let options = PgConnectOptions::from_str(&url)
+            .unwrap()
+            .options([("search_path", schema.as_str())]);
+        let database = PgPoolOptions::new().connect_with(options).await.unwrap();
+        sqlx::migrate!("./migrations/postgres")
+            .run(&database)
+            .await
+            .unwrap();
+        database
+    }
+
+    fn user(user_id: &str) -> User {
+        User {
+            user_id: user_id.to_owned(),
+            preferred_username: format!("{}-name", user_id),
+            email: format!("{}@example.com", user_id),
+            refresh_token_version: 1,
+            role: APP_USER_ROLE.to_owned(),
+            email_verified: false,
+        }
+    }
+
+    async fn create_spirit(database: &PgPool, name: &str) -> String {
+        let spirit_id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO spirits (uuid, name, description, distiller, bottler, type, abv, age)
+            VALUES ($1, $2, '', 'Test Distillery', 'Test Bottler', 'Whisky', 45.0, '')",
+        )
+        .bind(&spirit_id)
+        .bind(name)
+        .execute(database)
+        .await
+        .unwrap();
+        spirit_id
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn users_are_stored_and_updated() {
+        let database = database().await;
+        let users = PostgresUserRepository::new(database.clone());
+        assert!(!users.exists("alice").await.unwrap());
+        users.insert(&user("alice")).await.unwrap();
+        sqlx::query("UPDATE users SET refresh_token_version = 3")
+            .execute(&database)
+            .await
+            .unwrap();
+
+        let mut login = user("alice");
+        login.email = "renamed@example.com".to_owned();
+        login.email_verified = true;
+        users.record_login(&login).await.unwrap();
+        let found = users.find("alice").await.unwrap().unwrap();
+        assert_eq!(found.email, "renamed@example.com");
+        assert!(found.email_verified);
+        assert_eq!(found.refresh_token_version, 3);
+
+        assert!(users.set_role("alice", APP_ADMIN_ROLE).await.unwrap());
+        assert!(!users.set_role("nobody", APP_ADMIN_ROLE).await.unwrap());
+        assert_eq!(
+            users.find("alice").await.unwrap().unwrap().role,
+            APP_ADMIN_ROLE
+        );
+        users.set_email_verified("alice", false).await.unwrap();
+        assert!(!users.find("alice").await.unwrap().unwrap().email_verified);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn identity_provider_token_is_kept_replaced_and_forgotten() {
+        let database = database().await;
+        let users = PostgresUserRepository::new(database);
+        users.insert(&user("alice")).await.unwrap();
+
+        users
+            .set_identity_provider_token("alice", Some("first"))
+            .await
+            .unwrap();
+        users
+            .set_identity_provider_token("alice", Some("second"))
+            .await
+            .unwrap();
+        assert_eq!(
+            users
+                .identity_provider_token("alice")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("second")
+        );
+        users
+            .set_identity_provider_token("alice", None)
+            .await
+            .unwrap();
+        assert_eq!(users.identity_provider_token("alice").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn spirit_details_include_ratings_images_and_releases() {
+        let database = database().await;
+        let spirits = PostgresSpiritRepository::new(database.clone());
+        let spirit_id = create_spirit(&database, "Harbor Light").await;
+        let related_id = create_spirit(&database, "Harbor Light Cask Strength").await;
+        for (user_id, score) in [("alice", 80), ("bob", 91)] {
+            sqlx::query("INSERT INTO ratings (user_id, spirit_id, score) VALUES ($1, $2, $3)")
+                .bind(user_id)
+                .bind(&spirit_id)
+                .bind(score)
+                .execute(&database)
+                .await
+                .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO spirit_images
+                (id, spirit_id, content_type, size_bytes, width, height, sha256, is_primary)
+            VALUES ('image', $1, 'image/png', 1, 1, 1, '', TRUE)",
+        )
+        .bind(&spirit_id)
+        .execute(&database)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO spirit_relations (spirit_id, related_id, created_by)
+            VALUES ($1, $2, 'alice')",
+        )
+        .bind(&related_id)
+        .bind(&spirit_id)
+        .execute(&database)
+        .await
+        .unwrap();
+
+        let found = spirits.find(&spirit_id, "bob").await.unwrap().unwrap();
+        assert_eq!(found.average_rating, Some(85.5));
+        assert_eq!(found.rating_count, 2);
+        assert_eq!(found.my_rating, Some(91));
+        assert_eq!(found.images.0.len(), 1);
+        assert_eq!(found.related_releases.0.len(), 1);
+        assert_eq!(found.status, "approved");
+
+        let unrated = spirits.find(&related_id, "bob").await.unwrap().unwrap();
+        assert_eq!(unrated.average_rating, None);
+        assert!(unrated.images.0.is_empty());
+        assert!(spirits.find("missing", "bob").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn find_by_name_ignores_case() {
+        let database = database().await;
+        let spirits = PostgresSpiritRepository::new(database.clone());
+        let spirit_id = create_spirit(&database, "Harbor Light").await;
+
+        let found = spirits
+            .find_by_name("harbor light", "TEST DISTILLERY")
+            .await
+            .unwrap();
+        assert_eq!(found, Some(spirit_id));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_POSTGRES_URL"]
+    async fn search_matches_names_and_aliases_of_visible_spirits() {
+        let database = database().await;
+        let spirits = PostgresSpiritRepository::new(database.clone());
+        let named = create_spirit(&database, "Harbor Light").await;
+        let aliased = create_spirit(&database, "Old Cask").await;
+        let pending = create_spirit(&database, "Harbor Mist").await;
+        sqlx::query(
+            "INSERT INTO spirit_aliases (spirit_id, alias, created_by) VALUES ($1, 'Harbor', 'a')",
+        )
+        .bind(&aliased)
+        .execute(&database)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO spirit_submissions (spirit_id, user_id, status)
+            VALUES ($1, 'alice', 'pending')",
+        )
+        .bind(&pending)
+        .execute(&database)
+        .await
+        .unwrap();
+
+        let results = spirits.search("harbor", None, None).await.unwrap();
+        let mut ids = results
+            .into_iter()
+            .filter_map(|result| result.uuid)
+            .collect::<Vec<_>>();
+        ids.sort();
+        let mut expected = vec![named, aliased];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        let unavailable = spirits
+            .search("harbor", None, Some("discontinued"))
+            .await
+            .unwrap();
+        assert!(unavailable.is_empty());
+    }
+}
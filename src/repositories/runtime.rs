@@ -0,0 +1,272 @@
+//! The SQLite repositories with queries checked when they run instead of at compile time, for
+//! forks whose schema differs from the offline query data. Built with the `runtime-queries`
+//! feature in place of the `sqlite` module, reading the same SQL files.
+
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteRow, Column, Decode, Row, Sqlite, SqlitePool, Type};
+
+use crate::{
+    json_web::User,
+    services::{SearchResponse, SpiritDetailResponse},
+};
+
+use super::{RepositoryResult, ReviewRepository, SpiritRepository, Transaction, UserRepository};
+
+/// A column's name without the `?`, `!` and `: Type` overrides the compile-time checked macros
+/// read from aliases such as `'region?: String'`.
+fn column_name(alias: &str) -> &str {
+    alias
+        .split(':')
+        .next()
+        .unwrap_or(alias)
+        .trim_end()
+        .trim_end_matches(['?', '!'])
+}
+
+/// Decodes the column called `name`, ignoring any overrides in its alias.
+fn column<'r, T>(row: &'r SqliteRow, name: &str) -> sqlx::Result<T>
+where
+    T: Decode<'r, Sqlite> + Type<Sqlite>,
+{
+    let index = row
+        .columns()
+        .iter()
+        .position(|column| column_name(column.name()) == name)
+        .ok_or_else(|| sqlx::Error::ColumnNotFound(name.to_owned()))?;
+    row.try_get(index)
+}
+
+fn user(row: &SqliteRow) -> sqlx::Result<User> {
+    Ok(User {
+        user_id: column(row, "user_id")?,
+        preferred_username: column(row, "preferred_username")?,
+        email: column(row, "email")?,
+        refresh_token_version: column(row, "refresh_token_version")?,
+        role: column(row, "role")?,
+        email_verified: column(row, "email_verified")?,
+    })
+}
+
+fn spirit_detail(row: &SqliteRow) -> sqlx::Result<SpiritDetailResponse> {
+    Ok(SpiritDetailResponse {
+        uuid: column(row, "uuid")?,
+        name: column(row, "name")?,
+        description: column(row, "description")?,
+        distiller: column(row, "distiller")?,
+        bottler: column(row, "bottler")?,
+        typ: column(row, "typ")?,
+        abv: column(row, "abv")?,
+        age: column(row, "age")?,
+        version: column(row, "version")?,
+        availability: column(row, "availability")?,
+        region: column(row, "region")?,
+        average_rating: column(row, "average_rating")?,
+        rating_count: column(row, "rating_count")?,
+        my_rating: column(row, "my_rating")?,
+        images: column(row, "images")?,
+        related_releases: column(row, "related_releases")?,
+        status: column(row, "status")?,
+        submitted_by: column(row, "submitted_by")?,
+    })
+}
+
+fn search_result(row: &SqliteRow) -> sqlx::Result<SearchResponse> {
+    Ok(SearchResponse {
+        uuid: column(row, "uuid")?,
+        name: column(row, "name")?,
+        distiller: column(row, "distiller")?,
+        bottler: column(row, "bottler")?,
+        typ: column(row, "typ")?,
+        average_rating: column(row, "average_rating")?,
+        rating_count: column(row, "rating_count")?,
+    })
+}
+
+pub struct SqliteUserRepository {
+    database: SqlitePool,
+}
+
+impl SqliteUserRepository {
+    pub fn new(database: SqlitePool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn find(&self, user_id: &str) -> RepositoryResult<Option<User>> {
+        let row = sqlx::query(include_str!("../../sql/select_user.sql"))
+            .bind(user_id)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.as_ref().map(user).transpose()?)
+    }
+
+    async fn exists(&self, user_id: &str) -> RepositoryResult<bool> {
+        Ok(
+            sqlx::query(include_str!("../../sql/select_user_exists.sql"))
+                .bind(user_id)
+                .fetch_optional(&self.database)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn insert(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query(include_str!("../../sql/insert_user.sql"))
+            .bind(&user.user_id)
+            .bind(&user.preferred_username)
+            .bind(&user.email)
+            .bind(user.refresh_token_version)
+            .bind(&user.role)
+            .bind(user.email_verified)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_login(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query(include_str!("../../sql/upsert_user_login.sql"))
+            .bind(&user.user_id)
+            .bind(&user.preferred_username)
+            .bind(&user.email)
+            .bind(user.refresh_token_version)
+            .bind(&user.role)
+            .bind(user.email_verified)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    async fn advance_refresh_token_version(
+        &self,
+        user_id: &str,
+        version: i64,
+    ) -> RepositoryResult<Option<i64>> {
+        let row = sqlx::query(include_str!("../../sql/update_refresh_token_version.sql"))
+            .bind(user_id)
+            .bind(version)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.map(|row| column(&row, "refresh_token_version")).transpose()?)
+    }
+
+    async fn set_role(&self, user_id: &str, role: &str) -> RepositoryResult<bool> {
+        let result = sqlx::query(include_str!("../../sql/update_user_role.sql"))
+            .bind(user_id)
+            .bind(role)
+            .execute(&self.database)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &str,
+        email_verified: bool,
+    ) -> RepositoryResult<()> {
+        sqlx::query(include_str!("../../sql/update_user_email_verified.sql"))
+            .bind(user_id)
+            .bind(email_verified)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    async fn identity_provider_token(&self, user_id: &str) -> RepositoryResult<Option<String>> {
+        let row = sqlx::query(include_str!("../../sql/select_identity_provider_token.sql"))
+            .bind(user_id)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.map(|row| column(&row, "refresh_token")).transpose()?)
+    }
+
+    async fn set_identity_provider_token(
+        &self,
+        user_id: &str,
+        refresh_token: Option<&str>,
+    ) -> RepositoryResult<()> {
+        let query = match refresh_token {
+            Some(refresh_token) => {
+                sqlx::query(include_str!("../../sql/upsert_identity_provider_token.sql"))
+                    .bind(user_id)
+                    .bind(refresh_token)
+            }
+            None => sqlx::query(include_str!("../../sql/delete_identity_provider_token.sql"))
+                .bind(user_id),
+        };
+        query.execute(&self.database).await?;
+        Ok(())
+    }
+}
+
+pub struct SqliteSpiritRepository {
+    database: SqlitePool,
+}
+
+impl SqliteSpiritRepository {
+    pub fn new(database: SqlitePool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl SpiritRepository for SqliteSpiritRepository {
+    async fn find(
+        &self,
+        spirit_id: &str,
+        viewer_id: &str,
+    ) -> RepositoryResult<Option<SpiritDetailResponse>> {
+        let row = sqlx::query(include_str!("../../sql/select_spirit.sql"))
+            .bind(spirit_id)
+            .bind(viewer_id)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.as_ref().map(spirit_detail).transpose()?)
+    }
+
+    async fn find_by_name(&self, name: &str, distiller: &str) -> RepositoryResult<Option<String>> {
+        let row = sqlx::query(include_str!("../../sql/select_spirit_by_name.sql"))
+            .bind(name)
+            .bind(distiller)
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(row.map(|row| column(&row, "id")).transpose()?)
+    }
+
+    async fn search(
+        &self,
+        name: &str,
+        region: Option<i64>,
+        availability: Option<&str>,
+    ) -> RepositoryResult<Vec<SearchResponse>> {
+        let rows = sqlx::query(include_str!("../../sql/search_spirit.sql"))
+            .bind(name)
+            .bind(region)
+            .bind(availability)
+            .fetch_all(&self.database)
+            .await?;
+        Ok(rows.iter().map(search_result).collect::<Result<_, _>>()?)
+    }
+}
+
+pub struct SqliteReviewRepository;
+
+#[async_trait]
+impl ReviewRepository for SqliteReviewRepository {
+    async fn insert(
+        &self,
+        transaction: &mut Transaction,
+        user_id: &str,
+        spirit_id: &str,
+        body: &str,
+    ) -> RepositoryResult<i64> {
+        let row = sqlx::query(include_str!("../../sql/insert_review.sql"))
+            .bind(user_id)
+            .bind(spirit_id)
+            .bind(body)
+            .fetch_one(&mut **transaction)
+            .await?;
+        Ok(column(&row, "id")?)
+    }
+}
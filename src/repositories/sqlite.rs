@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+
+use crate::{
+    json_web::User,
+    services::{RelatedRelease, SearchResponse, SpiritDetailResponse, SpiritImageSummary},
+};
+
+use super::{RepositoryResult, ReviewRepository, SpiritRepository, Transaction, UserRepository};
+
+pub struct SqliteUserRepository {
+    database: SqlitePool,
+}
+
+impl SqliteUserRepository {
+    pub fn new(database: SqlitePool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    async fn find(&self, user_id: &str) -> RepositoryResult<Option<User>> {
+        Ok(sqlx::query_file_as!(User, "sql/select_user.sql", user_id)
+            .fetch_optional(&self.database)
+            .await?)
+    }
+
+    async fn exists(&self, user_id: &str) -> RepositoryResult<bool> {
+        Ok(sqlx::query_file!("sql/select_user_exists.sql", user_id)
+            .fetch_optional(&self.database)
+            .await?
+            .is_some())
+    }
+
+    async fn insert(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query_file!(
+            "sql/insert_user.sql",
+            user.user_id,
+            user.preferred_username,
+            user.email,
+            user.refresh_token_version,
+            user.role,
+            user.email_verified
+        )
+        .execute(&self.database)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_login(&self, user: &User) -> RepositoryResult<()> {
+        sqlx::query_file!(
+            "sql/upsert_user_login.sql",
+            user.user_id,
+            user.preferred_username,
+            user.email,
+            user.refresh_token_version,
+            user.role,
+            user.email_verified
+        )
+        .execute(&self.database)
+        .await?;
+        Ok(())
+    }
+
+    async fn advance_refresh_token_version(
+        &self,
+        user_id: &str,
+        version: i64,
+    ) -> RepositoryResult<Option<i64>> {
+        Ok(
+            sqlx::query_file!("sql/update_refresh_token_version.sql", user_id, version)
+                .fetch_optional(&self.database)
+                .await?
+                .map(|row| row.refresh_token_version),
+        )
+    }
+
+    async fn set_role(&self, user_id: &str, role: &str) -> RepositoryResult<bool> {
+        let result = sqlx::query_file!("sql/update_user_role.sql", user_id, role)
+            .execute(&self.database)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_email_verified(
+        &self,
+        user_id: &str,
+        email_verified: bool,
+    ) -> RepositoryResult<()> {
+        sqlx::query_file!(
+            "sql/update_user_email_verified.sql",
+            user_id,
+            email_verified
+        )
+        .execute(&self.database)
+        .await?;
+        Ok(())
+    }
+
+    async fn identity_provider_token(&self, user_id: &str) -> RepositoryResult<Option<String>> {
+        Ok(
+            sqlx::query_file!("sql/select_identity_provider_token.sql", user_id)
+                .fetch_optional(&self.database)
+                .await?
+                .map(|row| row.refresh_token),
+        )
+    }
+
+    async fn set_identity_provider_token(
+        &self,
+        user_id: &str,
+        refresh_token: Option<&str>,
+    ) -> RepositoryResult<()> {
+        match refresh_token {
+            Some(refresh_token) => {
+                sqlx::query_file!(
+                    "sql/upsert_identity_provider_token.sql",
+                    user_id,
+                    refresh_token
+                )
+                .execute(&self.database)
+                .await?
+            }
+            None => {
+                sqlx::query_file!("sql/delete_identity_provider_token.sql", user_id)
+                    .execute(&self.database)
+                    .await?
+            }
+        };
+        Ok(())
+    }
+}
+
+pub struct SqliteSpiritRepository {
+    database: SqlitePool,
+}
+
+impl SqliteSpiritRepository {
+    pub fn new(database: SqlitePool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl SpiritRepository for SqliteSpiritRepository {
+    async fn find(
+        &self,
+        spirit_id: &str,
+        viewer_id: &str,
+    ) -> RepositoryResult<Option<SpiritDetailResponse>> {
+        Ok(sqlx::query_file_as!(
+            SpiritDetailResponse,
+            "sql/select_spirit.sql",
+            spirit_id,
+            viewer_id
+        )
+        .fetch_optional(&self.database)
+        .await?)
+    }
+
+    async fn find_by_name(&self, name: &str, distiller: &str) -> RepositoryResult<Option<String>> {
+        Ok(
+            sqlx::query_file!("sql/select_spirit_by_name.sql", name, distiller)
+                .fetch_optional(&self.database)
+                .await?
+                .map(|row| row.id),
+        )
+    }
+
+    async fn search(
+        &self,
+        name: &str,
+        region: Option<i64>,
+        availability: Option<&str>,
+    ) -> RepositoryResult<Vec<SearchResponse>> {
+        Ok(sqlx::query_file_as!(
+            SearchResponse,
+            "sql/search_spirit.sql",
+            name,
+            region,
+            availability
+        )
+        .fetch_all(&self.database)
+        .await?)
+    }
+}
+
+pub struct SqliteReviewRepository;
+
+#[async_trait]
+impl ReviewRepository for SqliteReviewRepository {
+    async fn insert(
+        &self,
+        transaction: &mut Transaction,
+        user_id: &str,
+        spirit_id: &str,
+        body: &str,
+    ) -> RepositoryResult<i64> {
+        Ok(
+            sqlx::query_file!("sql/insert_review.sql", user_id, spirit_id, body)
+                .fetch_one(&mut **transaction)
+                .await?
+                .id,
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::infra::RateLimitStore;
+
+/// Security events are logged under this target so operators can route them separately.
+pub const SECURITY_LOG_TARGET: &str = "security";
+
+/// This many failed token validations across all clients within the window is reported as a spike.
+const JWT_FAILURE_THRESHOLD: u64 = 20;
+const JWT_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+/// This many 403s for a single account within the window is reported.
+const FORBIDDEN_THRESHOLD: u64 = 10;
+const FORBIDDEN_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+const JWT_FAILURE_KEY: &str = "security:jwt";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    JwtValidationFailureSpike,
+    RefreshTokenReuse,
+    AdminRoleChange,
+    RepeatedForbidden,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityEvent {
+    kind: SecurityEventKind,
+    user_id: Option<String>,
+    detail: String,
+    timestamp: u64,
+}
+
+impl SecurityEvent {
+    pub fn new(kind: SecurityEventKind, user_id: Option<&str>, detail: String) -> Self {
+        Self {
+            kind,
+            user_id: user_id.map(str::to_owned),
+            detail,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Emits security events to the `security` log target and, if configured, a webhook.
+/// Failure spikes are counted in the shared rate limit store so they add up across instances.
+#[derive(Clone)]
+pub struct SecurityMonitor {
+    client: Client,
+    webhook_url: Option<String>,
+    counters: Arc<dyn RateLimitStore>,
+}
+
+impl SecurityMonitor {
+    pub fn new(
+        client: Client,
+        webhook_url: Option<String>,
+        counters: Arc<dyn RateLimitStore>,
+    ) -> Self {
+        Self {
+            client,
+            webhook_url,
+            counters,
+        }
+    }
+
+    pub fn emit(&self, event: SecurityEvent) {
+        tracing::warn!(
+            target: SECURITY_LOG_TARGET,
+            kind = ?event.kind,
+            user_id = event.user_id.as_deref().unwrap_or("-"),
+            detail = %event.detail,
+            "security event"
+        );
+
+        if let Some(webhook_url) = &self.webhook_url {
+            let request = self.client.post(webhook_url).json(&event);
+            tokio::spawn(async move {
+                if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                    tracing::error!(target: SECURITY_LOG_TARGET, "Could not deliver security webhook: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Records an occurrence and returns true when it is the `threshold`th within the current
+    /// window, so a sustained attack is reported once per window.
+    async fn record(&self, key: &str, threshold: u64, window: Duration) -> bool {
+        match self.counters.hit(key, window).await {
+            Ok(window) => window.hits == threshold,
+            Err(e) => {
+                tracing::error!(target: SECURITY_LOG_TARGET, "Could not count {}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    pub async fn record_jwt_failure(&self) {
+        if self
+            .record(JWT_FAILURE_KEY, JWT_FAILURE_THRESHOLD, JWT_FAILURE_WINDOW)
+            .await
+        {
+            self.emit(SecurityEvent::new(
+                SecurityEventKind::JwtValidationFailureSpike,
+                None,
+                format!(
+                    "{} failed token validations within {}s",
+                    JWT_FAILURE_THRESHOLD,
+                    JWT_FAILURE_WINDOW.as_secs()
+                ),
+            ));
+        }
+    }
+
+    pub async fn record_forbidden(&self, user_id: &str, path: &str) {
+        if self
+            .record(
+                &format!("security:forbidden:{}", user_id),
+                FORBIDDEN_THRESHOLD,
+                FORBIDDEN_WINDOW,
+            )
+            .await
+        {
+            self.emit(SecurityEvent::new(
+                SecurityEventKind::RepeatedForbidden,
+                Some(user_id),
+                format!(
+                    "{} forbidden responses within {}s, most recently for {}",
+                    FORBIDDEN_THRESHOLD,
+                    FORBIDDEN_WINDOW.as_secs(),
+                    path
+                ),
+            ));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Sample data for development and demos, loaded with `water-of-life seed`. Seeding is
+//! idempotent: anything already present, matched by id or by name, is left alone.
+
+use sqlx::SqlitePool;
+
+use crate::{
+    json_web::User,
+    permissions::role_permissions,
+    repositories::Repositories,
+    services::{insert_spirit, SpiritPayload, WebError, WebResult, APP_ADMIN_ROLE, APP_USER_ROLE},
+};
+
+/// Sample accounts. They can't sign in since Keycloak doesn't know them, but they own the
+/// sample spirits and show up in listings.
+const USERS: [(&str, &str, &str); 3] = [
+    ("seed-admin", "seed_admin", APP_ADMIN_ROLE),
+    ("seed-taster", "peat_chaser", APP_USER_ROLE),
+    ("seed-collector", "cask_strength", APP_USER_ROLE),
+];
+
+/// Distillers and the region they're in.
+const DISTILLERS: [(&str, &str); 14] = [
+    ("Ardbeg", "Islay"),
+    ("Lagavulin", "Islay"),
+    ("Laphroaig", "Islay"),
+    ("Glenfiddich", "Speyside"),
+    ("The Macallan", "Speyside"),
+    ("Springbank", "Campbeltown"),
+    ("Midleton", "Cork"),
+    ("Bushmills", "Antrim"),
+    ("Buffalo Trace", "Kentucky"),
+    ("Maker's Mark", "Kentucky"),
+    ("Jack Daniel's", "Tennessee"),
+    ("Yamazaki", "Osaka"),
+    ("Yoichi", "Hokkaido"),
+    ("Fortaleza", "Jalisco"),
+];
+
+/// Name, distiller, type, ABV and description.
+const SPIRITS: [(&str, &str, &str, f64, &str); 14] = [
+    (
+        "Ardbeg 10",
+        "Ardbeg",
+        "Scotch",
+        46.0,
+        "Heavily peated and unchill-filtered, with smoke, lemon and espresso.",
+    ),
+    (
+        "Lagavulin 16",
+        "Lagavulin",
+        "Scotch",
+        43.0,
+        "Rich peat smoke over dried fruit and sea salt.",
+    ),
+    (
+        "Laphroaig 10",
+        "Laphroaig",
+        "Scotch",
+        40.0,
+        "Medicinal peat, seaweed and a hint of sweetness.",
+    ),
+    (
+        "Glenfiddich 12",
+        "Glenfiddich",
+        "Scotch",
+        40.0,
+        "Fresh pear, oak and a light malty finish.",
+    ),
+    (
+        "The Macallan 12 Double Cask",
+        "The Macallan",
+        "Scotch",
+        40.0,
+        "Sherry and American oak casks give honey, citrus and ginger.",
+    ),
+    (
+        "Springbank 10",
+        "Springbank",
+        "Scotch",
+        46.0,
+        "Lightly peated, with brine, vanilla and orchard fruit.",
+    ),
+    (
+        "Redbreast 12",
+        "Midleton",
+        "Irish Whiskey",
+        40.0,
+        "Single pot still whiskey with spice, toasted wood and sherry.",
+    ),
+    (
+        "Bushmills 10",
+        "Bushmills",
+        "Irish Whiskey",
+        40.0,
+        "Triple distilled single malt, with honey and milk chocolate.",
+    ),
+    (
+        "Buffalo Trace",
+        "Buffalo Trace",
+        "Bourbon",
+        45.0,
+        "Vanilla, toffee and a touch of mint.",
+    ),
+    (
+        "Maker's Mark",
+        "Maker's Mark",
+        "Bourbon",
+        45.0,
+        "A wheated bourbon, soft with caramel and baking spice.",
+    ),
+    (
+        "Jack Daniel's Old No. 7",
+        "Jack Daniel's",
+        "Tennessee Whiskey",
+        40.0,
+        "Charcoal mellowed, with banana, caramel and oak.",
+    ),
+    (
+        "Yamazaki 12",
+        "Yamazaki",
+        "Japanese Whisky",
+        43.0,
+        "Peach, pineapple and Mizunara oak.",
+    ),
+    (
+        "Yoichi Single Malt",
+        "Yoichi",
+        "Japanese Whisky",
+        45.0,
+        "Coal-fired stills give a gentle smoke over dried fruit.",
+    ),
+    (
+        "Fortaleza Blanco",
+        "Fortaleza",
+        "Tequila",
+        40.0,
+        "Stone-milled agave, with olive, citrus and black pepper.",
+    ),
+];
+
+/// What a seed run added. Rows that were already present aren't counted.
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub users: usize,
+    pub distillers: usize,
+    pub spirits: usize,
+}
+
+/// Adds the sample users, distillers and spirits that aren't in the database yet. Spirits go
+/// through the same path as the API, so they get search entries, revisions and an approved
+/// submission.
+pub async fn seed(database: &SqlitePool) -> WebResult<SeedReport> {
+    let repositories = Repositories::sqlite(database);
+    let mut report = SeedReport::default();
+
+    for (user_id, username, role) in USERS {
+        if repositories.users.exists(user_id).await? {
+            continue;
+        }
+        let user = User {
+            user_id: user_id.to_owned(),
+            preferred_username: username.to_owned(),
+            email: format!("{}@example.com", username),
+            refresh_token_version: 1,
+            role: role.to_owned(),
+            email_verified: true,
+        };
+        repositories.users.insert(&user).await?;
+        report.users += 1;
+    }
+
+    for (name, region) in DISTILLERS {
+        let result = sqlx::query_file!("sql/insert_seed_distiller.sql", name, region)
+            .execute(database)
+            .await?;
+        report.distillers += result.rows_affected() as usize;
+    }
+
+    let (admin_id, _, _) = USERS[0];
+    let admin = repositories
+        .users
+        .find(admin_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let permissions = role_permissions(database, &admin.role).await?;
+    for (name, distiller, typ, abv, description) in SPIRITS {
+        if repositories
+            .spirits
+            .find_by_name(name, distiller)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        let region = DISTILLERS
+            .iter()
+            .find(|(seeded, _)| *seeded == distiller)
+            .map(|(_, region)| *region);
+        let region_id = match region {
+            Some(region) => sqlx::query_file!("sql/select_region_id_by_name.sql", region)
+                .fetch_optional(database)
+                .await?
+                .map(|row| row.id),
+            None => None,
+        };
+        let payload = SpiritPayload {
+            name: name.to_owned(),
+            distiller: distiller.to_owned(),
+            description: description.to_owned(),
+            typ: typ.to_owned(),
+            region_id,
+            abv,
+            version: None,
+        };
+
+        let mut transaction = repositories.begin().await?;
+        insert_spirit(&mut transaction, &payload, &admin, &permissions).await?;
+        transaction.commit().await?;
+        report.spirits += 1;
+    }
+
+    Ok(report)
+}
@@ -1,8 +1,162 @@
+mod aliases;
+mod anomalies;
 mod api;
+mod audit;
+mod availability;
+mod avatars;
+mod backups;
+mod badges;
+mod barcodes;
+mod bottles;
+mod cocktails;
+mod collection;
+mod compare;
+mod data_quality;
+mod discovery;
+mod duplicates;
+mod email_preferences;
+mod etags;
+mod export;
+mod feeds;
+mod flavors;
+mod flights;
+mod images;
+mod import;
+mod jobs;
+mod merge;
+mod messages;
+mod notifications;
 mod oidc;
+mod pagination;
+mod performance;
+mod pours;
+mod preferences;
+mod prices;
+mod profiles;
+mod ratings;
+mod regions;
+mod relations;
+mod releases;
+mod reports;
+mod reputation;
+mod revisions;
+mod reviews;
+mod scheduler;
+mod settings;
+mod share;
+mod stats;
+mod status;
+mod submissions;
+mod swaps;
+mod trending;
+mod uploads;
+mod validation;
+mod webhooks;
+mod wishlist;
 
+pub use aliases::{add_spirit_alias, delete_spirit_alias, list_spirit_aliases};
+pub use anomalies::{list_anomalies, resolve_anomaly};
 pub use api::{
-    add_spirit, edit_spirit, get_spirit_image, search_spirit, upload_spirit_image, user_info,
-    WebError, WebResult,
+    add_spirit, add_spirits, delete_spirit, edit_spirit, error_response, get_spirit,
+    get_spirit_image, insert_spirit, list_spirit_types, search_spirit, upload_spirit_image,
+    user_info, SearchResponse, SpiritDetailResponse, SpiritPayload, WebError, WebResult,
+};
+pub use availability::set_spirit_availability;
+pub use avatars::{delete_avatar, get_avatar, set_avatar};
+pub use backups::backup_database;
+pub use badges::list_badges;
+pub use barcodes::{
+    add_spirit_barcode, delete_barcode, get_spirit_by_barcode, list_spirit_barcodes,
+};
+pub use bottles::{
+    add_bottle, add_bottle_transfer, add_collection_bottle, bottle_custody, edit_collection_bottle,
+    list_bottles, list_collection_bottles, set_bottle_provenance,
+};
+pub use cocktails::{
+    add_cocktail, delete_cocktail, edit_cocktail, get_cocktail, list_cocktails,
+    makeable_cocktails,
+};
+pub use collection::{
+    add_collection_entry, delete_collection_entry, edit_collection_entry, export_collection,
+    get_collection_entry, list_collection,
+};
+pub use compare::compare_spirits;
+pub use data_quality::data_quality_report;
+pub use discovery::{random_spirit, recent_spirits, similar_spirits, spirit_of_the_day};
+pub use email_preferences::{get_email_preferences, set_email_preferences};
+pub use export::{export_spirits, spirit_export, ExportFormat};
+pub use feeds::{calendar_url, events_feed, spirits_feed};
+pub use flavors::{
+    delete_flavor_votes, get_flavor_cloud, get_flavor_profile, set_flavor_votes,
+};
+pub use flights::{
+    add_flight, clone_flight, delete_flight, edit_flight, get_flight, list_flights,
+    reveal_flight_sample, score_flight_sample, tasting_events, TastingEvent,
+};
+pub use images::{
+    backfill_images, delete_primary_spirit_image, delete_spirit_image, edit_spirit_image,
+    get_spirit_image_by_id, get_spirit_image_url, list_spirit_images, sweep_orphaned_images,
+    DEFAULT_MAX_IMAGE_BYTES,
+};
+#[cfg(not(feature = "runtime-queries"))]
+pub use images::SpiritImageSummary;
+pub use import::import_spirits;
+pub use jobs::{job_workers, list_jobs, retry_job};
+pub use merge::merge_spirits;
+pub use messages::{
+    add_message, block_user, hide_message, list_blocks, list_conversations, list_message_reports,
+    list_messages, message_events, report_message, start_conversation, unblock_user,
+    unread_message_count, MessageEvent,
+};
+pub use notifications::list_notifications;
+pub use oidc::{
+    check_role, get_jwks, get_well_known_configuration, login, logout, token,
+    IdentityProviderHealth, OpenidConfiguration, RoleCheck, RoleCheckLocks, APP_ADMIN_ROLE,
+    APP_ROLES, APP_USER_ROLE,
+};
+#[cfg(feature = "testing")]
+pub use oidc::APP_MODERATOR_ROLE;
+pub use performance::slow_routes;
+pub use pours::{add_pour, delete_pour, list_pours, pour_stats};
+pub use preferences::{get_preferences, set_preferences, StrengthUnit};
+pub use prices::{add_price_point, list_price_points, price_history};
+pub use profiles::get_profile;
+pub use ratings::{add_rating, delete_rating, edit_rating};
+pub use regions::{
+    distiller_map, list_countries, list_distillers, list_regions, set_distiller_location,
+    set_distiller_region,
+};
+pub use relations::{add_spirit_relation, delete_spirit_relation};
+#[cfg(not(feature = "runtime-queries"))]
+pub use relations::RelatedRelease;
+pub use releases::{
+    add_release, import_releases, list_releases, unwatch_release, watch_release,
+};
+pub use reports::{list_reports, report_review, report_spirit, report_spirit_image, resolve_report};
+pub use reputation::get_reputation;
+pub use revisions::{revert_spirit, spirit_history};
+pub use reviews::{
+    add_review, delete_review, edit_review, hide_review, list_review_reports, list_reviews,
+    vote_review_helpful,
+};
+pub use scheduler::{scheduler, scheduler_status, SchedulerStatus};
+pub use settings::{get_settings, set_settings, SettingsService};
+pub use share::{get_shared_spirit, share_spirit};
+pub use stats::user_stats;
+pub use status::status;
+pub use submissions::{approve_submission, list_pending_submissions, reject_submission};
+pub use swaps::{
+    add_swap_match, add_swap_message, add_swap_offer, add_swap_request, close_swap_offer,
+    close_swap_request, confirm_swap_match, list_swap_messages, list_swap_offers,
+    list_swap_requests, swap_offer_matches,
+};
+pub use trending::trending_spirits;
+pub use uploads::{
+    cancel_image_upload, image_upload_status, start_image_upload, upload_image_chunk,
+};
+pub use webhooks::{
+    add_webhook, delete_webhook, list_webhook_deliveries, list_webhooks, webhook_sender,
+};
+pub use wishlist::{
+    add_wishlist_entry, delete_wishlist_entry, edit_wishlist_entry, list_wishlist,
 };
-pub use oidc::{get_jwks, get_well_known_configuration, login, logout, token, OpenidConfiguration};
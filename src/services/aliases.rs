@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    json_web::AuthContext,
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{ensure_spirit_exists, find_visible_spirit},
+    WebError, WebResult,
+};
+
+const MAX_ALIAS_LENGTH: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct AliasPayload {
+    alias: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AliasResponse {
+    id: i64,
+    alias: String,
+    created_at: String,
+}
+
+pub async fn list_spirit_aliases(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let aliases = sqlx::query_file_as!(AliasResponse, "sql/select_spirit_aliases.sql", spirit_id)
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&aliases)?;
+    Ok(response.into_response())
+}
+
+/// Records another name the spirit goes by so searches for it find the spirit too.
+pub async fn add_spirit_alias(
+    RequirePermission(user, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<AliasPayload>,
+) -> WebResult<Response> {
+    let alias = payload
+        .alias
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if alias.is_empty() {
+        return Err(WebError::InvalidInput("Aliases cannot be empty.".into()));
+    }
+    if alias.chars().count() > MAX_ALIAS_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Aliases must be at most {} characters.",
+            MAX_ALIAS_LENGTH
+        )));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_exists(&mut *transaction, &spirit_id).await?;
+    let result = sqlx::query_file!(
+        "sql/insert_spirit_alias.sql",
+        spirit_id,
+        alias,
+        user.user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await;
+    let id = match result {
+        Ok(row) => row.id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(WebError::Conflict(format!(
+                "'{}' is already an alias of this spirit.",
+                alias
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    sqlx::query_file!("sql/insert_spirit_alias_fts.sql", id, spirit_id, alias)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&json!({ "id": id }))?;
+    Ok(response.into_response())
+}
+
+pub async fn delete_spirit_alias(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, alias_id)): Path<(String, i64)>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let result = sqlx::query_file!("sql/delete_spirit_alias.sql", alias_id, spirit_id)
+        .execute(&mut *transaction)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    sqlx::query_file!("sql/delete_spirit_alias_fts.sql", alias_id)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::SqliteConnection;
+
+use crate::{
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{WebError, WebResult};
+
+/// Types whose legal minimum ABV makes anything under [`MIN_WHISKY_ABV`] suspicious.
+const WHISKY_TYPE_KEYWORDS: [&str; 5] = ["whisk", "bourbon", "scotch", "rye", "malt"];
+const MIN_WHISKY_ABV: f64 = 20.0;
+const MAX_PLAUSIBLE_ABV: f64 = 80.0;
+/// Prices this many times the median for the spirit's type, in the same currency, are flagged.
+const MAX_PRICE_TO_MEDIAN: f64 = 100.0;
+/// With fewer prices than this for a type, the median says too little to compare against.
+const MIN_PRICES_FOR_MEDIAN: i64 = 5;
+
+#[derive(Debug)]
+pub struct Anomaly {
+    field: &'static str,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AnomalyResponse {
+    id: i64,
+    spirit_id: String,
+    spirit_name: Option<String>,
+    field: String,
+    reason: String,
+    created_at: String,
+}
+
+fn is_whisky(typ: &str) -> bool {
+    let typ = typ.to_lowercase();
+    WHISKY_TYPE_KEYWORDS
+        .iter()
+        .any(|keyword| typ.contains(keyword))
+}
+
+/// Returns the improbable values in a spirit record. These are flagged for review rather than
+/// rejected since the heuristics can't know about every oddball bottling.
+pub fn detect_anomalies(typ: Option<&str>, abv: f64) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+
+    if !(0.0..=100.0).contains(&abv) {
+        anomalies.push(Anomaly {
+            field: "abv",
+            reason: format!("ABV of {}% is outside of 0-100%", abv),
+        });
+    } else if abv > MAX_PLAUSIBLE_ABV {
+        anomalies.push(Anomaly {
+            field: "abv",
+            reason: format!("ABV of {}% is unusually high", abv),
+        });
+    }
+
+    if let Some(typ) = typ {
+        if is_whisky(typ) && abv < MIN_WHISKY_ABV {
+            anomalies.push(Anomaly {
+                field: "abv",
+                reason: format!("ABV of {}% is unusually low for a {}", abv, typ),
+            });
+        }
+    }
+
+    anomalies
+}
+
+/// Returns an anomaly when a price is far above the `median` of the `count` prices reported
+/// for spirits of the same type. Usually a misplaced decimal point or the wrong currency.
+pub fn detect_price_anomaly(
+    typ: &str,
+    amount: f64,
+    currency: &str,
+    median: Option<f64>,
+    count: i64,
+) -> Option<Anomaly> {
+    let median = median.filter(|_| count >= MIN_PRICES_FOR_MEDIAN)?;
+    if amount < median * MAX_PRICE_TO_MEDIAN {
+        return None;
+    }
+    Some(Anomaly {
+        field: "price",
+        reason: format!(
+            "Price of {:.2} {} is over {} times the median of {:.2} {} for a {}",
+            amount, currency, MAX_PRICE_TO_MEDIAN, median, currency, typ
+        ),
+    })
+}
+
+/// Queues an anomaly for review. One already flagged for the spirit isn't flagged again, even
+/// once resolved, so editing some other field doesn't bring back one an admin accepted.
+async fn flag(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    anomaly: &Anomaly,
+) -> sqlx::Result<()> {
+    let flagged = sqlx::query_file!(
+        "sql/insert_spirit_anomaly.sql",
+        spirit_id,
+        anomaly.field,
+        anomaly.reason
+    )
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+    if flagged > 0 {
+        tracing::info!("Flagged spirit {}: {}", spirit_id, anomaly.reason);
+    }
+    Ok(())
+}
+
+/// Runs the heuristics against a spirit and queues anything suspicious for review.
+pub async fn flag_anomalies(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    typ: Option<&str>,
+    abv: f64,
+) -> sqlx::Result<()> {
+    for anomaly in detect_anomalies(typ, abv) {
+        flag(connection, spirit_id, &anomaly).await?;
+    }
+    Ok(())
+}
+
+/// Compares a reported price with the median for the spirit's type and queues it for review if
+/// it's implausibly high. Call it before recording the price, so the price isn't part of the
+/// median it's compared with.
+pub async fn flag_price_anomaly(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    amount: f64,
+    currency: &str,
+) -> sqlx::Result<()> {
+    let prices = sqlx::query_file!("sql/select_type_median_price.sql", spirit_id, currency)
+        .fetch_one(&mut *connection)
+        .await?;
+    if let Some(anomaly) =
+        detect_price_anomaly(&prices.typ, amount, currency, prices.median, prices.count)
+    {
+        flag(connection, spirit_id, &anomaly).await?;
+    }
+    Ok(())
+}
+
+pub async fn list_anomalies(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let anomalies = sqlx::query_file!("sql/select_unresolved_anomalies.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| AnomalyResponse {
+            id: row.id,
+            spirit_id: row.spirit_id,
+            spirit_name: row.spirit_name,
+            field: row.field,
+            reason: row.reason,
+            created_at: row.created_at,
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&anomalies)?;
+    Ok(response.into_response())
+}
+
+pub async fn resolve_anomaly(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(anomaly_id): Path<i64>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/update_anomaly_resolved.sql", anomaly_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
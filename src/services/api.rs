@@ -1,21 +1,63 @@
-use std::{fs, io::Write};
+use std::io;
 
 use axum::{
-    extract::{multipart::MultipartError, Multipart, Path, Query, State},
+    extract::{multipart::MultipartError, Multipart, Path, Query, Request, State},
+    http::{
+        header::{ETAG, IF_MATCH, RETRY_AFTER},
+        HeaderMap,
+    },
     response::{IntoResponse, Response},
-    Extension, Form, Json,
+    Extension, Json,
 };
 use futures::TryStreamExt;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use sqlx::{query, SqlitePool};
+use sqlx::{SqliteConnection, SqliteExecutor, SqlitePool};
 use thiserror::Error;
-use tower_cookies::Cookies;
 use uuid::Uuid;
 
-use crate::{json_web::User, WaterOfLifeState};
+use crate::{
+    infra::StoreError,
+    json_web::{AuthContext, TokenExpiry, User},
+    permissions::{
+        ImagesUpload, ModerationReview, Permissions, RequirePermission, SpiritsManage,
+        SpiritsWrite,
+    },
+    proxy::ClientIp,
+    repositories::{RepositoryError, SpiritRepository},
+    WaterOfLifeState,
+};
+
+use super::{
+    anomalies::flag_anomalies,
+    audit::record_audit,
+    availability::Availability,
+    avatars::avatar_url,
+    duplicates::find_duplicate_candidates,
+    etags::{conditional_json, content_hash},
+    export::{csv_response, ExportFormat, ExportParameter},
+    images::{
+        load_uploaded_spirit_images, receive_image_field, serve_primary_spirit_image,
+        store_spirit_image, ImageSizeParameter, SpiritImageSummary,
+    },
+    regions::ensure_region_exists,
+    relations::RelatedRelease,
+    reputation::user_reputation,
+    revisions::{load_snapshot, record_revision, RevisionAction},
+    submissions::{record_submission, SUBMISSION_APPROVED},
+    trending::{record_activity, ActivityKind},
+    webhooks::{queue_spirit_event, WebhookEvent},
+};
 
-pub const FORM_FILE_KEY: &'static str = "file";
+pub const FORM_FILE_KEY: &str = "file";
+const IMAGE_CAPTION_FORM_KEY: &str = "caption";
+/// Most spirits accepted by a single batch request.
+const MAX_BATCH_SPIRITS: usize = 500;
+/// How long clients are told to wait before retrying while the database is busy or unreachable.
+const DATABASE_RETRY_AFTER_SECONDS: u64 = 5;
+/// SQLite's primary result codes for a database locked by another connection.
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
 
 #[derive(Error, Debug)]
 pub enum WebError {
@@ -25,24 +67,153 @@ pub enum WebError {
     Json(#[from] serde_json::Error),
     #[error("Error reading multipart request.")]
     MultipartError(#[from] MultipartError),
+    #[error("Sign in to use this resource.")]
+    Unauthorized,
+    #[error("Insufficient permissions for this resource.")]
+    Forbidden,
+    #[error("Verify your email address to make changes.")]
+    EmailUnverified,
+    #[error("Resource not found.")]
+    NotFound,
+    #[error("Invalid request: {0}")]
+    InvalidInput(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+    #[error("Error accessing the filesystem.")]
+    Io(#[from] io::Error),
+    #[error("Error loading records.")]
+    Repository(#[from] RepositoryError),
+    #[error("Error accessing the session or cache store.")]
+    Store(#[from] StoreError),
 }
 
 impl IntoResponse for WebError {
     fn into_response(self) -> Response {
-        let mut status_code = StatusCode::INTERNAL_SERVER_ERROR;
-        let message = match self {
-            Self::Database(e) => e.to_string(),
-            Self::Json(e) => e.to_string(),
-            Self::MultipartError(e) => {
-                status_code = StatusCode::BAD_REQUEST;
-                e.to_string()
+        let (status_code, code, message) = match self {
+            Self::Database(e) => return database_error(&e),
+            Self::Repository(RepositoryError::Database(e)) => return database_error(&e),
+            Self::Json(e) => return internal_error(&e),
+            Self::Io(e) => return internal_error(&e),
+            Self::Store(e) => return internal_error(&e),
+            // Tripping the route's body limit surfaces while reading a field.
+            Self::MultipartError(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "The upload is larger than this server accepts.".into(),
+            ),
+            Self::MultipartError(e) => (StatusCode::BAD_REQUEST, "invalid_request", e.body_text()),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized", self.to_string()),
+            Self::Forbidden => (StatusCode::FORBIDDEN, "forbidden", self.to_string()),
+            Self::EmailUnverified => {
+                (StatusCode::FORBIDDEN, "email_unverified", self.to_string())
+            }
+            Self::NotFound => (StatusCode::NOT_FOUND, "not_found", self.to_string()),
+            Self::InvalidInput(message) => (StatusCode::BAD_REQUEST, "invalid_input", message),
+            Self::Conflict(message) => (StatusCode::CONFLICT, "conflict", message),
+            Self::PayloadTooLarge(message) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", message)
+            }
+            Self::UnsupportedMediaType(message) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                message,
+            ),
+            Self::TooManyRequests(message) => {
+                (StatusCode::TOO_MANY_REQUESTS, "rate_limited", message)
             }
         };
-        tracing::warn!("{}", message);
-        status_code.into_response()
+        error_response(status_code, code, message, None)
+    }
+}
+
+/// The body of every error response. `code` is stable for clients to branch on while `message`
+/// is meant for people. A `request_id` is added on the way out.
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<serde_json::Value>,
+}
+
+pub fn error_response(
+    status_code: StatusCode,
+    code: &'static str,
+    message: String,
+    details: Option<serde_json::Value>,
+) -> Response {
+    tracing::warn!("{}: {}", code, message);
+    let body = ErrorResponse {
+        code,
+        message,
+        details,
+    };
+    (status_code, Json(body)).into_response()
+}
+
+/// Logs what went wrong but tells the client nothing about it.
+fn internal_error(e: &dyn std::error::Error) -> Response {
+    tracing::error!("{}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal_error",
+        "Something went wrong, please try again later.".into(),
+        None,
+    )
+}
+
+/// Whether the query could succeed if it's retried, because the pool was exhausted or another
+/// connection held a lock.
+fn is_transient(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::PoolTimedOut | sqlx::Error::Io(_) | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // Extended result codes keep the primary code in the low byte.
+            .is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED)),
+        _ => false,
     }
 }
 
+/// Constraint violations come from what the client sent, so they get a 4xx saying what went
+/// wrong. Errors that may go away on their own are a 503 with `Retry-After`. Anything else is
+/// logged and reported as a 500 without the database's own wording.
+pub(super) fn database_error(e: &sqlx::Error) -> Response {
+    if is_transient(e) {
+        tracing::error!("{}", e);
+        let response = error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "database_unavailable",
+            "The service is busy, please try again shortly.".into(),
+            Some(serde_json::json!({ "retry_after": DATABASE_RETRY_AFTER_SECONDS })),
+        );
+        let retry_after = DATABASE_RETRY_AFTER_SECONDS.to_string();
+        return ([(RETRY_AFTER, retry_after)], response).into_response();
+    }
+    let (status_code, code, message) = match e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "not_found", "Resource not found."),
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "conflict",
+            "That conflicts with an existing record.",
+        ),
+        sqlx::Error::Database(e) if e.is_foreign_key_violation() => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_reference",
+            "That refers to a record that doesn't exist.",
+        ),
+        e => return internal_error(e),
+    };
+    error_response(status_code, code, message.into(), None)
+}
+
 pub type WebResult<T> = Result<T, WebError>;
 
 #[derive(Debug, Serialize)]
@@ -50,6 +221,24 @@ struct UserInfo {
     username: String,
     role: String,
     scopes: Vec<String>,
+    reputation: i64,
+    trusted: bool,
+    avatar_url: Option<String>,
+    email_verified: bool,
+    /// Whether changes are refused until the email is verified, so the frontend knows to ask.
+    email_verification_required: bool,
+    /// What the user's role allows, so the frontend can hide what they can't use.
+    permissions: Permissions,
+    /// So the frontend can warn before the session lapses.
+    session: SessionInfo,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionInfo {
+    #[serde(flatten)]
+    expiry: TokenExpiry,
+    /// Whether this request refreshed the tokens.
+    refreshed: bool,
 }
 
 async fn get_scopes(pool: &SqlitePool, user_id: &str) -> sqlx::Result<Vec<String>> {
@@ -57,7 +246,6 @@ async fn get_scopes(pool: &SqlitePool, user_id: &str) -> sqlx::Result<Vec<String
 
     let mut scopes = Vec::new();
     while let Some(row) = rows.try_next().await? {
-        tracing::info!("user_info: {}", row.scope);
         scopes.push(row.scope);
     }
 
@@ -65,15 +253,32 @@ async fn get_scopes(pool: &SqlitePool, user_id: &str) -> sqlx::Result<Vec<String
 }
 
 pub async fn user_info(
-    Extension(user): Extension<User>,
+    Extension(context): Extension<AuthContext>,
     State(state): State<WaterOfLifeState>,
 ) -> WebResult<Response> {
+    let user = context.user;
     let scopes = get_scopes(&state.database, &user.user_id).await?;
+    let reputation = user_reputation(&state.database, &user.user_id).await?;
+    let avatar_url = sqlx::query_file!("sql/select_user_avatar.sql", user.user_id)
+        .fetch_optional(&state.database)
+        .await?
+        .and_then(|row| row.avatar_sha256)
+        .map(|_| avatar_url(&user.user_id));
 
     let json = serde_json::to_string(&UserInfo {
         username: user.preferred_username,
         role: user.role,
         scopes,
+        reputation: reputation.score,
+        trusted: reputation.trusted,
+        avatar_url,
+        email_verified: user.email_verified,
+        email_verification_required: state.config.oidc.require_verified_email,
+        permissions: context.permissions,
+        session: SessionInfo {
+            expiry: context.expiry,
+            refreshed: context.refreshed,
+        },
     })?;
 
     Ok(json.into_response())
@@ -82,110 +287,705 @@ pub async fn user_info(
 #[derive(Debug, Deserialize)]
 pub struct SearchParameter {
     name: String,
+    region: Option<i64>,
+    availability: Option<Availability>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchResponse {
-    uuid: Option<String>,
-    name: Option<String>,
-    distiller: Option<String>,
-    bottler: Option<String>,
-    typ: Option<String>,
+    pub uuid: Option<String>,
+    pub name: Option<String>,
+    pub distiller: Option<String>,
+    pub bottler: Option<String>,
+    pub typ: Option<String>,
+    pub average_rating: Option<f64>,
+    pub rating_count: Option<i64>,
 }
 
 pub async fn search_spirit(
+    headers: HeaderMap,
     State(state): State<WaterOfLifeState>,
     Query(query_params): Query<SearchParameter>,
+    Query(export_params): Query<ExportParameter>,
 ) -> WebResult<Response> {
-    let names = sqlx::query_file_as!(SearchResponse, "sql/search_spirit.sql", query_params.name)
-        .fetch_all(&state.database)
+    let availability = query_params.availability.map(|a| a.as_str());
+    let names = state
+        .repositories
+        .spirits
+        .search(&query_params.name, query_params.region, availability)
         .await?;
 
+    if ExportFormat::negotiate(export_params.format, &headers) == ExportFormat::Csv {
+        return csv_response(&names);
+    }
     let response = serde_json::to_string(&names)?;
-    Ok(response.into_response())
+    let etag = format!("W/\"{}\"", content_hash(response.as_bytes()));
+    Ok(conditional_json(&headers, etag, response))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SpiritPayload {
-    name: String,
-    distiller: String,
-    description: String,
-    abv: f64,
+    pub name: String,
+    pub distiller: String,
+    pub description: String,
+    pub typ: String,
+    #[serde(default)]
+    pub region_id: Option<i64>,
+    pub abv: f64,
+    /// The version being edited, when `If-Match` isn't sent. Ignored when adding spirits.
+    #[serde(default)]
+    pub version: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddSpiritParameter {
+    /// Add the spirit even if it looks like one already in the catalog.
+    #[serde(default)]
+    force: bool,
+}
+
+/// Rejects ids that can't belong to a spirit without querying for them.
+pub fn ensure_spirit_id_format(spirit_id: &str) -> WebResult<()> {
+    Uuid::parse_str(spirit_id)
+        .map(|_| ())
+        .map_err(|_| WebError::InvalidInput(format!("'{}' is not a spirit id.", spirit_id)))
+}
+
+/// Returns [`WebError::NotFound`] when no spirit has the given id.
+pub async fn ensure_spirit_exists<'e, E>(executor: E, spirit_id: &str) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file!("sql/select_spirit_exists.sql", spirit_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    Ok(())
+}
+
+/// Only the user who added a spirit, or a user allowed to manage every spirit, may change it,
+/// delete it or add images to it.
+pub async fn ensure_spirit_editable<'e, E>(
+    executor: E,
+    context: &AuthContext,
+    spirit_id: &str,
+) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    let created_by = sqlx::query_file!("sql/select_spirit_owner.sql", spirit_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .created_by;
+    let is_creator = created_by.as_deref() == Some(context.user.user_id.as_str());
+    if !is_creator && !context.permissions.has::<SpiritsManage>() {
+        return Err(WebError::Forbidden);
+    }
+    Ok(())
 }
 
 #[derive(Debug, Serialize)]
 pub struct SpiritResponse {
-    id: String,
+    pub id: String,
+    pub version: i64,
 }
 
-pub async fn add_spirit(
+#[derive(Debug, Serialize)]
+pub struct SpiritDetailResponse {
+    pub uuid: String,
+    pub name: String,
+    pub description: String,
+    pub distiller: String,
+    pub bottler: String,
+    pub typ: String,
+    pub abv: f64,
+    pub age: String,
+    pub version: i64,
+    pub availability: String,
+    pub region: Option<String>,
+    pub average_rating: Option<f64>,
+    pub rating_count: i64,
+    pub my_rating: Option<i64>,
+    pub images: sqlx::types::Json<Vec<SpiritImageSummary>>,
+    pub related_releases: sqlx::types::Json<Vec<RelatedRelease>>,
+    pub status: String,
+    #[serde(skip)]
+    pub submitted_by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubmittedSpiritResponse {
+    pub id: String,
+    pub status: &'static str,
+}
+
+/// The outcome of one spirit in a batch, at the same index as it was submitted.
+#[derive(Debug, Serialize)]
+struct BatchSpiritResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpiritTypeResponse {
+    pub id: i64,
+    name: String,
+}
+
+pub async fn list_spirit_types(State(state): State<WaterOfLifeState>) -> WebResult<Response> {
+    let types = sqlx::query_file_as!(SpiritTypeResponse, "sql/select_spirit_types.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&types)?;
+    Ok(response.into_response())
+}
+
+/// Resolves a spirit type by name, rejecting types that aren't in the taxonomy.
+pub async fn find_spirit_type<'e, E>(executor: E, typ: &str) -> WebResult<SpiritTypeResponse>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file_as!(SpiritTypeResponse, "sql/select_spirit_type.sql", typ)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| WebError::InvalidInput(format!("Unknown spirit type '{}'.", typ)))
+}
+
+/// Loads a spirit with its rating aggregate. Spirits still waiting on moderation are only
+/// visible to their submitter and those who review submissions.
+pub async fn find_visible_spirit(
+    spirits: &dyn SpiritRepository,
+    context: &AuthContext,
+    spirit_id: &str,
+) -> WebResult<SpiritDetailResponse> {
+    let user_id = &context.user.user_id;
+    let spirit = spirits
+        .find(spirit_id, user_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let is_visible = spirit.status == SUBMISSION_APPROVED
+        || context.permissions.has::<ModerationReview>()
+        || spirit.submitted_by.as_deref() == Some(user_id.as_str());
+    if !is_visible {
+        return Err(WebError::NotFound);
+    }
+    Ok(spirit)
+}
+
+/// The ETag leads with the spirit's version so it can be sent back in `If-Match` when editing,
+/// followed by a hash of the body since ratings and images change without bumping the version.
+pub async fn get_spirit(
+    Extension(context): Extension<AuthContext>,
+    headers: HeaderMap,
     State(state): State<WaterOfLifeState>,
-    Json(payload): Json<SpiritPayload>,
+    Path(spirit_id): Path<String>,
 ) -> WebResult<Response> {
-    tracing::debug!("add_spirit: {:#?}", payload.name);
-    tracing::debug!("add_spirit: {:#?}", payload.distiller);
-    tracing::debug!("add_spirit: {:#?}", payload.description);
-    tracing::debug!("add_spirit: {:#?}", payload.abv);
+    let spirit = find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    record_activity(&state.database, &spirit_id, ActivityKind::View).await;
+
+    let response = serde_json::to_string(&spirit)?;
+    let etag = format!(
+        "W/\"{}-{}\"",
+        spirit.version,
+        content_hash(response.as_bytes())
+    );
+    Ok(conditional_json(&headers, etag, response))
+}
 
+/// Inserts a spirit along with its search entry, anomaly flags and moderation submission.
+pub async fn insert_spirit(
+    connection: &mut SqliteConnection,
+    payload: &SpiritPayload,
+    user: &User,
+    permissions: &Permissions,
+) -> WebResult<SubmittedSpiritResponse> {
     let id = Uuid::new_v4().to_string();
+    let spirit_type = find_spirit_type(&mut *connection, &payload.typ).await?;
+    ensure_region_exists(&mut *connection, payload.region_id).await?;
     let _ = sqlx::query_file!(
         "sql/insert_spirit.sql",
         id,
         payload.name,
-        payload.distiller,
         payload.description,
-        payload.abv
+        payload.distiller,
+        spirit_type.name,
+        spirit_type.id,
+        payload.region_id,
+        payload.abv,
+        user.user_id
     )
-    .execute(&state.database)
+    .execute(&mut *connection)
     .await?;
+    sqlx::query_file!(
+        "sql/insert_spirit_fts.sql",
+        id,
+        payload.name,
+        payload.distiller,
+        spirit_type.name
+    )
+    .execute(&mut *connection)
+    .await?;
+    flag_anomalies(&mut *connection, &id, Some(&spirit_type.name), payload.abv).await?;
+    let status = record_submission(&mut *connection, &id, user, permissions).await?;
+    record_revision(
+        &mut *connection,
+        &id,
+        &user.user_id,
+        RevisionAction::Create,
+        None,
+    )
+    .await?;
+    queue_spirit_event(&mut *connection, WebhookEvent::SpiritCreated, &id).await?;
+
+    Ok(SubmittedSpiritResponse { id, status })
+}
 
-    let response = serde_json::to_string(&SpiritResponse { id })?;
+/// Adds a spirit unless it looks like one already in the catalog, in which case the likely
+/// duplicates are returned in a 409's `details.candidates`. Pass `force=true` to add it anyway.
+pub async fn add_spirit(
+    RequirePermission(user, _): RequirePermission<SpiritsWrite>,
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<AddSpiritParameter>,
+    Json(payload): Json<SpiritPayload>,
+) -> WebResult<Response> {
+    if !query_params.force {
+        let candidates =
+            find_duplicate_candidates(&state.database, &payload.name, &payload.distiller).await?;
+        if !candidates.is_empty() {
+            return Ok(error_response(
+                StatusCode::CONFLICT,
+                "possible_duplicate",
+                "This spirit looks like one already in the catalog.".into(),
+                Some(serde_json::json!({ "candidates": candidates })),
+            ));
+        }
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let spirit = insert_spirit(&mut transaction, &payload, &user, &context.permissions).await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&spirit)?;
+    Ok(response.into_response())
+}
+
+/// Adds several spirits in one transaction. Spirits that fail validation are reported at their
+/// index and skipped, the rest are still added.
+pub async fn add_spirits(
+    RequirePermission(user, _): RequirePermission<SpiritsWrite>,
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<Vec<SpiritPayload>>,
+) -> WebResult<Response> {
+    if payload.len() > MAX_BATCH_SPIRITS {
+        return Err(WebError::InvalidInput(format!(
+            "At most {} spirits can be added at once.",
+            MAX_BATCH_SPIRITS
+        )));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let mut results = Vec::with_capacity(payload.len());
+    for spirit in &payload {
+        let inserted = insert_spirit(&mut transaction, spirit, &user, &context.permissions).await;
+        let result = match inserted {
+            Ok(spirit) => BatchSpiritResponse {
+                id: Some(spirit.id),
+                status: Some(spirit.status),
+                error: None,
+            },
+            Err(WebError::InvalidInput(message)) => BatchSpiritResponse {
+                id: None,
+                status: None,
+                error: Some(message),
+            },
+            Err(e) => return Err(e),
+        };
+        results.push(result);
+    }
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&results)?;
     Ok(response.into_response())
 }
 
 pub async fn upload_spirit_image(
-    Extension(user): Extension<User>,
+    RequirePermission(user, _): RequirePermission<ImagesUpload>,
+    Extension(context): Extension<AuthContext>,
     State(state): State<WaterOfLifeState>,
     Path(spirit_id): Path<String>,
     mut multipart: Multipart,
 ) -> WebResult<Response> {
     tracing::debug!("upload_spirit_image: Got spirit id: {}", spirit_id);
-    while let Some(field) = multipart.next_field().await? {
+    // Checked before the body is read so a mistyped id doesn't leave an orphaned image.
+    ensure_spirit_id_format(&spirit_id)?;
+    ensure_spirit_editable(&state.database, &context, &spirit_id).await?;
+    // A caption applies to the files that follow it in the form.
+    let mut caption = String::new();
+    let mut stored = Vec::new();
+    while let Some(mut field) = multipart.next_field().await? {
         let name = if let Some(name) = field.name() {
             name.to_owned()
         } else {
             continue;
         };
 
+        if name == IMAGE_CAPTION_FORM_KEY {
+            caption = field.text().await?.trim().to_owned();
+            continue;
+        }
         if name != FORM_FILE_KEY {
             continue;
         }
 
-        let data = field.bytes().await?;
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(state.images_path.join(&spirit_id))
-            .unwrap();
-
-        file.write_all(&data).unwrap();
-        tracing::debug!("Length of `{}` is {} bytes", name, data.len());
+        let received_path = state
+            .config
+            .storage
+            .uploads_path
+            .join(format!("{}.part", Uuid::new_v4()));
+        receive_image_field(
+            &mut field,
+            &received_path,
+            state.config.limits.max_image_upload_bytes,
+        )
+        .await?;
+        stored.push(
+            store_spirit_image(&state, &spirit_id, &user.user_id, &caption, &received_path)
+                .await?,
+        );
     }
 
-    Ok("".into_response())
+    let images = load_uploaded_spirit_images(&state, &spirit_id, stored).await?;
+    let response = serde_json::to_string(&images)?;
+    Ok(response.into_response())
 }
 
+/// Serves a spirit's primary image to a user who can see the spirit. Requests with a signed
+/// URL carry no user, but links are only handed out for spirits their requester could see.
 pub async fn get_spirit_image(
+    context: Option<Extension<AuthContext>>,
     State(state): State<WaterOfLifeState>,
     Path(spirit_id): Path<String>,
+    Query(query_params): Query<ImageSizeParameter>,
+    request: Request,
 ) -> WebResult<Response> {
-    Ok("".into_response())
+    ensure_spirit_id_format(&spirit_id)?;
+    match context {
+        Some(Extension(context)) => {
+            find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+        }
+        None => ensure_spirit_exists(&state.database, &spirit_id).await?,
+    }
+    serve_primary_spirit_image(&state, &spirit_id, query_params.size, request).await
+}
+
+pub fn version_etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Reads the version a client is editing from `If-Match`, falling back to the payload.
+fn expected_version(headers: &HeaderMap, payload_version: Option<i64>) -> WebResult<i64> {
+    let Some(if_match) = headers.get(IF_MATCH) else {
+        return payload_version.ok_or_else(|| {
+            WebError::InvalidInput(
+                "Send the version being edited in If-Match or the 'version' field.".into(),
+            )
+        });
+    };
+
+    if_match
+        .to_str()
+        .ok()
+        .map(|etag| etag.trim().trim_start_matches("W/").trim_matches('"'))
+        .and_then(|etag| etag.split('-').next()?.parse::<i64>().ok())
+        .ok_or_else(|| WebError::InvalidInput("If-Match is not a spirit version.".into()))
+}
+
+/// Applies `payload` to a spirit still at `expected_version`, returning the new version or
+/// `None` when the spirit is missing or was changed in the meantime.
+pub async fn update_spirit(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    payload: &SpiritPayload,
+    expected_version: i64,
+) -> WebResult<Option<i64>> {
+    let spirit_type = find_spirit_type(&mut *connection, &payload.typ).await?;
+    ensure_region_exists(&mut *connection, payload.region_id).await?;
+    let Some(updated) = sqlx::query_file!(
+        "sql/update_spirit.sql",
+        spirit_id,
+        payload.name,
+        payload.description,
+        payload.distiller,
+        spirit_type.name,
+        spirit_type.id,
+        payload.region_id,
+        payload.abv,
+        expected_version
+    )
+    .fetch_optional(&mut *connection)
+    .await?
+    else {
+        return Ok(None);
+    };
+    sqlx::query_file!(
+        "sql/update_spirit_fts.sql",
+        spirit_id,
+        payload.name,
+        payload.distiller,
+        spirit_type.name
+    )
+    .execute(&mut *connection)
+    .await?;
+    flag_anomalies(
+        &mut *connection,
+        spirit_id,
+        Some(&spirit_type.name),
+        payload.abv,
+    )
+    .await?;
+    queue_spirit_event(&mut *connection, WebhookEvent::SpiritUpdated, spirit_id).await?;
+
+    Ok(Some(updated.version))
+}
+
+/// Takes a spirit out of the catalog and search, keeping its row so it can be restored.
+/// Returns whether there was a spirit to delete.
+pub async fn remove_spirit(connection: &mut SqliteConnection, spirit_id: &str) -> WebResult<bool> {
+    let deleted = sqlx::query_file!("sql/delete_spirit.sql", spirit_id)
+        .execute(&mut *connection)
+        .await?
+        .rows_affected()
+        > 0;
+    sqlx::query_file!("sql/delete_spirit_fts.sql", spirit_id)
+        .execute(&mut *connection)
+        .await?;
+    Ok(deleted)
+}
+
+/// Deletes a spirit and records the deletion as a revision, so it shows in the spirit's history
+/// and reverting to an earlier revision brings the spirit back.
+pub async fn delete_spirit_record(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    user_id: &str,
+) -> WebResult<bool> {
+    let before = load_snapshot(&mut *connection, spirit_id).await?;
+    if !remove_spirit(connection, spirit_id).await? {
+        return Ok(false);
+    }
+    record_revision(
+        connection,
+        spirit_id,
+        user_id,
+        RevisionAction::Delete,
+        before.as_ref(),
+    )
+    .await?;
+    Ok(true)
 }
 
+/// Updates a spirit if it is still at the version the client last saw. When someone else
+/// saved first, responds with 409 and the current record in `details.current` so the client
+/// can reconcile.
 pub async fn edit_spirit(
-    cookies: Cookies,
+    RequirePermission(user, _): RequirePermission<SpiritsWrite>,
+    Extension(context): Extension<AuthContext>,
     State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<SpiritPayload>,
-) -> WebResult<()> {
-    todo!("edit_spirit");
+) -> WebResult<Response> {
+    let expected_version = expected_version(&headers, payload.version)?;
+
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_editable(&mut *transaction, &context, &spirit_id).await?;
+    let before = load_snapshot(&mut *transaction, &spirit_id).await?;
+    let updated = update_spirit(&mut transaction, &spirit_id, &payload, expected_version).await?;
+    let Some(version) = updated else {
+        drop(transaction);
+        let current =
+            find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+        let etag = version_etag(current.version);
+        let response = error_response(
+            StatusCode::CONFLICT,
+            "version_conflict",
+            "This spirit was changed since you loaded it.".into(),
+            Some(serde_json::json!({ "current": current })),
+        );
+        return Ok(([(ETAG, etag)], response).into_response());
+    };
+    record_revision(
+        &mut transaction,
+        &spirit_id,
+        &user.user_id,
+        RevisionAction::Edit,
+        before.as_ref(),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&SpiritResponse {
+        id: spirit_id,
+        version,
+    })?;
+    Ok(([(ETAG, version_etag(version))], response).into_response())
+}
+
+/// Removes a spirit from the catalog. Its ratings, reviews and images are kept, so a spirit
+/// deleted by mistake can be restored by reverting to an earlier revision.
+pub async fn delete_spirit(
+    RequirePermission(user, _): RequirePermission<SpiritsWrite>,
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_editable(&mut *transaction, &context, &spirit_id).await?;
+    if !delete_spirit_record(&mut transaction, &spirit_id, &user.user_id).await? {
+        return Err(WebError::NotFound);
+    }
+    record_audit(
+        &mut *transaction,
+        Some(&user.user_id),
+        client_ip,
+        "spirit_deleted",
+        &serde_json::json!({ "spirit_id": spirit_id }),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok("".into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header::COOKIE, Request},
+    };
+    use tower::ServiceExt;
+
+    use crate::{services::APP_USER_ROLE, testing};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn unavailable_database_is_a_503_with_retry_after() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let cookie = testing::auth_cookie(&app.state, &user);
+        // The test pool has a single connection, so holding it leaves the request waiting
+        // until the pool times out.
+        let _connection = app.state.database.acquire().await.unwrap();
+
+        let request = Request::get("/api/user_info")
+            .header(COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(RETRY_AFTER).unwrap(),
+            &DATABASE_RETRY_AFTER_SECONDS.to_string()
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "database_unavailable");
+    }
+
+    async fn get_primary_image(app: &testing::TestApp, user: &User, spirit_id: &str) -> Response {
+        let request = Request::get(format!("/api/spirit/{}/image", spirit_id))
+            .header(COOKIE, testing::auth_cookie(&app.state, user))
+            .body(Body::empty())
+            .unwrap();
+        app.router.clone().oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn primary_image_ids_cannot_leave_the_images_directory() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let images_path = &app.state.config.storage.images_path;
+        std::fs::write(images_path.parent().unwrap().join("secret"), "secret").unwrap();
+
+        let response = get_primary_image(&app, &user, "..%2Fsecret").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn legacy_primary_image_follows_spirit_visibility() {
+        let app = testing::app().await;
+        let submitter = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let other = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Pending Bourbon").await;
+        sqlx::query(
+            "INSERT INTO spirit_submissions (spirit_id, user_id, status) VALUES (?, ?, 'pending')",
+        )
+        .bind(&spirit_id)
+        .bind(&submitter.user_id)
+        .execute(&app.state.database)
+        .await
+        .unwrap();
+        let images_path = &app.state.config.storage.images_path;
+        std::fs::write(images_path.join(&spirit_id), b"GIF89a").unwrap();
+
+        let response = get_primary_image(&app, &other, &spirit_id).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = get_primary_image(&app, &submitter, &spirit_id).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let unknown_id = Uuid::new_v4().to_string();
+        std::fs::write(images_path.join(&unknown_id), b"GIF89a").unwrap();
+        let response = get_primary_image(&app, &submitter, &unknown_id).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn signed_primary_image_urls_only_open_their_own_image() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let images_path = &app.state.config.storage.images_path;
+        let mut spirit_ids = Vec::new();
+        for name in ["Harbor Light", "Mountain Rye"] {
+            let spirit_id = testing::create_spirit(&app.state.database, name).await;
+            std::fs::write(images_path.join(&spirit_id), b"GIF89a").unwrap();
+            spirit_ids.push(spirit_id);
+        }
+
+        let request = Request::get(format!("/api/spirit/{}/image_url", spirit_ids[0]))
+            .header(COOKIE, testing::auth_cookie(&app.state, &user))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let url = body["url"].as_str().unwrap().to_owned();
+
+        let fetch = |uri: String| {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            app.router.clone().oneshot(request)
+        };
+        assert_eq!(fetch(url.clone()).await.unwrap().status(), StatusCode::OK);
+        let unsigned = format!("/api/spirit/{}/image", spirit_ids[0]);
+        assert_eq!(
+            fetch(unsigned).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let other_image = url.replace(&spirit_ids[0], &spirit_ids[1]);
+        assert_eq!(
+            fetch(other_image).await.unwrap().status(),
+            StatusCode::FORBIDDEN
+        );
+    }
 }
@@ -0,0 +1,33 @@
+use std::net::IpAddr;
+
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+
+use super::WebResult;
+
+/// Records an administrative action, or the outcome of a background job when `user_id` is
+/// `None`, with `detail` stored as JSON. `client_ip` is where the request came from.
+pub async fn record_audit<'e, E, T>(
+    executor: E,
+    user_id: Option<&str>,
+    client_ip: Option<IpAddr>,
+    action: &str,
+    detail: &T,
+) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+    T: Serialize,
+{
+    let detail = serde_json::to_string(detail)?;
+    let ip_address = client_ip.map(|client_ip| client_ip.to_string());
+    sqlx::query_file!(
+        "sql/insert_audit_log.sql",
+        user_id,
+        ip_address,
+        action,
+        detail
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
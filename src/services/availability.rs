@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{api::ensure_spirit_exists, wishlist::notify_wishlist, WebResult};
+
+/// How realistically a bottle can be bought.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    /// On shelves and easy to find.
+    Available,
+    /// Sold through allocation lists or lotteries.
+    Allocated,
+    /// A limited release that is still around but won't be restocked.
+    Limited,
+    /// No longer produced.
+    Discontinued,
+}
+
+impl Availability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Available => "available",
+            Self::Allocated => "allocated",
+            Self::Limited => "limited",
+            Self::Discontinued => "discontinued",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityPayload {
+    availability: Availability,
+}
+
+/// Changes how obtainable a spirit is and lets anyone wishing for it know.
+pub async fn set_spirit_availability(
+    RequirePermission(user, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<AvailabilityPayload>,
+) -> WebResult<Response> {
+    let availability = payload.availability.as_str();
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_exists(&mut *transaction, &spirit_id).await?;
+    let result = sqlx::query_file!(
+        "sql/update_spirit_availability.sql",
+        spirit_id,
+        availability
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() > 0 {
+        notify_wishlist(
+            &mut *transaction,
+            &spirit_id,
+            &user.user_id,
+            &format!("Availability changed to {}.", availability),
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Multipart, Path, Request, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use image::{imageops::FilterType, ImageFormat};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::FORM_FILE_KEY,
+    images::{
+        encode_webp, read_image_header, receive_image_field, remove_file_if_exists,
+        serve_image_file, sniff_image_format,
+    },
+    WebError, WebResult,
+};
+
+/// Avatars are cropped to a square and scaled to this many pixels a side.
+const AVATAR_SIZE: u32 = 128;
+const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+/// Avatars are kept here, in the image store, named after their user.
+const AVATAR_DIRECTORY: &str = "avatars";
+
+#[derive(Debug, Serialize)]
+struct AvatarResponse {
+    avatar_url: String,
+}
+
+pub fn avatar_url(user_id: &str) -> String {
+    format!("/api/user/{}/avatar", user_id)
+}
+
+/// The image store key of a user's avatar.
+fn avatar_key(user_id: &str) -> String {
+    format!("{}/{}.webp", AVATAR_DIRECTORY, user_id)
+}
+
+/// Crops the middle square out of an upload and scales it down to a WebP avatar. Decoding
+/// happens off the async runtime since large photos take a while.
+async fn process_avatar(path: PathBuf, format: ImageFormat) -> WebResult<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let data = std::fs::read(&path)?;
+        let image = image::load_from_memory_with_format(&data, format)
+            .map_err(|e| WebError::InvalidInput(format!("Unsupported image: {}", e)))?;
+        let side = image.width().min(image.height());
+        let square = image.crop_imm(
+            (image.width() - side) / 2,
+            (image.height() - side) / 2,
+            side,
+            side,
+        );
+        let scaled = if side > AVATAR_SIZE {
+            square.resize_exact(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3)
+        } else {
+            square
+        };
+        encode_webp(&scaled)
+            .map_err(|e| WebError::InvalidInput(format!("Unsupported image: {}", e)))
+    })
+    .await
+    .expect("avatar processing task panicked")
+}
+
+/// Replaces the user's avatar with the first image in the form's `file` field.
+pub async fn set_avatar(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    mut multipart: Multipart,
+) -> WebResult<Response> {
+    let max_bytes = state
+        .config
+        .limits
+        .max_image_upload_bytes
+        .min(MAX_AVATAR_BYTES);
+    let received_path = state
+        .config
+        .storage
+        .uploads_path
+        .join(format!("{}.part", Uuid::new_v4()));
+    let mut received = false;
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() == Some(FORM_FILE_KEY) {
+            receive_image_field(&mut field, &received_path, max_bytes).await?;
+            received = true;
+            break;
+        }
+    }
+    if !received {
+        return Err(WebError::InvalidInput(format!(
+            "Send the avatar in the '{}' field.",
+            FORM_FILE_KEY
+        )));
+    }
+
+    let result = async {
+        let format = sniff_image_format(&read_image_header(&received_path).await?)?;
+        process_avatar(received_path.clone(), format).await
+    }
+    .await;
+    remove_file_if_exists(&received_path).await;
+    let avatar = result?;
+
+    state
+        .images
+        .put(&avatar_key(&user.user_id), &avatar)
+        .await?;
+
+    let sha256 = Sha256::digest(&avatar)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    sqlx::query_file!("sql/update_user_avatar.sql", user.user_id, sha256)
+        .execute(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&AvatarResponse {
+        avatar_url: avatar_url(&user.user_id),
+    })?;
+    Ok(response.into_response())
+}
+
+pub async fn delete_avatar(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let no_avatar: Option<String> = None;
+    sqlx::query_file!("sql/update_user_avatar.sql", user.user_id, no_avatar)
+        .execute(&state.database)
+        .await?;
+    state.images.discard(&avatar_key(&user.user_id)).await;
+
+    Ok("".into_response())
+}
+
+pub async fn get_avatar(
+    State(state): State<WaterOfLifeState>,
+    Path(user_id): Path<String>,
+    request: Request,
+) -> WebResult<Response> {
+    let sha256 = sqlx::query_file!("sql/select_user_avatar.sql", user_id)
+        .fetch_optional(&state.database)
+        .await?
+        .and_then(|row| row.avatar_sha256)
+        .ok_or(WebError::NotFound)?;
+
+    let path = state.images.path(&avatar_key(&user_id));
+    let etag = format!("\"{}\"", sha256);
+    serve_image_file(&path, "image/webp", Some(etag), request).await
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use tokio::fs;
+
+use crate::{
+    permissions::{BackupsCreate, RequirePermission},
+    proxy::ClientIp,
+    WaterOfLifeState,
+};
+
+use super::{audit::record_audit, WebError, WebResult};
+
+const BACKUP_PREFIX: &str = "water-of-life-";
+const BACKUP_EXTENSION: &str = "db";
+
+#[derive(Debug, Serialize)]
+pub struct BackupResponse {
+    pub file: String,
+    pub size_bytes: u64,
+    /// Older snapshots deleted to stay within `backup.keep`.
+    pub removed: Vec<String>,
+}
+
+/// The snapshots in `directory`, oldest first. Their timestamped names sort by age.
+async fn list_backups(directory: &Path) -> WebResult<Vec<PathBuf>> {
+    let mut backups = Vec::new();
+    let mut entries = fs::read_dir(directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(BACKUP_PREFIX))
+            && path
+                .extension()
+                .is_some_and(|extension| extension == BACKUP_EXTENSION);
+        if is_backup && entry.file_type().await?.is_file() {
+            backups.push(path);
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Snapshots the live database into `backup.path`, then deletes the oldest snapshots beyond
+/// `backup.keep`. `VACUUM INTO` copies a consistent view of the database without blocking
+/// readers, so this is safe to run while serving requests.
+pub async fn create_backup(state: &WaterOfLifeState) -> WebResult<BackupResponse> {
+    let directory = &state.config.backup.path;
+    fs::create_dir_all(directory).await?;
+
+    let timestamp = sqlx::query_file!("sql/select_backup_timestamp.sql")
+        .fetch_one(&state.database)
+        .await?
+        .timestamp;
+    let path = directory.join(format!(
+        "{}{}.{}",
+        BACKUP_PREFIX, timestamp, BACKUP_EXTENSION
+    ));
+    if fs::try_exists(&path).await? {
+        return Err(WebError::Conflict(
+            "A backup was already taken this second.".into(),
+        ));
+    }
+    let target = path.to_string_lossy();
+    sqlx::query_file!("sql/vacuum_into.sql", target)
+        .execute(&state.database)
+        .await?;
+    let size_bytes = fs::metadata(&path).await?.len();
+
+    let mut removed = Vec::new();
+    let keep = state.config.backup.keep;
+    if keep > 0 {
+        let backups = list_backups(directory).await?;
+        let excess = backups.len().saturating_sub(keep);
+        for old in &backups[..excess] {
+            fs::remove_file(old).await?;
+            removed.push(old.display().to_string());
+        }
+    }
+
+    Ok(BackupResponse {
+        file: path.display().to_string(),
+        size_bytes,
+        removed,
+    })
+}
+
+pub async fn backup_database(
+    RequirePermission(user, _): RequirePermission<BackupsCreate>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+) -> WebResult<Response> {
+    let backup = create_backup(&state).await?;
+    record_audit(
+        &state.database,
+        Some(&user.user_id),
+        client_ip,
+        "backup_created",
+        &backup,
+    )
+    .await?;
+
+    let response = serde_json::to_string(&backup)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    jobs::{queue_job, Job},
+    notifications::notify,
+    WebResult,
+};
+
+/// Counts badges are earned from. A spirit counts as tried once the user has rated, reviewed or
+/// poured it, or opened or finished a bottle of it.
+struct BadgeProgress {
+    ratings: i64,
+    reviews: i64,
+    pours: i64,
+    islay_spirits: i64,
+    distilleries: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Badge {
+    FirstRating,
+    FirstReview,
+    HundredPours,
+    IslayExplorer,
+    DistilleryHopper,
+}
+
+impl Badge {
+    const ALL: [Badge; 5] = [
+        Self::FirstRating,
+        Self::FirstReview,
+        Self::HundredPours,
+        Self::IslayExplorer,
+        Self::DistilleryHopper,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::FirstRating => "first_rating",
+            Self::FirstReview => "first_review",
+            Self::HundredPours => "hundred_pours",
+            Self::IslayExplorer => "islay_explorer",
+            Self::DistilleryHopper => "distillery_hopper",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::FirstRating => "First rating",
+            Self::FirstReview => "First review",
+            Self::HundredPours => "Century",
+            Self::IslayExplorer => "Islay explorer",
+            Self::DistilleryHopper => "Distillery hopper",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::FirstRating => "Rated a spirit.",
+            Self::FirstReview => "Wrote a review.",
+            Self::HundredPours => "Logged 100 pours.",
+            Self::IslayExplorer => "Tried 10 spirits from Islay.",
+            Self::DistilleryHopper => "Tried spirits from 25 distilleries.",
+        }
+    }
+
+    fn is_earned(&self, progress: &BadgeProgress) -> bool {
+        match self {
+            Self::FirstRating => progress.ratings >= 1,
+            Self::FirstReview => progress.reviews >= 1,
+            Self::HundredPours => progress.pours >= 100,
+            Self::IslayExplorer => progress.islay_spirits >= 10,
+            Self::DistilleryHopper => progress.distilleries >= 25,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BadgeResponse {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    /// Unset until the badge is earned.
+    awarded_at: Option<String>,
+}
+
+/// Awards any badges the user has newly earned and lets them know.
+pub async fn check_badges(database: &SqlitePool, user_id: &str) -> sqlx::Result<()> {
+    let progress = sqlx::query_file_as!(BadgeProgress, "sql/select_badge_progress.sql", user_id)
+        .fetch_one(database)
+        .await?;
+
+    let mut transaction = database.begin().await?;
+    for badge in Badge::ALL.iter().filter(|badge| badge.is_earned(&progress)) {
+        let id = badge.as_str();
+        let result = sqlx::query_file!("sql/insert_user_badge.sql", user_id, id)
+            .execute(&mut *transaction)
+            .await?;
+        if result.rows_affected() > 0 {
+            notify(
+                &mut *transaction,
+                user_id,
+                &format!(
+                    "You earned the {} badge: {}",
+                    badge.name(),
+                    badge.description()
+                ),
+            )
+            .await?;
+        }
+    }
+    transaction.commit().await
+}
+
+/// Queues a badge check for the user. Called after anything that counts towards a badge.
+/// Failures are logged rather than returned since badges aren't worth failing a request over.
+pub async fn award_badges(database: &SqlitePool, user_id: &str) {
+    let job = Job::AwardBadges {
+        user_id: user_id.to_owned(),
+    };
+    if let Err(e) = queue_job(database, &job).await {
+        tracing::warn!("Could not queue badges for {}: {}", user_id, e);
+    }
+}
+
+/// Every badge, with when the user earned it. Badges are evaluated here too, so progress made
+/// before a badge existed still counts.
+pub async fn list_badges(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    if let Err(e) = check_badges(&state.database, &user.user_id).await {
+        tracing::warn!("Could not award badges to {}: {}", user.user_id, e);
+    }
+
+    let mut awarded = sqlx::query_file!("sql/select_user_badges.sql", user.user_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| (row.badge, row.awarded_at))
+        .collect::<HashMap<_, _>>();
+    let badges = Badge::ALL
+        .iter()
+        .map(|badge| BadgeResponse {
+            id: badge.as_str(),
+            name: badge.name(),
+            description: badge.description(),
+            awarded_at: awarded.remove(badge.as_str()),
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&badges)?;
+    Ok(response.into_response())
+}
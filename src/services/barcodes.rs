@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    json_web::AuthContext,
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{api::find_visible_spirit, WebError, WebResult};
+
+#[derive(Debug, Deserialize)]
+pub struct BarcodePayload {
+    code: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BarcodeResponse {
+    code: String,
+    created_at: String,
+}
+
+/// Validates a scanned UPC-A, EAN-8 or EAN-13 code and returns it in the form it is stored.
+/// UPC-A codes are stored as their EAN-13 equivalent so a bottle matches whichever way the
+/// scanner reports it.
+fn normalize_barcode(code: &str) -> WebResult<String> {
+    let code = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>();
+    if !code.chars().all(|c| c.is_ascii_digit()) || ![8, 12, 13].contains(&code.len()) {
+        return Err(WebError::InvalidInput(format!(
+            "'{}' is not a UPC or EAN barcode.",
+            code
+        )));
+    }
+
+    let code = if code.len() == 12 {
+        format!("0{}", code)
+    } else {
+        code
+    };
+    // GS1 check digit: weights alternate 3 and 1 starting from the digit left of the check digit.
+    let digits = code.bytes().map(|b| (b - b'0') as u32).collect::<Vec<_>>();
+    let (check_digit, payload) = digits.split_last().unwrap_or((&0, &[]));
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    if (10 - sum % 10) % 10 != *check_digit {
+        return Err(WebError::InvalidInput(format!(
+            "Barcode '{}' has an invalid check digit.",
+            code
+        )));
+    }
+
+    Ok(code)
+}
+
+/// Looks up the spirit a scanned barcode belongs to.
+pub async fn get_spirit_by_barcode(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(code): Path<String>,
+) -> WebResult<Response> {
+    let code = normalize_barcode(&code)?;
+    let spirit_id = sqlx::query_file!("sql/select_barcode_spirit.sql", code)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .spirit_id;
+
+    let spirit = find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    let response = serde_json::to_string(&spirit)?;
+    Ok(response.into_response())
+}
+
+pub async fn list_spirit_barcodes(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let barcodes =
+        sqlx::query_file_as!(BarcodeResponse, "sql/select_spirit_barcodes.sql", spirit_id)
+            .fetch_all(&state.database)
+            .await?;
+
+    let response = serde_json::to_string(&barcodes)?;
+    Ok(response.into_response())
+}
+
+/// Attaches an unmatched barcode to a spirit. A barcode that already belongs to a spirit has to
+/// be removed by an admin before it can be attached elsewhere.
+pub async fn add_spirit_barcode(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<BarcodePayload>,
+) -> WebResult<Response> {
+    let code = normalize_barcode(&payload.code)?;
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let result = sqlx::query_file!(
+        "sql/insert_barcode.sql",
+        code,
+        spirit_id,
+        context.user.user_id
+    )
+    .execute(&state.database)
+    .await;
+    match result {
+        Ok(_) => Ok("".into_response()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(WebError::Conflict(
+            format!("Barcode '{}' is already attached to a spirit.", code),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_barcode(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(code): Path<String>,
+) -> WebResult<Response> {
+    let code = normalize_barcode(&code)?;
+
+    let result = sqlx::query_file!("sql/delete_barcode.sql", code)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
@@ -0,0 +1,457 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor};
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::ensure_spirit_exists,
+    validation::{is_valid_date, validate_optional_date},
+    WebError, WebResult,
+};
+
+const BOTTLE_HELD: &str = "held";
+const BOTTLE_TRANSFERRED: &str = "transferred";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Trade,
+    Sale,
+    Gift,
+}
+
+impl TransferKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trade => "trade",
+            Self::Sale => "sale",
+            Self::Gift => "gift",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BottlePayload {
+    spirit_id: String,
+    #[serde(default)]
+    label: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProvenancePayload {
+    acquired_from: String,
+    acquired_on: Option<String>,
+    price: Option<f64>,
+    #[serde(default)]
+    condition_notes: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferPayload {
+    kind: TransferKind,
+    counterpart: String,
+    counterpart_user_id: Option<String>,
+    transferred_on: String,
+    price: Option<f64>,
+    #[serde(default)]
+    notes: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BottleResponse {
+    id: i64,
+    spirit_id: String,
+    spirit_name: String,
+    label: String,
+    status: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TransferResponse {
+    id: i64,
+    kind: String,
+    counterpart: String,
+    counterpart_user_id: Option<String>,
+    transferred_on: String,
+    price: Option<f64>,
+    notes: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CustodyEntry {
+    holder: String,
+    since: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CustodyResponse {
+    bottle: BottleResponse,
+    provenance: Option<ProvenancePayload>,
+    transfers: Vec<TransferResponse>,
+    custody: Vec<CustodyEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct BottleIdResponse {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionBottleDetails {
+    #[serde(default)]
+    label: String,
+    /// The bottle's capacity.
+    volume_ml: Option<f64>,
+    /// Defaults to the full volume for new bottles and goes down as pours are logged.
+    remaining_ml: Option<f64>,
+    opened_on: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionBottlePayload {
+    #[serde(flatten)]
+    details: CollectionBottleDetails,
+    provenance: Option<ProvenancePayload>,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectionBottleResponse {
+    id: i64,
+    label: String,
+    status: String,
+    volume_ml: Option<f64>,
+    remaining_ml: Option<f64>,
+    opened_on: Option<String>,
+    acquired_from: Option<String>,
+    acquired_on: Option<String>,
+    price: Option<f64>,
+    created_at: String,
+}
+
+/// Fetches one of the user's bottles, hiding other users' bottles behind a 404.
+async fn find_bottle<'e, E>(executor: E, bottle_id: i64, user_id: &str) -> WebResult<BottleResponse>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file_as!(BottleResponse, "sql/select_bottle.sql", bottle_id, user_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or(WebError::NotFound)
+}
+
+fn validate_collection_bottle(details: &CollectionBottleDetails) -> WebResult<()> {
+    validate_optional_date(&details.opened_on)?;
+    if details.volume_ml.is_some_and(|volume| volume <= 0.0) {
+        return Err(WebError::InvalidInput(
+            "Bottle volume must be greater than zero.".into(),
+        ));
+    }
+    match (details.remaining_ml, details.volume_ml) {
+        (Some(remaining), _) if remaining < 0.0 => Err(WebError::InvalidInput(
+            "Remaining volume cannot be negative.".into(),
+        )),
+        (Some(remaining), Some(volume)) if remaining > volume => Err(WebError::InvalidInput(
+            "Remaining volume cannot exceed the bottle's volume.".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Looks up the spirit of one of the user's collection entries.
+async fn collection_entry_spirit<'e, E>(
+    executor: E,
+    entry_id: i64,
+    user_id: &str,
+) -> WebResult<String>
+where
+    E: SqliteExecutor<'e>,
+{
+    Ok(
+        sqlx::query_file!("sql/select_collection_entry_spirit.sql", entry_id, user_id)
+            .fetch_optional(executor)
+            .await?
+            .ok_or(WebError::NotFound)?
+            .spirit_id,
+    )
+}
+
+/// Takes a logged pour out of a bottle, marking the bottle as opened if it wasn't already.
+pub async fn record_bottle_pour(
+    connection: &mut SqliteConnection,
+    bottle_id: i64,
+    user_id: &str,
+    spirit_id: &str,
+    amount_ml: f64,
+    poured_at: Option<&str>,
+) -> WebResult<()> {
+    let result = sqlx::query_file!(
+        "sql/update_bottle_poured.sql",
+        bottle_id,
+        user_id,
+        spirit_id,
+        amount_ml,
+        poured_at
+    )
+    .execute(&mut *connection)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::InvalidInput(format!(
+            "Bottle {} is not a bottle of this spirit on your shelf.",
+            bottle_id
+        )));
+    }
+    Ok(())
+}
+
+pub async fn add_bottle(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<BottlePayload>,
+) -> WebResult<Response> {
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let id = sqlx::query_file!(
+        "sql/insert_bottle.sql",
+        user.user_id,
+        payload.spirit_id,
+        payload.label
+    )
+    .fetch_one(&state.database)
+    .await?
+    .id;
+
+    let response = serde_json::to_string(&BottleIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn list_bottles(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let bottles = sqlx::query_file_as!(BottleResponse, "sql/select_bottles.sql", user.user_id)
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&bottles)?;
+    Ok(response.into_response())
+}
+
+pub async fn set_bottle_provenance(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(bottle_id): Path<i64>,
+    Json(payload): Json<ProvenancePayload>,
+) -> WebResult<Response> {
+    validate_optional_date(&payload.acquired_on)?;
+    find_bottle(&state.database, bottle_id, &user.user_id).await?;
+
+    sqlx::query_file!(
+        "sql/upsert_bottle_provenance.sql",
+        bottle_id,
+        payload.acquired_from,
+        payload.acquired_on,
+        payload.price,
+        payload.condition_notes
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok("".into_response())
+}
+
+/// Logs a bottle leaving the collection. A bottle can only be transferred out once.
+pub async fn add_bottle_transfer(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(bottle_id): Path<i64>,
+    Json(payload): Json<TransferPayload>,
+) -> WebResult<Response> {
+    if !is_valid_date(&payload.transferred_on) {
+        return Err(WebError::InvalidInput(format!(
+            "Date '{}' is not formatted as YYYY-MM-DD.",
+            payload.transferred_on
+        )));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let bottle = find_bottle(&mut *transaction, bottle_id, &user.user_id).await?;
+    if bottle.status != BOTTLE_HELD {
+        return Err(WebError::InvalidInput(
+            "This bottle has already left the collection.".into(),
+        ));
+    }
+
+    let kind = payload.kind.as_str();
+    let id = sqlx::query_file!(
+        "sql/insert_bottle_transfer.sql",
+        bottle_id,
+        kind,
+        payload.counterpart,
+        payload.counterpart_user_id,
+        payload.transferred_on,
+        payload.price,
+        payload.notes
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    sqlx::query_file!(
+        "sql/update_bottle_status.sql",
+        bottle_id,
+        BOTTLE_TRANSFERRED
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&BottleIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn bottle_custody(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(bottle_id): Path<i64>,
+) -> WebResult<Response> {
+    let bottle = find_bottle(&state.database, bottle_id, &user.user_id).await?;
+    let provenance = sqlx::query_file_as!(
+        ProvenancePayload,
+        "sql/select_bottle_provenance.sql",
+        bottle_id
+    )
+    .fetch_optional(&state.database)
+    .await?;
+    let transfers = sqlx::query_file_as!(
+        TransferResponse,
+        "sql/select_bottle_transfers.sql",
+        bottle_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let mut custody = Vec::with_capacity(transfers.len() + 2);
+    if let Some(provenance) = &provenance {
+        custody.push(CustodyEntry {
+            holder: provenance.acquired_from.clone(),
+            since: None,
+        });
+    }
+    custody.push(CustodyEntry {
+        holder: user.preferred_username,
+        since: provenance
+            .as_ref()
+            .and_then(|provenance| provenance.acquired_on.clone()),
+    });
+    custody.extend(transfers.iter().map(|transfer| CustodyEntry {
+        holder: transfer.counterpart.clone(),
+        since: Some(transfer.transferred_on.clone()),
+    }));
+
+    let response = serde_json::to_string(&CustodyResponse {
+        bottle,
+        provenance,
+        transfers,
+        custody,
+    })?;
+    Ok(response.into_response())
+}
+
+pub async fn list_collection_bottles(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(entry_id): Path<i64>,
+) -> WebResult<Response> {
+    collection_entry_spirit(&state.database, entry_id, &user.user_id).await?;
+
+    let bottles = sqlx::query_file_as!(
+        CollectionBottleResponse,
+        "sql/select_collection_bottles.sql",
+        entry_id,
+        user.user_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&bottles)?;
+    Ok(response.into_response())
+}
+
+/// Adds a physical bottle to a collection entry, optionally with where and when it was bought.
+pub async fn add_collection_bottle(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(entry_id): Path<i64>,
+    Json(payload): Json<CollectionBottlePayload>,
+) -> WebResult<Response> {
+    let details = &payload.details;
+    validate_collection_bottle(details)?;
+    if let Some(provenance) = &payload.provenance {
+        validate_optional_date(&provenance.acquired_on)?;
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let spirit_id = collection_entry_spirit(&mut *transaction, entry_id, &user.user_id).await?;
+    let remaining_ml = details.remaining_ml.or(details.volume_ml);
+    let id = sqlx::query_file!(
+        "sql/insert_collection_bottle.sql",
+        user.user_id,
+        spirit_id,
+        details.label,
+        entry_id,
+        details.volume_ml,
+        remaining_ml,
+        details.opened_on
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    if let Some(provenance) = &payload.provenance {
+        sqlx::query_file!(
+            "sql/upsert_bottle_provenance.sql",
+            id,
+            provenance.acquired_from,
+            provenance.acquired_on,
+            provenance.price,
+            provenance.condition_notes
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&BottleIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn edit_collection_bottle(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path((entry_id, bottle_id)): Path<(i64, i64)>,
+    Json(details): Json<CollectionBottleDetails>,
+) -> WebResult<Response> {
+    validate_collection_bottle(&details)?;
+
+    let result = sqlx::query_file!(
+        "sql/update_collection_bottle.sql",
+        bottle_id,
+        entry_id,
+        user.user_id,
+        details.label,
+        details.volume_ml,
+        details.remaining_ml,
+        details.opened_on
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    json_web::AuthContext, permissions::ModerationReview, repositories::SpiritRepository,
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{find_spirit_type, find_visible_spirit},
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_INGREDIENTS: usize = 20;
+const MAX_MISSING_INGREDIENTS: usize = 3;
+
+/// One ingredient of a recipe. Exactly one of `spirit_id`, `spirit_type` or `name` is set.
+#[derive(Debug, Deserialize)]
+pub struct IngredientPayload {
+    /// A specific spirit from the catalog.
+    spirit_id: Option<String>,
+    /// Any spirit of this type, such as "Bourbon".
+    spirit_type: Option<String>,
+    /// Anything outside the catalog, such as "Lemon juice".
+    name: Option<String>,
+    #[serde(default)]
+    amount: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CocktailPayload {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    instructions: String,
+    ingredients: Vec<IngredientPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MakeableParameter {
+    /// Also include recipes missing up to this many required spirits.
+    #[serde(default)]
+    max_missing: usize,
+}
+
+/// An ingredient as listed in a recipe's details.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CocktailIngredient {
+    position: i64,
+    spirit_id: Option<String>,
+    spirit_type: Option<String>,
+    name: String,
+    amount: String,
+    optional: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CocktailResponse {
+    id: i64,
+    user_id: String,
+    name: String,
+    description: String,
+    instructions: String,
+    ingredients: sqlx::types::Json<Vec<CocktailIngredient>>,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CocktailSummaryResponse {
+    id: i64,
+    user_id: String,
+    name: String,
+    description: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CocktailIdResponse {
+    id: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct MakeableCocktailResponse {
+    id: i64,
+    name: String,
+    /// Required spirits, or spirit types, the user doesn't own.
+    missing: Vec<String>,
+}
+
+/// An ingredient resolved to what gets stored.
+struct Ingredient {
+    spirit_id: Option<String>,
+    spirit_type_id: Option<i64>,
+    name: String,
+    amount: String,
+    optional: bool,
+}
+
+/// Checks the recipe and resolves each ingredient against the catalog.
+async fn validate_cocktail(
+    database: &SqlitePool,
+    spirits: &dyn SpiritRepository,
+    context: &AuthContext,
+    payload: &CocktailPayload,
+) -> WebResult<Vec<Ingredient>> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(WebError::InvalidInput("Cocktails need a name.".into()));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Cocktail names must be at most {} characters.",
+            MAX_NAME_LENGTH
+        )));
+    }
+    if payload.ingredients.is_empty() || payload.ingredients.len() > MAX_INGREDIENTS {
+        return Err(WebError::InvalidInput(format!(
+            "Cocktails must have between 1 and {} ingredients.",
+            MAX_INGREDIENTS
+        )));
+    }
+
+    let mut ingredients = Vec::with_capacity(payload.ingredients.len());
+    for ingredient in &payload.ingredients {
+        let name = ingredient
+            .name
+            .as_deref()
+            .map(str::trim)
+            .unwrap_or_default();
+        let (spirit_id, spirit_type_id) = match (&ingredient.spirit_id, &ingredient.spirit_type) {
+            (Some(spirit_id), None) if name.is_empty() => {
+                find_visible_spirit(spirits, context, spirit_id)
+                    .await
+                    .map_err(|e| match e {
+                        WebError::NotFound => {
+                            WebError::InvalidInput(format!("Unknown spirit '{}'.", spirit_id))
+                        }
+                        e => e,
+                    })?;
+                (Some(spirit_id.clone()), None)
+            }
+            (None, Some(spirit_type)) if name.is_empty() => (
+                None,
+                Some(find_spirit_type(database, spirit_type).await?.id),
+            ),
+            (None, None) if !name.is_empty() => (None, None),
+            _ => {
+                return Err(WebError::InvalidInput(
+                    "Each ingredient needs exactly one of a spirit_id, spirit_type or name.".into(),
+                ))
+            }
+        };
+        ingredients.push(Ingredient {
+            spirit_id,
+            spirit_type_id,
+            name: name.to_owned(),
+            amount: ingredient.amount.trim().to_owned(),
+            optional: ingredient.optional,
+        });
+    }
+    Ok(ingredients)
+}
+
+async fn insert_ingredients(
+    connection: &mut SqliteConnection,
+    cocktail_id: i64,
+    ingredients: &[Ingredient],
+) -> WebResult<()> {
+    for (position, ingredient) in (1_i64..).zip(ingredients) {
+        sqlx::query_file!(
+            "sql/insert_cocktail_ingredient.sql",
+            cocktail_id,
+            position,
+            ingredient.spirit_id,
+            ingredient.spirit_type_id,
+            ingredient.name,
+            ingredient.amount,
+            ingredient.optional
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Returns [`WebError::Forbidden`] unless the caller added the recipe or is a moderator.
+async fn ensure_cocktail_editable(
+    database: &SqlitePool,
+    context: &AuthContext,
+    cocktail_id: i64,
+) -> WebResult<()> {
+    let owner = sqlx::query_file!("sql/select_cocktail_owner.sql", cocktail_id)
+        .fetch_optional(database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .user_id;
+    if owner != context.user.user_id && !context.permissions.has::<ModerationReview>() {
+        return Err(WebError::Forbidden);
+    }
+    Ok(())
+}
+
+pub async fn list_cocktails(
+    State(state): State<WaterOfLifeState>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let (limit, offset) = (page.limit(), page.offset());
+    let cocktails = sqlx::query_file_as!(
+        CocktailSummaryResponse,
+        "sql/select_cocktails.sql",
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_cocktail_count.sql")
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(cocktails, &page, total))?;
+    Ok(response.into_response())
+}
+
+pub async fn get_cocktail(
+    State(state): State<WaterOfLifeState>,
+    Path(cocktail_id): Path<i64>,
+) -> WebResult<Response> {
+    let cocktail = sqlx::query_file_as!(CocktailResponse, "sql/select_cocktail.sql", cocktail_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let response = serde_json::to_string(&cocktail)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_cocktail(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<CocktailPayload>,
+) -> WebResult<Response> {
+    let ingredients = validate_cocktail(
+        &state.database,
+        &*state.repositories.spirits,
+        &context,
+        &payload,
+    )
+    .await?;
+
+    let name = payload.name.trim();
+    let mut transaction = state.repositories.begin().await?;
+    let id = sqlx::query_file!(
+        "sql/insert_cocktail.sql",
+        context.user.user_id,
+        name,
+        payload.description,
+        payload.instructions
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    insert_ingredients(&mut transaction, id, &ingredients).await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&CocktailIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+/// Replaces a recipe and its ingredients.
+pub async fn edit_cocktail(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(cocktail_id): Path<i64>,
+    Json(payload): Json<CocktailPayload>,
+) -> WebResult<Response> {
+    ensure_cocktail_editable(&state.database, &context, cocktail_id).await?;
+    let ingredients = validate_cocktail(
+        &state.database,
+        &*state.repositories.spirits,
+        &context,
+        &payload,
+    )
+    .await?;
+
+    let name = payload.name.trim();
+    let mut transaction = state.repositories.begin().await?;
+    sqlx::query_file!(
+        "sql/update_cocktail.sql",
+        cocktail_id,
+        name,
+        payload.description,
+        payload.instructions
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query_file!("sql/delete_cocktail_ingredients.sql", cocktail_id)
+        .execute(&mut *transaction)
+        .await?;
+    insert_ingredients(&mut transaction, cocktail_id, &ingredients).await?;
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_cocktail(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(cocktail_id): Path<i64>,
+) -> WebResult<Response> {
+    ensure_cocktail_editable(&state.database, &context, cocktail_id).await?;
+
+    sqlx::query_file!("sql/delete_cocktail.sql", cocktail_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+/// Recipes the user can mix from the bottles they own, fewest missing spirits first. A spirit
+/// type ingredient is covered by any owned spirit of that type. Ingredients outside the
+/// catalog and optional ones are assumed to be on hand.
+pub async fn makeable_cocktails(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<MakeableParameter>,
+) -> WebResult<Response> {
+    if query_params.max_missing > MAX_MISSING_INGREDIENTS {
+        return Err(WebError::InvalidInput(format!(
+            "max_missing must be at most {}.",
+            MAX_MISSING_INGREDIENTS
+        )));
+    }
+
+    let mut missing = HashMap::<i64, Vec<String>>::new();
+    let rows = sqlx::query_file!(
+        "sql/select_missing_cocktail_ingredients.sql",
+        context.user.user_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+    for row in rows {
+        missing
+            .entry(row.cocktail_id)
+            .or_default()
+            .push(row.ingredient);
+    }
+
+    let mut cocktails = sqlx::query_file!("sql/select_cocktail_names.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| MakeableCocktailResponse {
+            missing: missing.remove(&row.id).unwrap_or_default(),
+            id: row.id,
+            name: row.name,
+        })
+        .filter(|cocktail| cocktail.missing.len() <= query_params.max_missing)
+        .collect::<Vec<_>>();
+    // Stable, so recipes missing as many spirits stay in name order.
+    cocktails.sort_by_key(|cocktail| cocktail.missing.len());
+
+    let response = serde_json::to_string(&cocktails)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::ensure_spirit_exists,
+    badges::award_badges,
+    export::{csv_response, export_response, ExportFormat, ExportParameter, ExportSink},
+    trending::{record_activity, ActivityKind},
+    validation::validate_optional_date,
+    WebError, WebResult,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionStatus {
+    Owned,
+    Finished,
+    Wishlist,
+}
+
+impl CollectionStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Owned => "owned",
+            Self::Finished => "finished",
+            Self::Wishlist => "wishlist",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionParameter {
+    status: Option<CollectionStatus>,
+    opened: Option<bool>,
+    spirit_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionDetails {
+    status: CollectionStatus,
+    purchase_date: Option<String>,
+    price_paid: Option<f64>,
+    #[serde(default)]
+    opened: bool,
+    /// How full the bottle is, as a percentage.
+    fill_level: Option<i64>,
+    #[serde(default)]
+    notes: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionEntryPayload {
+    spirit_id: String,
+    #[serde(flatten)]
+    details: CollectionDetails,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectionEntryResponse {
+    id: i64,
+    spirit_id: String,
+    spirit_name: String,
+    status: String,
+    purchase_date: Option<String>,
+    price_paid: Option<f64>,
+    opened: bool,
+    fill_level: Option<i64>,
+    notes: String,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectionEntryIdResponse {
+    id: i64,
+}
+
+fn validate_details(details: &CollectionDetails) -> WebResult<()> {
+    validate_optional_date(&details.purchase_date)?;
+    if details.price_paid.is_some_and(|price| price < 0.0) {
+        return Err(WebError::InvalidInput(
+            "Price paid cannot be negative.".into(),
+        ));
+    }
+    if details
+        .fill_level
+        .is_some_and(|fill_level| !(0..=100).contains(&fill_level))
+    {
+        return Err(WebError::InvalidInput(
+            "Fill level must be between 0 and 100.".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn list_collection(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<CollectionParameter>,
+    Query(export_params): Query<ExportParameter>,
+    headers: HeaderMap,
+) -> WebResult<Response> {
+    let status = query_params.status.as_ref().map(CollectionStatus::as_str);
+    let entries = sqlx::query_file_as!(
+        CollectionEntryResponse,
+        "sql/select_collection_entries.sql",
+        user.user_id,
+        status,
+        query_params.opened,
+        query_params.spirit_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    if ExportFormat::negotiate(export_params.format, &headers) == ExportFormat::Csv {
+        return csv_response(&entries);
+    }
+    let response = serde_json::to_string(&entries)?;
+    Ok(response.into_response())
+}
+
+pub async fn get_collection_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(entry_id): Path<i64>,
+) -> WebResult<Response> {
+    let entry = sqlx::query_file_as!(
+        CollectionEntryResponse,
+        "sql/select_collection_entry.sql",
+        entry_id,
+        user.user_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or(WebError::NotFound)?;
+
+    let response = serde_json::to_string(&entry)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_collection_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<CollectionEntryPayload>,
+) -> WebResult<Response> {
+    let details = &payload.details;
+    validate_details(details)?;
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let status = details.status.as_str();
+    let id = sqlx::query_file!(
+        "sql/insert_collection_entry.sql",
+        user.user_id,
+        payload.spirit_id,
+        status,
+        details.purchase_date,
+        details.price_paid,
+        details.opened,
+        details.fill_level,
+        details.notes
+    )
+    .fetch_one(&state.database)
+    .await?
+    .id;
+    record_activity(
+        &state.database,
+        &payload.spirit_id,
+        ActivityKind::Collection,
+    )
+    .await;
+    award_badges(&state.database, &user.user_id).await;
+
+    let response = serde_json::to_string(&CollectionEntryIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn edit_collection_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(entry_id): Path<i64>,
+    Json(details): Json<CollectionDetails>,
+) -> WebResult<Response> {
+    validate_details(&details)?;
+
+    let status = details.status.as_str();
+    let result = sqlx::query_file!(
+        "sql/update_collection_entry.sql",
+        entry_id,
+        user.user_id,
+        status,
+        details.purchase_date,
+        details.price_paid,
+        details.opened,
+        details.fill_level,
+        details.notes
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    award_badges(&state.database, &user.user_id).await;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_collection_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(entry_id): Path<i64>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_collection_entry.sql", entry_id, user.user_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+/// Downloads every entry in the user's collection as CSV or JSON.
+pub async fn export_collection(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<ExportParameter>,
+    headers: HeaderMap,
+) -> WebResult<Response> {
+    let format = ExportFormat::negotiate(query_params.format, &headers);
+
+    let (sink, body) = ExportSink::new(format);
+    let database = state.database.clone();
+    tokio::spawn(async move {
+        // No filters, every entry is exported.
+        let status: Option<&str> = None;
+        let opened: Option<bool> = None;
+        let spirit_id: Option<String> = None;
+        let rows = sqlx::query_file_as!(
+            CollectionEntryResponse,
+            "sql/select_collection_entries.sql",
+            user.user_id,
+            status,
+            opened,
+            spirit_id
+        )
+        .fetch(&database);
+        sink.forward(rows).await;
+    });
+
+    Ok(export_response(format, "collection", body))
+}
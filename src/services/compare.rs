@@ -0,0 +1,61 @@
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{json_web::AuthContext, WaterOfLifeState};
+
+use super::{
+    api::{find_visible_spirit, SpiritDetailResponse},
+    flavors::{load_flavor_profile, FlavorProfileResponse},
+    WebError, WebResult,
+};
+
+const MAX_COMPARED_SPIRITS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct CompareParameter {
+    /// Comma separated spirit ids.
+    ids: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ComparedSpirit {
+    /// Includes the spirit's average rating and rating count.
+    spirit: SpiritDetailResponse,
+    flavor_profile: FlavorProfileResponse,
+}
+
+/// Loads several spirits with their ratings and flavor profiles in the order requested, so
+/// they can be shown side by side. Repeated ids are only returned once.
+pub async fn compare_spirits(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<CompareParameter>,
+) -> WebResult<Response> {
+    let mut ids = Vec::new();
+    for id in query_params.ids.split(',').map(str::trim) {
+        if !id.is_empty() && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() || ids.len() > MAX_COMPARED_SPIRITS {
+        return Err(WebError::InvalidInput(format!(
+            "Compare between 1 and {} spirits at a time.",
+            MAX_COMPARED_SPIRITS
+        )));
+    }
+
+    let mut spirits = Vec::with_capacity(ids.len());
+    for id in ids {
+        spirits.push(ComparedSpirit {
+            spirit: find_visible_spirit(&*state.repositories.spirits, &context, id).await?,
+            flavor_profile: load_flavor_profile(&state.database, id).await?,
+        });
+    }
+
+    let response = serde_json::to_string(&spirits)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,250 @@
+use std::{collections::HashSet, io};
+
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    infra::ImageStore,
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::WebResult;
+
+const DEFAULT_MIN_DESCRIPTION_LENGTH: i64 = 40;
+const DEFAULT_STALE_PRICE_DAYS: i64 = 180;
+/// Caps each worklist so the report stays small on large catalogs; totals are still reported.
+const MAX_ISSUES_PER_CATEGORY: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct DataQualityParameter {
+    min_description_length: Option<i64>,
+    /// Spirits whose newest price was seen longer ago than this are listed as stale.
+    stale_price_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpiritIssue {
+    spirit_id: String,
+    name: String,
+    detail: Option<String>,
+    fix_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DistillerIssue {
+    distiller: String,
+    spirit_count: i64,
+    fix_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DuplicateIssue {
+    first_id: String,
+    first_name: String,
+    second_id: String,
+    second_name: String,
+    fix_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Worklist<T> {
+    total: usize,
+    items: Vec<T>,
+}
+
+impl<T> From<Vec<T>> for Worklist<T> {
+    fn from(mut items: Vec<T>) -> Self {
+        let total = items.len();
+        items.truncate(MAX_ISSUES_PER_CATEGORY);
+        Self { total, items }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DataQualityReport {
+    missing_images: Worklist<SpiritIssue>,
+    short_descriptions: Worklist<SpiritIssue>,
+    stale_prices: Worklist<SpiritIssue>,
+    orphaned_distillers: Worklist<DistillerIssue>,
+    duplicate_suspects: Worklist<DuplicateIssue>,
+}
+
+/// Collects the spirit ids that have an image in the store, ignoring any file extension.
+async fn stored_image_ids(images: &ImageStore) -> io::Result<HashSet<String>> {
+    Ok(images
+        .list(None)
+        .await?
+        .into_iter()
+        .map(|file| file.stem)
+        .collect())
+}
+
+/// A curator worklist of catalog entries that need attention.
+pub async fn data_quality_report(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<DataQualityParameter>,
+) -> WebResult<Response> {
+    // Recorded images are named by image id; older unrecorded ones by their spirit's id.
+    let mut image_ids = stored_image_ids(&state.images).await.unwrap_or_else(|e| {
+        tracing::warn!("data_quality_report: could not read images: {}", e);
+        HashSet::new()
+    });
+    image_ids.extend(
+        sqlx::query_file!("sql/select_spirit_image_ids.sql")
+            .fetch_all(&state.database)
+            .await?
+            .into_iter()
+            .map(|row| row.spirit_id),
+    );
+    let missing_images = sqlx::query_file!("sql/select_spirit_ids.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .filter(|row| !image_ids.contains(&row.uuid))
+        .map(|row| SpiritIssue {
+            fix_url: format!("/api/spirit/{}/image", row.uuid),
+            spirit_id: row.uuid,
+            name: row.name,
+            detail: None,
+        })
+        .collect::<Vec<_>>();
+
+    let min_description_length = query_params
+        .min_description_length
+        .unwrap_or(DEFAULT_MIN_DESCRIPTION_LENGTH);
+    let short_descriptions =
+        sqlx::query_file!("sql/select_short_descriptions.sql", min_description_length)
+            .fetch_all(&state.database)
+            .await?
+            .into_iter()
+            .map(|row| SpiritIssue {
+                fix_url: format!("/api/spirit/{}", row.uuid),
+                spirit_id: row.uuid,
+                name: row.name,
+                detail: Some(format!(
+                    "Description is {} characters long",
+                    row.description_length
+                )),
+            })
+            .collect::<Vec<_>>();
+
+    let stale_price_days = query_params
+        .stale_price_days
+        .unwrap_or(DEFAULT_STALE_PRICE_DAYS);
+    let stale_prices = sqlx::query_file!("sql/select_stale_prices.sql", stale_price_days)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| SpiritIssue {
+            fix_url: format!("/api/spirit/{}/prices", row.uuid),
+            spirit_id: row.uuid,
+            name: row.name,
+            detail: Some(format!("Last priced on {}", row.last_observed_on)),
+        })
+        .collect::<Vec<_>>();
+
+    let orphaned_distillers = sqlx::query_file!("sql/select_orphaned_distillers.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| DistillerIssue {
+            distiller: row.distiller,
+            spirit_count: row.spirit_count,
+            fix_url: "/api/distillers".into(),
+        })
+        .collect::<Vec<_>>();
+
+    let duplicate_suspects = sqlx::query_file!("sql/select_duplicate_suspects.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| DuplicateIssue {
+            fix_url: format!("/api/spirit/{}", row.second_id),
+            first_id: row.first_id,
+            first_name: row.first_name,
+            second_id: row.second_id,
+            second_name: row.second_name,
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&DataQualityReport {
+        missing_images: missing_images.into(),
+        short_descriptions: short_descriptions.into(),
+        stale_prices: stale_prices.into(),
+        orphaned_distillers: orphaned_distillers.into(),
+        duplicate_suspects: duplicate_suspects.into(),
+    })?;
+    Ok(response.into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::testing;
+
+    async fn stale_prices(app: &testing::TestApp, query: &str) -> serde_json::Value {
+        let admin = testing::create_admin(&app.state.database).await;
+        let request = Request::get(format!("/api/admin/data-quality{}", query))
+            .header(header::COOKIE, testing::auth_cookie(&app.state, &admin))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["stale_prices"].take()
+    }
+
+    async fn add_price(app: &testing::TestApp, spirit_id: &str, observed_on: &str) {
+        sqlx::query(
+            "INSERT INTO price_points (spirit_id, user_id, amount, currency, observed_on)
+            VALUES (?, 'someone', 40.0, 'USD', ?)",
+        )
+        .bind(spirit_id)
+        .bind(observed_on)
+        .execute(&app.state.database)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn spirits_without_a_recent_price_are_listed() {
+        let app = testing::app().await;
+        let stale = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        let fresh = testing::create_spirit(&app.state.database, "Mountain Rye").await;
+        testing::create_spirit(&app.state.database, "Never Priced").await;
+        add_price(&app, &stale, "2020-01-01").await;
+        add_price(&app, &fresh, "2020-01-01").await;
+        sqlx::query(
+            "INSERT INTO price_points (spirit_id, user_id, amount, currency, observed_on)
+            VALUES (?, 'someone', 40.0, 'USD', date('now'))",
+        )
+        .bind(&fresh)
+        .execute(&app.state.database)
+        .await
+        .unwrap();
+
+        let report = stale_prices(&app, "").await;
+        assert_eq!(report["total"], 1);
+        assert_eq!(report["items"][0]["spirit_id"], stale.as_str());
+        assert_eq!(report["items"][0]["detail"], "Last priced on 2020-01-01");
+        assert_eq!(
+            report["items"][0]["fix_url"],
+            format!("/api/spirit/{}/prices", stale)
+        );
+
+        let report = stale_prices(&app, "?stale_price_days=100000").await;
+        assert_eq!(report["total"], 0);
+    }
+}
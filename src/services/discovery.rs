@@ -0,0 +1,191 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{json_web::AuthContext, WaterOfLifeState};
+
+use super::{
+    api::{find_spirit_type, find_visible_spirit, SpiritDetailResponse},
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+const MAX_SIMILAR_SPIRITS: i64 = 10;
+/// A flavor counts as one of a spirit's tags once voters average at least this score for it.
+const PROMINENT_FLAVOR_SCORE: f64 = 5.0;
+/// How much each kind of match adds to a similar spirit's score.
+const SAME_TYPE_WEIGHT: i64 = 2;
+const SAME_DISTILLER_WEIGHT: i64 = 3;
+const SAME_REGION_WEIGHT: i64 = 1;
+const SHARED_FLAVOR_WEIGHT: i64 = 1;
+
+#[derive(Debug, Deserialize)]
+pub struct RandomSpiritParameter {
+    /// Only pick spirits of this type.
+    #[serde(rename = "type")]
+    typ: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentOrder {
+    /// Newest additions to the catalog first.
+    #[default]
+    Added,
+    /// Most recently edited first.
+    Updated,
+}
+
+impl RecentOrder {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Updated => "updated",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecentParameter {
+    #[serde(default)]
+    order: RecentOrder,
+}
+
+#[derive(Debug, Serialize)]
+struct RecentSpiritResponse {
+    id: String,
+    name: String,
+    distiller: String,
+    typ: String,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SimilarSpiritResponse {
+    id: String,
+    name: String,
+    distiller: String,
+    typ: String,
+    same_type: bool,
+    same_distiller: bool,
+    same_region: bool,
+    /// How many of the spirit's prominent flavors the two have in common.
+    shared_flavors: i64,
+    score: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SpiritOfTheDayResponse {
+    /// The UTC date the pick is for.
+    date: String,
+    spirit: SpiritDetailResponse,
+}
+
+/// Picks an approved spirit at random, optionally of a single type.
+pub async fn random_spirit(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<RandomSpiritParameter>,
+) -> WebResult<Response> {
+    let type_id = match &query_params.typ {
+        Some(typ) => Some(find_spirit_type(&state.database, typ).await?.id),
+        None => None,
+    };
+    let spirit_id = sqlx::query_file!("sql/select_random_spirit.sql", type_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .uuid;
+
+    let spirit = find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    let response = serde_json::to_string(&spirit)?;
+    Ok(response.into_response())
+}
+
+/// Picks the same approved spirit for everyone for the whole of a UTC day. The pick is drawn
+/// from a hash of the date so consecutive days don't walk through the catalog in order.
+pub async fn spirit_of_the_day(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let candidates = sqlx::query_file!("sql/select_spirit_of_the_day_candidates.sql")
+        .fetch_one(&state.database)
+        .await?;
+    if candidates.count == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    let digest = Sha256::digest(candidates.date.as_bytes());
+    let seed = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    let offset = (seed % candidates.count as u64) as i64;
+    let spirit_id = sqlx::query_file!("sql/select_spirit_of_the_day.sql", offset)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .uuid;
+
+    let spirit = find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    let response = serde_json::to_string(&SpiritOfTheDayResponse {
+        date: candidates.date,
+        spirit,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Lists approved spirits by when they were added or last edited, newest first. Spirits added
+/// before the catalog kept timestamps aren't listed.
+pub async fn recent_spirits(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<RecentParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let (order, limit, offset) = (query_params.order.as_str(), page.limit(), page.offset());
+    let spirits = sqlx::query_file_as!(
+        RecentSpiritResponse,
+        "sql/select_recent_spirits.sql",
+        order,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_recent_spirit_count.sql")
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(spirits, &page, total))?;
+    Ok(response.into_response())
+}
+
+/// Lists approved spirits related to this one, scored by whether they share its type,
+/// distiller and region and how many of its prominent flavors they have in common.
+pub async fn similar_spirits(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let spirits = sqlx::query_file_as!(
+        SimilarSpiritResponse,
+        "sql/select_similar_spirits.sql",
+        spirit_id,
+        PROMINENT_FLAVOR_SCORE,
+        SAME_TYPE_WEIGHT,
+        SAME_DISTILLER_WEIGHT,
+        SAME_REGION_WEIGHT,
+        SHARED_FLAVOR_WEIGHT,
+        MAX_SIMILAR_SPIRITS
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&spirits)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+
+use super::WebResult;
+
+/// How alike two normalized names must be, from 0 to 1, to count as the same bottle.
+const NAME_SIMILARITY_THRESHOLD: f64 = 0.85;
+const DISTILLER_SIMILARITY_THRESHOLD: f64 = 0.8;
+/// When either distiller is blank the name has to carry the match on its own.
+const NAME_ONLY_SIMILARITY_THRESHOLD: f64 = 0.95;
+const MAX_DUPLICATE_CANDIDATES: usize = 5;
+/// Words that don't distinguish one bottle from another.
+const IGNORED_WORDS: [&str; 10] = [
+    "the",
+    "and",
+    "of",
+    "co",
+    "company",
+    "inc",
+    "ltd",
+    "distillery",
+    "distilling",
+    "distillers",
+];
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateCandidate {
+    id: String,
+    name: String,
+    distiller: String,
+    similarity: f64,
+}
+
+/// Lowercases, drops punctuation and filler words, and collapses whitespace so that
+/// "Blanton's Original" and "blantons  original" compare equal.
+fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .filter(|c| *c != '\'')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .filter(|word| !IGNORED_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// 1 for identical normalized strings, falling towards 0 as more edits are needed.
+fn similarity(a: &str, b: &str) -> f64 {
+    let length = a.chars().count().max(b.chars().count());
+    if length == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / length as f64
+}
+
+/// Finds existing spirits that look like the same bottle as `name` from `distiller`, most
+/// similar first.
+pub async fn find_duplicate_candidates<'e, E>(
+    executor: E,
+    name: &str,
+    distiller: &str,
+) -> WebResult<Vec<DuplicateCandidate>>
+where
+    E: SqliteExecutor<'e>,
+{
+    let name = normalize(name);
+    let distiller = normalize(distiller);
+    if name.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Narrow the catalog down to spirits sharing the start of a word with the name before
+    // comparing. Prefixes still match when the tokenizer split a word differently, such as
+    // "blanton's" into "blanton" and "s".
+    let fts_query = name
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.chars().take(4).collect::<String>()))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let rows = sqlx::query_file!("sql/select_duplicate_candidates.sql", fts_query)
+        .fetch_all(executor)
+        .await?;
+
+    let mut candidates = rows
+        .into_iter()
+        .filter_map(|row| {
+            let name_similarity = similarity(&name, &normalize(&row.name));
+            let row_distiller = normalize(&row.distiller);
+            let is_duplicate = if distiller.is_empty() || row_distiller.is_empty() {
+                name_similarity >= NAME_ONLY_SIMILARITY_THRESHOLD
+            } else {
+                name_similarity >= NAME_SIMILARITY_THRESHOLD
+                    && similarity(&distiller, &row_distiller) >= DISTILLER_SIMILARITY_THRESHOLD
+            };
+            is_duplicate.then_some(DuplicateCandidate {
+                id: row.id,
+                name: row.name,
+                distiller: row.distiller,
+                similarity: name_similarity,
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    candidates.truncate(MAX_DUPLICATE_CANDIDATES);
+    Ok(candidates)
+}
@@ -0,0 +1,55 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::WebResult;
+
+/// Which kinds of email a user gets. Moderation emails and price alerts are on by default and
+/// the weekly digest is off.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailPreferences {
+    /// Decisions about the user's submissions and reported content.
+    moderation: bool,
+    /// New prices for spirits on the user's wishlist.
+    price_alerts: bool,
+    weekly_digest: bool,
+}
+
+pub async fn get_email_preferences(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let preferences = sqlx::query_file_as!(
+        EmailPreferences,
+        "sql/select_email_preferences.sql",
+        user.user_id
+    )
+    .fetch_one(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&preferences)?;
+    Ok(response.into_response())
+}
+
+pub async fn set_email_preferences(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<EmailPreferences>,
+) -> WebResult<Response> {
+    sqlx::query_file!(
+        "sql/upsert_email_preferences.sql",
+        user.user_id,
+        payload.moderation,
+        payload.price_alerts,
+        payload.weekly_digest
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok("".into_response())
+}
@@ -0,0 +1,45 @@
+//! Conditional GETs. Responses carry an ETag and a request whose `If-None-Match` names it gets
+//! an empty 304 instead of the body.
+
+use axum::{
+    http::{
+        header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+/// Whether any tag in an `If-None-Match` header names `etag`. The comparison is weak, so a
+/// `W/` prefix on either side is ignored.
+pub fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.to_str().is_ok_and(|tags| {
+        tags.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag)
+    })
+}
+
+/// A short hash of a response body, for responses with nothing better to tag them by.
+pub fn content_hash(body: &[u8]) -> String {
+    Sha256::digest(body)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Sends `body` as JSON tagged with `etag`, or a 304 when the client already has it.
+pub fn conditional_json(headers: &HeaderMap, etag: String, body: String) -> Response {
+    if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+        if etag_matches(if_none_match, &etag) {
+            return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+        }
+    }
+    (
+        [(CONTENT_TYPE, "application/json".to_owned()), (ETAG, etag)],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,273 @@
+use std::io;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{
+        header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE, VARY},
+        HeaderMap,
+    },
+    response::{IntoResponse, Response},
+};
+use futures::{
+    channel::mpsc::{self, Sender},
+    SinkExt, Stream, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    permissions::{CatalogExport, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    preferences::{load_preferences, StrengthUnit},
+    WebResult,
+};
+
+/// How many encoded rows may queue up before the query waits for the client to catch up.
+const EXPORT_BUFFER_ROWS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Picks the `?format=` parameter when given, then the `Accept` header, then JSON.
+    pub fn negotiate(parameter: Option<Self>, headers: &HeaderMap) -> Self {
+        if let Some(format) = parameter {
+            return format;
+        }
+        let accept = headers
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or_default();
+        if accept.contains("text/csv") && !accept.contains("application/json") {
+            Self::Csv
+        } else {
+            Self::Json
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParameter {
+    pub format: Option<ExportFormat>,
+}
+
+/// Encodes rows one at a time into the response body as they are read from the database, so
+/// an export never holds the full result set in memory.
+pub struct ExportSink {
+    format: ExportFormat,
+    sender: Sender<io::Result<Bytes>>,
+    rows: usize,
+}
+
+impl ExportSink {
+    /// Returns the sink to feed from a spawned task and the body it streams into.
+    pub fn new(format: ExportFormat) -> (Self, Body) {
+        let (sender, receiver) = mpsc::channel(EXPORT_BUFFER_ROWS);
+        let sink = Self {
+            format,
+            sender,
+            rows: 0,
+        };
+        (sink, Body::from_stream(receiver))
+    }
+
+    fn encode<T: Serialize>(&self, row: &T) -> Result<Vec<u8>, String> {
+        match self.format {
+            ExportFormat::Csv => {
+                // Headers come from the row's field names, so only the first row writes them.
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(self.rows == 0)
+                    .from_writer(Vec::new());
+                writer.serialize(row).map_err(|e| e.to_string())?;
+                writer.into_inner().map_err(|e| e.to_string())
+            }
+            ExportFormat::Json => {
+                let mut encoded = if self.rows == 0 { b"[" } else { b"," }.to_vec();
+                serde_json::to_writer(&mut encoded, row).map_err(|e| e.to_string())?;
+                Ok(encoded)
+            }
+        }
+    }
+
+    /// Streams every row into the body. A failure part way through aborts the response so
+    /// the client sees a truncated download rather than a silently incomplete one.
+    pub async fn forward<T, S>(mut self, mut rows: S)
+    where
+        T: Serialize,
+        S: Stream<Item = sqlx::Result<T>> + Unpin,
+    {
+        while let Some(row) = rows.next().await {
+            let encoded = row
+                .map_err(|e| e.to_string())
+                .and_then(|row| self.encode(&row));
+            let chunk = match encoded {
+                Ok(encoded) => Ok(Bytes::from(encoded)),
+                Err(e) => {
+                    tracing::warn!("export: {}", e);
+                    let _ = self.sender.send(Err(io::Error::other(e))).await;
+                    return;
+                }
+            };
+            if self.sender.send(chunk).await.is_err() {
+                // The client went away.
+                return;
+            }
+            self.rows += 1;
+        }
+
+        if self.format == ExportFormat::Json {
+            let end = if self.rows == 0 { "[]" } else { "]" };
+            let _ = self
+                .sender
+                .send(Ok(Bytes::from_static(end.as_bytes())))
+                .await;
+        }
+    }
+}
+
+/// Sends `rows` as a CSV document, for list and stats endpoints whose client negotiated
+/// [`ExportFormat::Csv`]. Their JSON responses wrap rows in envelopes and nest fields, which a
+/// spreadsheet can't hold, so each handler passes the rows that make sense as a table.
+pub fn csv_response<T: Serialize>(rows: &[T]) -> WebResult<Response> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).map_err(io::Error::from)?;
+    }
+    let body = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok((
+        [
+            (CONTENT_TYPE, ExportFormat::Csv.content_type()),
+            // The same URL also serves JSON.
+            (VARY, "accept"),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Wraps a streamed export body as a file download named `<name>.<extension>`.
+pub fn export_response(format: ExportFormat, name: &str, body: Body) -> Response {
+    (
+        [
+            (CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+struct SpiritRow {
+    id: String,
+    name: String,
+    description: String,
+    distiller: String,
+    bottler: String,
+    typ: String,
+    abv: f64,
+    age: String,
+    region_id: Option<i64>,
+    status: String,
+}
+
+/// A spirit as exported. Exactly one of `abv` and `proof` is set, following the exporting
+/// user's preferred strength unit.
+#[derive(Debug, Serialize)]
+struct SpiritExportRow {
+    id: String,
+    name: String,
+    description: String,
+    distiller: String,
+    bottler: String,
+    #[serde(rename = "type")]
+    typ: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    abv: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<f64>,
+    age: String,
+    region_id: Option<i64>,
+    status: String,
+}
+
+impl SpiritExportRow {
+    fn new(row: SpiritRow, strength_unit: StrengthUnit) -> Self {
+        let strength = strength_unit.convert_abv(row.abv);
+        let (abv, proof) = match strength_unit {
+            StrengthUnit::Abv => (Some(strength), None),
+            StrengthUnit::Proof => (None, Some(strength)),
+        };
+        Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            distiller: row.distiller,
+            bottler: row.bottler,
+            typ: row.typ,
+            abv,
+            proof,
+            age: row.age,
+            region_id: row.region_id,
+            status: row.status,
+        }
+    }
+}
+
+/// Exports the full catalog, including spirits still waiting on moderation. Strength is given
+/// as ABV or proof depending on the admin's preferences.
+pub async fn export_spirits(
+    RequirePermission(user, _): RequirePermission<CatalogExport>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<ExportParameter>,
+    headers: HeaderMap,
+) -> WebResult<Response> {
+    let format = ExportFormat::negotiate(query_params.format, &headers);
+    let strength_unit = load_preferences(&state.database, &user.user_id)
+        .await?
+        .strength_unit;
+
+    let body = spirit_export(state.database.clone(), format, strength_unit);
+    Ok(export_response(format, "spirits", body))
+}
+
+/// Streams the full catalog as it's read from the database. Also used by the `export`
+/// command.
+pub fn spirit_export(
+    database: SqlitePool,
+    format: ExportFormat,
+    strength_unit: StrengthUnit,
+) -> Body {
+    let (sink, body) = ExportSink::new(format);
+    tokio::spawn(async move {
+        let rows = sqlx::query_file_as!(SpiritRow, "sql/select_spirit_export.sql")
+            .fetch(&database)
+            .map(|row| row.map(|row| SpiritExportRow::new(row, strength_unit)));
+        sink.forward(rows).await;
+    });
+    body
+}
@@ -0,0 +1,273 @@
+//! Feeds for following the site from other apps. The Atom feed of the catalog is public, so
+//! it only lists approved spirits and links to their share pages. Calendar feeds are per user
+//! and reached through a signed link, since calendar apps can't sign in.
+
+use std::{fmt::Write as _, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    json_web::{sign_calendar_token, sign_slug, verify_calendar_token, User},
+    WaterOfLifeState,
+};
+
+use super::{WebError, WebResult};
+
+const SPIRITS_FEED_PATH: &str = "/feeds/spirits.atom";
+const SPIRITS_FEED_CACHE_KEY: &str = "feed:spirits";
+const SPIRITS_FEED_ENTRIES: i64 = 50;
+/// New approvals show up within this long. Feed readers poll far less often than that.
+const SPIRITS_FEED_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const ATOM_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+/// Used as the feed's `updated` time while there's nothing in it.
+const EMPTY_FEED_UPDATED: &str = "1970-01-01T00:00:00Z";
+const EVENTS_FEED_PATH: &str = "/feeds/events.ics";
+const CALENDAR_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+/// Flights don't say how long a tasting takes, so every event gets this long.
+const TASTING_DURATION: &str = "PT2H";
+/// iCalendar lines longer than this many bytes have to be folded onto continuation lines.
+const MAX_CALENDAR_LINE_BYTES: usize = 75;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarParameter {
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CalendarUrlResponse {
+    url: String,
+}
+
+/// Escapes text for an XML element or attribute, dropping control characters XML can't carry.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(character),
+            character if character.is_control() => {}
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Builds the feed of recently approved spirits, newest first.
+async fn render_spirits_feed(state: &WaterOfLifeState) -> WebResult<String> {
+    let spirits = sqlx::query_file!("sql/select_feed_spirits.sql", SPIRITS_FEED_ENTRIES)
+        .fetch_all(&state.database)
+        .await?;
+
+    let origin = state.config.oidc.public_origin();
+    let feed_url = escape_xml(&format!("{}{}", origin, SPIRITS_FEED_PATH));
+    let updated = spirits
+        .first()
+        .map(|spirit| spirit.approved_at.as_str())
+        .unwrap_or(EMPTY_FEED_UPDATED);
+
+    // Writing to a String can't fail, so the results are ignored.
+    let mut feed = String::new();
+    feed.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(feed, "  <id>{}</id>", feed_url);
+    feed.push_str("  <title>Water of Life: new spirits</title>\n");
+    let _ = writeln!(feed, "  <updated>{}</updated>", updated);
+    let _ = writeln!(
+        feed,
+        "  <link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>",
+        feed_url
+    );
+    let _ = writeln!(feed, "  <link href=\"{}/\"/>", escape_xml(&origin));
+    feed.push_str("  <author><name>Water of Life</name></author>\n");
+    for spirit in spirits {
+        let slug = sign_slug(&state.config.tokens.access_token_hmac_secret, &spirit.id);
+        let link = format!("{}/share/{}", origin, slug);
+        feed.push_str("  <entry>\n");
+        let _ = writeln!(feed, "    <id>urn:uuid:{}</id>", escape_xml(&spirit.id));
+        let _ = writeln!(feed, "    <title>{}</title>", escape_xml(&spirit.name));
+        let _ = writeln!(feed, "    <updated>{}</updated>", spirit.approved_at);
+        let _ = writeln!(feed, "    <link href=\"{}\"/>", escape_xml(&link));
+        let _ = writeln!(
+            feed,
+            "    <summary>{}</summary>",
+            escape_xml(
+                format!("Distilled by {}. {}", spirit.distiller, spirit.description).trim_end()
+            )
+        );
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+    Ok(feed)
+}
+
+/// Recently approved spirits as an Atom feed. The feed is built on request and cached for a
+/// few minutes, since every reader polling it would otherwise rebuild it.
+pub async fn spirits_feed(State(state): State<WaterOfLifeState>) -> WebResult<Response> {
+    let cached = match state.stores.cache.get(SPIRITS_FEED_CACHE_KEY).await {
+        Ok(cached) => cached.and_then(|feed| String::from_utf8(feed).ok()),
+        Err(e) => {
+            tracing::warn!("spirits_feed: {}", e);
+            None
+        }
+    };
+    let feed = match cached {
+        Some(feed) => feed,
+        None => {
+            let feed = render_spirits_feed(&state).await?;
+            if let Err(e) = state
+                .stores
+                .cache
+                .set(
+                    SPIRITS_FEED_CACHE_KEY,
+                    feed.as_bytes(),
+                    SPIRITS_FEED_CACHE_TTL,
+                )
+                .await
+            {
+                tracing::warn!("spirits_feed: {}", e);
+            }
+            feed
+        }
+    };
+
+    Ok((
+        [
+            (CONTENT_TYPE, ATOM_CONTENT_TYPE.to_owned()),
+            (
+                CACHE_CONTROL,
+                format!("public, max-age={}", SPIRITS_FEED_CACHE_TTL.as_secs()),
+            ),
+        ],
+        feed,
+    )
+        .into_response())
+}
+
+/// Escapes text for an iCalendar property value.
+fn escape_calendar_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            character if character.is_control() => {}
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Appends a content line, folding it so no line is longer than iCalendar allows. Folds only
+/// fall between characters, never inside one.
+fn push_calendar_line(calendar: &mut String, line: &str) {
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > MAX_CALENDAR_LINE_BYTES {
+            calendar.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length.
+            length = 1;
+        }
+        calendar.push(character);
+        length += character.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+/// Builds a calendar of the user's scheduled flights, from the last day onwards so a tasting
+/// in progress doesn't drop off.
+async fn render_events_feed(state: &WaterOfLifeState, user_id: &str) -> WebResult<String> {
+    let flights = sqlx::query_file!("sql/select_upcoming_flights.sql", user_id)
+        .fetch_all(&state.database)
+        .await?;
+
+    let origin = state.config.oidc.public_origin();
+    let host = origin
+        .split_once("://")
+        .map_or(origin.as_str(), |(_, host)| host);
+
+    let mut calendar = String::new();
+    push_calendar_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_calendar_line(&mut calendar, "VERSION:2.0");
+    push_calendar_line(&mut calendar, "PRODID:-//Water of Life//Tastings//EN");
+    push_calendar_line(&mut calendar, "CALSCALE:GREGORIAN");
+    push_calendar_line(&mut calendar, "X-WR-CALNAME:Water of Life tastings");
+    for flight in flights {
+        let mut description = flight.description;
+        if let Some(spirits) = flight.spirits {
+            if !description.is_empty() {
+                description.push_str("\n\n");
+            }
+            description.push_str(&format!("Tasting {}.", spirits));
+        }
+
+        push_calendar_line(&mut calendar, "BEGIN:VEVENT");
+        push_calendar_line(&mut calendar, &format!("UID:flight-{}@{}", flight.id, host));
+        push_calendar_line(&mut calendar, &format!("DTSTAMP:{}", flight.updated_at));
+        push_calendar_line(&mut calendar, &format!("DTSTART:{}", flight.starts_at));
+        push_calendar_line(&mut calendar, &format!("DURATION:{}", TASTING_DURATION));
+        push_calendar_line(
+            &mut calendar,
+            &format!("SUMMARY:{}", escape_calendar_text(&flight.title)),
+        );
+        push_calendar_line(
+            &mut calendar,
+            &format!("DESCRIPTION:{}", escape_calendar_text(&description)),
+        );
+        push_calendar_line(&mut calendar, "END:VEVENT");
+    }
+    push_calendar_line(&mut calendar, "END:VCALENDAR");
+    Ok(calendar)
+}
+
+/// The link to subscribe to the caller's calendar of scheduled flights. Anyone with the link
+/// can read the calendar, so it should be treated like a password.
+pub async fn calendar_url(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let token = sign_calendar_token(&state.config.tokens.access_token_hmac_secret, &user.user_id);
+    let response = serde_json::to_string(&CalendarUrlResponse {
+        url: format!(
+            "{}{}?token={}",
+            state.config.oidc.public_origin(),
+            EVENTS_FEED_PATH,
+            token
+        ),
+    })?;
+    Ok(response.into_response())
+}
+
+/// A user's scheduled flights as an iCalendar feed, for subscribing from a calendar app.
+pub async fn events_feed(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<CalendarParameter>,
+) -> WebResult<Response> {
+    let user_id = verify_calendar_token(
+        &state.config.tokens.access_token_hmac_secret,
+        &query_params.token,
+    )
+    .ok_or(WebError::NotFound)?;
+    let calendar = render_events_feed(&state, user_id).await?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, CALENDAR_CONTENT_TYPE.to_owned()),
+            // Per user, so shared caches mustn't keep it.
+            (CACHE_CONTROL, "private, no-cache".to_owned()),
+        ],
+        calendar,
+    )
+        .into_response())
+}
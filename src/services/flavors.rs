@@ -0,0 +1,310 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{api::ensure_spirit_exists, WebError, WebResult};
+
+/// Words recognised in review text and the tag each one counts towards.
+const FLAVOR_DESCRIPTORS: [(&str, &str); 40] = [
+    ("smoke", "smoke"),
+    ("smoky", "smoke"),
+    ("smokey", "smoke"),
+    ("peat", "peat"),
+    ("peaty", "peat"),
+    ("oak", "oak"),
+    ("oaky", "oak"),
+    ("wood", "oak"),
+    ("woody", "oak"),
+    ("vanilla", "vanilla"),
+    ("caramel", "caramel"),
+    ("toffee", "toffee"),
+    ("butterscotch", "toffee"),
+    ("honey", "honey"),
+    ("chocolate", "chocolate"),
+    ("cocoa", "chocolate"),
+    ("coffee", "coffee"),
+    ("cinnamon", "cinnamon"),
+    ("clove", "clove"),
+    ("pepper", "pepper"),
+    ("peppery", "pepper"),
+    ("spice", "spice"),
+    ("spicy", "spice"),
+    ("fruit", "fruit"),
+    ("fruity", "fruit"),
+    ("apple", "apple"),
+    ("pear", "pear"),
+    ("citrus", "citrus"),
+    ("orange", "citrus"),
+    ("lemon", "citrus"),
+    ("cherry", "cherry"),
+    ("raisin", "dried fruit"),
+    ("fig", "dried fruit"),
+    ("sherry", "sherry"),
+    ("nutty", "nut"),
+    ("almond", "nut"),
+    ("floral", "floral"),
+    ("grass", "grass"),
+    ("grassy", "grass"),
+    ("brine", "brine"),
+];
+const MAX_FLAVOR_TAGS: usize = 30;
+/// Clouds are invalidated on review writes, this only bounds staleness if that fails.
+const FLAVOR_CLOUD_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The axes of the flavor wheel, in the order they are returned.
+const FLAVOR_DIMENSIONS: [&str; 8] = [
+    "smoke",
+    "peat",
+    "fruit",
+    "floral",
+    "spice",
+    "sweetness",
+    "oak",
+    "malt",
+];
+const MAX_FLAVOR_SCORE: i64 = 10;
+
+#[derive(Debug, Serialize)]
+struct FlavorTag {
+    tag: &'static str,
+    count: i64,
+    weight: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct FlavorCloud {
+    review_count: i64,
+    tags: Vec<FlavorTag>,
+}
+
+/// A user's scores for any subset of [`FLAVOR_DIMENSIONS`].
+pub type FlavorVotePayload = HashMap<String, i64>;
+
+#[derive(Debug, Serialize)]
+struct FlavorDimension {
+    dimension: &'static str,
+    average: Option<f64>,
+    votes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlavorProfileResponse {
+    voter_count: i64,
+    dimensions: Vec<FlavorDimension>,
+}
+
+fn descriptor_tag(word: &str) -> Option<&'static str> {
+    FLAVOR_DESCRIPTORS
+        .iter()
+        .find(|(descriptor, _)| *descriptor == word)
+        .map(|(_, tag)| *tag)
+}
+
+/// Counts the reviews mentioning each tag. A review counts once per tag so a single
+/// long-winded review can't dominate the cloud.
+fn build_flavor_cloud(bodies: &[String]) -> FlavorCloud {
+    let mut counts: HashMap<&'static str, i64> = HashMap::new();
+    for body in bodies {
+        let body = body.to_lowercase();
+        let tags = body
+            .split(|c: char| !c.is_alphanumeric())
+            .filter_map(|word| descriptor_tag(word.strip_suffix('s').unwrap_or(word)))
+            .collect::<HashSet<_>>();
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    let max_count = counts.values().copied().max().unwrap_or(1) as f64;
+    let mut tags = counts
+        .into_iter()
+        .map(|(tag, count)| FlavorTag {
+            tag,
+            count,
+            weight: count as f64 / max_count,
+        })
+        .collect::<Vec<_>>();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(b.tag)));
+    tags.truncate(MAX_FLAVOR_TAGS);
+
+    FlavorCloud {
+        review_count: bodies.len() as i64,
+        tags,
+    }
+}
+
+fn flavor_cloud_cache_key(spirit_id: &str) -> String {
+    format!("flavor_cloud:{}", spirit_id)
+}
+
+/// Drops the cached tag cloud for a spirit. Call after any review write.
+pub async fn invalidate_flavor_cloud(state: &WaterOfLifeState, spirit_id: &str) {
+    if let Err(e) = state
+        .stores
+        .cache
+        .delete(&flavor_cloud_cache_key(spirit_id))
+        .await
+    {
+        tracing::warn!("invalidate_flavor_cloud: {}", e);
+    }
+}
+
+pub async fn get_flavor_cloud(
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let cache_key = flavor_cloud_cache_key(&spirit_id);
+    match state.stores.cache.get(&cache_key).await {
+        Ok(Some(cached)) => {
+            if let Ok(response) = String::from_utf8(cached) {
+                return Ok(response.into_response());
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("get_flavor_cloud: {}", e),
+    }
+
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+    let bodies = sqlx::query_file!("sql/select_review_bodies.sql", spirit_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| row.body)
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&build_flavor_cloud(&bodies))?;
+    if let Err(e) = state
+        .stores
+        .cache
+        .set(&cache_key, response.as_bytes(), FLAVOR_CLOUD_CACHE_TTL)
+        .await
+    {
+        tracing::warn!("get_flavor_cloud: {}", e);
+    }
+    Ok(response.into_response())
+}
+
+fn validate_flavor_votes(payload: &FlavorVotePayload) -> WebResult<()> {
+    if payload.is_empty() {
+        return Err(WebError::InvalidInput(
+            "At least one flavor score is required.".into(),
+        ));
+    }
+    for (dimension, score) in payload {
+        if !FLAVOR_DIMENSIONS.contains(&dimension.as_str()) {
+            return Err(WebError::InvalidInput(format!(
+                "Unknown flavor '{}'. Expected one of: {}.",
+                dimension,
+                FLAVOR_DIMENSIONS.join(", ")
+            )));
+        }
+        if !(0..=MAX_FLAVOR_SCORE).contains(score) {
+            return Err(WebError::InvalidInput(format!(
+                "Flavor scores must be between 0 and {}.",
+                MAX_FLAVOR_SCORE
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Records the user's scores for the given dimensions, leaving any others untouched.
+pub async fn set_flavor_votes(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<FlavorVotePayload>,
+) -> WebResult<Response> {
+    validate_flavor_votes(&payload)?;
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+
+    let mut transaction = state.repositories.begin().await?;
+    for (dimension, score) in &payload {
+        sqlx::query_file!(
+            "sql/upsert_flavor_vote.sql",
+            user.user_id,
+            spirit_id,
+            dimension,
+            score
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_flavor_votes(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_flavor_votes.sql", user.user_id, spirit_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+/// Averages every user's scores per dimension for rendering as a radar chart. Dimensions
+/// nobody has scored yet are included with a null average so the chart keeps its shape.
+pub async fn load_flavor_profile(
+    database: &SqlitePool,
+    spirit_id: &str,
+) -> WebResult<FlavorProfileResponse> {
+    let averages = sqlx::query_file!("sql/select_flavor_profile.sql", spirit_id)
+        .fetch_all(database)
+        .await?
+        .into_iter()
+        .map(|row| (row.dimension, (row.average, row.votes)))
+        .collect::<HashMap<_, _>>();
+    let voter_count = sqlx::query_file!("sql/select_flavor_voter_count.sql", spirit_id)
+        .fetch_one(database)
+        .await?
+        .count;
+
+    let dimensions = FLAVOR_DIMENSIONS
+        .iter()
+        .map(|dimension| {
+            let (average, votes) = averages
+                .get(*dimension)
+                .map(|(average, votes)| (Some(*average), *votes))
+                .unwrap_or((None, 0));
+            FlavorDimension {
+                dimension,
+                average,
+                votes,
+            }
+        })
+        .collect();
+
+    Ok(FlavorProfileResponse {
+        voter_count,
+        dimensions,
+    })
+}
+
+pub async fn get_flavor_profile(
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+
+    let response = serde_json::to_string(&load_flavor_profile(&state.database, &spirit_id).await?)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,659 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    json_web::AuthContext, permissions::ModerationReview, repositories::SpiritRepository,
+    WaterOfLifeState,
+};
+
+use super::{
+    api::find_visible_spirit,
+    pagination::{Page, PageParameter},
+    validation::is_valid_timestamp,
+    WebError, WebResult,
+};
+
+const MIN_FLIGHT_SPIRITS: usize = 3;
+const MAX_FLIGHT_SPIRITS: usize = 6;
+const MAX_TITLE_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct FlightPayload {
+    title: String,
+    #[serde(default)]
+    description: String,
+    /// Public flights are listed for, and can be cloned by, everyone.
+    #[serde(default)]
+    public: bool,
+    /// In tasting order.
+    spirit_ids: Vec<String>,
+    /// When the flight is being tasted, as a UTC `YYYY-MM-DD HH:MM:SS` timestamp. Scheduled
+    /// flights show up in the owner's calendar feed.
+    #[serde(default)]
+    tasting_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlightParameter {
+    /// List the caller's own flights, public or not, instead of everyone's public flights.
+    #[serde(default)]
+    mine: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TastingScorePayload {
+    /// The sample's position in the flight's tasting order.
+    position: i64,
+    score: i64,
+}
+
+/// A spirit in a flight, as listed in the flight's details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlightSpirit {
+    position: i64,
+    id: String,
+    name: String,
+    distiller: String,
+    abv: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct FlightResponse {
+    id: i64,
+    user_id: String,
+    title: String,
+    description: String,
+    public: bool,
+    cloned_from: Option<i64>,
+    spirits: sqlx::types::Json<Vec<FlightSpirit>>,
+    tasting_at: Option<String>,
+    /// How many samples of the live tasting have been revealed, in tasting order.
+    revealed_count: i64,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FlightSummaryResponse {
+    id: i64,
+    user_id: String,
+    title: String,
+    description: String,
+    public: bool,
+    spirit_count: i64,
+    tasting_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FlightIdResponse {
+    id: i64,
+}
+
+/// Pushed over WebSocket to everyone following a flight's live tasting.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TastingEvent {
+    SampleRevealed {
+        flight_id: i64,
+        sample: FlightSpirit,
+    },
+    ScoreSubmitted {
+        flight_id: i64,
+        position: i64,
+        user_id: String,
+        score: i64,
+    },
+}
+
+impl TastingEvent {
+    fn flight_id(&self) -> i64 {
+        match self {
+            TastingEvent::SampleRevealed { flight_id, .. }
+            | TastingEvent::ScoreSubmitted { flight_id, .. } => *flight_id,
+        }
+    }
+}
+
+/// Checks the title and line-up, making sure the caller can see every spirit in it.
+async fn validate_flight(
+    spirits: &dyn SpiritRepository,
+    context: &AuthContext,
+    payload: &FlightPayload,
+) -> WebResult<()> {
+    let title = payload.title.trim();
+    if title.is_empty() {
+        return Err(WebError::InvalidInput("Flights need a title.".into()));
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Flight titles must be at most {} characters.",
+            MAX_TITLE_LENGTH
+        )));
+    }
+    if !(MIN_FLIGHT_SPIRITS..=MAX_FLIGHT_SPIRITS).contains(&payload.spirit_ids.len()) {
+        return Err(WebError::InvalidInput(format!(
+            "Flights must have between {} and {} spirits.",
+            MIN_FLIGHT_SPIRITS, MAX_FLIGHT_SPIRITS
+        )));
+    }
+    let unique = payload.spirit_ids.iter().collect::<HashSet<_>>();
+    if unique.len() != payload.spirit_ids.len() {
+        return Err(WebError::InvalidInput(
+            "A spirit can only appear once in a flight.".into(),
+        ));
+    }
+    if let Some(tasting_at) = &payload.tasting_at {
+        if !is_valid_timestamp(tasting_at) {
+            return Err(WebError::InvalidInput(format!(
+                "Tasting time '{}' is not formatted as YYYY-MM-DD HH:MM:SS.",
+                tasting_at
+            )));
+        }
+    }
+
+    for spirit_id in &payload.spirit_ids {
+        find_visible_spirit(spirits, context, spirit_id)
+            .await
+            .map_err(|e| match e {
+                WebError::NotFound => {
+                    WebError::InvalidInput(format!("Unknown spirit '{}'.", spirit_id))
+                }
+                e => e,
+            })?;
+    }
+    Ok(())
+}
+
+async fn insert_flight_spirits(
+    connection: &mut SqliteConnection,
+    flight_id: i64,
+    spirit_ids: &[String],
+) -> WebResult<()> {
+    for (position, spirit_id) in (1_i64..).zip(spirit_ids) {
+        sqlx::query_file!(
+            "sql/insert_flight_spirit.sql",
+            flight_id,
+            position,
+            spirit_id
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Returns [`WebError::NotFound`] unless the flight is public, the caller's, or the caller is a
+/// moderator.
+/// Otherwise returns the id of the user who created it.
+async fn ensure_flight_visible(
+    database: &SqlitePool,
+    context: &AuthContext,
+    flight_id: i64,
+) -> WebResult<String> {
+    let flight = sqlx::query_file!("sql/select_flight_owner.sql", flight_id)
+        .fetch_optional(database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let moderates = context.permissions.has::<ModerationReview>();
+    if !flight.public && flight.user_id != context.user.user_id && !moderates {
+        return Err(WebError::NotFound);
+    }
+    Ok(flight.user_id)
+}
+
+/// Returns [`WebError::Forbidden`] unless the caller created the flight or is a moderator.
+async fn ensure_flight_editable(
+    database: &SqlitePool,
+    context: &AuthContext,
+    flight_id: i64,
+) -> WebResult<()> {
+    let owner = ensure_flight_visible(database, context, flight_id).await?;
+    if owner != context.user.user_id && !context.permissions.has::<ModerationReview>() {
+        return Err(WebError::Forbidden);
+    }
+    Ok(())
+}
+
+pub async fn list_flights(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<FlightParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let (limit, offset) = (page.limit(), page.offset());
+    let flights = sqlx::query_file_as!(
+        FlightSummaryResponse,
+        "sql/select_flights.sql",
+        context.user.user_id,
+        query_params.mine,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!(
+        "sql/select_flight_count.sql",
+        context.user.user_id,
+        query_params.mine
+    )
+    .fetch_one(&state.database)
+    .await?
+    .count;
+
+    let response = serde_json::to_string(&Page::new(flights, &page, total))?;
+    Ok(response.into_response())
+}
+
+pub async fn get_flight(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+) -> WebResult<Response> {
+    ensure_flight_visible(&state.database, &context, flight_id).await?;
+
+    let flight = sqlx::query_file_as!(FlightResponse, "sql/select_flight.sql", flight_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let response = serde_json::to_string(&flight)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_flight(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<FlightPayload>,
+) -> WebResult<Response> {
+    validate_flight(&*state.repositories.spirits, &context, &payload).await?;
+
+    let title = payload.title.trim();
+    let cloned_from: Option<i64> = None;
+    let mut transaction = state.repositories.begin().await?;
+    let id = sqlx::query_file!(
+        "sql/insert_flight.sql",
+        context.user.user_id,
+        title,
+        payload.description,
+        payload.public,
+        cloned_from,
+        payload.tasting_at
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    insert_flight_spirits(&mut transaction, id, &payload.spirit_ids).await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&FlightIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+/// Replaces a flight's details and line-up.
+pub async fn edit_flight(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+    Json(payload): Json<FlightPayload>,
+) -> WebResult<Response> {
+    ensure_flight_editable(&state.database, &context, flight_id).await?;
+    validate_flight(&*state.repositories.spirits, &context, &payload).await?;
+
+    let title = payload.title.trim();
+    let mut transaction = state.repositories.begin().await?;
+    sqlx::query_file!(
+        "sql/update_flight.sql",
+        flight_id,
+        title,
+        payload.description,
+        payload.public,
+        payload.tasting_at
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query_file!("sql/delete_flight_spirits.sql", flight_id)
+        .execute(&mut *transaction)
+        .await?;
+    insert_flight_spirits(&mut transaction, flight_id, &payload.spirit_ids).await?;
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_flight(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+) -> WebResult<Response> {
+    ensure_flight_editable(&state.database, &context, flight_id).await?;
+
+    sqlx::query_file!("sql/delete_flight.sql", flight_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+/// Copies a flight into a new private flight owned by the caller, remembering where it came
+/// from.
+pub async fn clone_flight(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+) -> WebResult<Response> {
+    ensure_flight_visible(&state.database, &context, flight_id).await?;
+
+    let mut transaction = state.repositories.begin().await?;
+    let source = sqlx::query_file_as!(FlightResponse, "sql/select_flight.sql", flight_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let spirit_ids = sqlx::query_file!("sql/select_flight_spirit_ids.sql", flight_id)
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|row| row.spirit_id)
+        .collect::<Vec<_>>();
+    // The copy is the caller's own tasting, so it isn't scheduled for the original's time.
+    let public = false;
+    let tasting_at: Option<String> = None;
+    let id = sqlx::query_file!(
+        "sql/insert_flight.sql",
+        context.user.user_id,
+        source.title,
+        source.description,
+        public,
+        flight_id,
+        tasting_at
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    insert_flight_spirits(&mut transaction, id, &spirit_ids).await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&FlightIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+/// Reveals the next sample of the flight's live tasting to everyone following it.
+pub async fn reveal_flight_sample(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+) -> WebResult<Response> {
+    ensure_flight_editable(&state.database, &context, flight_id).await?;
+
+    let mut transaction = state.repositories.begin().await?;
+    let position = sqlx::query_file!("sql/reveal_flight_sample.sql", flight_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            WebError::Conflict("Every sample in this flight has already been revealed.".into())
+        })?
+        .revealed_count;
+    let sample = sqlx::query_file_as!(
+        FlightSpirit,
+        "sql/select_flight_sample.sql",
+        flight_id,
+        position
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    let _ = state.tasting_events.send(TastingEvent::SampleRevealed {
+        flight_id,
+        sample: sample.clone(),
+    });
+
+    let response = serde_json::to_string(&sample)?;
+    Ok(response.into_response())
+}
+
+/// Scores a sample of the flight's live tasting, replacing the caller's earlier score for it.
+/// Samples can be scored before they are revealed, for blind tastings.
+pub async fn score_flight_sample(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+    Json(payload): Json<TastingScorePayload>,
+) -> WebResult<Response> {
+    ensure_flight_visible(&state.database, &context, flight_id).await?;
+    if !(0..=100).contains(&payload.score) {
+        return Err(WebError::InvalidInput(
+            "Scores must be between 0 and 100.".into(),
+        ));
+    }
+    sqlx::query_file_as!(
+        FlightSpirit,
+        "sql/select_flight_sample.sql",
+        flight_id,
+        payload.position
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or_else(|| {
+        WebError::InvalidInput(format!("The flight has no sample {}.", payload.position))
+    })?;
+
+    sqlx::query_file!(
+        "sql/upsert_flight_score.sql",
+        flight_id,
+        payload.position,
+        context.user.user_id,
+        payload.score
+    )
+    .execute(&state.database)
+    .await?;
+
+    let _ = state.tasting_events.send(TastingEvent::ScoreSubmitted {
+        flight_id,
+        position: payload.position,
+        user_id: context.user.user_id,
+        score: payload.score,
+    });
+
+    Ok("".into_response())
+}
+
+/// Upgrades to a WebSocket that sends the flight's live tasting events as JSON text messages.
+/// The access token is checked by the authentication middleware before the upgrade.
+pub async fn tasting_events(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(flight_id): Path<i64>,
+    upgrade: WebSocketUpgrade,
+) -> WebResult<Response> {
+    ensure_flight_visible(&state.database, &context, flight_id).await?;
+
+    let receiver = state.tasting_events.subscribe();
+    Ok(upgrade.on_upgrade(move |socket| send_tasting_events(socket, receiver, flight_id)))
+}
+
+async fn send_tasting_events(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<TastingEvent>,
+    flight_id: i64,
+) {
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) if event.flight_id() == flight_id => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    if socket.send(Message::Text(data)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("tasting_events: skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return,
+            },
+            // Attendees only listen, so anything but a close is ignored.
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        json_web::User,
+        services::APP_USER_ROLE,
+        testing::{self, TestApp},
+    };
+
+    use super::*;
+
+    async fn send(
+        app: &TestApp,
+        user: &User,
+        method: Method,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::COOKIE, testing::auth_cookie(&app.state, user))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.router.clone().oneshot(request).await.unwrap()
+    }
+
+    /// Creates a public flight of three spirits owned by `owner`, returning its id.
+    async fn create_flight(app: &TestApp, owner: &User) -> i64 {
+        let mut spirit_ids = Vec::new();
+        for name in ["Harbor Light", "Mountain Rye", "Old Cask"] {
+            spirit_ids.push(testing::create_spirit(&app.state.database, name).await);
+        }
+        let payload = serde_json::json!({
+            "title": "Tasting night",
+            "public": true,
+            "spirit_ids": spirit_ids,
+        });
+        let response = send(app, owner, Method::POST, "/api/flights", payload).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_i64()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn revealed_samples_are_sent_to_followers_in_order() {
+        let app = testing::app().await;
+        let owner = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let flight_id = create_flight(&app, &owner).await;
+        let mut events = app.state.tasting_events.subscribe();
+        let uri = format!("/api/flights/{}/reveal", flight_id);
+
+        for expected in 1..=3 {
+            let response = send(&app, &owner, Method::POST, &uri, serde_json::json!({})).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            match events.try_recv().unwrap() {
+                TastingEvent::SampleRevealed {
+                    flight_id: id,
+                    sample,
+                } => {
+                    assert_eq!(id, flight_id);
+                    assert_eq!(sample.position, expected);
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        let response = send(&app, &owner, Method::POST, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_or_a_moderator_can_reveal_samples() {
+        let app = testing::app().await;
+        let owner = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let attendee = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let moderator = testing::create_moderator(&app.state.database).await;
+        let flight_id = create_flight(&app, &owner).await;
+
+        let uri = format!("/api/flights/{}/reveal", flight_id);
+        let response = send(&app, &attendee, Method::POST, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&app, &moderator, Method::POST, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn scores_are_checked_and_sent_to_followers() {
+        let app = testing::app().await;
+        let owner = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let attendee = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let flight_id = create_flight(&app, &owner).await;
+        let mut events = app.state.tasting_events.subscribe();
+        let uri = format!("/api/flights/{}/scores", flight_id);
+
+        let unknown = serde_json::json!({ "position": 4, "score": 80 });
+        let response = send(&app, &attendee, Method::POST, &uri, unknown).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let out_of_range = serde_json::json!({ "position": 2, "score": 101 });
+        let response = send(&app, &attendee, Method::POST, &uri, out_of_range).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(events.try_recv().is_err());
+
+        let score = serde_json::json!({ "position": 2, "score": 87 });
+        let response = send(&app, &attendee, Method::POST, &uri, score).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        match events.try_recv().unwrap() {
+            TastingEvent::ScoreSubmitted {
+                position,
+                user_id,
+                score,
+                ..
+            } => {
+                assert_eq!((position, score), (2, 87));
+                assert_eq!(user_id, attendee.user_id);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn following_a_tasting_requires_a_signed_in_user() {
+        let app = testing::app().await;
+        let request = Request::get("/api/flights/1/live")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
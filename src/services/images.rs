@@ -0,0 +1,1024 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Cursor},
+    path::{Path as FsPath, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{multipart::Field, Path, Query, Request, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use image::{
+    codecs::webp::WebPEncoder, imageops::FilterType, DynamicImage, ImageFormat, ImageResult,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use tower_http::services::ServeFile;
+use uuid::Uuid;
+
+use crate::{
+    infra::ImageStore,
+    json_web::{sign_url, AuthContext},
+    permissions::{ImagesDelete, ImagesManage, Permission, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{ensure_spirit_id_format, find_visible_spirit},
+    audit::record_audit,
+    etags::etag_matches,
+    WebError, WebResult,
+};
+
+/// Largest image accepted when `MAX_IMAGE_UPLOAD_BYTES` isn't set.
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 50 * 1024 * 1024;
+/// Thumbnails are scaled to fit within a square of this many pixels.
+const THUMBNAIL_SIZE: u32 = 320;
+const MEDIUM_SIZE: u32 = 1024;
+/// Full size WebP copies of uploads are kept here, alongside the originals.
+const TRANSCODED_DIRECTORY: &str = "webp";
+const DEFAULT_SIGNED_URL_LIFETIME: Duration = Duration::from_secs(60 * 60);
+const MAX_SIGNED_URL_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Uploads whose perceptual hashes differ in at most this many of their 64 bits are treated
+/// as the same photo.
+const DUPLICATE_IMAGE_DISTANCE: u32 = 6;
+
+/// The sizes an image is served in. Everything but `Full` is a WebP scaled down at upload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSize {
+    Thumb,
+    Medium,
+    #[default]
+    Full,
+}
+
+impl ImageSize {
+    const SCALED: [Self; 2] = [Self::Thumb, Self::Medium];
+    const ALL: [Self; 3] = [Self::Full, Self::Thumb, Self::Medium];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Thumb => "thumb",
+            Self::Medium => "medium",
+            Self::Full => "full",
+        }
+    }
+
+    fn max_dimension(self) -> Option<u32> {
+        match self {
+            Self::Thumb => Some(THUMBNAIL_SIZE),
+            Self::Medium => Some(MEDIUM_SIZE),
+            Self::Full => None,
+        }
+    }
+
+    fn directory(self) -> Option<&'static str> {
+        match self {
+            Self::Thumb => Some("thumbnails"),
+            Self::Medium => Some("medium"),
+            Self::Full => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImageSizeParameter {
+    #[serde(default)]
+    pub size: ImageSize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignedImageUrlParameters {
+    #[serde(default)]
+    size: ImageSize,
+    /// How long the link stays valid, in seconds.
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SignedImageUrlResponse {
+    url: String,
+    /// When the link stops working, in seconds since the epoch.
+    expires: u64,
+}
+
+/// The derived variants and metadata for an uploaded image.
+struct ProcessedImage {
+    content_type: &'static str,
+    size_bytes: i64,
+    width: i64,
+    height: i64,
+    sha256: String,
+    phash: String,
+    scaled: Vec<(ImageSize, Vec<u8>)>,
+    transcoded: Vec<u8>,
+}
+
+/// A newly stored image, and the existing image of the same spirit it looks like, if any.
+pub struct StoredImage {
+    pub id: String,
+    pub duplicate_of: Option<String>,
+}
+
+/// An image as listed in a spirit's details.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiritImageSummary {
+    id: String,
+    url: String,
+    caption: String,
+    primary: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpiritImageResponse {
+    id: String,
+    url: String,
+    caption: String,
+    position: i64,
+    primary: bool,
+    content_type: String,
+    width: i64,
+    height: i64,
+    created_at: String,
+    /// Set in an upload response when the new image is nearly identical to one the spirit
+    /// already had.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpiritImagePayload {
+    caption: Option<String>,
+    position: Option<i64>,
+    primary: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct BackfillResponse {
+    scanned: i64,
+    processed: i64,
+    skipped: i64,
+    failed: i64,
+}
+
+pub fn encode_webp(image: &DynamicImage) -> ImageResult<Vec<u8>> {
+    let mut buffer = Vec::new();
+    DynamicImage::ImageRgba8(image.to_rgba8())
+        .write_with_encoder(WebPEncoder::new_lossless(Cursor::new(&mut buffer)))?;
+    Ok(buffer)
+}
+
+/// Identifies an upload from its leading bytes rather than trusting its name or declared
+/// type. Only JPEG, PNG and WebP are accepted.
+pub fn sniff_image_format(data: &[u8]) -> WebResult<ImageFormat> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Ok(ImageFormat::Jpeg)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok(ImageFormat::Png)
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Ok(ImageFormat::WebP)
+    } else {
+        Err(WebError::UnsupportedMediaType(
+            "Only JPEG, PNG and WebP images are accepted.".into(),
+        ))
+    }
+}
+
+/// A 64-bit difference hash: the image is shrunk to 9x8 greyscale pixels and each bit records
+/// whether a pixel is brighter than its right-hand neighbour. Re-encoded, resized or slightly
+/// recoloured copies of a photo end up only a few bits apart.
+fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let pixels = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = pixels.get_pixel(x, y)[0] > pixels.get_pixel(x + 1, y)[0];
+            hash = hash << 1 | u64::from(brighter);
+        }
+    }
+    hash
+}
+
+fn process_image(data: &[u8], format: ImageFormat) -> ImageResult<ProcessedImage> {
+    let image = image::load_from_memory_with_format(data, format)?;
+    let sha256 = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Ok(ProcessedImage {
+        content_type: format.to_mime_type(),
+        size_bytes: data.len() as i64,
+        width: image.width() as i64,
+        height: image.height() as i64,
+        sha256,
+        phash: format!("{:016x}", perceptual_hash(&image)),
+        transcoded: encode_webp(&image)?,
+        scaled: ImageSize::SCALED
+            .into_iter()
+            .filter_map(|size| Some((size, size.max_dimension()?)))
+            .map(|(size, dimension)| {
+                // Images already small enough are re-encoded rather than scaled up.
+                let scaled = if image.width() <= dimension && image.height() <= dimension {
+                    encode_webp(&image)?
+                } else {
+                    encode_webp(&image.thumbnail(dimension, dimension))?
+                };
+                Ok((size, scaled))
+            })
+            .collect::<ImageResult<_>>()?,
+    })
+}
+
+/// Reads and decodes an image off the async runtime since large photos take a while.
+async fn process_image_file(path: PathBuf, format: ImageFormat) -> WebResult<ProcessedImage> {
+    tokio::task::spawn_blocking(move || {
+        let data = std::fs::read(&path)?;
+        process_image(&data, format)
+            .map_err(|e| WebError::InvalidInput(format!("Unsupported image: {}", e)))
+    })
+    .await
+    .expect("image processing task panicked")
+}
+
+/// The first bytes of a file, enough to tell which image format it holds.
+pub async fn read_image_header(path: &FsPath) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(32);
+    fs::File::open(path)
+        .await?
+        .take(32)
+        .read_to_end(&mut header)
+        .await?;
+    Ok(header)
+}
+
+/// Streams a multipart file field to `path` without holding the whole image in memory. The
+/// file is synced before returning so a later rename can't expose a partly written image, and
+/// removed again if the upload fails or runs over `max_bytes`.
+pub async fn receive_image_field(
+    field: &mut Field<'_>,
+    path: &FsPath,
+    max_bytes: usize,
+) -> WebResult<()> {
+    let result = async {
+        let mut file = fs::File::create(path).await?;
+        let mut received = 0;
+        while let Some(chunk) = field.chunk().await? {
+            received += chunk.len();
+            if received > max_bytes {
+                return Err(WebError::PayloadTooLarge(format!(
+                    "Images may be at most {} bytes.",
+                    max_bytes
+                )));
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.sync_all().await?;
+        Ok(())
+    }
+    .await;
+
+    if result.is_err() {
+        remove_file_if_exists(path).await;
+    }
+    result
+}
+
+/// Removes a file, logging rather than failing since it only leaves clutter behind.
+pub async fn remove_file_if_exists(path: &FsPath) {
+    if let Err(e) = fs::remove_file(path).await {
+        if e.kind() != io::ErrorKind::NotFound {
+            tracing::warn!("Could not remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// The image store key of an image at the given size.
+fn image_key(image_id: &str, size: ImageSize) -> String {
+    match size.directory() {
+        Some(directory) => format!("{}/{}.webp", directory, image_id),
+        None => image_id.to_owned(),
+    }
+}
+
+/// The image store key of the full size WebP copy of an image.
+fn transcoded_key(image_id: &str) -> String {
+    format!("{}/{}.webp", TRANSCODED_DIRECTORY, image_id)
+}
+
+/// Every file stored for an image: the original, its scaled variants and its WebP copy.
+fn image_keys(image_id: &str) -> Vec<String> {
+    ImageSize::ALL
+        .into_iter()
+        .map(|size| image_key(image_id, size))
+        .chain([transcoded_key(image_id)])
+        .collect()
+}
+
+fn image_url(spirit_id: &str, image_id: &str) -> String {
+    format!("/api/spirit/{}/images/{}", spirit_id, image_id)
+}
+
+/// Writes the derived variants and records the metadata for one of a spirit's images. The
+/// first image a spirit gets becomes its primary image.
+async fn save_image_variants(
+    state: &WaterOfLifeState,
+    image_id: &str,
+    spirit_id: &str,
+    uploaded_by: Option<&str>,
+    caption: &str,
+    processed: &ProcessedImage,
+) -> WebResult<()> {
+    let scaled = processed
+        .scaled
+        .iter()
+        .map(|(size, data)| (image_key(image_id, *size), data));
+    let transcoded = (transcoded_key(image_id), &processed.transcoded);
+    for (key, data) in scaled.chain([transcoded]) {
+        state.images.put(&key, data).await?;
+    }
+
+    sqlx::query_file!(
+        "sql/upsert_spirit_image.sql",
+        image_id,
+        spirit_id,
+        uploaded_by,
+        caption,
+        processed.content_type,
+        processed.size_bytes,
+        processed.width,
+        processed.height,
+        processed.sha256,
+        processed.phash
+    )
+    .execute(&state.database)
+    .await?;
+    Ok(())
+}
+
+/// The spirit's existing image that looks most like one with the given perceptual hash, if any
+/// is close enough to be the same photo.
+async fn find_duplicate_image(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    phash: &str,
+) -> WebResult<Option<String>> {
+    let Ok(phash) = u64::from_str_radix(phash, 16) else {
+        return Ok(None);
+    };
+    let duplicate = sqlx::query_file!("sql/select_spirit_image_hashes.sql", spirit_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            let distance = (u64::from_str_radix(&row.phash, 16).ok()? ^ phash).count_ones();
+            (distance <= DUPLICATE_IMAGE_DISTANCE).then_some((distance, row.id))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, id)| id);
+    Ok(duplicate)
+}
+
+/// Runs a received upload at `received_path` through the pipeline and adds it to the spirit's
+/// images. The upload is moved into place with a rename, or deleted if it is rejected. A photo
+/// the spirit already has is still stored, but flagged with the image it duplicates.
+pub async fn store_spirit_image(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    uploaded_by: &str,
+    caption: &str,
+    received_path: &FsPath,
+) -> WebResult<StoredImage> {
+    let image_id = Uuid::new_v4().to_string();
+
+    let result = async {
+        if fs::metadata(received_path).await?.len()
+            > state.config.limits.max_image_upload_bytes as u64
+        {
+            return Err(WebError::PayloadTooLarge(format!(
+                "Images may be at most {} bytes.",
+                state.config.limits.max_image_upload_bytes
+            )));
+        }
+        let format = sniff_image_format(&read_image_header(received_path).await?)?;
+        let processed = process_image_file(received_path.to_owned(), format).await?;
+        let duplicate_of = find_duplicate_image(state, spirit_id, &processed.phash).await?;
+
+        state
+            .images
+            .put_file(&image_key(&image_id, ImageSize::Full), received_path)
+            .await?;
+        save_image_variants(
+            state,
+            &image_id,
+            spirit_id,
+            Some(uploaded_by),
+            caption,
+            &processed,
+        )
+        .await?;
+        Ok(duplicate_of)
+    }
+    .await;
+
+    match result {
+        Ok(duplicate_of) => Ok(StoredImage {
+            id: image_id,
+            duplicate_of,
+        }),
+        Err(e) => {
+            remove_file_if_exists(received_path).await;
+            for key in image_keys(&image_id) {
+                state.images.discard(&key).await;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Lists a spirit's images in display order.
+pub async fn load_spirit_images(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+) -> WebResult<Vec<SpiritImageResponse>> {
+    let images = sqlx::query_file!("sql/select_spirit_images.sql", spirit_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| SpiritImageResponse {
+            url: image_url(spirit_id, &row.id),
+            id: row.id,
+            caption: row.caption,
+            position: row.position,
+            primary: row.is_primary,
+            content_type: row.content_type,
+            width: row.width,
+            height: row.height,
+            created_at: row.created_at,
+            duplicate_of: None,
+        })
+        .collect();
+    Ok(images)
+}
+
+/// Lists a spirit's images after an upload, flagging each newly stored image that duplicates
+/// one the spirit already had.
+pub async fn load_uploaded_spirit_images(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    stored: Vec<StoredImage>,
+) -> WebResult<Vec<SpiritImageResponse>> {
+    let mut duplicates = stored
+        .into_iter()
+        .filter_map(|image| Some((image.id, image.duplicate_of?)))
+        .collect::<HashMap<_, _>>();
+    let mut images = load_spirit_images(state, spirit_id).await?;
+    for image in &mut images {
+        image.duplicate_of = duplicates.remove(&image.id);
+    }
+    Ok(images)
+}
+
+/// Reads the image type from the first bytes of a file stored before uploads were recorded.
+async fn sniff_content_type(path: &FsPath) -> WebResult<&'static str> {
+    let header = read_image_header(path).await?;
+    Ok(image::guess_format(&header)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("application/octet-stream"))
+}
+
+/// Streams an image from disk. `Content-Length`, `Last-Modified` and `If-Modified-Since` are
+/// handled by [`ServeFile`]; the ETag is the image's SHA-256 so it stays the same across
+/// servers and restarts.
+pub async fn serve_image_file(
+    path: &FsPath,
+    content_type: &str,
+    etag: Option<String>,
+    request: Request,
+) -> WebResult<Response> {
+    match fs::metadata(path).await {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) => return Err(WebError::NotFound),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(WebError::NotFound),
+        Err(e) => return Err(e.into()),
+    }
+
+    if let (Some(etag), Some(if_none_match)) = (&etag, request.headers().get(IF_NONE_MATCH)) {
+        if etag_matches(if_none_match, etag) {
+            return Ok((StatusCode::NOT_MODIFIED, [(ETAG, etag.clone())]).into_response());
+        }
+    }
+
+    let mut response = ServeFile::new(path).try_call(request).await?.map(Body::new);
+    let headers = response.headers_mut();
+    if let Ok(content_type) = HeaderValue::from_str(content_type) {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+        headers.insert(ETAG, etag);
+    }
+    Ok(response)
+}
+
+/// Whether the client lists WebP in its `Accept` header. Wildcards aren't enough since plenty
+/// of clients send `*/*` without being able to decode WebP.
+fn accepts_webp(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|range| {
+            let mut parameters = range.split(';').map(str::trim);
+            let is_webp = parameters
+                .next()
+                .is_some_and(|media_type| media_type.eq_ignore_ascii_case("image/webp"));
+            let refused = parameters.any(|parameter| {
+                parameter
+                    .strip_prefix("q=")
+                    .and_then(|quality| quality.parse::<f32>().ok())
+                    .is_some_and(|quality| quality <= 0.0)
+            });
+            is_webp && !refused
+        })
+}
+
+/// Picks the WebP file to send for a recorded image, if the client accepts WebP and one is
+/// worth sending. The full size copy is only used when it is smaller than the original.
+async fn negotiated_webp(
+    state: &WaterOfLifeState,
+    image_id: &str,
+    size: ImageSize,
+) -> WebResult<Option<String>> {
+    if size != ImageSize::Full {
+        let key = image_key(image_id, size);
+        return Ok(state.images.exists(&key).await?.then_some(key));
+    }
+
+    let key = transcoded_key(image_id);
+    let original = image_key(image_id, ImageSize::Full);
+    let smaller = match (
+        state.images.size(&key).await?,
+        state.images.size(&original).await?,
+    ) {
+        (Some(transcoded), Some(original)) => transcoded < original,
+        _ => false,
+    };
+    Ok(smaller.then_some(key))
+}
+
+/// Streams a recorded image at the requested size, as WebP when the client accepts it. Clients
+/// without WebP support, and images whose variants haven't been generated yet, get the
+/// original at full size.
+async fn serve_recorded_image(
+    state: &WaterOfLifeState,
+    image_id: &str,
+    content_type: &str,
+    sha256: &str,
+    size: ImageSize,
+    request: Request,
+) -> WebResult<Response> {
+    let webp = if accepts_webp(request.headers()) {
+        negotiated_webp(state, image_id, size).await?
+    } else {
+        None
+    };
+
+    let mut response = match webp {
+        Some(key) => {
+            let variant = match size {
+                ImageSize::Full => "webp",
+                size => size.as_str(),
+            };
+            let etag = format!("\"{}-{}\"", sha256, variant);
+            let path = state.images.path(&key);
+            serve_image_file(&path, "image/webp", Some(etag), request).await?
+        }
+        None => {
+            let path = state.images.path(&image_key(image_id, ImageSize::Full));
+            let etag = format!("\"{}\"", sha256);
+            serve_image_file(&path, content_type, Some(etag), request).await?
+        }
+    };
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("Accept"));
+    Ok(response)
+}
+
+/// Streams a spirit's primary image, falling back to an image stored under the spirit's id
+/// before uploads were recorded.
+pub async fn serve_primary_spirit_image(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    size: ImageSize,
+    request: Request,
+) -> WebResult<Response> {
+    ensure_spirit_id_format(spirit_id)?;
+    let primary = sqlx::query_file!("sql/select_primary_spirit_image.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?;
+    match primary {
+        Some(primary) => {
+            serve_recorded_image(
+                state,
+                &primary.id,
+                &primary.content_type,
+                &primary.sha256,
+                size,
+                request,
+            )
+            .await
+        }
+        None => {
+            if !state.images.exists(spirit_id).await? {
+                return Err(WebError::NotFound);
+            }
+            let path = state.images.path(spirit_id);
+            let content_type = sniff_content_type(&path).await?;
+            serve_image_file(&path, content_type, None, request).await
+        }
+    }
+}
+
+/// Whether a spirit has a primary image, recorded or stored before uploads were recorded.
+pub async fn has_primary_image(state: &WaterOfLifeState, spirit_id: &str) -> WebResult<bool> {
+    ensure_spirit_id_format(spirit_id)?;
+    let has_primary = sqlx::query_file!("sql/select_primary_spirit_image.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .is_some();
+    Ok(has_primary || state.images.exists(spirit_id).await?)
+}
+
+/// A link to a spirit's primary image that works without cookies until it expires. Returns
+/// the link and its expiry in seconds since the epoch.
+pub fn signed_primary_image_url(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    size: ImageSize,
+    lifetime: Duration,
+) -> (String, u64) {
+    let url = match size {
+        ImageSize::Full => format!("/api/spirit/{}/image", spirit_id),
+        size => format!("/api/spirit/{}/image?size={}", spirit_id, size.as_str()),
+    };
+    sign_url(
+        &state.config.tokens.access_token_hmac_secret,
+        &url,
+        lifetime,
+    )
+}
+
+/// Hands out a signed, expiring link to a spirit's primary image that works without cookies,
+/// for use behind a CDN or object store.
+pub async fn get_spirit_image_url(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Query(query_params): Query<SignedImageUrlParameters>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    if !has_primary_image(&state, &spirit_id).await? {
+        return Err(WebError::NotFound);
+    }
+
+    let lifetime = match query_params.expires_in {
+        Some(0) => {
+            return Err(WebError::InvalidInput(
+                "Links must last at least a second.".into(),
+            ))
+        }
+        Some(seconds) => Duration::from_secs(seconds).min(MAX_SIGNED_URL_LIFETIME),
+        None => DEFAULT_SIGNED_URL_LIFETIME,
+    };
+    let (url, expires) = signed_primary_image_url(&state, &spirit_id, query_params.size, lifetime);
+
+    let response = serde_json::to_string(&SignedImageUrlResponse { url, expires })?;
+    Ok(response.into_response())
+}
+
+pub async fn get_spirit_image_by_id(
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, image_id)): Path<(String, String)>,
+    Query(query_params): Query<ImageSizeParameter>,
+    request: Request,
+) -> WebResult<Response> {
+    let image = sqlx::query_file!("sql/select_spirit_image.sql", image_id, spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    serve_recorded_image(
+        &state,
+        &image_id,
+        &image.content_type,
+        &image.sha256,
+        query_params.size,
+        request,
+    )
+    .await
+}
+
+pub async fn list_spirit_images(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let response = serde_json::to_string(&load_spirit_images(&state, &spirit_id).await?)?;
+    Ok(response.into_response())
+}
+
+/// Only the uploader, or a user whose role has the permission `P`, may change or remove an image.
+async fn ensure_image_permitted<P: Permission>(
+    state: &WaterOfLifeState,
+    context: &AuthContext,
+    spirit_id: &str,
+    image_id: &str,
+) -> WebResult<()> {
+    let uploaded_by = sqlx::query_file!("sql/select_spirit_image_owner.sql", image_id, spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .uploaded_by;
+    let is_uploader = uploaded_by.as_deref() == Some(context.user.user_id.as_str());
+    if !is_uploader && !context.permissions.has::<P>() {
+        return Err(WebError::Forbidden);
+    }
+    Ok(())
+}
+
+/// Updates an image's caption or position, or makes it the spirit's primary image.
+pub async fn edit_spirit_image(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, image_id)): Path<(String, String)>,
+    Json(payload): Json<SpiritImagePayload>,
+) -> WebResult<Response> {
+    ensure_image_permitted::<ImagesManage>(&state, &context, &spirit_id, &image_id).await?;
+    if payload.primary == Some(false) {
+        return Err(WebError::InvalidInput(
+            "Make another image primary instead.".into(),
+        ));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    sqlx::query_file!(
+        "sql/update_spirit_image.sql",
+        image_id,
+        spirit_id,
+        payload.caption,
+        payload.position
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if payload.primary == Some(true) {
+        // Cleared first since only one image per spirit may be primary at any point.
+        sqlx::query_file!("sql/clear_primary_spirit_image.sql", spirit_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query_file!("sql/update_primary_spirit_image.sql", image_id, spirit_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&load_spirit_images(&state, &spirit_id).await?)?;
+    Ok(response.into_response())
+}
+
+/// Deletes an image's record, promoting another image if it was the primary one. Its files
+/// stay on disk until [`remove_image_files`] is called once the change is committed.
+pub async fn delete_spirit_image_record(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    image_id: &str,
+) -> WebResult<()> {
+    sqlx::query_file!("sql/delete_spirit_image.sql", image_id, spirit_id)
+        .execute(&mut *connection)
+        .await?;
+    sqlx::query_file!("sql/promote_primary_spirit_image.sql", spirit_id)
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
+
+pub async fn remove_image_files(state: &WaterOfLifeState, image_id: &str) {
+    for key in image_keys(image_id) {
+        state.images.discard(&key).await;
+    }
+}
+
+async fn remove_spirit_image(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    image_id: &str,
+) -> WebResult<()> {
+    let mut transaction = state.repositories.begin().await?;
+    delete_spirit_image_record(&mut transaction, spirit_id, image_id).await?;
+    transaction.commit().await?;
+
+    remove_image_files(state, image_id).await;
+    Ok(())
+}
+
+/// Deletes an image's record and files. When it was the primary image the next one in order
+/// takes its place.
+pub async fn delete_spirit_image(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, image_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    ensure_image_permitted::<ImagesDelete>(&state, &context, &spirit_id, &image_id).await?;
+    remove_spirit_image(&state, &spirit_id, &image_id).await?;
+    Ok("".into_response())
+}
+
+/// Deletes a spirit's primary image. An image stored before uploads were recorded has no
+/// known owner, so only a user allowed to delete every image may remove it.
+pub async fn delete_primary_spirit_image(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    ensure_spirit_id_format(&spirit_id)?;
+    let primary = sqlx::query_file!("sql/select_primary_spirit_image.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?;
+    if let Some(primary) = primary {
+        ensure_image_permitted::<ImagesDelete>(&state, &context, &spirit_id, &primary.id).await?;
+        remove_spirit_image(&state, &spirit_id, &primary.id).await?;
+        return Ok("".into_response());
+    }
+
+    let key = image_key(&spirit_id, ImageSize::Full);
+    if !state.images.exists(&key).await? {
+        return Err(WebError::NotFound);
+    }
+    if !context.permissions.has::<ImagesDelete>() {
+        return Err(WebError::Forbidden);
+    }
+    state.images.remove(&key).await?;
+    Ok("".into_response())
+}
+
+/// Generates scaled variants and metadata for images that were stored before the pipeline
+/// existed or before a size, the WebP copy or the perceptual hash was added. Images that already
+/// have everything are left alone, so the task is safe to re-run.
+pub async fn backfill_images(
+    RequirePermission(_, _): RequirePermission<ImagesManage>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let spirit_ids = sqlx::query_file!("sql/select_spirit_ids.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| row.uuid)
+        .collect::<HashSet<_>>();
+    let recorded = sqlx::query_file!("sql/select_spirit_image_ids.sql")
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| (row.id, (row.spirit_id, row.has_phash)))
+        .collect::<HashMap<_, _>>();
+
+    let mut summary = BackfillResponse::default();
+    for file in state.images.list(None).await? {
+        summary.scanned += 1;
+
+        // Images stored before uploads were recorded are named after their spirit.
+        let image_id = file.key;
+        let mut has_variants = state.images.exists(&transcoded_key(&image_id)).await?;
+        for size in ImageSize::SCALED {
+            has_variants &= state.images.exists(&image_key(&image_id, size)).await?;
+        }
+        let spirit_id = match recorded.get(&image_id) {
+            Some((_, has_phash)) if has_variants && *has_phash => None,
+            Some((spirit_id, _)) => Some(spirit_id.clone()),
+            None => spirit_ids.contains(&image_id).then(|| image_id.clone()),
+        };
+        let Some(spirit_id) = spirit_id else {
+            summary.skipped += 1;
+            continue;
+        };
+
+        let path = state.images.path(&image_id);
+        let header = read_image_header(&path).await?;
+        let format = match image::guess_format(&header) {
+            Ok(format) => format,
+            Err(e) => {
+                tracing::warn!("backfill_images: could not process {}: {}", image_id, e);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        match process_image_file(path, format).await {
+            Ok(processed) => {
+                save_image_variants(&state, &image_id, &spirit_id, None, "", &processed).await?;
+                summary.processed += 1;
+            }
+            Err(e) => {
+                tracing::warn!("backfill_images: could not process {}: {}", image_id, e);
+                summary.failed += 1;
+            }
+        }
+    }
+    tracing::info!("backfill_images: {:?}", summary);
+
+    let response = serde_json::to_string(&summary)?;
+    Ok(response.into_response())
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OrphanSweep {
+    pub removed_files: Vec<String>,
+    pub removed_records: Vec<String>,
+}
+
+/// Files younger than this are left alone by the sweep since an upload may be between writing
+/// its file and recording it.
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Removes image files that no recorded image or spirit accounts for, and image records whose
+/// file has gone missing.
+pub async fn sweep_orphaned_images(
+    database: &SqlitePool,
+    images: &ImageStore,
+) -> WebResult<OrphanSweep> {
+    let spirit_ids = sqlx::query_file!("sql/select_spirit_ids.sql")
+        .fetch_all(database)
+        .await?
+        .into_iter()
+        .map(|row| row.uuid)
+        .collect::<HashSet<_>>();
+    let recorded = sqlx::query_file!("sql/select_spirit_image_ids.sql")
+        .fetch_all(database)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<HashSet<_>>();
+
+    let mut sweep = OrphanSweep::default();
+    let directories = ImageSize::ALL
+        .into_iter()
+        .filter_map(ImageSize::directory)
+        .chain([TRANSCODED_DIRECTORY])
+        .map(Some)
+        .chain([None]);
+    for directory in directories {
+        let holds_originals = directory.is_none();
+        for file in images.list(directory).await? {
+            let is_recent = file
+                .modified
+                .and_then(|modified| modified.elapsed().ok())
+                .is_none_or(|age| age < ORPHAN_GRACE_PERIOD);
+            if is_recent {
+                continue;
+            }
+
+            // Images stored before uploads were recorded are named after their spirit.
+            let is_legacy = holds_originals && spirit_ids.contains(&file.stem);
+            if !recorded.contains(&file.stem) && !is_legacy {
+                images.remove(&file.key).await?;
+                sweep.removed_files.push(file.key);
+            }
+        }
+    }
+
+    for image_id in &recorded {
+        if images.exists(&image_key(image_id, ImageSize::Full)).await? {
+            continue;
+        }
+        let mut transaction = database.begin().await?;
+        let spirit_id = sqlx::query_file!("sql/delete_missing_spirit_image.sql", image_id)
+            .fetch_one(&mut *transaction)
+            .await?
+            .spirit_id;
+        sqlx::query_file!("sql/promote_primary_spirit_image.sql", spirit_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        for key in image_keys(image_id) {
+            images.discard(&key).await;
+        }
+        sweep.removed_records.push(image_id.clone());
+    }
+
+    record_audit(database, None, None, "image_orphan_sweep", &sweep).await?;
+    Ok(sweep)
+}
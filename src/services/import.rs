@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Multipart, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    json_web::AuthContext,
+    permissions::{CatalogImport, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{insert_spirit, SpiritPayload, FORM_FILE_KEY},
+    WebError, WebResult,
+};
+
+/// Optional form field holding a JSON [`ColumnMapping`].
+const MAPPING_FORM_KEY: &str = "mapping";
+
+/// The CSV column header each spirit field is read from. Any field left out of an uploaded
+/// mapping keeps its default header.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ColumnMapping {
+    name: String,
+    distiller: String,
+    description: String,
+    typ: String,
+    region_id: String,
+    abv: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            name: "name".into(),
+            distiller: "distiller".into(),
+            description: "description".into(),
+            typ: "type".into(),
+            region_id: "region_id".into(),
+            abv: "abv".into(),
+        }
+    }
+}
+
+/// Column positions resolved from the header row. Description and region are optional.
+struct Columns {
+    name: usize,
+    distiller: usize,
+    description: Option<usize>,
+    typ: usize,
+    region_id: Option<usize>,
+    abv: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatedRow {
+    row: u64,
+    id: String,
+    status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct RowIssue {
+    row: u64,
+    message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ImportReport {
+    created: Vec<CreatedRow>,
+    skipped: Vec<RowIssue>,
+    errors: Vec<RowIssue>,
+}
+
+impl Columns {
+    fn resolve(headers: &csv::StringRecord, mapping: &ColumnMapping) -> WebResult<Self> {
+        let positions = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| (header.trim().to_lowercase(), i))
+            .collect::<HashMap<_, _>>();
+        let find = |header: &str| positions.get(&header.trim().to_lowercase()).copied();
+        let require = |header: &str| {
+            find(header).ok_or_else(|| {
+                WebError::InvalidInput(format!("The CSV has no '{}' column.", header))
+            })
+        };
+
+        Ok(Self {
+            name: require(&mapping.name)?,
+            distiller: require(&mapping.distiller)?,
+            description: find(&mapping.description),
+            typ: require(&mapping.typ)?,
+            region_id: find(&mapping.region_id),
+            abv: require(&mapping.abv)?,
+        })
+    }
+
+    fn parse(&self, record: &csv::StringRecord) -> Result<SpiritPayload, String> {
+        let field = |i: usize| record.get(i).unwrap_or_default().trim();
+        let optional_field = |i: Option<usize>| i.map(field).unwrap_or_default();
+
+        let name = field(self.name);
+        if name.is_empty() {
+            return Err("Name is required.".into());
+        }
+        let abv = field(self.abv)
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .ok()
+            .filter(|abv| abv.is_finite())
+            .ok_or_else(|| format!("ABV '{}' is not a number.", field(self.abv)))?;
+        let region_id = match optional_field(self.region_id) {
+            "" => None,
+            region_id => Some(
+                region_id
+                    .parse::<i64>()
+                    .map_err(|_| format!("Region '{}' is not a region id.", region_id))?,
+            ),
+        };
+
+        Ok(SpiritPayload {
+            name: name.to_owned(),
+            distiller: field(self.distiller).to_owned(),
+            description: optional_field(self.description).to_owned(),
+            typ: field(self.typ).to_owned(),
+            region_id,
+            abv,
+            version: None,
+        })
+    }
+}
+
+/// Creates spirits from an uploaded CSV. Rows that fail validation or duplicate an existing
+/// spirit are reported rather than failing the whole import.
+pub async fn import_spirits(
+    RequirePermission(user, _): RequirePermission<CatalogImport>,
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    mut multipart: Multipart,
+) -> WebResult<Response> {
+    let mut data = None;
+    let mut mapping = ColumnMapping::default();
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = if let Some(name) = field.name() {
+            name.to_owned()
+        } else {
+            continue;
+        };
+
+        if name == FORM_FILE_KEY {
+            let mut buffer = Vec::new();
+            while let Some(chunk) = field.chunk().await? {
+                buffer.extend_from_slice(&chunk);
+            }
+            tracing::debug!("import_spirits: Received {} bytes", buffer.len());
+            data = Some(buffer);
+        } else if name == MAPPING_FORM_KEY {
+            mapping = serde_json::from_str(&field.text().await?)
+                .map_err(|e| WebError::InvalidInput(format!("Invalid column mapping: {}", e)))?;
+        }
+    }
+    let data = data.ok_or_else(|| {
+        WebError::InvalidInput(format!("Expected a CSV in the '{}' field.", FORM_FILE_KEY))
+    })?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_slice());
+    let headers = reader
+        .headers()
+        .map_err(|e| WebError::InvalidInput(format!("Could not read the CSV header: {}", e)))?;
+    let columns = Columns::resolve(headers, &mapping)?;
+
+    let mut report = ImportReport::default();
+    let mut seen = HashMap::new();
+    let mut transaction = state.repositories.begin().await?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let row = e.position().map(|p| p.line()).unwrap_or_default();
+                report.errors.push(RowIssue {
+                    row,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let row = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let payload = match columns.parse(&record) {
+            Ok(payload) => payload,
+            Err(message) => {
+                report.errors.push(RowIssue { row, message });
+                continue;
+            }
+        };
+
+        let key = (
+            payload.name.to_lowercase(),
+            payload.distiller.to_lowercase(),
+        );
+        if let Some(first_row) = seen.get(&key) {
+            report.skipped.push(RowIssue {
+                row,
+                message: format!("Duplicate of row {}.", first_row),
+            });
+            continue;
+        }
+        seen.insert(key, row);
+        let existing = sqlx::query_file!(
+            "sql/select_spirit_by_name.sql",
+            payload.name,
+            payload.distiller
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+        if let Some(existing) = existing {
+            report.skipped.push(RowIssue {
+                row,
+                message: format!("Already exists as spirit {}.", existing.id),
+            });
+            continue;
+        }
+
+        match insert_spirit(&mut transaction, &payload, &user, &context.permissions).await {
+            Ok(spirit) => report.created.push(CreatedRow {
+                row,
+                id: spirit.id,
+                status: spirit.status,
+            }),
+            Err(WebError::InvalidInput(message)) => report.errors.push(RowIssue { row, message }),
+            Err(e) => return Err(e),
+        }
+    }
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&report)?;
+    Ok(response.into_response())
+}
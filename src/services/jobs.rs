@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+use thiserror::Error;
+use tokio::task::JoinSet;
+
+use crate::{
+    permissions::{JobsManage, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    badges::check_badges,
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+/// How long an idle worker waits before looking for new jobs.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Jobs that still fail after this many tries are marked dead and left for an admin to retry.
+const MAX_ATTEMPTS: i64 = 5;
+/// Wait before the first retry, doubled after each failed attempt.
+const RETRY_BACKOFF_SECS: i64 = 30;
+
+/// Work that can wait until after the response has been sent.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    /// Awards any badges the user has newly earned.
+    AwardBadges { user_id: String },
+}
+
+impl Job {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::AwardBadges { .. } => "award_badges",
+        }
+    }
+
+    async fn run(self, state: &WaterOfLifeState) -> Result<(), JobError> {
+        match self {
+            Self::AwardBadges { user_id } => check_badges(&state.database, &user_id).await?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+enum JobError {
+    #[error("Unreadable job payload: {0}")]
+    Payload(#[from] serde_json::Error),
+    #[error("Error querying database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Job panicked")]
+    Panicked,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for its first attempt or a retry.
+    Queued,
+    Running,
+    /// Gave up after the last retry.
+    Dead,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobStatusParameter {
+    status: Option<JobStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct JobResponse {
+    id: i64,
+    kind: String,
+    payload: String,
+    status: String,
+    attempts: i64,
+    last_error: Option<String>,
+    /// When the job is next due to run.
+    run_at: String,
+    started_at: Option<String>,
+    created_at: String,
+}
+
+/// Queues a job for the background workers. Queued inside a handler's transaction, the job
+/// only runs if the transaction commits.
+pub async fn queue_job<'e, E>(executor: E, job: &Job) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    let kind = job.kind();
+    let payload = serde_json::to_string(job)?;
+    sqlx::query_file!("sql/insert_job.sql", kind, payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Runs the oldest due job, if there is one. Returns whether a job was found.
+async fn run_next_job(state: &WaterOfLifeState) -> sqlx::Result<bool> {
+    let Some(job) = sqlx::query_file!("sql/claim_job.sql")
+        .fetch_optional(&state.database)
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    let result = match serde_json::from_str::<Job>(&job.payload) {
+        // Run as its own task so a panicking job is recorded as a failure instead of taking
+        // the worker down with it.
+        Ok(payload) => {
+            let state = state.clone();
+            tokio::spawn(async move { payload.run(&state).await })
+                .await
+                .unwrap_or(Err(JobError::Panicked))
+        }
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(()) => {
+            sqlx::query_file!("sql/delete_job.sql", job.id)
+                .execute(&state.database)
+                .await?;
+        }
+        Err(e) => {
+            let error = e.to_string();
+            tracing::warn!("Job {} ({}) failed: {}", job.id, job.kind, error);
+            let backoff = RETRY_BACKOFF_SECS << (job.attempts - 1).clamp(0, 16);
+            sqlx::query_file!(
+                "sql/update_job_failed.sql",
+                job.id,
+                error,
+                MAX_ATTEMPTS,
+                backoff
+            )
+            .execute(&state.database)
+            .await?;
+        }
+    }
+    Ok(true)
+}
+
+async fn job_worker(state: WaterOfLifeState, worker: usize) {
+    loop {
+        match run_next_job(&state).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("job_worker {}: {}", worker, e),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Runs queued jobs on `jobs.workers` workers, retrying failures with backoff.
+pub async fn job_workers(state: WaterOfLifeState) {
+    // Anything still marked running was cut short by a restart, so it gets another go.
+    if let Err(e) = sqlx::query_file!("sql/requeue_running_jobs.sql")
+        .execute(&state.database)
+        .await
+    {
+        tracing::warn!("job_workers: {}", e);
+    }
+
+    let mut workers = JoinSet::new();
+    for worker in 0..state.config.jobs.workers {
+        workers.spawn(job_worker(state.clone(), worker));
+    }
+    while workers.join_next().await.is_some() {}
+}
+
+/// Lists jobs, newest first, optionally only those with one status.
+pub async fn list_jobs(
+    RequirePermission(_, _): RequirePermission<JobsManage>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<JobStatusParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let status = query_params.status.as_ref().map(JobStatus::as_str);
+    let (limit, offset) = (page.limit(), page.offset());
+    let jobs = sqlx::query_file_as!(JobResponse, "sql/select_jobs.sql", status, limit, offset)
+        .fetch_all(&state.database)
+        .await?;
+    let total = sqlx::query_file!("sql/select_job_count.sql", status)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(jobs, &page, total))?;
+    Ok(response.into_response())
+}
+
+/// Gives a dead job a fresh set of attempts.
+pub async fn retry_job(
+    RequirePermission(_, _): RequirePermission<JobsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(job_id): Path<i64>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/retry_dead_job.sql", job_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
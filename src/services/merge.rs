@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::SqliteConnection;
+
+use crate::{
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{flavors::invalidate_flavor_cloud, revisions::record_merge, WebError, WebResult};
+
+/// How many rows moved from the duplicate onto the kept spirit, per table.
+#[derive(Debug, Serialize)]
+struct MergedCounts {
+    ratings: u64,
+    reviews: u64,
+    flavor_votes: u64,
+    wishlist_entries: u64,
+    collection_entries: u64,
+    bottles: u64,
+    pours: u64,
+    barcodes: u64,
+    swap_offers: u64,
+    swap_requests: u64,
+    images: u64,
+    aliases: u64,
+    relations: u64,
+    price_points: u64,
+    flights: u64,
+    cocktail_ingredients: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct MergeResponse {
+    id: String,
+    merged_id: String,
+    moved: MergedCounts,
+}
+
+/// Repoints everything that references `dup_id` at `keep_id`. Where a user has a rating,
+/// review, flavor vote or wishlist entry on both spirits, the one on the kept spirit wins and
+/// the other stays behind on the merged record.
+async fn move_references(
+    connection: &mut SqliteConnection,
+    keep_id: &str,
+    dup_id: &str,
+) -> WebResult<MergedCounts> {
+    macro_rules! merge {
+        ($file:literal) => {
+            sqlx::query_file!($file, keep_id, dup_id)
+                .execute(&mut *connection)
+                .await?
+                .rows_affected()
+        };
+    }
+
+    let aliases = merge!("sql/merge_spirit_aliases.sql");
+    merge!("sql/merge_spirit_aliases_fts.sql");
+
+    Ok(MergedCounts {
+        ratings: merge!("sql/merge_spirit_ratings.sql"),
+        reviews: merge!("sql/merge_spirit_reviews.sql"),
+        flavor_votes: merge!("sql/merge_spirit_flavor_votes.sql"),
+        wishlist_entries: merge!("sql/merge_spirit_wishlist_entries.sql"),
+        collection_entries: merge!("sql/merge_spirit_collection_entries.sql"),
+        bottles: merge!("sql/merge_spirit_bottles.sql"),
+        pours: merge!("sql/merge_spirit_pours.sql"),
+        barcodes: merge!("sql/merge_spirit_barcodes.sql"),
+        swap_offers: merge!("sql/merge_spirit_swap_offers.sql"),
+        swap_requests: merge!("sql/merge_spirit_swap_requests.sql"),
+        // The kept spirit's primary image stays primary.
+        images: merge!("sql/merge_spirit_images.sql"),
+        // Aliases the kept spirit already has stay behind on the merged record.
+        aliases,
+        // A link between the two spirits themselves stays behind on the merged record.
+        relations: merge!("sql/merge_spirit_relations.sql"),
+        price_points: merge!("sql/merge_spirit_price_points.sql"),
+        // A flight that already has the kept spirit keeps the duplicate in its place.
+        flights: merge!("sql/merge_spirit_flights.sql"),
+        cocktail_ingredients: merge!("sql/merge_spirit_cocktail_ingredients.sql"),
+    })
+}
+
+/// Folds a duplicate spirit into the one being kept, moving its ratings, reviews, flavor
+/// votes, barcodes, images and collection references across before retiring it. The duplicate
+/// is soft-deleted and remembers which spirit it was merged into.
+pub async fn merge_spirits(
+    RequirePermission(user, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path((keep_id, dup_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    if keep_id == dup_id {
+        return Err(WebError::InvalidInput(
+            "A spirit can't be merged into itself.".into(),
+        ));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    for spirit_id in [&keep_id, &dup_id] {
+        sqlx::query_file!("sql/select_spirit_exists.sql", spirit_id)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or(WebError::NotFound)?;
+    }
+
+    let moved = move_references(&mut transaction, &keep_id, &dup_id).await?;
+    sqlx::query_file!("sql/delete_merged_spirit.sql", keep_id, dup_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query_file!("sql/delete_spirit_fts.sql", dup_id)
+        .execute(&mut *transaction)
+        .await?;
+    record_merge(&mut transaction, &keep_id, &dup_id, &user.user_id).await?;
+    transaction.commit().await?;
+
+    invalidate_flavor_cloud(&state, &keep_id).await;
+    invalidate_flavor_cloud(&state, &dup_id).await;
+
+    let response = serde_json::to_string(&MergeResponse {
+        id: keep_id,
+        merged_id: dup_id,
+        moved,
+    })?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,604 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, State},
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Response, Sse,
+    },
+    Extension, Json,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    json_web::User,
+    permissions::{ModerationReview, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{WebError, WebResult};
+
+/// Pushed to connected clients over SSE whenever a message is sent to them.
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageEvent {
+    conversation_id: i64,
+    message_id: i64,
+    sender_id: String,
+    body: String,
+    created_at: String,
+    #[serde(skip)]
+    recipient_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewConversationPayload {
+    recipient_id: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessagePayload {
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportPayload {
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ConversationResponse {
+    id: i64,
+    swap_match_id: Option<i64>,
+    created_at: String,
+    participants: Vec<String>,
+    unread_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    id: i64,
+    sender_id: String,
+    sender_username: Option<String>,
+    body: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UnreadResponse {
+    unread_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockResponse {
+    blocked_id: String,
+    username: Option<String>,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MessageReportResponse {
+    id: i64,
+    message_id: i64,
+    sender_id: String,
+    body: String,
+    reporter_id: String,
+    reason: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MessageIdResponse {
+    conversation_id: i64,
+    message_id: i64,
+}
+
+pub async fn is_blocked<'e, E>(executor: E, user_id: &str, other_user_id: &str) -> sqlx::Result<bool>
+where
+    E: SqliteExecutor<'e>,
+{
+    Ok(
+        sqlx::query_file!("sql/select_block_between.sql", user_id, other_user_id)
+            .fetch_optional(executor)
+            .await?
+            .is_some(),
+    )
+}
+
+async fn participants<'e, E>(executor: E, conversation_id: i64) -> sqlx::Result<Vec<String>>
+where
+    E: SqliteExecutor<'e>,
+{
+    Ok(
+        sqlx::query_file!("sql/select_conversation_participants.sql", conversation_id)
+            .fetch_all(executor)
+            .await?
+            .into_iter()
+            .map(|row| row.user_id)
+            .collect(),
+    )
+}
+
+/// Creates a conversation between the given users, optionally attached to a swap match.
+pub async fn create_conversation(
+    connection: &mut SqliteConnection,
+    swap_match_id: Option<i64>,
+    user_ids: &[&str],
+) -> sqlx::Result<i64> {
+    let id = sqlx::query_file!("sql/insert_conversation.sql", swap_match_id)
+        .fetch_one(&mut *connection)
+        .await?
+        .id;
+    for user_id in user_ids {
+        sqlx::query_file!("sql/insert_conversation_participant.sql", id, user_id)
+            .execute(&mut *connection)
+            .await?;
+    }
+    Ok(id)
+}
+
+/// Returns the visible messages in a conversation and marks them read for the user.
+pub async fn read_conversation(
+    state: &WaterOfLifeState,
+    conversation_id: i64,
+    user_id: &str,
+) -> WebResult<Vec<MessageResponse>> {
+    if !participants(&state.database, conversation_id)
+        .await?
+        .iter()
+        .any(|participant| participant == user_id)
+    {
+        return Err(WebError::NotFound);
+    }
+
+    let messages =
+        sqlx::query_file_as!(MessageResponse, "sql/select_messages.sql", conversation_id)
+            .fetch_all(&state.database)
+            .await?;
+    sqlx::query_file!(
+        "sql/update_conversation_last_read.sql",
+        conversation_id,
+        user_id
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok(messages)
+}
+
+/// Sends a message into a conversation, enforcing block lists between the participants, and
+/// pushes it to any connected recipients.
+pub async fn send_message(
+    state: &WaterOfLifeState,
+    conversation_id: i64,
+    sender_id: &str,
+    body: &str,
+) -> WebResult<i64> {
+    if body.trim().is_empty() {
+        return Err(WebError::InvalidInput("Messages can't be empty.".into()));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let mut recipient_ids = participants(&mut *transaction, conversation_id).await?;
+    let Some(position) = recipient_ids.iter().position(|id| id == sender_id) else {
+        return Err(WebError::NotFound);
+    };
+    recipient_ids.swap_remove(position);
+
+    for recipient_id in &recipient_ids {
+        if is_blocked(&mut *transaction, sender_id, recipient_id).await? {
+            return Err(WebError::Forbidden);
+        }
+    }
+
+    let message = sqlx::query_file!("sql/insert_message.sql", conversation_id, sender_id, body)
+        .fetch_one(&mut *transaction)
+        .await?;
+    sqlx::query_file!(
+        "sql/update_conversation_last_read.sql",
+        conversation_id,
+        sender_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    // Nobody being subscribed isn't an error, the message is still stored.
+    let _ = state.message_events.send(MessageEvent {
+        conversation_id,
+        message_id: message.id,
+        sender_id: sender_id.to_owned(),
+        body: body.to_owned(),
+        created_at: message.created_at,
+        recipient_ids,
+    });
+
+    Ok(message.id)
+}
+
+pub async fn list_conversations(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let conversations = sqlx::query_file!("sql/select_conversations.sql", user.user_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| ConversationResponse {
+            id: row.id,
+            swap_match_id: row.swap_match_id,
+            created_at: row.created_at,
+            participants: row
+                .participants
+                .map(|participants| participants.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+            unread_count: row.unread_count,
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&conversations)?;
+    Ok(response.into_response())
+}
+
+pub async fn unread_message_count(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let unread = sqlx::query_file_as!(
+        UnreadResponse,
+        "sql/select_unread_message_count.sql",
+        user.user_id
+    )
+    .fetch_one(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&unread)?;
+    Ok(response.into_response())
+}
+
+/// Starts (or reuses) a direct conversation with another user.
+pub async fn start_conversation(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<NewConversationPayload>,
+) -> WebResult<Response> {
+    if payload.recipient_id == user.user_id {
+        return Err(WebError::InvalidInput("You can't message yourself.".into()));
+    }
+    if !state.repositories.users.exists(&payload.recipient_id).await? {
+        return Err(WebError::NotFound);
+    }
+    if is_blocked(&state.database, &user.user_id, &payload.recipient_id).await? {
+        return Err(WebError::Forbidden);
+    }
+
+    let existing = sqlx::query_file!(
+        "sql/select_direct_conversation.sql",
+        user.user_id,
+        payload.recipient_id
+    )
+    .fetch_optional(&state.database)
+    .await?;
+    let conversation_id = match existing {
+        Some(conversation) => conversation.id,
+        None => {
+            let mut transaction = state.repositories.begin().await?;
+            let id = create_conversation(
+                &mut transaction,
+                None,
+                &[&user.user_id, &payload.recipient_id],
+            )
+            .await?;
+            transaction.commit().await?;
+            id
+        }
+    };
+
+    let message_id = send_message(&state, conversation_id, &user.user_id, &payload.body).await?;
+
+    let response = serde_json::to_string(&MessageIdResponse {
+        conversation_id,
+        message_id,
+    })?;
+    Ok(response.into_response())
+}
+
+pub async fn list_messages(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(conversation_id): Path<i64>,
+) -> WebResult<Response> {
+    let messages = read_conversation(&state, conversation_id, &user.user_id).await?;
+
+    let response = serde_json::to_string(&messages)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_message(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(conversation_id): Path<i64>,
+    Json(payload): Json<MessagePayload>,
+) -> WebResult<Response> {
+    let message_id = send_message(&state, conversation_id, &user.user_id, &payload.body).await?;
+
+    let response = serde_json::to_string(&MessageIdResponse {
+        conversation_id,
+        message_id,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Streams new messages addressed to the user as server-sent events.
+pub async fn message_events(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.message_events.subscribe();
+    let stream = futures::stream::unfold(
+        (receiver, user.user_id),
+        |(mut receiver, user_id)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.recipient_ids.contains(&user_id) => {
+                        let data = serde_json::to_string(&event).unwrap_or_default();
+                        let event = Event::default().event("message").data(data);
+                        return Some((Ok(event), (receiver, user_id)));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("message_events: skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+pub async fn list_blocks(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let blocks = sqlx::query_file_as!(BlockResponse, "sql/select_user_blocks.sql", user.user_id)
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&blocks)?;
+    Ok(response.into_response())
+}
+
+pub async fn block_user(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(blocked_id): Path<String>,
+) -> WebResult<Response> {
+    if blocked_id == user.user_id {
+        return Err(WebError::InvalidInput("You can't block yourself.".into()));
+    }
+
+    sqlx::query_file!("sql/insert_user_block.sql", user.user_id, blocked_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+pub async fn unblock_user(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(blocked_id): Path<String>,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/delete_user_block.sql", user.user_id, blocked_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+pub async fn report_message(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(message_id): Path<i64>,
+    Json(payload): Json<ReportPayload>,
+) -> WebResult<Response> {
+    let message = sqlx::query_file!("sql/select_message.sql", message_id, user.user_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    sqlx::query_file!(
+        "sql/insert_message_report.sql",
+        message.id,
+        user.user_id,
+        payload.reason
+    )
+    .execute(&state.database)
+    .await?;
+    tracing::info!(
+        "Message {} in conversation {} reported by {}",
+        message.id,
+        message.conversation_id,
+        user.user_id
+    );
+
+    Ok("".into_response())
+}
+
+pub async fn list_message_reports(
+    _: RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let reports = sqlx::query_file_as!(MessageReportResponse, "sql/select_message_reports.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&reports)?;
+    Ok(response.into_response())
+}
+
+/// Hides a reported message from both participants and resolves its reports.
+pub async fn hide_message(
+    _: RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    Path(message_id): Path<i64>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let result = sqlx::query_file!("sql/update_message_hidden.sql", message_id)
+        .execute(&mut *transaction)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    sqlx::query_file!("sql/update_message_reports_resolved.sql", message_id)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        services::APP_USER_ROLE,
+        testing::{self, TestApp},
+    };
+
+    use super::*;
+
+    async fn send(
+        app: &TestApp,
+        user: &User,
+        method: Method,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::COOKIE, testing::auth_cookie(&app.state, user))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Sends `body` from `sender` to `recipient`, returning the conversation and message ids.
+    async fn start(app: &TestApp, sender: &User, recipient: &User, body: &str) -> (i64, i64) {
+        let payload = serde_json::json!({ "recipient_id": recipient.user_id, "body": body });
+        let response = send(app, sender, Method::POST, "/api/conversations", payload).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let ids = json(response).await;
+        (
+            ids["conversation_id"].as_i64().unwrap(),
+            ids["message_id"].as_i64().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn only_participants_can_read_or_post_to_a_conversation() {
+        let app = testing::app().await;
+        let sender = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let recipient = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let outsider = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let (conversation_id, _) = start(&app, &sender, &recipient, "Fancy a swap?").await;
+        let uri = format!("/api/conversations/{}/messages", conversation_id);
+        let reply = serde_json::json!({ "body": "Sure" });
+
+        let response = send(&app, &recipient, Method::POST, &uri, reply.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(&app, &recipient, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await.as_array().unwrap().len(), 2);
+
+        let response = send(&app, &outsider, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send(&app, &outsider, Method::POST, &uri, reply).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn blocks_stop_messages_in_both_directions() {
+        let app = testing::app().await;
+        let blocker = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let blocked = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let (conversation_id, _) = start(&app, &blocked, &blocker, "Hello").await;
+        let uri = format!("/api/user/blocks/{}", blocked.user_id);
+        let response = send(&app, &blocker, Method::PUT, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let uri = format!("/api/conversations/{}/messages", conversation_id);
+        let message = serde_json::json!({ "body": "Hello again" });
+        for user in [&blocked, &blocker] {
+            let response = send(&app, user, Method::POST, &uri, message.clone()).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let payload = serde_json::json!({ "recipient_id": blocker.user_id, "body": "Hi" });
+        let response = send(&app, &blocked, Method::POST, "/api/conversations", payload).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let uri = format!("/api/user/blocks/{}", blocked.user_id);
+        let response = send(&app, &blocker, Method::DELETE, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let uri = format!("/api/conversations/{}/messages", conversation_id);
+        let response = send(&app, &blocked, Method::POST, &uri, message).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn moderators_hide_reported_messages_from_the_conversation() {
+        let app = testing::app().await;
+        let sender = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let recipient = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let moderator = testing::create_moderator(&app.state.database).await;
+        let (conversation_id, message_id) = start(&app, &sender, &recipient, "Rude").await;
+        let uri = format!("/api/conversations/{}/messages", conversation_id);
+        let reply = serde_json::json!({ "body": "Please don't" });
+        let response = send(&app, &recipient, Method::POST, &uri, reply).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let report_uri = format!("/api/messages/{}/report", message_id);
+        let report = serde_json::json!({ "reason": "Abusive" });
+        let response = send(&app, &recipient, Method::POST, &report_uri, report).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let hide_uri = format!("/api/admin/messages/{}/hide", message_id);
+        let response = send(&app, &recipient, Method::PUT, &hide_uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&app, &moderator, Method::PUT, &hide_uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for user in [&sender, &recipient] {
+            let response = send(&app, user, Method::GET, &uri, serde_json::json!({})).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let messages = json(response).await;
+            let ids = messages
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|message| message["id"].as_i64().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(ids.len(), 1);
+            assert!(!ids.contains(&message_id));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::WebResult;
+
+#[derive(Debug, Serialize)]
+pub struct NotificationResponse {
+    id: i64,
+    message: String,
+    is_read: bool,
+    created_at: String,
+}
+
+/// Queues an in-app notification for a user.
+pub async fn notify<'e, E>(executor: E, user_id: &str, message: &str) -> sqlx::Result<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file!("sql/insert_notification.sql", user_id, message)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+pub async fn list_notifications(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let notifications = sqlx::query_file!("sql/select_notifications.sql", user.user_id)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| NotificationResponse {
+            id: row.id,
+            message: row.message,
+            is_read: row.is_read != 0,
+            created_at: row.created_at,
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&notifications)?;
+    Ok(response.into_response())
+}
@@ -1,38 +1,56 @@
 use core::str;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
     extract::{Query, State},
-    http::HeaderMap,
+    http::header::RETRY_AFTER,
     response::{IntoResponse, Redirect, Response},
-    Json,
 };
 use jsonwebtoken::TokenData;
-use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
-use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
-use sqlx::SqlitePool;
+use reqwest::{header::CONTENT_TYPE, Client, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use textnonce::TextNonce;
 use thiserror::Error;
-use tower_cookies::{cookie::SameSite, Cookie, Cookies};
+use tower_cookies::{Cookie, Cookies};
 use tower_sessions::Session;
+use tracing::Instrument;
 use url::Url;
 
 use crate::{
-    cookie::create_token_cookie, json_web::{
-        generate_access_and_refresh_tokens, verify_jwt, verify_tokens, JWKCertificate,
-        KeycloakIDClaims, TokenState,
-    }, WaterOfLifeState
+    config::RoleMappingConfig, cookie::create_token_cookie, json_web::{
+        generate_access_and_refresh_tokens, refresh_token_version, verify_jwt, JWKCertificate,
+        KeycloakIDClaims, User,
+    }, permissions::{role_permissions, UsersManage},
+    security::{SecurityEvent, SecurityEventKind}, telemetry::{trace_context_headers, Redacted},
+    proxy::Scheme, repositories::{RepositoryError, UserRepository}, WaterOfLifeState
 };
 
-pub const KEYCLOAK_ADMIN_ROLE: &'static str = "wol-admin";
-pub const APP_ADMIN_ROLE: &'static str = "admin";
-pub const APP_USER_ROLE: &'static str = "user";
+use super::{api::database_error, error_response};
+
+pub const APP_ADMIN_ROLE: &str = "admin";
+/// Reviews submissions and reports, without the rest of an admin's access.
+pub const APP_MODERATOR_ROLE: &str = "moderator";
+pub const APP_USER_ROLE: &str = "user";
+/// Every app role, most privileged first.
+pub const APP_ROLES: [&str; 3] = [APP_ADMIN_ROLE, APP_MODERATOR_ROLE, APP_USER_ROLE];
 
-pub const REALM_URL: &'static str = "https://sso.loucaskreger.com/realms/main";
-pub const WELL_KNOWN_CONFIGURATION_ENDPOINT: &'static str = ".well-known/openid-configuration";
+pub const WELL_KNOWN_CONFIGURATION_ENDPOINT: &str = ".well-known/openid-configuration";
 
-const NONCE_SESSION_KEY: &'static str = "nonce";
-const REDIRECT_URI: &'static str = "http://localhost:3000/oidc/token";
+const NONCE_SESSION_KEY: &str = "nonce";
+/// Set at login when the user ticked "remember me", and read back once Keycloak sends them here.
+const REMEMBER_ME_SESSION_KEY: &str = "remember_me";
+
+/// Requests to Keycloak give up after this long so an outage fails fast.
+const IDENTITY_PROVIDER_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long clients are told to wait before retrying while Keycloak is unreachable.
+const IDENTITY_PROVIDER_RETRY_AFTER_SECONDS: u64 = 30;
+/// Where people signing in for the first time are sent while registration is closed.
+const REGISTRATION_CLOSED_ENDPOINT: &str = "/login?registration=closed";
 
 #[derive(Error, Debug)]
 pub enum AuthenticationError {
@@ -46,38 +64,114 @@ pub enum AuthenticationError {
     SessionStorage(#[from] tower_sessions::session::Error),
     #[error("Error deserializing json.")]
     Deserialization(#[from] serde_json::Error),
+    #[error("The identity provider is unreachable")]
+    IdentityProviderUnavailable,
+    #[error("Error loading or storing the user")]
+    Database(#[from] RepositoryError),
 }
 
 impl IntoResponse for AuthenticationError {
     fn into_response(self) -> Response {
-        #[derive(Serialize)]
-        struct ErrorResponse {
-            message: String,
-        }
-
-        let mut status = StatusCode::INTERNAL_SERVER_ERROR;
-
         match self {
             Self::HttpError(e) => tracing::error!("{}", e),
             Self::ParseError(e) => tracing::error!("{}", e),
             Self::SessionStorage(e) => tracing::error!("{}", e),
             Self::Deserialization(e) => tracing::error!("{}", e),
+            Self::Database(RepositoryError::Database(e)) => return database_error(&e),
             Self::Internal => {}
+            Self::IdentityProviderUnavailable => {
+                let response = error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "idp_unavailable",
+                    "Sign in is temporarily unavailable, please try again shortly.".into(),
+                    Some(serde_json::json!({
+                        "retry_after": IDENTITY_PROVIDER_RETRY_AFTER_SECONDS
+                    })),
+                );
+                let retry_after = IDENTITY_PROVIDER_RETRY_AFTER_SECONDS.to_string();
+                return ([(RETRY_AFTER, retry_after)], response).into_response();
+            }
         }
-        (
-            status,
-            Json(ErrorResponse {
-                message: "Please try again later".into(),
-            }),
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "auth_error",
+            "Please try again later".into(),
+            None,
         )
-            .into_response()
     }
 }
 
 pub type AuthenticationResult<T> = Result<T, AuthenticationError>;
 
+/// Tracks whether Keycloak answered the most recent request made to it.
+#[derive(Clone, Default)]
+pub struct IdentityProviderHealth {
+    last_failure: Arc<Mutex<Option<SystemTime>>>,
+}
+
+impl IdentityProviderHealth {
+    fn record_success(&self) {
+        *self.last_failure.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn record_failure(&self) {
+        *self.last_failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(SystemTime::now());
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.last_failure().is_some()
+    }
+
+    /// Seconds since the epoch of the failure that put auth into a degraded state.
+    pub fn last_failure(&self) -> Option<u64> {
+        self.last_failure
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .and_then(|failed_at| failed_at.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+    }
+}
+
+/// Sends a request to Keycloak, treating connection failures, timeouts and 5xx responses as
+/// an outage rather than an internal error.
+async fn send_to_identity_provider(
+    state: &WaterOfLifeState,
+    request: RequestBuilder,
+) -> AuthenticationResult<reqwest::Response> {
+    let span = tracing::debug_span!("identity_provider", otel.kind = "client");
+    let request = request
+        .headers(trace_context_headers(&span))
+        .timeout(IDENTITY_PROVIDER_TIMEOUT);
+    match request.send().instrument(span).await {
+        Ok(response) if response.status().is_server_error() => {
+            tracing::warn!("Identity provider returned {}", response.status());
+            state.idp_health.record_failure();
+            Err(AuthenticationError::IdentityProviderUnavailable)
+        }
+        Ok(response) => {
+            state.idp_health.record_success();
+            Ok(response)
+        }
+        Err(e) if e.is_connect() || e.is_timeout() => {
+            tracing::warn!("Identity provider is unreachable: {}", e);
+            state.idp_health.record_failure();
+            Err(AuthenticationError::IdentityProviderUnavailable)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Checks whether Keycloak is reachable again by fetching its discovery document.
+pub async fn probe_identity_provider(state: &WaterOfLifeState) -> bool {
+    let request = state.client.get(format!(
+        "{}/{}",
+        state.config.oidc.realm_url, WELL_KNOWN_CONFIGURATION_ENDPOINT
+    ));
+    send_to_identity_provider(state, request).await.is_ok()
+}
+
 #[allow(unused)]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct OpenidConfiguration {
     issuer: String,
     authorization_endpoint: String,
@@ -90,9 +184,17 @@ pub struct OpenidConfiguration {
 #[derive(Debug, Deserialize, Serialize)]
 struct Nonce(String);
 
+#[derive(Debug, Deserialize)]
+pub struct LoginParameter {
+    /// Keep the user signed in across browser restarts, for longer than usual.
+    #[serde(default)]
+    remember_me: bool,
+}
+
 pub async fn login(
     session: Session,
     State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<LoginParameter>,
 ) -> AuthenticationResult<Redirect> {
     let nonce = match TextNonce::sized(32) {
         Ok(nonce) => nonce,
@@ -102,15 +204,21 @@ pub async fn login(
         }
     }
     .0;
-    tracing::info!("Nonce: {}", nonce);
+    // Don't send the user off to a login page that won't load.
+    if state.idp_health.is_degraded() && !probe_identity_provider(&state).await {
+        return Err(AuthenticationError::IdentityProviderUnavailable);
+    }
     session
         .insert(NONCE_SESSION_KEY, Nonce(nonce.clone()))
         .await?;
+    session
+        .insert(REMEMBER_ME_SESSION_KEY, query_params.remember_me)
+        .await?;
     let url = Url::parse_with_params(
         &state.oidc_configuration.authorization_endpoint,
         &[
-            ("client_id", state.client_id.as_str()),
-            ("redirect_uri", REDIRECT_URI),
+            ("client_id", state.config.oidc.client_id.as_str()),
+            ("redirect_uri", state.config.oidc.redirect_uri.as_str()),
             ("response_type", "code"),
             ("scope", "openid roles"),
             ("nonce", &nonce),
@@ -122,84 +230,106 @@ pub async fn login(
     Ok(redirect)
 }
 
+/// Signs the user out. Their refresh token version is moved on, so the refresh token they
+/// held can't be used again, even if it was copied elsewhere.
 pub async fn logout(
-    headers: HeaderMap,
     cookies: Cookies,
     State(state): State<WaterOfLifeState>,
 ) -> AuthenticationResult<()> {
-    tracing::info!("Cookies: {:#?}", cookies.get("wl_id").unwrap().value());
-    let cookie = cookies.get("wl_id").unwrap().value();
-    let client = Client::new();
-    // client
-    //     .post("https://sso.loucaskreger.com/realms/main/protocol/openid-connect/logout")
-    //     .query(&[("client_id", "CLIENT_ID")])
-    //     .send()
-    //     .await;
+    let refresh_token = cookies.get("wl_rid").map(|cookie| cookie.value().to_owned());
+    if let Some((user_id, version)) =
+        refresh_token.and_then(|refresh_token| refresh_token_version(&refresh_token, &state))
+    {
+        state
+            .repositories
+            .users
+            .advance_refresh_token_version(&user_id, version)
+            .await?;
+    }
+
+    for name in ["wl_id", "wl_rid"] {
+        let mut cookie = Cookie::from(name);
+        cookie.set_path("/");
+        cookies.remove(cookie);
+    }
     Ok(())
 }
 
 #[derive(Debug, Deserialize)]
 struct KeycloakUserInfo {
-    sub: String,
-    #[serde(rename = "resource_access")]
-    #[serde(deserialize_with = "as_app_roles")]
-    roles: Vec<String>,
+    /// Roles granted on each client, keyed by client id.
+    #[serde(default)]
+    resource_access: HashMap<String, HashMap<String, Vec<String>>>,
+    /// Roles granted on the realm, under `roles`.
+    #[serde(default)]
+    realm_access: HashMap<String, Vec<String>>,
+    /// Group paths, when a group membership mapper adds them.
+    #[serde(default)]
+    groups: Vec<String>,
     email_verified: bool,
-    name: String,
-    preferred_username: String,
-    given_name: String,
-    family_name: String,
-    email: String,
 }
 
-fn as_app_roles<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let resource_access: HashMap<String, HashMap<String, Vec<String>>> =
-        Deserialize::deserialize(deserializer)?;
-
-    let client_id = std::env::var("CLIENT_ID")
-        .expect("Expected the 'CLIENT_ID' environment variable to be set.");
-
-    Ok(match extract_roles(resource_access, &client_id) {
-        Some(roles) => roles,
-        None => Vec::new(),
-    })
-}
+impl KeycloakUserInfo {
+    /// The roles granted on the `client_id` client.
+    fn client_roles(&self, client_id: &str) -> &[String] {
+        self.resource_access
+            .get(client_id)
+            .and_then(|resource| resource.get("roles"))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
 
-fn extract_roles(
-    mut resources: HashMap<String, HashMap<String, Vec<String>>>,
-    client_id: &str,
-) -> Option<Vec<String>> {
-    let mut role_map = resources.remove(client_id)?;
-    role_map.remove("roles")
+    /// The most privileged app role `mapping` grants for these roles and groups.
+    fn app_role(&self, client_id: &str, mapping: &RoleMappingConfig) -> &'static str {
+        let realm_roles = self.realm_access.get("roles").map(Vec::as_slice).unwrap_or_default();
+        let granted = [
+            (self.client_roles(client_id), &mapping.client_roles),
+            (realm_roles, &mapping.realm_roles),
+            (self.groups.as_slice(), &mapping.groups),
+        ]
+        .into_iter()
+        .flat_map(|(names, mapping)| names.iter().filter_map(|name| mapping.get(name)))
+        .collect::<Vec<_>>();
+        APP_ROLES
+            .into_iter()
+            .find(|role| granted.iter().any(|granted| granted == role))
+            .unwrap_or(APP_USER_ROLE)
+    }
 }
 
 async fn user_info(
-    client: &Client,
-    userinfo_endpoint: &str,
+    state: &WaterOfLifeState,
     keycloak_access_token: &str,
 ) -> AuthenticationResult<KeycloakUserInfo> {
-    let response = client
-        .get(userinfo_endpoint)
-        .bearer_auth(keycloak_access_token)
-        .send()
-        .await?;
+    let request = state
+        .client
+        .get(&state.oidc_configuration.userinfo_endpoint)
+        .bearer_auth(keycloak_access_token);
+    let response = send_to_identity_provider(state, request).await?;
 
     Ok(response.json().await?)
 }
 
 #[allow(unused)]
-#[derive(Debug, Deserialize)]
+#[derive(Deserialize)]
 pub struct AuthCode {
     session_state: String,
     iss: String,
     code: String,
 }
 
+impl fmt::Debug for AuthCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthCode")
+            .field("session_state", &self.session_state)
+            .field("iss", &self.iss)
+            .field("code", &Redacted)
+            .finish()
+    }
+}
+
 #[allow(unused)]
-#[derive(Debug, Deserialize)]
+#[derive(Deserialize)]
 struct TokenResponse {
     access_token: String,
     expires_in: u32,
@@ -211,63 +341,103 @@ struct TokenResponse {
     scope: String,
 }
 
+impl fmt::Debug for TokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenResponse")
+            .field("access_token", &Redacted)
+            .field("expires_in", &self.expires_in)
+            .field("refresh_expires_in", &self.refresh_expires_in)
+            .field("refresh_token", &Redacted)
+            .field("token_type", &self.token_type)
+            .field("id_token", &Redacted)
+            .field("session_state", &self.session_state)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
 pub async fn token(
     session: Session,
     cookies: Cookies,
+    scheme: Scheme,
     State(state): State<WaterOfLifeState>,
     Query(query_params): Query<AuthCode>,
 ) -> AuthenticationResult<Response> {
     let nonce = session.get::<Nonce>(NONCE_SESSION_KEY).await?;
-    tracing::info!("Session nonce: {:#?}", nonce);
+    tracing::debug!("Session has a nonce: {}", nonce.is_some());
+    let remember_me = session
+        .remove::<bool>(REMEMBER_ME_SESSION_KEY)
+        .await?
+        .unwrap_or_default();
 
     tracing::debug!("auth_response: {:#?}", query_params);
-    let response = state
+    let request = state
         .client
-        .post(state.oidc_configuration.token_endpoint)
+        .post(&state.oidc_configuration.token_endpoint)
         .form(&[
-            ("client_id", state.client_id.as_str()),
-            ("client_secret", state.client_secret.as_str()),
+            ("client_id", state.config.oidc.client_id.as_str()),
+            ("client_secret", state.config.oidc.client_secret.as_str()),
             ("code", &query_params.code),
             ("grant_type", "authorization_code"),
-            ("redirect_uri", REDIRECT_URI),
+            ("redirect_uri", state.config.oidc.redirect_uri.as_str()),
         ])
-        .header(CONTENT_TYPE, "x-www-form-urlencoded")
-        .send()
-        .await?;
+        .header(CONTENT_TYPE, "x-www-form-urlencoded");
+    let response = send_to_identity_provider(&state, request).await?;
 
     let tokens: TokenResponse = response.json().await?;
     tracing::debug!("Got tokens: {:#?}", tokens);
 
-    let endpoint: &'static str =
-        match verify_jwt::<KeycloakIDClaims>(&tokens.id_token, &state.client_id, &state.jwks) {
+    let client_id = &state.config.oidc.client_id;
+    // Copied out so the lock isn't held across the awaits below.
+    let jwks = state.jwks.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let endpoint: &str =
+        match verify_jwt::<KeycloakIDClaims>(&tokens.id_token, client_id, &jwks) {
             Ok(token_data) => {
-                let role = match user_info(
-                    &state.client,
-                    &state.oidc_configuration.userinfo_endpoint,
-                    &tokens.access_token,
-                )
-                .await
-                {
-                    Ok(user_info) if user_info.roles.contains(&KEYCLOAK_ADMIN_ROLE.to_owned()) => {
-                        APP_ADMIN_ROLE
+                if !registration_allowed(&state, &token_data.claims.sub).await {
+                    tracing::info!("Refused sign in by new user {}", token_data.claims.sub);
+                    return Ok(Redirect::to(REGISTRATION_CLOSED_ENDPOINT).into_response());
+                }
+
+                let role = match user_info(&state, &tokens.access_token).await {
+                    Ok(user_info) => user_info.app_role(client_id, &state.config.oidc.roles),
+                    // Falling back to the user role here would silently demote admins.
+                    Err(AuthenticationError::IdentityProviderUnavailable) => {
+                        return Err(AuthenticationError::IdentityProviderUnavailable)
                     }
                     _ => APP_USER_ROLE,
                 };
 
+                audit_role_change(&state, &token_data.claims.sub, role).await;
+
+                let users = &*state.repositories.users;
+                record_login(users, &token_data, role).await?;
+                // New users start on version 1, and returning ones keep theirs.
+                let refresh_token_version = users
+                    .find(&token_data.claims.sub)
+                    .await?
+                    .map_or(1, |user| user.refresh_token_version);
                 let maybe_tokens = generate_access_and_refresh_tokens(
-                    &state.access_token_hmac_secret,
-                    &state.refresh_token_hmac_secret,
-                    &state.client_id,
+                    &*state.clock,
+                    &state.config.tokens,
+                    client_id,
                     &token_data.claims.sub,
                     role,
+                    refresh_token_version,
+                    remember_me,
                 );
 
                 if let Some((access_token, refresh_token)) = maybe_tokens {
-                    let _ = insert_user(&state.database, &token_data, role).await.unwrap();
+                    let keycloak_refresh_token = Some(tokens.refresh_token.as_str());
+                    users
+                        .set_identity_provider_token(&token_data.claims.sub, keycloak_refresh_token)
+                        .await?;
 
                     // FIXME: Replace with axum's CookieJar which must be returned from the handler.
-                    cookies.add(create_token_cookie("wl_id", access_token));
-                    cookies.add(create_token_cookie("wl_rid", refresh_token));
+                    let secure = state.config.cookies.secure || scheme.https;
+                    let max_age =
+                        remember_me.then(|| state.config.tokens.refresh_token_lifetime(true));
+                    cookies.add(create_token_cookie("wl_id", access_token, secure, max_age));
+                    cookies.add(create_token_cookie("wl_rid", refresh_token, secure, max_age));
 
                     "/"
                 } else {
@@ -276,6 +446,7 @@ pub async fn token(
             }
             Err(error) => {
                 tracing::info!("{}", error);
+                state.security.record_jwt_failure().await;
                 "/login"
             }
         };
@@ -283,22 +454,201 @@ pub async fn token(
     Ok(Redirect::to(endpoint).into_response())
 }
 
-async fn insert_user(
-    database: &SqlitePool,
+#[derive(Deserialize)]
+struct RefreshedTokens {
+    access_token: String,
+    refresh_token: String,
+}
+
+/// How long a role check's outcome is reused. Covers the burst of requests a page load sends once
+/// the access token has expired, which would otherwise each spend the same Keycloak refresh token.
+const ROLE_CHECK_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// One lock per user whose role is being checked, so concurrent requests wait for the first
+/// check instead of each making their own.
+#[derive(Clone, Default)]
+pub struct RoleCheckLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl RoleCheckLocks {
+    fn acquire(&self, user_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        locks.entry(user_id.to_owned()).or_default().clone()
+    }
+
+    /// Forgets the user's lock once nobody else is waiting on it.
+    fn release(&self, user_id: &str, lock: Arc<tokio::sync::Mutex<()>>) {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        // One reference is the map's and the other is `lock`.
+        if Arc::strong_count(&lock) <= 2 {
+            locks.remove(user_id);
+        }
+    }
+}
+
+/// What Keycloak says about a user's role when the app's tokens are refreshed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RoleCheck {
+    /// Keycloak confirmed the user's current role, which has been stored.
+    Current(String),
+    /// Keycloak no longer has a session for the user, say because they signed out or were
+    /// disabled, or no Keycloak refresh token was kept for them.
+    SessionEnded,
+}
+
+fn role_check_cache_key(user_id: &str) -> String {
+    format!("role_check:{}", user_id)
+}
+
+/// Asks Keycloak for the user's current role using their stored Keycloak refresh token, so a
+/// role granted or revoked in Keycloak takes effect the next time the app's tokens are
+/// refreshed rather than at the next sign in. Role changes are stored and audited. Whether
+/// their email has since been verified is stored too.
+///
+/// Checks for the same user run one at a time, and a check's outcome is reused for
+/// [`ROLE_CHECK_CACHE_TTL`], so concurrent refreshes don't race to spend a refresh token that
+/// Keycloak revokes once used.
+pub async fn check_role(state: &WaterOfLifeState, user: &User) -> AuthenticationResult<RoleCheck> {
+    let lock = state.role_checks.acquire(&user.user_id);
+    let guard = lock.lock().await;
+    let check = cached_role_check(state, user).await;
+    drop(guard);
+    state.role_checks.release(&user.user_id, lock);
+    check
+}
+
+async fn cached_role_check(
+    state: &WaterOfLifeState,
+    user: &User,
+) -> AuthenticationResult<RoleCheck> {
+    let cache_key = role_check_cache_key(&user.user_id);
+    match state.stores.cache.get(&cache_key).await {
+        Ok(Some(cached)) => {
+            if let Ok(check) = serde_json::from_slice(&cached) {
+                return Ok(check);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("check_role: {}", e),
+    }
+
+    let check = fetch_role(state, user).await?;
+    match serde_json::to_vec(&check) {
+        Ok(cached) => {
+            if let Err(e) = state
+                .stores
+                .cache
+                .set(&cache_key, &cached, ROLE_CHECK_CACHE_TTL)
+                .await
+            {
+                tracing::warn!("check_role: {}", e);
+            }
+        }
+        Err(e) => tracing::warn!("check_role: {}", e),
+    }
+    Ok(check)
+}
+
+async fn fetch_role(state: &WaterOfLifeState, user: &User) -> AuthenticationResult<RoleCheck> {
+    let users = &*state.repositories.users;
+    let Some(refresh_token) = users.identity_provider_token(&user.user_id).await? else {
+        return Ok(RoleCheck::SessionEnded);
+    };
+
+    let client_id = &state.config.oidc.client_id;
+    let request = state
+        .client
+        .post(&state.oidc_configuration.token_endpoint)
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", state.config.oidc.client_secret.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ]);
+    let response = send_to_identity_provider(state, request).await?;
+    // Keycloak answers `invalid_grant` once the session behind the token has ended.
+    if response.status().is_client_error() {
+        tracing::debug!("Keycloak refused {}'s refresh token", user.user_id);
+        users.set_identity_provider_token(&user.user_id, None).await?;
+        return Ok(RoleCheck::SessionEnded);
+    }
+    let tokens: RefreshedTokens = response.json().await?;
+    users
+        .set_identity_provider_token(&user.user_id, Some(&tokens.refresh_token))
+        .await?;
+
+    let info = user_info(state, &tokens.access_token).await?;
+    let role = info.app_role(client_id, &state.config.oidc.roles);
+    if role != user.role {
+        audit_role_change(state, &user.user_id, role).await;
+        users.set_role(&user.user_id, role).await?;
+    }
+    if info.email_verified != user.email_verified {
+        users
+            .set_email_verified(&user.user_id, info.email_verified)
+            .await?;
+    }
+    Ok(RoleCheck::Current(role.to_owned()))
+}
+
+/// Whether the user may sign in as far as the `registration_open` setting goes. Users who have
+/// signed in before always can.
+async fn registration_allowed(state: &WaterOfLifeState, user_id: &str) -> bool {
+    if state.settings.get().await.registration_open {
+        return true;
+    }
+    match state.repositories.users.exists(user_id).await {
+        Ok(exists) => exists,
+        Err(e) => {
+            tracing::warn!("registration_allowed: {}", e);
+            false
+        }
+    }
+}
+
+/// Reports logins where the identity provider grants or revokes the admin role.
+async fn audit_role_change(state: &WaterOfLifeState, user_id: &str, role: &str) {
+    let user = match state.repositories.users.find(user_id).await {
+        Ok(Some(user)) if user.role != role => user,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!("audit_role_change: {}", e);
+            return;
+        }
+    };
+    // Only changes to or from a role that can manage users are worth a security event.
+    let before = role_permissions(&state.database, &user.role).await;
+    let after = role_permissions(&state.database, role).await;
+    match (before, after) {
+        (Ok(before), Ok(after)) if before.has::<UsersManage>() || after.has::<UsersManage>() => {
+            state.security.emit(SecurityEvent::new(
+                SecurityEventKind::AdminRoleChange,
+                Some(user_id),
+                format!("Role changed from '{}' to '{}'", user.role, role),
+            ));
+        }
+        (Ok(_), Ok(_)) => {}
+        (Err(e), _) | (_, Err(e)) => tracing::warn!("audit_role_change: {}", e),
+    }
+}
+
+/// Adds the user on their first login, and afterwards keeps their profile in step with
+/// Keycloak's.
+async fn record_login(
+    users: &dyn UserRepository,
     data: &TokenData<KeycloakIDClaims>,
     role: &str,
 ) -> AuthenticationResult<()> {
-    let x = sqlx::query_file!(
-        "sql/insert_user.sql",
-        data.claims.sub,
-        data.claims.preferred_username,
-        data.claims.email,
-        1,
-        role
-    )
-    .execute(database)
-    .await
-    .unwrap();
+    let user = User {
+        user_id: data.claims.sub.clone(),
+        preferred_username: data.claims.preferred_username.clone(),
+        email: data.claims.email.clone(),
+        refresh_token_version: 1,
+        role: role.to_owned(),
+        email_verified: data.claims.email_verified,
+    };
+    users.record_login(&user).await?;
 
     Ok(())
 }
@@ -312,22 +662,28 @@ where
 
 pub async fn get_well_known_configuration(
     client: &Client,
+    realm_url: &str,
 ) -> AuthenticationResult<OpenidConfiguration> {
     get_as_json(
         client,
-        &format!("{}/{}", REALM_URL, WELL_KNOWN_CONFIGURATION_ENDPOINT),
+        &format!("{}/{}", realm_url, WELL_KNOWN_CONFIGURATION_ENDPOINT),
     )
     .await
 }
 
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<JWKCertificate>,
+}
+
 pub async fn get_jwks(
     jwks_uri: &str,
     client: &Client,
 ) -> AuthenticationResult<HashMap<String, JWKCertificate>> {
-    let mut response = get_as_json::<HashMap<String, serde_json::Value>>(client, jwks_uri).await?;
+    let response = get_as_json::<JwkSet>(client, jwks_uri).await?;
 
-    let keys = response.remove("keys").unwrap();
-    Ok(serde_json::from_value::<Vec<JWKCertificate>>(keys)?
+    Ok(response
+        .keys
         .into_iter()
         .map(|cert| (cert.alg.clone(), cert))
         .collect::<HashMap<String, JWKCertificate>>())
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{rejection::QueryRejection, FromRequestParts, Query},
+    http::request::Parts,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::WaterOfLifeState;
+
+/// Used when the `default_page_size` setting can't be read.
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+/// `?page=&per_page=` query parameters. Pages start at 1 and hold the `default_page_size`
+/// setting's worth of items unless `per_page` says otherwise.
+#[derive(Debug)]
+pub struct PageParameter {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    default_per_page: i64,
+}
+
+impl PageParameter {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+            .unwrap_or(self.default_per_page)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1) * self.limit()
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<WaterOfLifeState> for PageParameter {
+    type Rejection = QueryRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &WaterOfLifeState,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PageQuery>::from_request_parts(parts, state).await?;
+        Ok(Self {
+            page: query.page,
+            per_page: query.per_page,
+            default_per_page: state.settings.get().await.default_page_size,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    items: Vec<T>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, parameter: &PageParameter, total: i64) -> Self {
+        Self {
+            items,
+            page: parameter.page(),
+            per_page: parameter.limit(),
+            total,
+        }
+    }
+}
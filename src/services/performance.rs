@@ -0,0 +1,42 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{
+    permissions::{RequirePermission, SystemMonitor},
+    telemetry::SlowRoute,
+    WaterOfLifeState,
+};
+
+use super::WebResult;
+
+#[derive(Debug, Serialize)]
+struct SlowRouteResponse {
+    route: String,
+    #[serde(flatten)]
+    counts: SlowRoute,
+}
+
+/// Routes that have been slow since this instance started, worst first. Statements run outside
+/// a request are listed under `<background>`.
+pub async fn slow_routes(
+    RequirePermission(_, _): RequirePermission<SystemMonitor>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let mut routes = state
+        .slow_operations
+        .routes()
+        .into_iter()
+        .map(|(route, counts)| SlowRouteResponse { route, counts })
+        .collect::<Vec<_>>();
+    routes.sort_by(|a, b| {
+        (b.counts.slow_requests + b.counts.slow_queries)
+            .cmp(&(a.counts.slow_requests + a.counts.slow_queries))
+            .then_with(|| a.route.cmp(&b.route))
+    });
+
+    let response = serde_json::to_string(&routes)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,242 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::ensure_spirit_exists,
+    badges::award_badges,
+    bottles::record_bottle_pour,
+    export::{csv_response, ExportFormat, ExportParameter},
+    pagination::{Page, PageParameter},
+    preferences::{load_preferences, VolumeUnit},
+    validation::is_valid_timestamp,
+    WebError, WebResult,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PourPayload {
+    spirit_id: String,
+    /// Defaults to now. Formatted as `YYYY-MM-DD HH:MM:SS`.
+    poured_at: Option<String>,
+    amount: f64,
+    #[serde(default)]
+    unit: VolumeUnit,
+    #[serde(default)]
+    occasion: String,
+    #[serde(default)]
+    note: String,
+    /// The bottle on the user's shelf this was poured from, if they track bottles.
+    bottle_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PourParameter {
+    spirit_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PourResponse {
+    id: i64,
+    spirit_id: String,
+    spirit_name: String,
+    poured_at: String,
+    amount_ml: f64,
+    occasion: String,
+    note: String,
+    bottle_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct PourIdResponse {
+    id: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthlyPours {
+    month: String,
+    pours: i64,
+    /// In the user's preferred volume unit.
+    total: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FavoriteSpirit {
+    spirit_id: String,
+    spirit_name: String,
+    pours: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct PourStatsResponse {
+    volume_unit: VolumeUnit,
+    per_month: Vec<MonthlyPours>,
+    favorite_spirit: Option<FavoriteSpirit>,
+}
+
+pub async fn add_pour(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<PourPayload>,
+) -> WebResult<Response> {
+    if payload.amount.is_nan() || payload.amount <= 0.0 {
+        return Err(WebError::InvalidInput(
+            "Pour amounts must be greater than zero.".into(),
+        ));
+    }
+    if let Some(poured_at) = &payload.poured_at {
+        if !is_valid_timestamp(poured_at) {
+            return Err(WebError::InvalidInput(format!(
+                "Time '{}' is not formatted as YYYY-MM-DD HH:MM:SS.",
+                poured_at
+            )));
+        }
+    }
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let amount_ml = payload.unit.to_ml(payload.amount);
+    let mut transaction = state.repositories.begin().await?;
+    if let Some(bottle_id) = payload.bottle_id {
+        record_bottle_pour(
+            &mut transaction,
+            bottle_id,
+            &user.user_id,
+            &payload.spirit_id,
+            amount_ml,
+            payload.poured_at.as_deref(),
+        )
+        .await?;
+    }
+    let id = sqlx::query_file!(
+        "sql/insert_pour.sql",
+        user.user_id,
+        payload.spirit_id,
+        payload.poured_at,
+        amount_ml,
+        payload.occasion,
+        payload.note,
+        payload.bottle_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    transaction.commit().await?;
+    award_badges(&state.database, &user.user_id).await;
+
+    let response = serde_json::to_string(&PourIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn list_pours(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<PourParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let (limit, offset) = (page.limit(), page.offset());
+    let pours = sqlx::query_file_as!(
+        PourResponse,
+        "sql/select_pours.sql",
+        user.user_id,
+        query_params.spirit_id,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!(
+        "sql/select_pour_count.sql",
+        user.user_id,
+        query_params.spirit_id
+    )
+    .fetch_one(&state.database)
+    .await?
+    .count;
+
+    let response = serde_json::to_string(&Page::new(pours, &page, total))?;
+    Ok(response.into_response())
+}
+
+pub async fn delete_pour(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(pour_id): Path<i64>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_pour.sql", pour_id, user.user_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+/// Pours per month, newest first, with totals converted to `volume_unit`.
+pub async fn monthly_pours<'e, E>(
+    executor: E,
+    user_id: &str,
+    volume_unit: VolumeUnit,
+) -> sqlx::Result<Vec<MonthlyPours>>
+where
+    E: SqliteExecutor<'e>,
+{
+    Ok(sqlx::query_file!("sql/select_pours_per_month.sql", user_id)
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|row| MonthlyPours {
+            month: row.month,
+            pours: row.pours,
+            total: volume_unit.convert_ml(row.total_ml),
+        })
+        .collect())
+}
+
+/// The spirit the user has poured most often, breaking ties by the most recent pour.
+pub async fn favorite_poured_spirit<'e, E>(
+    executor: E,
+    user_id: &str,
+) -> sqlx::Result<Option<FavoriteSpirit>>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file_as!(
+        FavoriteSpirit,
+        "sql/select_favorite_poured_spirit.sql",
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Pours per month, in the user's preferred volume unit, and the spirit the user reaches for
+/// most often. As CSV, only the months are sent.
+pub async fn pour_stats(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Query(export_params): Query<ExportParameter>,
+    headers: HeaderMap,
+) -> WebResult<Response> {
+    let volume_unit = load_preferences(&state.database, &user.user_id)
+        .await?
+        .volume_unit;
+    let per_month = monthly_pours(&state.database, &user.user_id, volume_unit).await?;
+    if ExportFormat::negotiate(export_params.format, &headers) == ExportFormat::Csv {
+        return csv_response(&per_month);
+    }
+    let favorite_spirit = favorite_poured_spirit(&state.database, &user.user_id).await?;
+
+    let response = serde_json::to_string(&PourStatsResponse {
+        volume_unit,
+        per_month,
+        favorite_spirit,
+    })?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,245 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{WebError, WebResult};
+
+const MILLILITERS_PER_OUNCE: f64 = 29.5735;
+const MAX_LOCALE_LENGTH: usize = 35;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeUnit {
+    #[default]
+    Ml,
+    /// US fluid ounces.
+    Oz,
+}
+
+impl VolumeUnit {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ml => "ml",
+            Self::Oz => "oz",
+        }
+    }
+
+    fn parse(unit: &str) -> Self {
+        match unit {
+            "oz" => Self::Oz,
+            _ => Self::Ml,
+        }
+    }
+
+    pub fn to_ml(self, amount: f64) -> f64 {
+        match self {
+            Self::Ml => amount,
+            Self::Oz => amount * MILLILITERS_PER_OUNCE,
+        }
+    }
+
+    pub fn convert_ml(self, ml: f64) -> f64 {
+        match self {
+            Self::Ml => ml,
+            Self::Oz => ml / MILLILITERS_PER_OUNCE,
+        }
+    }
+}
+
+/// How alcohol content is shown. Everything is stored as ABV.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrengthUnit {
+    #[default]
+    Abv,
+    /// US proof, twice the ABV.
+    Proof,
+}
+
+impl StrengthUnit {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Abv => "abv",
+            Self::Proof => "proof",
+        }
+    }
+
+    fn parse(unit: &str) -> Self {
+        match unit {
+            "proof" => Self::Proof,
+            _ => Self::Abv,
+        }
+    }
+
+    pub fn convert_abv(self, abv: f64) -> f64 {
+        match self {
+            Self::Abv => abv,
+            Self::Proof => abv * 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Follow the device's setting.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+
+    fn parse(theme: &str) -> Self {
+        match theme {
+            "light" => Self::Light,
+            "dark" => Self::Dark,
+            _ => Self::System,
+        }
+    }
+}
+
+/// Who can see a user's collection on their profile. Owners and admins always can.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionVisibility {
+    /// Any signed in user who hasn't been blocked.
+    Public,
+    #[default]
+    Private,
+}
+
+impl CollectionVisibility {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Private => "private",
+        }
+    }
+
+    fn parse(visibility: &str) -> Self {
+        match visibility {
+            "public" => Self::Public,
+            _ => Self::Private,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserPreferences {
+    pub volume_unit: VolumeUnit,
+    pub strength_unit: StrengthUnit,
+    /// A BCP 47 language tag such as `en-US`.
+    pub locale: String,
+    pub theme: Theme,
+    pub collection_visibility: CollectionVisibility,
+    /// List the user's recent ratings and reviews on their profile.
+    pub show_activity: bool,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            volume_unit: VolumeUnit::default(),
+            strength_unit: StrengthUnit::default(),
+            locale: "en-US".to_owned(),
+            theme: Theme::default(),
+            collection_visibility: CollectionVisibility::default(),
+            show_activity: true,
+        }
+    }
+}
+
+/// Checks that a locale looks like a BCP 47 tag: a two or three letter language followed by
+/// alphanumeric subtags.
+fn validate_locale(locale: &str) -> WebResult<()> {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let is_valid = locale.len() <= MAX_LOCALE_LENGTH
+        && (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    if !is_valid {
+        return Err(WebError::InvalidInput(format!(
+            "'{}' is not a locale such as en-US.",
+            locale
+        )));
+    }
+    Ok(())
+}
+
+/// The user's preferences, with defaults for anything they haven't set.
+pub async fn load_preferences<'e, E>(executor: E, user_id: &str) -> sqlx::Result<UserPreferences>
+where
+    E: SqliteExecutor<'e>,
+{
+    let row = sqlx::query_file!("sql/select_user_preferences.sql", user_id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(row
+        .map(|row| UserPreferences {
+            volume_unit: VolumeUnit::parse(&row.volume_unit),
+            strength_unit: StrengthUnit::parse(&row.strength_unit),
+            locale: row.locale,
+            theme: Theme::parse(&row.theme),
+            collection_visibility: CollectionVisibility::parse(&row.collection_visibility),
+            show_activity: row.show_activity,
+        })
+        .unwrap_or_default())
+}
+
+pub async fn get_preferences(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let preferences = load_preferences(&state.database, &user.user_id).await?;
+
+    let response = serde_json::to_string(&preferences)?;
+    Ok(response.into_response())
+}
+
+/// Replaces the user's preferences. Fields left out go back to their defaults.
+pub async fn set_preferences(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<UserPreferences>,
+) -> WebResult<Response> {
+    let locale = payload.locale.trim();
+    validate_locale(locale)?;
+
+    let volume_unit = payload.volume_unit.as_str();
+    let strength_unit = payload.strength_unit.as_str();
+    let theme = payload.theme.as_str();
+    let collection_visibility = payload.collection_visibility.as_str();
+    sqlx::query_file!(
+        "sql/upsert_user_preferences.sql",
+        user.user_id,
+        volume_unit,
+        strength_unit,
+        locale,
+        theme,
+        collection_visibility,
+        payload.show_activity
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok("".into_response())
+}
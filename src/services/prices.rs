@@ -0,0 +1,274 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    json_web::{AuthContext, User},
+    mailer::{queue_wishlist_email, EmailTemplate},
+    WaterOfLifeState,
+};
+
+use super::{
+    anomalies::flag_price_anomaly,
+    api::find_visible_spirit,
+    pagination::{Page, PageParameter},
+    validation::validate_optional_date,
+    wishlist::notify_wishlist,
+    WebError, WebResult,
+};
+
+const MAX_STORE_LENGTH: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct PricePayload {
+    amount: f64,
+    /// An ISO 4217 code such as `USD`.
+    currency: String,
+    #[serde(default)]
+    store: String,
+    /// When the price was seen, formatted as `YYYY-MM-DD`. Defaults to today.
+    date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceParameter {
+    currency: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PricePointResponse {
+    id: i64,
+    user_id: String,
+    amount: f64,
+    currency: String,
+    store: String,
+    observed_on: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PricePointIdResponse {
+    id: i64,
+}
+
+/// Prices seen in one currency during one month.
+#[derive(Debug, Serialize)]
+struct MonthlyPrices {
+    month: String,
+    currency: String,
+    min: f64,
+    avg: f64,
+    max: f64,
+    count: i64,
+}
+
+/// Uppercases a currency code, rejecting anything that isn't three letters.
+fn normalize_currency(currency: &str) -> WebResult<String> {
+    let currency = currency.trim().to_ascii_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(WebError::InvalidInput(format!(
+            "'{}' is not a three letter currency code.",
+            currency
+        )));
+    }
+    Ok(currency)
+}
+
+fn currency_filter(query_params: &PriceParameter) -> WebResult<Option<String>> {
+    query_params
+        .currency
+        .as_deref()
+        .map(normalize_currency)
+        .transpose()
+}
+
+/// Records a price a user saw the spirit selling for and lets anyone wishing for it know. Prices
+/// far above the usual for the spirit's type are recorded but flagged for review.
+pub async fn add_price_point(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<PricePayload>,
+) -> WebResult<Response> {
+    if !payload.amount.is_finite() || payload.amount <= 0.0 {
+        return Err(WebError::InvalidInput(
+            "Prices must be greater than zero.".into(),
+        ));
+    }
+    let currency = normalize_currency(&payload.currency)?;
+    let store = payload.store.trim();
+    if store.chars().count() > MAX_STORE_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Store names must be at most {} characters.",
+            MAX_STORE_LENGTH
+        )));
+    }
+    validate_optional_date(&payload.date)?;
+    let spirit = sqlx::query_file!("sql/select_spirit_name.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .name;
+
+    let mut transaction = state.repositories.begin().await?;
+    flag_price_anomaly(&mut transaction, &spirit_id, payload.amount, &currency).await?;
+    let id = sqlx::query_file!(
+        "sql/insert_price_point.sql",
+        spirit_id,
+        user.user_id,
+        payload.amount,
+        currency,
+        store,
+        payload.date
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    notify_wishlist(
+        &mut *transaction,
+        &spirit_id,
+        &user.user_id,
+        &format!(
+            "A new price of {:.2} {} was reported.",
+            payload.amount, currency
+        ),
+    )
+    .await?;
+    queue_wishlist_email(
+        &mut *transaction,
+        &spirit_id,
+        &user.user_id,
+        &EmailTemplate::PriceAlert {
+            spirit: &spirit,
+            amount: payload.amount,
+            currency: &currency,
+            store,
+        },
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&PricePointIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+/// Lists reported prices for a spirit, most recently seen first.
+pub async fn list_price_points(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Query(query_params): Query<PriceParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let currency = currency_filter(&query_params)?;
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let (limit, offset) = (page.limit(), page.offset());
+    let prices = sqlx::query_file_as!(
+        PricePointResponse,
+        "sql/select_price_points.sql",
+        spirit_id,
+        currency,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_price_point_count.sql", spirit_id, currency)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(prices, &page, total))?;
+    Ok(response.into_response())
+}
+
+/// The lowest, average and highest reported price per month, oldest month first. Currencies
+/// are never converted, so each month has one entry per currency prices were reported in.
+pub async fn price_history(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Query(query_params): Query<PriceParameter>,
+) -> WebResult<Response> {
+    let currency = currency_filter(&query_params)?;
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+
+    let history = sqlx::query_file_as!(
+        MonthlyPrices,
+        "sql/select_price_history.sql",
+        spirit_id,
+        currency
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&history)?;
+    Ok(response.into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{services::APP_USER_ROLE, testing};
+
+    use super::*;
+
+    async fn report_price(app: &testing::TestApp, user: &User, spirit_id: &str, amount: f64) {
+        let request = Request::post(format!("/api/spirit/{}/prices", spirit_id))
+            .header(header::COOKIE, testing::auth_cookie(&app.state, user))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({ "amount": amount, "currency": "usd" }).to_string(),
+            ))
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    async fn price_anomalies(app: &testing::TestApp) -> Vec<String> {
+        sqlx::query_scalar("SELECT reason FROM spirit_anomalies WHERE field = 'price'")
+            .fetch_all(&app.state.database)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn prices_far_above_the_median_for_the_type_are_flagged() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        for (index, amount) in [30.0, 40.0, 45.0, 50.0, 60.0].into_iter().enumerate() {
+            let spirit_id =
+                testing::create_spirit(&app.state.database, &format!("Bourbon {}", index)).await;
+            report_price(&app, &user, &spirit_id, amount).await;
+        }
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+
+        report_price(&app, &user, &spirit_id, 4500.0).await;
+        report_price(&app, &user, &spirit_id, 55.0).await;
+        let anomalies = price_anomalies(&app).await;
+        assert_eq!(anomalies.len(), 1);
+        assert!(anomalies[0].contains("median of 45.00 USD"));
+    }
+
+    #[tokio::test]
+    async fn prices_are_not_flagged_without_enough_to_compare() {
+        let app = testing::app().await;
+        let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        for amount in [40.0, 45.0, 50.0, 60.0] {
+            report_price(&app, &user, &spirit_id, amount).await;
+        }
+
+        report_price(&app, &user, &spirit_id, 100_000.0).await;
+        assert!(price_anomalies(&app).await.is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Serialize;
+
+use crate::{json_web::AuthContext, permissions::UsersManage, WaterOfLifeState};
+
+use super::{
+    messages::is_blocked,
+    preferences::{load_preferences, CollectionVisibility},
+    reputation::user_reputation,
+    WebError, WebResult,
+};
+
+const MAX_ACTIVITY_ITEMS: i64 = 20;
+
+#[derive(Debug, Serialize)]
+struct ProfileCollectionEntry {
+    spirit_id: String,
+    spirit_name: String,
+    status: String,
+    opened: bool,
+}
+
+/// A rating or review, newest first.
+#[derive(Debug, Serialize)]
+struct ProfileActivity {
+    kind: String,
+    spirit_id: String,
+    spirit_name: String,
+    score: Option<i64>,
+    body: Option<String>,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ProfileResponse {
+    user_id: String,
+    username: String,
+    avatar_url: Option<String>,
+    reputation: i64,
+    trusted: bool,
+    /// Left out unless the user made their collection public.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collection: Option<Vec<ProfileCollectionEntry>>,
+    /// Left out if the user opted out of showing their activity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activity: Option<Vec<ProfileActivity>>,
+}
+
+/// A user's public profile. The collection and recent activity follow the user's privacy
+/// preferences, which don't apply to the user themselves or to users allowed to manage every
+/// user. Users who blocked each other can't see each other's profiles.
+pub async fn get_profile(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(user_id): Path<String>,
+) -> WebResult<Response> {
+    let user = &context.user;
+    let is_privileged = user.user_id == user_id || context.permissions.has::<UsersManage>();
+    if !is_privileged && is_blocked(&state.database, &user.user_id, &user_id).await? {
+        return Err(WebError::NotFound);
+    }
+
+    let profile = sqlx::query_file!("sql/select_user_profile.sql", user_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let reputation = user_reputation(&state.database, &user_id).await?;
+    let preferences = load_preferences(&state.database, &user_id).await?;
+
+    let collection =
+        if is_privileged || preferences.collection_visibility == CollectionVisibility::Public {
+            Some(
+                sqlx::query_file_as!(
+                    ProfileCollectionEntry,
+                    "sql/select_profile_collection.sql",
+                    user_id
+                )
+                .fetch_all(&state.database)
+                .await?,
+            )
+        } else {
+            None
+        };
+    let activity = if is_privileged || preferences.show_activity {
+        Some(
+            sqlx::query_file_as!(
+                ProfileActivity,
+                "sql/select_profile_activity.sql",
+                user_id,
+                MAX_ACTIVITY_ITEMS
+            )
+            .fetch_all(&state.database)
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let response = serde_json::to_string(&ProfileResponse {
+        user_id: profile.user_id,
+        username: profile.username,
+        avatar_url: profile.avatar_url,
+        reputation: reputation.score,
+        trusted: reputation.trusted,
+        collection,
+        activity,
+    })?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::ensure_spirit_exists,
+    badges::award_badges,
+    trending::{record_activity, ActivityKind},
+    WebError, WebResult,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct RatingPayload {
+    score: i64,
+}
+
+fn validate_rating(payload: &RatingPayload) -> WebResult<()> {
+    if !(0..=100).contains(&payload.score) {
+        return Err(WebError::InvalidInput(
+            "Scores must be between 0 and 100.".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn add_rating(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<RatingPayload>,
+) -> WebResult<Response> {
+    validate_rating(&payload)?;
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+
+    let result = sqlx::query_file!(
+        "sql/insert_rating.sql",
+        user.user_id,
+        spirit_id,
+        payload.score
+    )
+    .execute(&state.database)
+    .await;
+    match result {
+        Ok(_) => {
+            record_activity(&state.database, &spirit_id, ActivityKind::Rating).await;
+            award_badges(&state.database, &user.user_id).await;
+            Ok("".into_response())
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(WebError::Conflict(
+            "You have already rated this spirit.".into(),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn edit_rating(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<RatingPayload>,
+) -> WebResult<Response> {
+    validate_rating(&payload)?;
+
+    let result = sqlx::query_file!(
+        "sql/update_rating.sql",
+        user.user_id,
+        spirit_id,
+        payload.score
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+pub async fn delete_rating(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_rating.sql", user.user_id, spirit_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
@@ -0,0 +1,273 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{
+    permissions::{DistillersManage, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{WebError, WebResult};
+
+#[derive(Debug, Deserialize)]
+pub struct RegionParameter {
+    country: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DistillerRegionPayload {
+    region_id: Option<i64>,
+}
+
+/// Send both as `null` to clear a distillery's location.
+#[derive(Debug, Deserialize)]
+pub struct DistillerLocationPayload {
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MapParameter {
+    /// `min_longitude,min_latitude,max_longitude,max_latitude`, as in GeoJSON. A minimum
+    /// longitude greater than the maximum wraps around the antimeridian.
+    bbox: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CountryResponse {
+    id: i64,
+    code: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RegionResponse {
+    id: i64,
+    name: String,
+    country_id: i64,
+    country: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DistillerResponse {
+    id: i64,
+    name: String,
+    region_id: Option<i64>,
+    region: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct DistillerFeatureCollection {
+    #[serde(rename = "type")]
+    typ: &'static str,
+    features: Vec<DistillerFeature>,
+}
+
+#[derive(Debug, Serialize)]
+struct DistillerFeature {
+    #[serde(rename = "type")]
+    typ: &'static str,
+    geometry: PointGeometry,
+    properties: DistillerProperties,
+}
+
+#[derive(Debug, Serialize)]
+struct PointGeometry {
+    #[serde(rename = "type")]
+    typ: &'static str,
+    /// Longitude first, as GeoJSON expects.
+    coordinates: [f64; 2],
+}
+
+#[derive(Debug, Serialize)]
+struct DistillerProperties {
+    id: i64,
+    name: String,
+    region: Option<String>,
+    spirit_count: i64,
+}
+
+fn validate_coordinates(latitude: f64, longitude: f64) -> WebResult<()> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(WebError::InvalidInput(format!(
+            "({}, {}) is not a valid latitude and longitude.",
+            latitude, longitude
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a GeoJSON-style bounding box into its minimum and maximum corners.
+fn parse_bbox(bbox: &str) -> WebResult<[f64; 4]> {
+    let invalid = || {
+        WebError::InvalidInput(format!(
+            "'{}' is not a min_longitude,min_latitude,max_longitude,max_latitude bounding box.",
+            bbox
+        ))
+    };
+    let values = bbox
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let [min_longitude, min_latitude, max_longitude, max_latitude] =
+        <[f64; 4]>::try_from(values).map_err(|_| invalid())?;
+    validate_coordinates(min_latitude, min_longitude)?;
+    validate_coordinates(max_latitude, max_longitude)?;
+    if min_latitude > max_latitude {
+        return Err(invalid());
+    }
+    Ok([min_longitude, min_latitude, max_longitude, max_latitude])
+}
+
+/// Returns [`WebError::InvalidInput`] when a region id is given that isn't in the reference data.
+pub async fn ensure_region_exists<'e, E>(executor: E, region_id: Option<i64>) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    let Some(region_id) = region_id else {
+        return Ok(());
+    };
+
+    sqlx::query_file!("sql/select_region_exists.sql", region_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| WebError::InvalidInput(format!("Unknown region '{}'.", region_id)))?;
+    Ok(())
+}
+
+pub async fn list_countries(State(state): State<WaterOfLifeState>) -> WebResult<Response> {
+    let countries = sqlx::query_file_as!(CountryResponse, "sql/select_countries.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&countries)?;
+    Ok(response.into_response())
+}
+
+pub async fn list_regions(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<RegionParameter>,
+) -> WebResult<Response> {
+    let regions = sqlx::query_file_as!(
+        RegionResponse,
+        "sql/select_regions.sql",
+        query_params.country
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&regions)?;
+    Ok(response.into_response())
+}
+
+pub async fn list_distillers(State(state): State<WaterOfLifeState>) -> WebResult<Response> {
+    let distillers = sqlx::query_file_as!(DistillerResponse, "sql/select_distillers.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&distillers)?;
+    Ok(response.into_response())
+}
+
+pub async fn set_distiller_region(
+    RequirePermission(_, _): RequirePermission<DistillersManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(distiller_id): Path<i64>,
+    Json(payload): Json<DistillerRegionPayload>,
+) -> WebResult<Response> {
+    ensure_region_exists(&state.database, payload.region_id).await?;
+
+    let result = sqlx::query_file!(
+        "sql/update_distiller_region.sql",
+        distiller_id,
+        payload.region_id
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+pub async fn set_distiller_location(
+    RequirePermission(_, _): RequirePermission<DistillersManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(distiller_id): Path<i64>,
+    Json(payload): Json<DistillerLocationPayload>,
+) -> WebResult<Response> {
+    match (payload.latitude, payload.longitude) {
+        (Some(latitude), Some(longitude)) => validate_coordinates(latitude, longitude)?,
+        (None, None) => {}
+        _ => {
+            return Err(WebError::InvalidInput(
+                "Latitude and longitude must be set together.".into(),
+            ))
+        }
+    }
+
+    let result = sqlx::query_file!(
+        "sql/update_distiller_location.sql",
+        distiller_id,
+        payload.latitude,
+        payload.longitude
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+/// Distilleries with a known location as a GeoJSON feature collection, limited to those
+/// inside `bbox` when one is given.
+pub async fn distiller_map(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<MapParameter>,
+) -> WebResult<Response> {
+    let bbox = query_params.bbox.as_deref().map(parse_bbox).transpose()?;
+    let [min_longitude, min_latitude, max_longitude, max_latitude] =
+        bbox.map_or([None; 4], |bbox| bbox.map(Some));
+
+    let features = sqlx::query_file!(
+        "sql/select_distiller_map.sql",
+        min_longitude,
+        min_latitude,
+        max_longitude,
+        max_latitude
+    )
+    .fetch_all(&state.database)
+    .await?
+    .into_iter()
+    .map(|row| DistillerFeature {
+        typ: "Feature",
+        geometry: PointGeometry {
+            typ: "Point",
+            coordinates: [row.longitude, row.latitude],
+        },
+        properties: DistillerProperties {
+            id: row.id,
+            name: row.name,
+            region: row.region,
+            spirit_count: row.spirit_count,
+        },
+    })
+    .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&DistillerFeatureCollection {
+        typ: "FeatureCollection",
+        features,
+    })?;
+    Ok(([(CONTENT_TYPE, "application/geo+json")], response).into_response())
+}
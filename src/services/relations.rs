@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{api::ensure_spirit_exists, WebError, WebResult};
+
+/// Another edition of the same expression, as listed in a spirit's details.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelatedRelease {
+    id: String,
+    name: String,
+    abv: f64,
+}
+
+/// Links two spirits as editions of each other. The link shows up on both spirits.
+pub async fn add_spirit_relation(
+    RequirePermission(user, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, related_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    if spirit_id == related_id {
+        return Err(WebError::InvalidInput(
+            "A spirit can't be related to itself.".into(),
+        ));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_exists(&mut *transaction, &spirit_id).await?;
+    ensure_spirit_exists(&mut *transaction, &related_id).await?;
+    let result = sqlx::query_file!(
+        "sql/insert_spirit_relation.sql",
+        spirit_id,
+        related_id,
+        user.user_id
+    )
+    .execute(&mut *transaction)
+    .await;
+    match result {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(WebError::Conflict(
+                "These spirits are already related.".into(),
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    }
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_spirit_relation(
+    RequirePermission(_, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, related_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_spirit_relation.sql", spirit_id, related_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
@@ -0,0 +1,287 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    json_web::User,
+    permissions::{ReleasesManage, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    notifications::notify,
+    validation::{is_valid_date, is_valid_month},
+    WebError, WebResult,
+};
+
+/// Watchers are notified once a release is this many days away.
+const RELEASE_NOTIFICATION_WINDOW_DAYS: i64 = 7;
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseMonthParameter {
+    month: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleasePayload {
+    name: String,
+    distiller: String,
+    expected_date: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    regions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseResponse {
+    id: i64,
+    name: String,
+    distiller: String,
+    expected_date: String,
+    description: String,
+    regions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReleaseIdsResponse {
+    ids: Vec<i64>,
+}
+
+fn validate_release(payload: &ReleasePayload) -> WebResult<()> {
+    if payload.name.trim().is_empty() {
+        return Err(WebError::InvalidInput("Release name is required.".into()));
+    }
+    if !is_valid_date(&payload.expected_date) {
+        return Err(WebError::InvalidInput(format!(
+            "Expected date '{}' is not formatted as YYYY-MM-DD.",
+            payload.expected_date
+        )));
+    }
+    Ok(())
+}
+
+async fn insert_release(
+    connection: &mut SqliteConnection,
+    payload: &ReleasePayload,
+) -> sqlx::Result<i64> {
+    let id = sqlx::query_file!(
+        "sql/insert_release.sql",
+        payload.name,
+        payload.distiller,
+        payload.expected_date,
+        payload.description
+    )
+    .fetch_one(&mut *connection)
+    .await?
+    .id;
+
+    for region in &payload.regions {
+        sqlx::query_file!("sql/insert_release_region.sql", id, region)
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    Ok(id)
+}
+
+pub async fn list_releases(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<ReleaseMonthParameter>,
+) -> WebResult<Response> {
+    if let Some(month) = &query_params.month {
+        if !is_valid_month(month) {
+            return Err(WebError::InvalidInput(format!(
+                "Month '{}' is not formatted as YYYY-MM.",
+                month
+            )));
+        }
+    }
+
+    let releases = sqlx::query_file!("sql/select_releases.sql", query_params.month)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| ReleaseResponse {
+            id: row.id,
+            name: row.name,
+            distiller: row.distiller,
+            expected_date: row.expected_date,
+            description: row.description,
+            regions: row.regions.0,
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&releases)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_release(
+    RequirePermission(_, _): RequirePermission<ReleasesManage>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<ReleasePayload>,
+) -> WebResult<Response> {
+    validate_release(&payload)?;
+
+    let mut transaction = state.repositories.begin().await?;
+    let id = insert_release(&mut transaction, &payload).await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&ReleaseIdsResponse { ids: vec![id] })?;
+    Ok(response.into_response())
+}
+
+/// Imports a batch of releases atomically, rejecting the whole batch if any entry is invalid.
+pub async fn import_releases(
+    RequirePermission(_, _): RequirePermission<ReleasesManage>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<Vec<ReleasePayload>>,
+) -> WebResult<Response> {
+    for release in &payload {
+        validate_release(release)?;
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let mut ids = Vec::with_capacity(payload.len());
+    for release in &payload {
+        ids.push(insert_release(&mut transaction, release).await?);
+    }
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&ReleaseIdsResponse { ids })?;
+    Ok(response.into_response())
+}
+
+pub async fn watch_release(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(release_id): Path<i64>,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/select_release_exists.sql", release_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    sqlx::query_file!("sql/insert_release_watcher.sql", release_id, user.user_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+pub async fn unwatch_release(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(release_id): Path<i64>,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/delete_release_watcher.sql", release_id, user.user_id)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+/// Notifies watchers of releases that are about to drop, once per release. Returns how many
+/// notifications were sent.
+pub async fn notify_due_releases(database: &SqlitePool) -> sqlx::Result<usize> {
+    let due = sqlx::query_file!(
+        "sql/select_due_release_watchers.sql",
+        RELEASE_NOTIFICATION_WINDOW_DAYS
+    )
+    .fetch_all(database)
+    .await?;
+
+    let count = due.len();
+    for watcher in due {
+        let mut transaction = database.begin().await?;
+        notify(
+            &mut *transaction,
+            &watcher.user_id,
+            &format!(
+                "{} is expected to release on {}.",
+                watcher.name, watcher.expected_date
+            ),
+        )
+        .await?;
+        sqlx::query_file!(
+            "sql/update_release_watcher_notified.sql",
+            watcher.release_id,
+            watcher.user_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+    }
+
+    Ok(count)
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::testing;
+
+    #[tokio::test]
+    async fn release_regions_round_trip_through_the_listing() {
+        let app = testing::app().await;
+        let admin = testing::create_admin(&app.state.database).await;
+        let cookie = testing::auth_cookie(&app.state, &admin);
+        let releases = [
+            ("Harbor Light 12", vec!["Campbeltown, Scotland", "Islay"]),
+            ("Mountain Rye", vec![]),
+        ];
+        for (name, regions) in &releases {
+            let payload = serde_json::json!({
+                "name": name,
+                "distiller": "Harbor",
+                "expected_date": "2031-05-01",
+                "regions": regions,
+            });
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("/api/releases")
+                .header(header::COOKIE, &cookie)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap();
+            let response = app.router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let request = Request::get("/api/releases?month=2031-05")
+            .header(header::COOKIE, &cookie)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        for (name, regions) in &releases {
+            let release = listed
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|release| release["name"] == *name)
+                .unwrap();
+            let mut listed_regions = release["regions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|region| region.as_str().unwrap())
+                .collect::<Vec<_>>();
+            listed_regions.sort();
+            assert_eq!(&listed_regions, regions);
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqliteConnection;
+
+use crate::{
+    json_web::User,
+    mailer::{queue_email, EmailTemplate},
+    permissions::{ModerationReview, RequirePermission},
+    proxy::ClientIp,
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{delete_spirit_record, ensure_spirit_exists},
+    audit::record_audit,
+    flavors::invalidate_flavor_cloud,
+    images::{delete_spirit_image_record, remove_image_files},
+    notifications::notify,
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+const MAX_REPORT_DETAIL_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTarget {
+    Spirit,
+    Review,
+    Image,
+}
+
+impl ReportTarget {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spirit => "spirit",
+            Self::Review => "review",
+            Self::Image => "image",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "spirit" => Some(Self::Spirit),
+            "review" => Some(Self::Review),
+            "image" => Some(Self::Image),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Offensive,
+    Inaccurate,
+    Duplicate,
+    Copyright,
+    Other,
+}
+
+impl ReportReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spam => "spam",
+            Self::Offensive => "offensive",
+            Self::Inaccurate => "inaccurate",
+            Self::Duplicate => "duplicate",
+            Self::Copyright => "copyright",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// What an admin decided to do about the reported content.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportAction {
+    /// Leave the content alone.
+    Dismiss,
+    /// Take the content down: reviews are hidden, images deleted and spirits soft-deleted.
+    Hide,
+    /// Leave the content up but send its author a notification.
+    Warn,
+}
+
+impl ReportAction {
+    /// The status the resolved reports are left in.
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Dismiss => "dismissed",
+            Self::Hide => "hidden",
+            Self::Warn => "warned",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    #[default]
+    Open,
+    Dismissed,
+    Hidden,
+    Warned,
+}
+
+impl ReportStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Dismissed => "dismissed",
+            Self::Hidden => "hidden",
+            Self::Warned => "warned",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportPayload {
+    reason: ReportReason,
+    #[serde(default)]
+    detail: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportParameter {
+    #[serde(default)]
+    status: ReportStatus,
+    #[serde(rename = "type")]
+    target_type: Option<ReportTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolvePayload {
+    action: ReportAction,
+    /// Shown to the author when warning them, and kept with the resolved reports.
+    #[serde(default)]
+    note: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportResponse {
+    id: i64,
+    target_type: String,
+    target_id: String,
+    reporter_id: String,
+    reason: String,
+    detail: String,
+    status: String,
+    resolved_by: Option<String>,
+    resolution_note: String,
+    resolved_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResolveResponse {
+    resolved: u64,
+}
+
+/// Work left over from resolving reports that can only happen once the transaction commits.
+#[derive(Debug, Default)]
+pub struct ResolvedReports {
+    count: u64,
+    /// A review was hidden from this spirit, so its flavor cloud is stale.
+    stale_cloud: Option<String>,
+    /// An image was deleted and its files can go.
+    removed_image: Option<String>,
+}
+
+impl ResolvedReports {
+    pub async fn clean_up(&self, state: &WaterOfLifeState) {
+        if let Some(spirit_id) = &self.stale_cloud {
+            invalidate_flavor_cloud(state, spirit_id).await;
+        }
+        if let Some(image_id) = &self.removed_image {
+            remove_image_files(state, image_id).await;
+        }
+    }
+}
+
+async fn file_report(
+    state: &WaterOfLifeState,
+    user: &User,
+    target: ReportTarget,
+    target_id: &str,
+    payload: ReportPayload,
+) -> WebResult<Response> {
+    let detail = payload.detail.trim();
+    if detail.chars().count() > MAX_REPORT_DETAIL_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Report details must be at most {} characters.",
+            MAX_REPORT_DETAIL_LENGTH
+        )));
+    }
+
+    let target_type = target.as_str();
+    let reason = payload.reason.as_str();
+    let result = sqlx::query_file!(
+        "sql/insert_report.sql",
+        target_type,
+        target_id,
+        user.user_id,
+        reason,
+        detail
+    )
+    .fetch_one(&state.database)
+    .await;
+    let id = match result {
+        Ok(row) => row.id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(WebError::Conflict(format!(
+                "You have already reported this {}.",
+                target_type
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    tracing::info!(
+        "{} {} reported by {} as {}",
+        target_type,
+        target_id,
+        user.user_id,
+        reason
+    );
+
+    let response = serde_json::to_string(&json!({ "id": id }))?;
+    Ok(response.into_response())
+}
+
+pub async fn report_spirit(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<ReportPayload>,
+) -> WebResult<Response> {
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+    file_report(&state, &user, ReportTarget::Spirit, &spirit_id, payload).await
+}
+
+pub async fn report_review(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(review_id): Path<i64>,
+    Json(payload): Json<ReportPayload>,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/select_review_exists.sql", review_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let review_id = review_id.to_string();
+    file_report(&state, &user, ReportTarget::Review, &review_id, payload).await
+}
+
+pub async fn report_spirit_image(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, image_id)): Path<(String, String)>,
+    Json(payload): Json<ReportPayload>,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/select_spirit_image_owner.sql", image_id, spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    file_report(&state, &user, ReportTarget::Image, &image_id, payload).await
+}
+
+/// Takes reported content down, returning what still needs cleaning up after commit.
+async fn hide_content(
+    connection: &mut SqliteConnection,
+    admin: &User,
+    target: ReportTarget,
+    target_id: &str,
+) -> WebResult<ResolvedReports> {
+    let mut resolved = ResolvedReports::default();
+    match target {
+        ReportTarget::Spirit => {
+            delete_spirit_record(connection, target_id, &admin.user_id).await?;
+        }
+        ReportTarget::Review => {
+            let review_id = target_id.parse::<i64>().map_err(|_| WebError::NotFound)?;
+            let spirit_id = sqlx::query_file!("sql/update_review_hidden.sql", review_id)
+                .fetch_optional(&mut *connection)
+                .await?
+                .ok_or(WebError::NotFound)?
+                .spirit_id;
+            resolved.stale_cloud = Some(spirit_id);
+        }
+        ReportTarget::Image => {
+            let spirit_id = sqlx::query_file!("sql/select_spirit_image_spirit.sql", target_id)
+                .fetch_optional(&mut *connection)
+                .await?
+                .ok_or(WebError::NotFound)?
+                .spirit_id;
+            delete_spirit_image_record(connection, &spirit_id, target_id).await?;
+            resolved.removed_image = Some(target_id.to_owned());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Carries out `action` against the content and closes every open report on it, recording the
+/// decision in the audit log. Call [`ResolvedReports::clean_up`] after committing.
+pub async fn resolve_reports(
+    connection: &mut SqliteConnection,
+    admin: &User,
+    client_ip: Option<IpAddr>,
+    target: ReportTarget,
+    target_id: &str,
+    action: ReportAction,
+    note: &str,
+) -> WebResult<ResolvedReports> {
+    let target_type = target.as_str();
+    let mut resolved = match action {
+        ReportAction::Dismiss => ResolvedReports::default(),
+        ReportAction::Hide => hide_content(connection, admin, target, target_id).await?,
+        ReportAction::Warn => {
+            let author_id = sqlx::query_file!(
+                "sql/select_report_target_author.sql",
+                target_type,
+                target_id
+            )
+            .fetch_one(&mut *connection)
+            .await?
+            .author_id
+            .ok_or_else(|| {
+                WebError::InvalidInput(format!("This {} has no known author to warn.", target_type))
+            })?;
+            let mut message = format!("An admin reviewed a report about your {}.", target_type);
+            if !note.is_empty() {
+                message = format!("{} {}", message, note);
+            }
+            notify(&mut *connection, &author_id, &message).await?;
+            queue_email(
+                &mut *connection,
+                &author_id,
+                &EmailTemplate::ContentWarning { target_type, note },
+            )
+            .await?;
+            ResolvedReports::default()
+        }
+    };
+
+    let status = action.status();
+    resolved.count = sqlx::query_file!(
+        "sql/update_reports_resolved.sql",
+        target_type,
+        target_id,
+        status,
+        admin.user_id,
+        note
+    )
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+    record_audit(
+        &mut *connection,
+        Some(&admin.user_id),
+        client_ip,
+        "report_resolved",
+        &json!({
+            "target_type": target_type,
+            "target_id": target_id,
+            "action": status,
+            "note": note,
+            "reports": resolved.count,
+        }),
+    )
+    .await?;
+    Ok(resolved)
+}
+
+/// The moderation queue, oldest first. Defaults to open reports of every kind.
+pub async fn list_reports(
+    _: RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<ReportParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let status = query_params.status.as_str();
+    let target_type = query_params.target_type.map(|target| target.as_str());
+    let (limit, offset) = (page.limit(), page.offset());
+    let reports = sqlx::query_file_as!(
+        ReportResponse,
+        "sql/select_reports.sql",
+        status,
+        target_type,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_report_count.sql", status, target_type)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(reports, &page, total))?;
+    Ok(response.into_response())
+}
+
+/// Resolves a report along with every other open report on the same content.
+pub async fn resolve_report(
+    RequirePermission(user, _): RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Path(report_id): Path<i64>,
+    Json(payload): Json<ResolvePayload>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let report = sqlx::query_file!("sql/select_report_target.sql", report_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    if report.status != ReportStatus::Open.as_str() {
+        return Err(WebError::Conflict(
+            "This report has already been resolved.".into(),
+        ));
+    }
+    let target = ReportTarget::parse(&report.target_type).ok_or(WebError::NotFound)?;
+    let resolved = resolve_reports(
+        &mut transaction,
+        &user,
+        client_ip,
+        target,
+        &report.target_id,
+        payload.action,
+        payload.note.trim(),
+    )
+    .await?;
+    transaction.commit().await?;
+    resolved.clean_up(&state).await;
+
+    let response = serde_json::to_string(&ResolveResponse {
+        resolved: resolved.count,
+    })?;
+    Ok(response.into_response())
+}
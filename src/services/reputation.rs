@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+
+use crate::WaterOfLifeState;
+
+use super::WebResult;
+
+/// Users at or above this score skip the moderation queue.
+const TRUSTED_REPUTATION: i64 = 50;
+
+const COMPLETED_SWAP_POINTS: f64 = 10.0;
+/// Points per completed swap for every star the average rating sits above (or below) 3.
+const SWAP_RATING_POINTS: f64 = 2.0;
+const APPROVED_SUBMISSION_POINTS: i64 = 5;
+const REJECTED_SUBMISSION_PENALTY: i64 = 10;
+const HIDDEN_MESSAGE_PENALTY: i64 = 25;
+const HIDDEN_REVIEW_PENALTY: i64 = 25;
+/// Points for a review record that's all helpful votes. All unhelpful costs as much, and an
+/// even split is worth nothing.
+const HELPFUL_RATIO_POINTS: f64 = 40.0;
+/// With fewer votes than this across a user's reviews, the ratio isn't counted.
+const MIN_REVIEW_VOTES: i64 = 5;
+
+#[derive(Debug, Serialize)]
+pub struct ReputationInputs {
+    completed_swaps: i64,
+    average_swap_rating: Option<f64>,
+    approved_submissions: i64,
+    rejected_submissions: i64,
+    hidden_messages: i64,
+    hidden_reviews: i64,
+    review_votes: i64,
+    helpful_ratio: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Reputation {
+    pub score: i64,
+    pub trusted: bool,
+    #[serde(flatten)]
+    inputs: ReputationInputs,
+}
+
+impl From<ReputationInputs> for Reputation {
+    fn from(inputs: ReputationInputs) -> Self {
+        let average_rating = inputs.average_swap_rating.unwrap_or(3.0);
+        let swaps = inputs.completed_swaps as f64
+            * (COMPLETED_SWAP_POINTS + (average_rating - 3.0) * SWAP_RATING_POINTS);
+        let reviews = match inputs.helpful_ratio {
+            Some(ratio) if inputs.review_votes >= MIN_REVIEW_VOTES => {
+                (ratio - 0.5) * 2.0 * HELPFUL_RATIO_POINTS
+            }
+            _ => 0.0,
+        };
+        let score = swaps.round() as i64
+            + reviews.round() as i64
+            + inputs.approved_submissions * APPROVED_SUBMISSION_POINTS
+            - inputs.rejected_submissions * REJECTED_SUBMISSION_PENALTY
+            - inputs.hidden_messages * HIDDEN_MESSAGE_PENALTY
+            - inputs.hidden_reviews * HIDDEN_REVIEW_PENALTY;
+
+        Self {
+            score,
+            trusted: score >= TRUSTED_REPUTATION,
+            inputs,
+        }
+    }
+}
+
+/// Computes a user's reputation from their swaps, submissions, review votes and moderation
+/// history.
+pub async fn user_reputation<'e, E>(executor: E, user_id: &str) -> sqlx::Result<Reputation>
+where
+    E: SqliteExecutor<'e>,
+{
+    let inputs = sqlx::query_file_as!(ReputationInputs, "sql/select_user_reputation.sql", user_id)
+        .fetch_one(executor)
+        .await?;
+    Ok(inputs.into())
+}
+
+pub async fn get_reputation(
+    State(state): State<WaterOfLifeState>,
+    Path(user_id): Path<String>,
+) -> WebResult<Response> {
+    let reputation = user_reputation(&state.database, &user_id).await?;
+
+    let response = serde_json::to_string(&reputation)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,343 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    json_web::User,
+    permissions::{ModerationReview, RequirePermission},
+    proxy::ClientIp,
+    repositories::RepositoryError,
+    WaterOfLifeState,
+};
+
+use super::{
+    api::ensure_spirit_exists,
+    badges::award_badges,
+    flavors::invalidate_flavor_cloud,
+    pagination::{Page, PageParameter},
+    reports::{resolve_reports, ReportAction, ReportTarget},
+    webhooks::{queue_webhook_event, WebhookEvent},
+    WebError, WebResult,
+};
+
+const MAX_REVIEW_LENGTH: usize = 5000;
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewPayload {
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HelpfulPayload {
+    helpful: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewResponse {
+    id: i64,
+    user_id: String,
+    username: Option<String>,
+    avatar_url: Option<String>,
+    score: Option<i64>,
+    body: String,
+    helpful_votes: i64,
+    votes: i64,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewIdResponse {
+    id: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ReviewReportResponse {
+    id: i64,
+    review_id: i64,
+    spirit_id: String,
+    user_id: String,
+    body: String,
+    reporter_id: String,
+    reason: String,
+    detail: String,
+    created_at: String,
+}
+
+fn validate_review(payload: &ReviewPayload) -> WebResult<()> {
+    if payload.body.trim().is_empty() {
+        return Err(WebError::InvalidInput("Reviews cannot be empty.".into()));
+    }
+    if payload.body.chars().count() > MAX_REVIEW_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Reviews are limited to {} characters.",
+            MAX_REVIEW_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+pub async fn list_reviews(
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    query_params: PageParameter,
+) -> WebResult<Response> {
+    ensure_spirit_exists(&state.database, &spirit_id).await?;
+
+    let (limit, offset) = (query_params.limit(), query_params.offset());
+    let reviews = sqlx::query_file_as!(
+        ReviewResponse,
+        "sql/select_reviews.sql",
+        spirit_id,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_review_count.sql", spirit_id)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(reviews, &query_params, total))?;
+    Ok(response.into_response())
+}
+
+/// Records whether the caller found someone else's review helpful, replacing any earlier vote.
+/// The share of helpful votes counts towards the author's reputation.
+pub async fn vote_review_helpful(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(review_id): Path<i64>,
+    Json(payload): Json<HelpfulPayload>,
+) -> WebResult<Response> {
+    let author = sqlx::query_file!("sql/select_review_author.sql", review_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .user_id;
+    if author == user.user_id {
+        return Err(WebError::InvalidInput(
+            "You can't vote on your own review.".into(),
+        ));
+    }
+
+    sqlx::query_file!(
+        "sql/upsert_review_vote.sql",
+        review_id,
+        user.user_id,
+        payload.helpful
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok("".into_response())
+}
+
+pub async fn add_review(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<ReviewPayload>,
+) -> WebResult<Response> {
+    validate_review(&payload)?;
+
+    let mut transaction = state.repositories.begin().await?;
+    ensure_spirit_exists(&mut *transaction, &spirit_id).await?;
+    let result = state
+        .repositories
+        .reviews
+        .insert(&mut transaction, &user.user_id, &spirit_id, &payload.body)
+        .await;
+    let id = match result {
+        Ok(id) => id,
+        Err(RepositoryError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            return Err(WebError::Conflict(
+                "You have already reviewed this spirit.".into(),
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    queue_webhook_event(
+        &mut *transaction,
+        WebhookEvent::ReviewCreated,
+        &json!({
+            "id": id,
+            "spirit_id": spirit_id,
+            "user_id": user.user_id,
+            "body": payload.body,
+        }),
+    )
+    .await?;
+    transaction.commit().await?;
+    invalidate_flavor_cloud(&state, &spirit_id).await;
+
+    award_badges(&state.database, &user.user_id).await;
+
+    let response = serde_json::to_string(&ReviewIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn edit_review(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<ReviewPayload>,
+) -> WebResult<Response> {
+    validate_review(&payload)?;
+
+    let result = sqlx::query_file!(
+        "sql/update_review.sql",
+        user.user_id,
+        spirit_id,
+        payload.body
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    invalidate_flavor_cloud(&state, &spirit_id).await;
+
+    Ok("".into_response())
+}
+
+pub async fn delete_review(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_review.sql", user.user_id, spirit_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    invalidate_flavor_cloud(&state, &spirit_id).await;
+
+    Ok("".into_response())
+}
+
+pub async fn list_review_reports(
+    _: RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let reports = sqlx::query_file_as!(ReviewReportResponse, "sql/select_review_reports.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&reports)?;
+    Ok(response.into_response())
+}
+
+/// Hides a reported review from listings and resolves its reports.
+pub async fn hide_review(
+    RequirePermission(user, _): RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Path(review_id): Path<i64>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let resolved = resolve_reports(
+        &mut transaction,
+        &user,
+        client_ip,
+        ReportTarget::Review,
+        &review_id.to_string(),
+        ReportAction::Hide,
+        "",
+    )
+    .await?;
+    transaction.commit().await?;
+    resolved.clean_up(&state).await;
+
+    Ok("".into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{services::APP_USER_ROLE, testing};
+
+    use super::*;
+
+    async fn vote(app: &testing::TestApp, voter: &User, review_id: i64, helpful: bool) -> Response {
+        let request = Request::post(format!("/api/reviews/{}/helpful", review_id))
+            .header(header::COOKIE, testing::auth_cookie(&app.state, voter))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "helpful": helpful }).to_string()))
+            .unwrap();
+        app.router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn reputation(app: &testing::TestApp, user: &User) -> serde_json::Value {
+        let request = Request::get(format!("/api/users/{}/reputation", user.user_id))
+            .header(header::COOKIE, testing::auth_cookie(&app.state, user))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn authors_cannot_vote_on_their_own_reviews() {
+        let app = testing::app().await;
+        let author = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        let review_id =
+            testing::create_review(&app.state.database, &author, &spirit_id, "Lovely").await;
+
+        let response = vote(&app, &author, review_id, true).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = vote(&app, &author, review_id + 1, true).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn helpful_votes_count_towards_the_authors_reputation() {
+        let app = testing::app().await;
+        let author = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        let review_id =
+            testing::create_review(&app.state.database, &author, &spirit_id, "Lovely").await;
+
+        let mut voters = Vec::new();
+        for _ in 0..5 {
+            voters.push(testing::create_user(&app.state.database, APP_USER_ROLE).await);
+        }
+        // Too few votes to count.
+        for voter in &voters[..4] {
+            assert_eq!(
+                vote(&app, voter, review_id, true).await.status(),
+                StatusCode::OK
+            );
+        }
+        assert_eq!(reputation(&app, &author).await["score"], 0);
+
+        // Changing a vote replaces it rather than adding another.
+        assert_eq!(
+            vote(&app, &voters[0], review_id, false).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            vote(&app, &voters[4], review_id, true).await.status(),
+            StatusCode::OK
+        );
+        let reputation = reputation(&app, &author).await;
+        assert_eq!(reputation["review_votes"], 5);
+        assert_eq!(reputation["helpful_ratio"], 0.8);
+        assert_eq!(reputation["score"], 24);
+    }
+}
@@ -0,0 +1,290 @@
+use axum::{
+    extract::{Path, State},
+    http::header::ETAG,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{SqliteConnection, SqliteExecutor};
+
+use crate::{
+    json_web::AuthContext,
+    permissions::{RequirePermission, SpiritsManage},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{
+        find_visible_spirit, remove_spirit, update_spirit, version_etag, SpiritPayload,
+        SpiritResponse,
+    },
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum RevisionAction {
+    Create,
+    Edit,
+    Revert,
+    Merge,
+    Delete,
+}
+
+impl RevisionAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Edit => "edit",
+            Self::Revert => "revert",
+            Self::Merge => "merge",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// The editable fields of a spirit, as stored with each revision.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpiritSnapshot {
+    name: String,
+    description: String,
+    distiller: String,
+    typ: String,
+    region_id: Option<i64>,
+    abv: f64,
+    /// Missing from revisions recorded before deletions were.
+    #[serde(default)]
+    deleted: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct RevisionResponse {
+    id: i64,
+    user_id: String,
+    username: Option<String>,
+    action: String,
+    /// Each changed field mapped to `{"from": .., "to": ..}`.
+    changes: Value,
+    created_at: String,
+}
+
+pub async fn load_snapshot<'e, E>(executor: E, spirit_id: &str) -> WebResult<Option<SpiritSnapshot>>
+where
+    E: SqliteExecutor<'e>,
+{
+    Ok(
+        sqlx::query_file_as!(SpiritSnapshot, "sql/select_spirit_snapshot.sql", spirit_id)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+/// Lists the fields that differ between two snapshots. Everything counts as changed for a
+/// newly created spirit.
+fn diff_snapshots(before: Option<&SpiritSnapshot>, after: &SpiritSnapshot) -> WebResult<Value> {
+    let before = match before {
+        Some(before) => serde_json::to_value(before)?,
+        None => Value::Null,
+    };
+    let Value::Object(after) = serde_json::to_value(after)? else {
+        return Ok(Value::Object(Map::new()));
+    };
+
+    let changes = after
+        .into_iter()
+        .filter_map(|(field, to)| {
+            let from = before.get(&field).cloned().unwrap_or(Value::Null);
+            (from != to).then(|| (field, json!({ "from": from, "to": to })))
+        })
+        .collect::<Map<_, _>>();
+    Ok(Value::Object(changes))
+}
+
+/// Records the spirit's current state, and what changed from `before`, as a new revision.
+/// Call after the change has been written, inside the same transaction.
+pub async fn record_revision(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    user_id: &str,
+    action: RevisionAction,
+    before: Option<&SpiritSnapshot>,
+) -> WebResult<()> {
+    let after = load_snapshot(&mut *connection, spirit_id)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let changes = diff_snapshots(before, &after)?;
+    insert_revision(connection, spirit_id, user_id, action, &changes, &after).await
+}
+
+/// Records a merge against both spirits: the kept one gains a `merged_from` change and the
+/// duplicate a `merged_into` change, each pointing at the other.
+pub async fn record_merge(
+    connection: &mut SqliteConnection,
+    keep_id: &str,
+    dup_id: &str,
+    user_id: &str,
+) -> WebResult<()> {
+    for (spirit_id, field, other_id) in [
+        (keep_id, "merged_from", dup_id),
+        (dup_id, "merged_into", keep_id),
+    ] {
+        let snapshot = load_snapshot(&mut *connection, spirit_id)
+            .await?
+            .ok_or(WebError::NotFound)?;
+        let changes = json!({ field: { "from": null, "to": other_id } });
+        insert_revision(
+            connection,
+            spirit_id,
+            user_id,
+            RevisionAction::Merge,
+            &changes,
+            &snapshot,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn insert_revision(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    user_id: &str,
+    action: RevisionAction,
+    changes: &Value,
+    snapshot: &SpiritSnapshot,
+) -> WebResult<()> {
+    let changes = serde_json::to_string(changes)?;
+    let snapshot = serde_json::to_string(snapshot)?;
+    let action = action.as_str();
+
+    sqlx::query_file!(
+        "sql/insert_spirit_revision.sql",
+        spirit_id,
+        user_id,
+        action,
+        changes,
+        snapshot
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+/// Lists a spirit's revisions, newest first. Users who can manage spirits also see the history
+/// of deleted ones, so they know which revision to revert to.
+pub async fn spirit_history(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    query_params: PageParameter,
+) -> WebResult<Response> {
+    if context.permissions.has::<SpiritsManage>() {
+        sqlx::query_file!("sql/select_spirit_version.sql", spirit_id)
+            .fetch_optional(&state.database)
+            .await?
+            .ok_or(WebError::NotFound)?;
+    } else {
+        find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    }
+
+    let (limit, offset) = (query_params.limit(), query_params.offset());
+    let revisions = sqlx::query_file!("sql/select_spirit_revisions.sql", spirit_id, limit, offset)
+        .fetch_all(&state.database)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(RevisionResponse {
+                id: row.id,
+                user_id: row.user_id,
+                username: row.username,
+                action: row.action,
+                changes: serde_json::from_str(&row.changes)?,
+                created_at: row.created_at,
+            })
+        })
+        .collect::<WebResult<Vec<_>>>()?;
+    let total = sqlx::query_file!("sql/select_spirit_revision_count.sql", spirit_id)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(revisions, &query_params, total))?;
+    Ok(response.into_response())
+}
+
+/// Restores a spirit to how it looked after the given revision, recorded as a new revision
+/// so the revert itself can be undone. Reverting a deleted spirit to a revision from before the
+/// deletion brings it back, and reverting to the deletion deletes it again.
+pub async fn revert_spirit(
+    RequirePermission(user, _): RequirePermission<SpiritsManage>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, revision_id)): Path<(String, i64)>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let snapshot = sqlx::query_file!(
+        "sql/select_spirit_revision_snapshot.sql",
+        revision_id,
+        spirit_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(WebError::NotFound)?
+    .snapshot;
+    let snapshot: SpiritSnapshot = serde_json::from_str(&snapshot)?;
+    let current_version = sqlx::query_file!("sql/select_spirit_version.sql", spirit_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(WebError::NotFound)?
+        .version;
+    let before = load_snapshot(&mut *transaction, &spirit_id).await?;
+    let was_deleted = before.as_ref().is_some_and(|before| before.deleted);
+    if was_deleted && !snapshot.deleted {
+        sqlx::query_file!("sql/restore_spirit.sql", spirit_id)
+            .execute(&mut *transaction)
+            .await?;
+        // Filled in with the reverted fields by `update_spirit`.
+        sqlx::query_file!(
+            "sql/insert_spirit_fts.sql",
+            spirit_id,
+            snapshot.name,
+            snapshot.distiller,
+            snapshot.typ
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    let payload = SpiritPayload {
+        name: snapshot.name,
+        distiller: snapshot.distiller,
+        description: snapshot.description,
+        typ: snapshot.typ,
+        region_id: snapshot.region_id,
+        abv: snapshot.abv,
+        version: None,
+    };
+    let version = update_spirit(&mut transaction, &spirit_id, &payload, current_version)
+        .await?
+        .ok_or(WebError::Conflict(
+            "This spirit is deleted. Revert to a revision from before it was deleted.".into(),
+        ))?;
+    if snapshot.deleted {
+        remove_spirit(&mut transaction, &spirit_id).await?;
+    }
+    record_revision(
+        &mut transaction,
+        &spirit_id,
+        &user.user_id,
+        RevisionAction::Revert,
+        before.as_ref(),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&SpiritResponse {
+        id: spirit_id,
+        version,
+    })?;
+    Ok(([(ETAG, version_etag(version))], response).into_response())
+}
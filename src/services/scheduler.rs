@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    error::Error as _,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::{task::JoinSet, time::MissedTickBehavior};
+
+use crate::{
+    config::SchedulerConfig,
+    infra::StoreError,
+    mailer::{queue_due_digests, send_pending_emails},
+    permissions::{RequirePermission, SystemMonitor},
+    WaterOfLifeState,
+};
+
+use super::{
+    backups::create_backup,
+    images::sweep_orphaned_images,
+    oidc::{get_jwks, AuthenticationError},
+    releases::notify_due_releases,
+    trending::refresh_trending,
+    uploads::expire_stale_uploads,
+    WebError, WebResult,
+};
+
+/// Housekeeping run on a timer rather than in response to a request.
+#[derive(Debug, Clone, Copy)]
+enum Task {
+    SessionCleanup,
+    JwksRefresh,
+    OrphanedImages,
+    Trending,
+    Vacuum,
+    Backup,
+    StaleUploads,
+    ReleaseNotifications,
+    Emails,
+    Digests,
+}
+
+impl Task {
+    const ALL: [Self; 10] = [
+        Self::SessionCleanup,
+        Self::JwksRefresh,
+        Self::OrphanedImages,
+        Self::Trending,
+        Self::Vacuum,
+        Self::Backup,
+        Self::StaleUploads,
+        Self::ReleaseNotifications,
+        Self::Emails,
+        Self::Digests,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::SessionCleanup => "session_cleanup",
+            Self::JwksRefresh => "jwks_refresh",
+            Self::OrphanedImages => "orphaned_images",
+            Self::Trending => "trending",
+            Self::Vacuum => "vacuum",
+            Self::Backup => "backup",
+            Self::StaleUploads => "stale_uploads",
+            Self::ReleaseNotifications => "release_notifications",
+            Self::Emails => "emails",
+            Self::Digests => "digests",
+        }
+    }
+
+    /// How often the task runs, or `None` if it's turned off.
+    fn interval(&self, config: &SchedulerConfig) -> Option<Duration> {
+        let seconds = match self {
+            Self::SessionCleanup => config.session_cleanup_interval_seconds,
+            Self::JwksRefresh => config.jwks_refresh_interval_seconds,
+            Self::OrphanedImages => config.orphaned_images_interval_seconds,
+            Self::Trending => config.trending_interval_seconds,
+            Self::Vacuum => config.vacuum_interval_seconds,
+            Self::Backup => config.backup_interval_seconds,
+            Self::StaleUploads => config.stale_uploads_interval_seconds,
+            Self::ReleaseNotifications => config.release_notifications_interval_seconds,
+            Self::Emails => config.email_interval_seconds,
+            Self::Digests => config.digest_interval_seconds,
+        };
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Runs the task once, returning a short summary of what it did.
+    async fn run(self, state: &WaterOfLifeState) -> Result<String, TaskError> {
+        Ok(match self {
+            Self::SessionCleanup => {
+                let removed = state.stores.sessions.remove_expired().await?;
+                format!("Removed {} expired entries", removed)
+            }
+            Self::JwksRefresh => {
+                let jwks = get_jwks(&state.oidc_configuration.jwks_uri, &state.client).await?;
+                let keys = jwks.len();
+                *state.jwks.write().unwrap_or_else(|e| e.into_inner()) = jwks;
+                format!("Loaded {} keys", keys)
+            }
+            Self::OrphanedImages => {
+                let sweep = sweep_orphaned_images(&state.database, &state.images).await?;
+                format!(
+                    "Removed {} files and {} records",
+                    sweep.removed_files.len(),
+                    sweep.removed_records.len()
+                )
+            }
+            Self::Trending => {
+                let ranked = refresh_trending(state).await?;
+                format!("Ranked {} spirits", ranked)
+            }
+            Self::Vacuum => {
+                sqlx::query_file!("sql/vacuum.sql")
+                    .execute(&state.database)
+                    .await?;
+                "Vacuumed the database".to_owned()
+            }
+            Self::Backup => {
+                let backup = create_backup(state).await?;
+                format!(
+                    "Wrote {} ({} bytes), removed {} old backups",
+                    backup.file,
+                    backup.size_bytes,
+                    backup.removed.len()
+                )
+            }
+            Self::StaleUploads => {
+                let expired = expire_stale_uploads(state).await?;
+                format!("Removed {} abandoned uploads", expired)
+            }
+            Self::ReleaseNotifications => {
+                let notified = notify_due_releases(&state.database).await?;
+                format!("Notified {} watchers", notified)
+            }
+            Self::Emails => {
+                let sent = send_pending_emails(&state.database, &state.mailer).await?;
+                format!("Sent {} emails", sent)
+            }
+            Self::Digests => {
+                let queued = queue_due_digests(&state.database).await?;
+                format!("Queued digests for {} users", queued)
+            }
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+enum TaskError {
+    #[error("Error querying database")]
+    Database(#[from] sqlx::Error),
+    #[error("Error accessing the session store")]
+    Store(#[from] StoreError),
+    #[error("Error fetching signing keys")]
+    Authentication(#[from] AuthenticationError),
+    #[error(transparent)]
+    Web(#[from] WebError),
+    #[error("Task panicked")]
+    Panicked,
+}
+
+/// What happened the last time a task ran.
+#[derive(Debug, Clone, Serialize)]
+struct TaskRun {
+    /// Seconds since the epoch.
+    started_at: u64,
+    duration_ms: u64,
+    succeeded: bool,
+    /// A summary of what the task did, or why it failed.
+    result: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskResponse {
+    name: &'static str,
+    /// Unset when the task is turned off.
+    interval_seconds: Option<u64>,
+    last_run: Option<TaskRun>,
+}
+
+/// The latest run of each scheduled task, kept in memory for the admin endpoint.
+#[derive(Clone, Default)]
+pub struct SchedulerStatus {
+    runs: Arc<Mutex<HashMap<&'static str, TaskRun>>>,
+}
+
+impl SchedulerStatus {
+    fn record(&self, task: Task, run: TaskRun) {
+        self.runs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(task.as_str(), run);
+    }
+
+    fn last_run(&self, task: Task) -> Option<TaskRun> {
+        self.runs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(task.as_str())
+            .cloned()
+    }
+}
+
+/// An error and everything that caused it, since the outermost message alone rarely says
+/// what went wrong.
+fn describe(error: &TaskError) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(": ");
+        description.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    description
+}
+
+async fn run_task(state: WaterOfLifeState, task: Task, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let started = Instant::now();
+        // Run as its own task so a panic is recorded as a failure instead of stopping the task
+        // from ever running again.
+        let task_state = state.clone();
+        let result = tokio::spawn(async move { task.run(&task_state).await })
+            .await
+            .unwrap_or(Err(TaskError::Panicked));
+
+        let (succeeded, result) = match result {
+            Ok(summary) => {
+                tracing::info!("{}: {}", task.as_str(), summary);
+                (true, summary)
+            }
+            Err(e) => {
+                let error = describe(&e);
+                tracing::warn!("{}: {}", task.as_str(), error);
+                (false, error)
+            }
+        };
+        state.scheduler.record(
+            task,
+            TaskRun {
+                started_at,
+                duration_ms: started.elapsed().as_millis() as u64,
+                succeeded,
+                result,
+            },
+        );
+    }
+}
+
+/// Runs each enabled task once at startup and then every `scheduler.*_interval_seconds`.
+pub async fn scheduler(state: WaterOfLifeState) {
+    let mut tasks = JoinSet::new();
+    for task in Task::ALL {
+        if let Some(period) = task.interval(&state.config.scheduler) {
+            tasks.spawn(run_task(state.clone(), task, period));
+        }
+    }
+    while tasks.join_next().await.is_some() {}
+}
+
+/// Lists the scheduled tasks with how often they run and how their last run went.
+pub async fn scheduler_status(
+    RequirePermission(_, _): RequirePermission<SystemMonitor>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let tasks = Task::ALL
+        .into_iter()
+        .map(|task| TaskResponse {
+            name: task.as_str(),
+            interval_seconds: task
+                .interval(&state.config.scheduler)
+                .map(|interval| interval.as_secs()),
+            last_run: state.scheduler.last_run(task),
+        })
+        .collect::<Vec<_>>();
+
+    let response = serde_json::to_string(&tasks)?;
+    Ok(response.into_response())
+}
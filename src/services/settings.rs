@@ -0,0 +1,192 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use crate::{
+    permissions::{RequirePermission, SettingsManage},
+    proxy::ClientIp,
+    WaterOfLifeState,
+};
+
+use super::{
+    audit::record_audit,
+    pagination::{DEFAULT_PER_PAGE, MAX_PER_PAGE},
+    WebError, WebResult,
+};
+
+/// How long settings are served from memory. Changes made through this instance apply
+/// straight away, ones made through another instance within this long.
+const SETTINGS_CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_BANNER_LENGTH: usize = 500;
+
+/// Site-wide settings admins can change without a restart, unlike [`AppConfig`].
+///
+/// [`AppConfig`]: crate::config::AppConfig
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Let people sign in for the first time. Existing users can always sign in.
+    pub registration_open: bool,
+    /// Hold spirits added by users who aren't trusted yet for moderation. When off, every
+    /// submission is approved straight away.
+    pub moderation_required: bool,
+    /// How many items list endpoints return when `per_page` isn't given.
+    pub default_page_size: i64,
+    /// Shown across the top of the site, such as ahead of planned downtime.
+    pub maintenance_banner: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            registration_open: true,
+            moderation_required: true,
+            default_page_size: DEFAULT_PER_PAGE,
+            maintenance_banner: None,
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&mut self) -> WebResult<()> {
+        if !(1..=MAX_PER_PAGE).contains(&self.default_page_size) {
+            return Err(WebError::InvalidInput(format!(
+                "The default page size must be between 1 and {}.",
+                MAX_PER_PAGE
+            )));
+        }
+        // A blank banner is the same as none, so the frontend only has to check for null.
+        self.maintenance_banner = self
+            .maintenance_banner
+            .take()
+            .map(|banner| banner.trim().to_owned())
+            .filter(|banner| !banner.is_empty());
+        if self
+            .maintenance_banner
+            .as_ref()
+            .is_some_and(|banner| banner.chars().count() > MAX_BANNER_LENGTH)
+        {
+            return Err(WebError::InvalidInput(format!(
+                "The maintenance banner must be at most {} characters.",
+                MAX_BANNER_LENGTH
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reads the settings straight from the database, for use inside a transaction. Everywhere
+/// else should go through [`SettingsService::get`].
+pub async fn load_settings<'e, E>(executor: E) -> sqlx::Result<Settings>
+where
+    E: SqliteExecutor<'e>,
+{
+    let row = sqlx::query_file!("sql/select_settings.sql")
+        .fetch_one(executor)
+        .await?;
+    Ok(Settings {
+        registration_open: row.registration_open,
+        moderation_required: row.moderation_required,
+        default_page_size: row.default_page_size,
+        maintenance_banner: row.maintenance_banner,
+    })
+}
+
+/// The settings, cached in memory so the handlers that check them on every request don't
+/// each query the database.
+#[derive(Clone)]
+pub struct SettingsService {
+    database: SqlitePool,
+    cached: Arc<RwLock<Option<(Instant, Settings)>>>,
+}
+
+impl SettingsService {
+    pub fn new(database: SqlitePool) -> Self {
+        Self {
+            database,
+            cached: Arc::default(),
+        }
+    }
+
+    /// The current settings. If they can't be reloaded, the last ones loaded are kept, or the
+    /// defaults if none have been.
+    pub async fn get(&self) -> Settings {
+        let cached = self
+            .cached
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some((loaded_at, settings)) = &cached {
+            if loaded_at.elapsed() < SETTINGS_CACHE_TTL {
+                return settings.clone();
+            }
+        }
+
+        match load_settings(&self.database).await {
+            Ok(settings) => {
+                self.replace(settings.clone());
+                settings
+            }
+            Err(e) => {
+                tracing::warn!("Could not load settings: {}", e);
+                cached.map(|(_, settings)| settings).unwrap_or_default()
+            }
+        }
+    }
+
+    fn replace(&self, settings: Settings) {
+        *self.cached.write().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), settings));
+    }
+}
+
+pub async fn get_settings(
+    RequirePermission(_, _): RequirePermission<SettingsManage>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let settings = load_settings(&state.database).await?;
+    let response = serde_json::to_string(&settings)?;
+    Ok(response.into_response())
+}
+
+/// Replaces every setting. They take effect on this instance straight away.
+pub async fn set_settings(
+    RequirePermission(user, _): RequirePermission<SettingsManage>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Json(mut payload): Json<Settings>,
+) -> WebResult<Response> {
+    payload.validate()?;
+
+    let mut transaction = state.repositories.begin().await?;
+    sqlx::query_file!(
+        "sql/update_settings.sql",
+        payload.registration_open,
+        payload.moderation_required,
+        payload.default_page_size,
+        payload.maintenance_banner,
+        user.user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    record_audit(
+        &mut *transaction,
+        Some(&user.user_id),
+        client_ip,
+        "settings_updated",
+        &payload,
+    )
+    .await?;
+    transaction.commit().await?;
+    state.settings.replace(payload.clone());
+
+    let response = serde_json::to_string(&payload)?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Serialize;
+
+use crate::{
+    json_web::{sign_slug, verify_slug, AuthContext},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::find_visible_spirit,
+    images::{has_primary_image, signed_primary_image_url, ImageSize},
+    WebError, WebResult,
+};
+
+/// How long the image link in a shared page keeps working. Each visit gets a fresh one.
+const SHARED_IMAGE_URL_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Serialize)]
+struct ShareResponse {
+    slug: String,
+    url: String,
+}
+
+/// The read-only view of a spirit shown to anyone with a share link.
+#[derive(Debug, Serialize)]
+struct SharedSpiritResponse {
+    id: String,
+    name: String,
+    description: String,
+    distiller: String,
+    typ: String,
+    abv: f64,
+    region: Option<String>,
+    average_rating: Option<f64>,
+    rating_count: i64,
+    image_url: Option<String>,
+}
+
+/// Creates a link to a public, read-only page for an approved spirit. The slug is signed so
+/// it can't be altered to reach other spirits.
+pub async fn share_spirit(
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    find_visible_spirit(&*state.repositories.spirits, &context, &spirit_id).await?;
+    sqlx::query_file!("sql/select_shared_spirit.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or_else(|| WebError::InvalidInput("Only approved spirits can be shared.".into()))?;
+
+    let slug = sign_slug(&state.config.tokens.access_token_hmac_secret, &spirit_id);
+    let response = serde_json::to_string(&ShareResponse {
+        url: format!("/share/{}", slug),
+        slug,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Serves the page behind a share link. This route is public, so it only ever shows approved
+/// spirits and links the image with a signed URL.
+pub async fn get_shared_spirit(
+    State(state): State<WaterOfLifeState>,
+    Path(slug): Path<String>,
+) -> WebResult<Response> {
+    let spirit_id = verify_slug(&state.config.tokens.access_token_hmac_secret, &slug)
+        .ok_or(WebError::NotFound)?;
+    let spirit = sqlx::query_file!("sql/select_shared_spirit.sql", spirit_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let image_url = if has_primary_image(&state, spirit_id).await? {
+        let (url, _) = signed_primary_image_url(
+            &state,
+            spirit_id,
+            ImageSize::Medium,
+            SHARED_IMAGE_URL_LIFETIME,
+        );
+        Some(url)
+    } else {
+        None
+    };
+
+    let response = serde_json::to_string(&SharedSpiritResponse {
+        id: spirit.id,
+        name: spirit.name,
+        description: spirit.description,
+        distiller: spirit.distiller,
+        typ: spirit.typ,
+        abv: spirit.abv,
+        region: spirit.region,
+        average_rating: spirit.average_rating,
+        rating_count: spirit.rating_count,
+        image_url,
+    })?;
+    Ok(response.into_response())
+}
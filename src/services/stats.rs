@@ -0,0 +1,67 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Serialize;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    export::{csv_response, ExportFormat, ExportParameter},
+    pours::{favorite_poured_spirit, monthly_pours, FavoriteSpirit, MonthlyPours},
+    preferences::{load_preferences, VolumeUnit},
+    WebResult,
+};
+
+/// Counts over the user's collection and ratings. A spirit counts as tried once the user has
+/// rated, reviewed or poured it, or opened or finished a bottle of it.
+#[derive(Debug, Serialize)]
+struct CollectionTotals {
+    bottles_owned: i64,
+    bottles_finished: i64,
+    distilleries_tried: i64,
+    regions_tried: i64,
+    ratings_given: i64,
+    average_rating: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct UserStatsResponse {
+    #[serde(flatten)]
+    totals: CollectionTotals,
+    most_poured_spirit: Option<FavoriteSpirit>,
+    volume_unit: VolumeUnit,
+    /// Newest month first.
+    monthly_pours: Vec<MonthlyPours>,
+}
+
+/// The user's totals and monthly pours. As CSV, only the totals are sent, as a single row;
+/// the monthly pours are in the pour stats.
+pub async fn user_stats(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Query(export_params): Query<ExportParameter>,
+    headers: HeaderMap,
+) -> WebResult<Response> {
+    let totals = sqlx::query_file_as!(CollectionTotals, "sql/select_user_stats.sql", user.user_id)
+        .fetch_one(&state.database)
+        .await?;
+    if ExportFormat::negotiate(export_params.format, &headers) == ExportFormat::Csv {
+        return csv_response(&[totals]);
+    }
+    let volume_unit = load_preferences(&state.database, &user.user_id)
+        .await?
+        .volume_unit;
+    let monthly_pours = monthly_pours(&state.database, &user.user_id, volume_unit).await?;
+    let most_poured_spirit = favorite_poured_spirit(&state.database, &user.user_id).await?;
+
+    let response = serde_json::to_string(&UserStatsResponse {
+        totals,
+        most_poured_spirit,
+        volume_unit,
+        monthly_pours,
+    })?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,58 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::WaterOfLifeState;
+
+use super::{oidc::probe_identity_provider, WebResult};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ServiceStatus {
+    Ok,
+    Degraded,
+}
+
+#[derive(Debug, Serialize)]
+struct AuthStatus {
+    status: ServiceStatus,
+    /// Seconds since the epoch of the identity provider failure, if auth is degraded.
+    last_failure: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: ServiceStatus,
+    auth: AuthStatus,
+    /// Set by admins to warn of planned downtime and the like.
+    maintenance_banner: Option<String>,
+}
+
+/// Reports whether the app and its dependencies are healthy, along with any maintenance
+/// banner. Unauthenticated so the login page can warn users before sending them to an
+/// unreachable identity provider.
+pub async fn status(State(state): State<WaterOfLifeState>) -> WebResult<Response> {
+    // Re-check a degraded identity provider so the status recovers without a login attempt.
+    if state.idp_health.is_degraded() {
+        probe_identity_provider(&state).await;
+    }
+
+    let auth = match state.idp_health.last_failure() {
+        Some(last_failure) => AuthStatus {
+            status: ServiceStatus::Degraded,
+            last_failure: Some(last_failure),
+        },
+        None => AuthStatus {
+            status: ServiceStatus::Ok,
+            last_failure: None,
+        },
+    };
+    let response = serde_json::to_string(&StatusResponse {
+        status: auth.status,
+        auth,
+        maintenance_banner: state.settings.get().await.maintenance_banner,
+    })?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::SqliteConnection;
+
+use crate::{
+    json_web::User,
+    mailer::{queue_email, EmailTemplate},
+    permissions::{ModerationReview, Permissions, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    notifications::notify,
+    reputation::user_reputation,
+    settings::load_settings,
+    webhooks::{queue_spirit_event, WebhookEvent},
+    WebError, WebResult,
+};
+
+pub const SUBMISSION_APPROVED: &str = "approved";
+const SUBMISSION_PENDING: &str = "pending";
+const SUBMISSION_REJECTED: &str = "rejected";
+
+#[derive(Debug, Serialize)]
+struct SubmissionResponse {
+    spirit_id: String,
+    name: String,
+    distiller: String,
+    typ: String,
+    abv: f64,
+    user_id: String,
+    username: Option<String>,
+    created_at: String,
+}
+
+/// Records who submitted a spirit. Those who review submissions and trusted users are approved
+/// straight away, everyone else waits in the moderation queue unless the `moderation_required`
+/// setting is off.
+pub async fn record_submission(
+    connection: &mut SqliteConnection,
+    spirit_id: &str,
+    user: &User,
+    permissions: &Permissions,
+) -> sqlx::Result<&'static str> {
+    let status = if permissions.has::<ModerationReview>()
+        || !load_settings(&mut *connection).await?.moderation_required
+        || user_reputation(&mut *connection, &user.user_id)
+            .await?
+            .trusted
+    {
+        SUBMISSION_APPROVED
+    } else {
+        SUBMISSION_PENDING
+    };
+
+    sqlx::query_file!(
+        "sql/insert_spirit_submission.sql",
+        spirit_id,
+        user.user_id,
+        status
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(status)
+}
+
+pub async fn list_pending_submissions(
+    _: RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let submissions =
+        sqlx::query_file_as!(SubmissionResponse, "sql/select_pending_submissions.sql")
+            .fetch_all(&state.database)
+            .await?;
+
+    let response = serde_json::to_string(&submissions)?;
+    Ok(response.into_response())
+}
+
+async fn review_submission(
+    state: &WaterOfLifeState,
+    reviewer: &User,
+    spirit_id: &str,
+    status: &str,
+) -> WebResult<()> {
+    let mut transaction = state.repositories.begin().await?;
+    let submission = sqlx::query_file!(
+        "sql/update_submission_status.sql",
+        spirit_id,
+        status,
+        reviewer.user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(WebError::NotFound)?;
+
+    notify(
+        &mut *transaction,
+        &submission.user_id,
+        &format!("Your submission '{}' was {}.", submission.name, status),
+    )
+    .await?;
+    queue_email(
+        &mut *transaction,
+        &submission.user_id,
+        &EmailTemplate::SubmissionReviewed {
+            name: &submission.name,
+            status,
+        },
+    )
+    .await?;
+    if status == SUBMISSION_APPROVED {
+        queue_spirit_event(&mut transaction, WebhookEvent::SpiritCreated, spirit_id).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub async fn approve_submission(
+    RequirePermission(user, _): RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    review_submission(&state, &user, &spirit_id, SUBMISSION_APPROVED).await?;
+    Ok("".into_response())
+}
+
+pub async fn reject_submission(
+    RequirePermission(user, _): RequirePermission<ModerationReview>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    review_submission(&state, &user, &spirit_id, SUBMISSION_REJECTED).await?;
+    Ok("".into_response())
+}
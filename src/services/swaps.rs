@@ -0,0 +1,569 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{
+    api::ensure_spirit_exists,
+    messages::{create_conversation, read_conversation, send_message},
+    wishlist::notify_wishlist,
+    WebError, WebResult,
+};
+
+const MIN_SAMPLE_ML: i64 = 30;
+const MAX_SAMPLE_ML: i64 = 50;
+
+const SWAP_OPEN: &str = "open";
+const SWAP_MATCHED: &str = "matched";
+const SWAP_CLOSED: &str = "closed";
+const MATCH_PENDING: &str = "pending";
+const MATCH_COMPLETED: &str = "completed";
+
+#[derive(Debug, Deserialize)]
+pub struct SwapBoardParameter {
+    spirit_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwapPayload {
+    spirit_id: String,
+    volume_ml: i64,
+    #[serde(default)]
+    notes: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchPayload {
+    offer_id: i64,
+    request_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmationPayload {
+    rating: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessagePayload {
+    body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapListingResponse {
+    id: i64,
+    user_id: String,
+    username: Option<String>,
+    spirit_id: String,
+    spirit_name: String,
+    volume_ml: i64,
+    notes: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapIdResponse {
+    id: i64,
+}
+
+struct SwapListing {
+    id: i64,
+    user_id: String,
+    spirit_id: String,
+    status: String,
+}
+
+struct SwapMatch {
+    id: i64,
+    offer_id: i64,
+    request_id: i64,
+    status: String,
+    offerer_id: String,
+    requester_id: String,
+}
+
+impl SwapMatch {
+    fn is_participant(&self, user_id: &str) -> bool {
+        self.offerer_id == user_id || self.requester_id == user_id
+    }
+}
+
+fn validate_swap(payload: &SwapPayload) -> WebResult<()> {
+    if !(MIN_SAMPLE_ML..=MAX_SAMPLE_ML).contains(&payload.volume_ml) {
+        return Err(WebError::InvalidInput(format!(
+            "Samples must be between {}ml and {}ml.",
+            MIN_SAMPLE_ML, MAX_SAMPLE_ML
+        )));
+    }
+    Ok(())
+}
+
+/// Fetches one of the user's own offers, hiding everyone else's behind a 404.
+async fn find_own_offer<'e, E>(executor: E, offer_id: i64, user_id: &str) -> WebResult<SwapListing>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file_as!(SwapListing, "sql/select_swap_offer.sql", offer_id)
+        .fetch_optional(executor)
+        .await?
+        .filter(|offer| offer.user_id == user_id)
+        .ok_or(WebError::NotFound)
+}
+
+/// Fetches a match the user takes part in, hiding everyone else's matches behind a 404.
+async fn find_match<'e, E>(executor: E, match_id: i64, user_id: &str) -> WebResult<SwapMatch>
+where
+    E: SqliteExecutor<'e>,
+{
+    let swap_match = sqlx::query_file_as!(SwapMatch, "sql/select_swap_match.sql", match_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    if swap_match.is_participant(user_id) {
+        Ok(swap_match)
+    } else {
+        Err(WebError::NotFound)
+    }
+}
+
+pub async fn list_swap_offers(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<SwapBoardParameter>,
+) -> WebResult<Response> {
+    let offers = sqlx::query_file_as!(
+        SwapListingResponse,
+        "sql/select_open_swap_offers.sql",
+        query_params.spirit_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&offers)?;
+    Ok(response.into_response())
+}
+
+pub async fn list_swap_requests(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<SwapBoardParameter>,
+) -> WebResult<Response> {
+    let requests = sqlx::query_file_as!(
+        SwapListingResponse,
+        "sql/select_open_swap_requests.sql",
+        query_params.spirit_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&requests)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_swap_offer(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<SwapPayload>,
+) -> WebResult<Response> {
+    validate_swap(&payload)?;
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let mut transaction = state.repositories.begin().await?;
+    let id = sqlx::query_file!(
+        "sql/insert_swap_offer.sql",
+        user.user_id,
+        payload.spirit_id,
+        payload.volume_ml,
+        payload.notes
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    notify_wishlist(
+        &mut *transaction,
+        &payload.spirit_id,
+        &user.user_id,
+        "A bottle is now available to swap.",
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&SwapIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn add_swap_request(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<SwapPayload>,
+) -> WebResult<Response> {
+    validate_swap(&payload)?;
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let id = sqlx::query_file!(
+        "sql/insert_swap_request.sql",
+        user.user_id,
+        payload.spirit_id,
+        payload.volume_ml,
+        payload.notes
+    )
+    .fetch_one(&state.database)
+    .await?
+    .id;
+
+    let response = serde_json::to_string(&SwapIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+pub async fn close_swap_offer(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(offer_id): Path<i64>,
+) -> WebResult<Response> {
+    let offer = find_own_offer(&state.database, offer_id, &user.user_id).await?;
+
+    sqlx::query_file!("sql/update_swap_offer_status.sql", offer.id, SWAP_CLOSED)
+        .execute(&state.database)
+        .await?;
+
+    Ok("".into_response())
+}
+
+pub async fn close_swap_request(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(request_id): Path<i64>,
+) -> WebResult<Response> {
+    let request = sqlx::query_file_as!(SwapListing, "sql/select_swap_request.sql", request_id)
+        .fetch_optional(&state.database)
+        .await?
+        .filter(|request| request.user_id == user.user_id)
+        .ok_or(WebError::NotFound)?;
+
+    sqlx::query_file!(
+        "sql/update_swap_request_status.sql",
+        request.id,
+        SWAP_CLOSED
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok("".into_response())
+}
+
+/// Lists open requests for the same spirit that an offer could fill.
+pub async fn swap_offer_matches(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(offer_id): Path<i64>,
+) -> WebResult<Response> {
+    find_own_offer(&state.database, offer_id, &user.user_id).await?;
+
+    let candidates = sqlx::query_file_as!(
+        SwapListingResponse,
+        "sql/select_swap_offer_matches.sql",
+        offer_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&candidates)?;
+    Ok(response.into_response())
+}
+
+/// Pairs an open offer with an open request. Either side of the swap may create the match.
+pub async fn add_swap_match(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<MatchPayload>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let offer = sqlx::query_file_as!(SwapListing, "sql/select_swap_offer.sql", payload.offer_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(WebError::NotFound)?;
+    let request = sqlx::query_file_as!(
+        SwapListing,
+        "sql/select_swap_request.sql",
+        payload.request_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(WebError::NotFound)?;
+
+    if offer.user_id != user.user_id && request.user_id != user.user_id {
+        return Err(WebError::Forbidden);
+    }
+    if offer.user_id == request.user_id {
+        return Err(WebError::InvalidInput(
+            "You can't swap samples with yourself.".into(),
+        ));
+    }
+    if offer.spirit_id != request.spirit_id {
+        return Err(WebError::InvalidInput(
+            "The offer and request are for different spirits.".into(),
+        ));
+    }
+    if offer.status != SWAP_OPEN || request.status != SWAP_OPEN {
+        return Err(WebError::InvalidInput(
+            "The offer or request is no longer open.".into(),
+        ));
+    }
+
+    let id = sqlx::query_file!("sql/insert_swap_match.sql", offer.id, request.id)
+        .fetch_one(&mut *transaction)
+        .await?
+        .id;
+    sqlx::query_file!("sql/update_swap_offer_status.sql", offer.id, SWAP_MATCHED)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query_file!(
+        "sql/update_swap_request_status.sql",
+        request.id,
+        SWAP_MATCHED
+    )
+    .execute(&mut *transaction)
+    .await?;
+    create_conversation(
+        &mut transaction,
+        Some(id),
+        &[&offer.user_id, &request.user_id],
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&SwapIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+async fn swap_conversation(state: &WaterOfLifeState, match_id: i64) -> WebResult<i64> {
+    Ok(
+        sqlx::query_file!("sql/select_swap_match_conversation.sql", match_id)
+            .fetch_optional(&state.database)
+            .await?
+            .ok_or(WebError::NotFound)?
+            .id,
+    )
+}
+
+pub async fn list_swap_messages(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(match_id): Path<i64>,
+) -> WebResult<Response> {
+    find_match(&state.database, match_id, &user.user_id).await?;
+    let conversation_id = swap_conversation(&state, match_id).await?;
+
+    let messages = read_conversation(&state, conversation_id, &user.user_id).await?;
+
+    let response = serde_json::to_string(&messages)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_swap_message(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(match_id): Path<i64>,
+    Json(payload): Json<MessagePayload>,
+) -> WebResult<Response> {
+    find_match(&state.database, match_id, &user.user_id).await?;
+    let conversation_id = swap_conversation(&state, match_id).await?;
+
+    let id = send_message(&state, conversation_id, &user.user_id, &payload.body).await?;
+
+    let response = serde_json::to_string(&SwapIdResponse { id })?;
+    Ok(response.into_response())
+}
+
+/// Records one side's confirmation that the samples arrived, along with a rating of the other
+/// party. Once both sides confirm the swap is complete and counts towards reputation.
+pub async fn confirm_swap_match(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(match_id): Path<i64>,
+    Json(payload): Json<ConfirmationPayload>,
+) -> WebResult<Response> {
+    if !(1..=5).contains(&payload.rating) {
+        return Err(WebError::InvalidInput(
+            "Ratings must be between 1 and 5.".into(),
+        ));
+    }
+
+    let mut transaction = state.repositories.begin().await?;
+    let swap_match = find_match(&mut *transaction, match_id, &user.user_id).await?;
+    if swap_match.status != MATCH_PENDING {
+        return Err(WebError::InvalidInput(
+            "This swap has already been completed.".into(),
+        ));
+    }
+
+    sqlx::query_file!(
+        "sql/insert_swap_confirmation.sql",
+        swap_match.id,
+        user.user_id,
+        payload.rating
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let confirmations = sqlx::query_file!("sql/select_swap_confirmation_count.sql", swap_match.id)
+        .fetch_one(&mut *transaction)
+        .await?
+        .count;
+    if confirmations >= 2 {
+        sqlx::query_file!(
+            "sql/update_swap_match_status.sql",
+            swap_match.id,
+            MATCH_COMPLETED
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query_file!(
+            "sql/update_swap_offer_status.sql",
+            swap_match.offer_id,
+            SWAP_CLOSED
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query_file!(
+            "sql/update_swap_request_status.sql",
+            swap_match.request_id,
+            SWAP_CLOSED
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        services::APP_USER_ROLE,
+        testing::{self, TestApp},
+    };
+
+    use super::*;
+
+    async fn send(
+        app: &TestApp,
+        user: &User,
+        method: Method,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::COOKIE, testing::auth_cookie(&app.state, user))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.router.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Posts a 30ml listing of `spirit_id` to `uri`, returning its id.
+    async fn create_listing(app: &TestApp, user: &User, uri: &str, spirit_id: &str) -> i64 {
+        let payload = serde_json::json!({ "spirit_id": spirit_id, "volume_ml": 30 });
+        let response = send(app, user, Method::POST, uri, payload).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        json(response).await["id"].as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_the_offerer_sees_the_matches_for_an_offer() {
+        let app = testing::app().await;
+        let offerer = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let requester = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        let offer_id = create_listing(&app, &offerer, "/api/swaps/offers", &spirit_id).await;
+        let request_id = create_listing(&app, &requester, "/api/swaps/requests", &spirit_id).await;
+        let uri = format!("/api/swaps/offers/{}/matches", offer_id);
+
+        let response = send(&app, &offerer, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let candidates = json(response).await;
+        assert_eq!(candidates.as_array().unwrap().len(), 1);
+        assert_eq!(candidates[0]["id"], request_id);
+
+        let response = send(&app, &requester, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let uri = format!("/api/swaps/offers/{}", offer_id);
+        let response = send(
+            &app,
+            &requester,
+            Method::DELETE,
+            &uri,
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn only_the_two_sides_of_a_match_can_use_it() {
+        let app = testing::app().await;
+        let offerer = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let requester = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let outsider = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+        let spirit_id = testing::create_spirit(&app.state.database, "Harbor Light").await;
+        let offer_id = create_listing(&app, &offerer, "/api/swaps/offers", &spirit_id).await;
+        let request_id = create_listing(&app, &requester, "/api/swaps/requests", &spirit_id).await;
+        let payload = serde_json::json!({ "offer_id": offer_id, "request_id": request_id });
+
+        let response = send(
+            &app,
+            &outsider,
+            Method::POST,
+            "/api/swaps/matches",
+            payload.clone(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(
+            &app,
+            &requester,
+            Method::POST,
+            "/api/swaps/matches",
+            payload,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let match_id = json(response).await["id"].as_i64().unwrap();
+
+        let uri = format!("/api/swaps/matches/{}/messages", match_id);
+        let message = serde_json::json!({ "body": "Posting it Monday" });
+        let response = send(&app, &offerer, Method::POST, &uri, message.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(&app, &requester, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await.as_array().unwrap().len(), 1);
+
+        let response = send(&app, &outsider, Method::GET, &uri, serde_json::json!({})).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send(&app, &outsider, Method::POST, &uri, message).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let uri = format!("/api/swaps/matches/{}/confirm", match_id);
+        let rating = serde_json::json!({ "rating": 5 });
+        let response = send(&app, &outsider, Method::POST, &uri, rating).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use crate::WaterOfLifeState;
+
+use super::{
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+const DEFAULT_TRENDING_WINDOW_DAYS: i64 = 7;
+const MAX_TRENDING_WINDOW_DAYS: i64 = 90;
+/// Activity loses half its weight every this many days, so a burst last week counts for less
+/// than steady interest today.
+const TRENDING_HALF_LIFE_DAYS: f64 = 2.0;
+/// Outlives the scheduler's refresh interval so the default ranking is always cached, while a
+/// stopped scheduler still can't leave a stale ranking behind for long.
+const TRENDING_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ActivityKind {
+    View,
+    Rating,
+    Collection,
+}
+
+impl ActivityKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Rating => "rating",
+            Self::Collection => "collection",
+        }
+    }
+
+    /// How much one event counts towards a spirit trending. Unknown kinds count for nothing.
+    fn weight(kind: &str) -> f64 {
+        match kind {
+            "view" => 1.0,
+            "rating" => 5.0,
+            "collection" => 3.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrendingParameter {
+    /// How far back to look, such as `7d`.
+    window: Option<String>,
+}
+
+impl TrendingParameter {
+    fn window_days(&self) -> WebResult<i64> {
+        let Some(window) = &self.window else {
+            return Ok(DEFAULT_TRENDING_WINDOW_DAYS);
+        };
+        window
+            .strip_suffix('d')
+            .and_then(|days| days.parse::<i64>().ok())
+            .filter(|days| (1..=MAX_TRENDING_WINDOW_DAYS).contains(days))
+            .ok_or_else(|| {
+                WebError::InvalidInput(format!(
+                    "The window must be between 1d and {}d.",
+                    MAX_TRENDING_WINDOW_DAYS
+                ))
+            })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrendingSpiritResponse {
+    id: String,
+    name: String,
+    distiller: String,
+    typ: String,
+    score: f64,
+}
+
+/// Counts an event towards a spirit trending. Failures are logged rather than returned since
+/// the counters aren't worth failing a request over.
+pub async fn record_activity<'e, E>(executor: E, spirit_id: &str, kind: ActivityKind)
+where
+    E: SqliteExecutor<'e>,
+{
+    let kind = kind.as_str();
+    let result = sqlx::query_file!("sql/upsert_spirit_activity.sql", spirit_id, kind)
+        .execute(executor)
+        .await;
+    if let Err(e) = result {
+        tracing::warn!("Could not record {} of spirit {}: {}", kind, spirit_id, e);
+    }
+}
+
+/// Ranks approved spirits by their views, ratings and collection adds over the window, with
+/// older activity decaying away.
+async fn rank_trending(
+    database: &SqlitePool,
+    window_days: i64,
+) -> sqlx::Result<Vec<TrendingSpiritResponse>> {
+    let rows = sqlx::query_file!("sql/select_spirit_activity.sql", window_days)
+        .fetch_all(database)
+        .await?;
+
+    let mut spirits = HashMap::new();
+    for row in rows {
+        let decay = 0.5_f64.powf(row.age_days as f64 / TRENDING_HALF_LIFE_DAYS);
+        let score = ActivityKind::weight(&row.kind) * row.count as f64 * decay;
+        spirits
+            .entry(row.spirit_id.clone())
+            .or_insert_with(|| TrendingSpiritResponse {
+                id: row.spirit_id,
+                name: row.name,
+                distiller: row.distiller,
+                typ: row.typ,
+                score: 0.0,
+            })
+            .score += score;
+    }
+
+    let mut spirits = spirits.into_values().collect::<Vec<_>>();
+    spirits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+    Ok(spirits)
+}
+
+fn trending_cache_key(window_days: i64) -> String {
+    format!("trending:{}", window_days)
+}
+
+/// Ranks the default window ahead of time so the trending page doesn't have to. Returns how
+/// many spirits were ranked.
+pub async fn refresh_trending(state: &WaterOfLifeState) -> WebResult<usize> {
+    let spirits = rank_trending(&state.database, DEFAULT_TRENDING_WINDOW_DAYS).await?;
+    let ranking = serde_json::to_vec(&spirits)?;
+    state
+        .stores
+        .cache
+        .set(
+            &trending_cache_key(DEFAULT_TRENDING_WINDOW_DAYS),
+            &ranking,
+            TRENDING_CACHE_TTL,
+        )
+        .await?;
+    Ok(spirits.len())
+}
+
+/// Spirits trending over the window. The default window is served from the ranking the
+/// scheduler keeps cached, other windows are ranked on request.
+pub async fn trending_spirits(
+    State(state): State<WaterOfLifeState>,
+    Query(query_params): Query<TrendingParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    let window_days = query_params.window_days()?;
+    let cached = match state
+        .stores
+        .cache
+        .get(&trending_cache_key(window_days))
+        .await
+    {
+        Ok(cached) => cached.and_then(|ranking| serde_json::from_slice(&ranking).ok()),
+        Err(e) => {
+            tracing::warn!("trending_spirits: {}", e);
+            None
+        }
+    };
+    let spirits = match cached {
+        Some(spirits) => spirits,
+        None => rank_trending(&state.database, window_days).await?,
+    };
+
+    let total = spirits.len() as i64;
+    let spirits = spirits
+        .into_iter()
+        .skip(page.offset() as usize)
+        .take(page.limit() as usize)
+        .collect();
+
+    let response = serde_json::to_string(&Page::new(spirits, &page, total))?;
+    Ok(response.into_response())
+}
@@ -0,0 +1,275 @@
+use std::{
+    io::{self, SeekFrom},
+    path::{Path as FsPath, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs,
+    io::{AsyncSeekExt, AsyncWriteExt},
+};
+use uuid::Uuid;
+
+use crate::{
+    json_web::{AuthContext, User},
+    permissions::{ImagesUpload, RequirePermission},
+    WaterOfLifeState,
+};
+
+use super::{
+    api::{ensure_spirit_editable, ensure_spirit_id_format},
+    images::{store_spirit_image, StoredImage},
+    WebError, WebResult,
+};
+
+/// Uploads not finished within this long of starting are abandoned and cleaned up.
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Deserialize)]
+pub struct UploadPayload {
+    total_bytes: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChunkParameter {
+    offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct UploadResponse {
+    id: String,
+    offset: i64,
+    total_bytes: i64,
+    complete: bool,
+    /// Set once the upload completes if it is nearly identical to an image the spirit
+    /// already had.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<String>,
+}
+
+struct ImageUpload {
+    id: String,
+    total_bytes: i64,
+    received_bytes: i64,
+}
+
+fn upload_path(uploads_path: &FsPath, upload_id: &str) -> PathBuf {
+    uploads_path.join(upload_id)
+}
+
+async fn find_upload(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    upload_id: &str,
+    user_id: &str,
+) -> WebResult<ImageUpload> {
+    sqlx::query_file_as!(
+        ImageUpload,
+        "sql/select_image_upload.sql",
+        upload_id,
+        spirit_id,
+        user_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or(WebError::NotFound)
+}
+
+/// Writes a chunk at `offset`, discarding anything past it left behind by an interrupted request.
+async fn write_chunk(path: &FsPath, offset: i64, chunk: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .await?;
+    file.set_len(offset as u64).await?;
+    file.seek(SeekFrom::Start(offset as u64)).await?;
+    file.write_all(chunk).await?;
+    file.flush().await
+}
+
+/// Hands a fully received upload to the regular image pipeline and discards the session.
+async fn finish_upload(
+    state: &WaterOfLifeState,
+    spirit_id: &str,
+    upload_id: &str,
+    user_id: &str,
+) -> WebResult<StoredImage> {
+    let path = upload_path(&state.config.storage.uploads_path, upload_id);
+    fs::File::open(&path).await?.sync_all().await?;
+    // The pipeline moves the file into place, or deletes it if the image is rejected, so the
+    // session is over either way.
+    let stored = store_spirit_image(state, spirit_id, user_id, "", &path).await;
+
+    sqlx::query_file!("sql/delete_image_upload.sql", upload_id)
+        .execute(&state.database)
+        .await?;
+    stored
+}
+
+/// Starts a resumable image upload. The client then sends the image in chunks and can resume
+/// from the last acknowledged offset after a dropped connection.
+pub async fn start_image_upload(
+    RequirePermission(user, _): RequirePermission<ImagesUpload>,
+    Extension(context): Extension<AuthContext>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(payload): Json<UploadPayload>,
+) -> WebResult<Response> {
+    if payload.total_bytes <= 0 {
+        return Err(WebError::InvalidInput(
+            "Uploads must be at least 1 byte.".into(),
+        ));
+    }
+    if payload.total_bytes as u64 > state.config.limits.max_image_upload_bytes as u64 {
+        return Err(WebError::PayloadTooLarge(format!(
+            "Images may be at most {} bytes.",
+            state.config.limits.max_image_upload_bytes
+        )));
+    }
+    ensure_spirit_id_format(&spirit_id)?;
+    ensure_spirit_editable(&state.database, &context, &spirit_id).await?;
+
+    let id = Uuid::new_v4().to_string();
+    sqlx::query_file!(
+        "sql/insert_image_upload.sql",
+        id,
+        spirit_id,
+        user.user_id,
+        payload.total_bytes
+    )
+    .execute(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&UploadResponse {
+        id,
+        offset: 0,
+        total_bytes: payload.total_bytes,
+        complete: false,
+        duplicate_of: None,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Reports how much of an upload has been received so the client knows where to resume.
+pub async fn image_upload_status(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, upload_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    let upload = find_upload(&state, &spirit_id, &upload_id, &user.user_id).await?;
+
+    let response = serde_json::to_string(&UploadResponse {
+        id: upload.id,
+        offset: upload.received_bytes,
+        total_bytes: upload.total_bytes,
+        complete: false,
+        duplicate_of: None,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Appends a chunk to an upload. The offset must match what the server has already received,
+/// and the image is processed as soon as the final chunk arrives.
+pub async fn upload_image_chunk(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, upload_id)): Path<(String, String)>,
+    Query(query_params): Query<ChunkParameter>,
+    chunk: Bytes,
+) -> WebResult<Response> {
+    let upload = find_upload(&state, &spirit_id, &upload_id, &user.user_id).await?;
+    if query_params.offset != upload.received_bytes {
+        return Err(WebError::Conflict(format!(
+            "Expected a chunk at offset {}.",
+            upload.received_bytes
+        )));
+    }
+    let received_bytes = upload.received_bytes + chunk.len() as i64;
+    if received_bytes > upload.total_bytes {
+        return Err(WebError::InvalidInput(
+            "Chunk extends past the declared upload size.".into(),
+        ));
+    }
+
+    write_chunk(
+        &upload_path(&state.config.storage.uploads_path, &upload_id),
+        upload.received_bytes,
+        &chunk,
+    )
+    .await?;
+    let result = sqlx::query_file!(
+        "sql/update_image_upload_received.sql",
+        upload_id,
+        upload.received_bytes,
+        received_bytes
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::Conflict(
+            "Another chunk was received at this offset.".into(),
+        ));
+    }
+
+    let complete = received_bytes == upload.total_bytes;
+    let duplicate_of = if complete {
+        finish_upload(&state, &spirit_id, &upload_id, &user.user_id)
+            .await?
+            .duplicate_of
+    } else {
+        None
+    };
+
+    let response = serde_json::to_string(&UploadResponse {
+        id: upload.id,
+        offset: received_bytes,
+        total_bytes: upload.total_bytes,
+        complete,
+        duplicate_of,
+    })?;
+    Ok(response.into_response())
+}
+
+/// Removes upload sessions older than [`UPLOAD_EXPIRY`] along with whatever was received for
+/// them, returning how many there were. Clients that give up part way never cancel.
+pub async fn expire_stale_uploads(state: &WaterOfLifeState) -> WebResult<usize> {
+    let expiry = UPLOAD_EXPIRY.as_secs() as i64;
+    let stale = sqlx::query_file!("sql/delete_stale_image_uploads.sql", expiry)
+        .fetch_all(&state.database)
+        .await?;
+    for upload in &stale {
+        remove_upload_file(&state.config.storage.uploads_path, &upload.id).await?;
+    }
+    Ok(stale.len())
+}
+
+async fn remove_upload_file(uploads_path: &FsPath, upload_id: &str) -> io::Result<()> {
+    match fs::remove_file(upload_path(uploads_path, upload_id)).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+pub async fn cancel_image_upload(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path((spirit_id, upload_id)): Path<(String, String)>,
+) -> WebResult<Response> {
+    find_upload(&state, &spirit_id, &upload_id, &user.user_id).await?;
+
+    sqlx::query_file!("sql/delete_image_upload.sql", upload_id)
+        .execute(&state.database)
+        .await?;
+    remove_upload_file(&state.config.storage.uploads_path, &upload_id).await?;
+
+    Ok("".into_response())
+}
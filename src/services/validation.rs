@@ -0,0 +1,56 @@
+use super::{WebError, WebResult};
+
+fn is_numeric(value: &str, length: usize) -> bool {
+    value.len() == length && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Checks for a `YYYY-MM` month.
+pub fn is_valid_month(month: &str) -> bool {
+    match month.split_once('-') {
+        Some((year, month)) => {
+            is_numeric(year, 4)
+                && is_numeric(month, 2)
+                && (1..=12).contains(&month.parse::<u32>().unwrap_or(0))
+        }
+        None => false,
+    }
+}
+
+/// Checks for a `YYYY-MM-DD` date.
+pub fn is_valid_date(date: &str) -> bool {
+    match date.rsplit_once('-') {
+        Some((month, day)) => {
+            is_valid_month(month)
+                && is_numeric(day, 2)
+                && (1..=31).contains(&day.parse::<u32>().unwrap_or(0))
+        }
+        None => false,
+    }
+}
+
+/// Checks for a `YYYY-MM-DD HH:MM:SS` timestamp, as produced by SQLite's `CURRENT_TIMESTAMP`.
+pub fn is_valid_timestamp(timestamp: &str) -> bool {
+    match timestamp.split_once(' ') {
+        Some((date, time)) => {
+            let parts = time.split(':').collect::<Vec<_>>();
+            is_valid_date(date)
+                && parts.len() == 3
+                && parts.iter().all(|part| is_numeric(part, 2))
+                && parts[0].parse::<u32>().unwrap_or(24) < 24
+                && parts[1].parse::<u32>().unwrap_or(60) < 60
+                && parts[2].parse::<u32>().unwrap_or(60) < 60
+        }
+        None => false,
+    }
+}
+
+/// Rejects a date that is present but not formatted as `YYYY-MM-DD`.
+pub fn validate_optional_date(date: &Option<String>) -> WebResult<()> {
+    match date {
+        Some(date) if !is_valid_date(date) => Err(WebError::InvalidInput(format!(
+            "Date '{}' is not formatted as YYYY-MM-DD.",
+            date
+        ))),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,400 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{SqliteConnection, SqliteExecutor};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    permissions::{RequirePermission, WebhooksManage},
+    proxy::ClientIp,
+    WaterOfLifeState,
+};
+
+use super::{
+    audit::record_audit,
+    pagination::{Page, PageParameter},
+    WebError, WebResult,
+};
+
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+const SENDER_INTERVAL: Duration = Duration::from_secs(10);
+const SENDER_BATCH_SIZE: i64 = 20;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Deliveries that still fail after this many tries are marked failed and kept for the log.
+const MAX_ATTEMPTS: i64 = 8;
+/// Wait before the first retry, doubled after each failed attempt.
+const RETRY_BACKOFF_SECS: i64 = 30;
+
+const EVENT_HEADER: &str = "X-Webhook-Event";
+const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+/// `sha256=` followed by the base64 HMAC-SHA256 of `{timestamp}.{body}`, keyed with the
+/// webhook's secret.
+const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "spirit.created")]
+    SpiritCreated,
+    #[serde(rename = "spirit.updated")]
+    SpiritUpdated,
+    #[serde(rename = "review.created")]
+    ReviewCreated,
+}
+
+impl WebhookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::SpiritCreated => "spirit.created",
+            Self::SpiritUpdated => "spirit.updated",
+            Self::ReviewCreated => "review.created",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry.
+    Pending,
+    Delivered,
+    /// Gave up after the last retry.
+    Failed,
+}
+
+impl DeliveryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookPayload {
+    url: String,
+    events: Vec<WebhookEvent>,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryParameter {
+    status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookResponse {
+    id: i64,
+    url: String,
+    events: sqlx::types::Json<Vec<String>>,
+    description: String,
+    created_by: String,
+    created_at: String,
+    failed_deliveries: i64,
+}
+
+/// The secret is only ever shown here, when the webhook is registered.
+#[derive(Debug, Serialize)]
+struct WebhookCreatedResponse {
+    id: i64,
+    secret: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DeliveryResponse {
+    id: i64,
+    event: String,
+    payload: String,
+    status: String,
+    attempts: i64,
+    response_status: Option<i64>,
+    last_error: Option<String>,
+    next_attempt_at: String,
+    delivered_at: Option<String>,
+    created_at: String,
+}
+
+/// Queues `data` for every webhook subscribed to `event`. The background sender delivers it,
+/// so this is safe to call from a handler's transaction.
+pub async fn queue_webhook_event<'e, E, T>(
+    executor: E,
+    event: WebhookEvent,
+    data: &T,
+) -> WebResult<()>
+where
+    E: SqliteExecutor<'e>,
+    T: Serialize,
+{
+    let event = event.as_str();
+    let payload = serde_json::to_string(&json!({
+        "event": event,
+        "data": data,
+    }))?;
+    sqlx::query_file!("sql/insert_webhook_deliveries.sql", event, payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Queues a spirit event carrying the spirit's current details. Spirits still waiting for
+/// moderation, or merged away, are skipped.
+pub async fn queue_spirit_event(
+    connection: &mut SqliteConnection,
+    event: WebhookEvent,
+    spirit_id: &str,
+) -> WebResult<()> {
+    let Some(spirit) = sqlx::query_file!("sql/select_webhook_spirit.sql", spirit_id)
+        .fetch_optional(&mut *connection)
+        .await?
+    else {
+        return Ok(());
+    };
+    queue_webhook_event(
+        connection,
+        event,
+        &json!({
+            "id": spirit.id,
+            "name": spirit.name,
+            "description": spirit.description,
+            "distiller": spirit.distiller,
+            "type": spirit.typ,
+            "region_id": spirit.region_id,
+            "abv": spirit.abv,
+            "availability": spirit.availability,
+            "version": spirit.version,
+        }),
+    )
+    .await
+}
+
+pub async fn list_webhooks(
+    RequirePermission(_, _): RequirePermission<WebhooksManage>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let webhooks = sqlx::query_file_as!(WebhookResponse, "sql/select_webhooks.sql")
+        .fetch_all(&state.database)
+        .await?;
+
+    let response = serde_json::to_string(&webhooks)?;
+    Ok(response.into_response())
+}
+
+/// Registers an endpoint for the given events and returns the secret its deliveries are
+/// signed with.
+pub async fn add_webhook(
+    RequirePermission(user, _): RequirePermission<WebhooksManage>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<WebhookPayload>,
+) -> WebResult<Response> {
+    let url = Url::parse(payload.url.trim())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| WebError::InvalidInput("Webhooks need an http or https URL.".into()))?;
+    if payload.events.is_empty() {
+        return Err(WebError::InvalidInput(
+            "Webhooks need at least one event.".into(),
+        ));
+    }
+    let description = payload.description.trim();
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        return Err(WebError::InvalidInput(format!(
+            "Descriptions must be at most {} characters.",
+            MAX_DESCRIPTION_LENGTH
+        )));
+    }
+
+    let mut events = payload
+        .events
+        .iter()
+        .map(WebhookEvent::as_str)
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+    events.dedup();
+    let events = serde_json::to_string(&events)?;
+    let secret = format!("whsec_{}", Uuid::new_v4().simple());
+    let url = url.as_str();
+
+    let mut transaction = state.repositories.begin().await?;
+    let id = sqlx::query_file!(
+        "sql/insert_webhook.sql",
+        url,
+        secret,
+        events,
+        description,
+        user.user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .id;
+    record_audit(
+        &mut *transaction,
+        Some(&user.user_id),
+        client_ip,
+        "webhook_added",
+        &json!({ "id": id, "url": url, "events": payload.events }),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let response = serde_json::to_string(&WebhookCreatedResponse { id, secret })?;
+    Ok(response.into_response())
+}
+
+pub async fn delete_webhook(
+    RequirePermission(user, _): RequirePermission<WebhooksManage>,
+    State(state): State<WaterOfLifeState>,
+    ClientIp(client_ip): ClientIp,
+    Path(webhook_id): Path<i64>,
+) -> WebResult<Response> {
+    let mut transaction = state.repositories.begin().await?;
+    let result = sqlx::query_file!("sql/delete_webhook.sql", webhook_id)
+        .execute(&mut *transaction)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+    record_audit(
+        &mut *transaction,
+        Some(&user.user_id),
+        client_ip,
+        "webhook_deleted",
+        &json!({ "id": webhook_id }),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok("".into_response())
+}
+
+/// Lists a webhook's deliveries, newest first, optionally only those with one status.
+pub async fn list_webhook_deliveries(
+    RequirePermission(_, _): RequirePermission<WebhooksManage>,
+    State(state): State<WaterOfLifeState>,
+    Path(webhook_id): Path<i64>,
+    Query(query_params): Query<DeliveryParameter>,
+    page: PageParameter,
+) -> WebResult<Response> {
+    sqlx::query_file!("sql/select_webhook_exists.sql", webhook_id)
+        .fetch_optional(&state.database)
+        .await?
+        .ok_or(WebError::NotFound)?;
+
+    let status = query_params.status.as_ref().map(DeliveryStatus::as_str);
+    let (limit, offset) = (page.limit(), page.offset());
+    let deliveries = sqlx::query_file_as!(
+        DeliveryResponse,
+        "sql/select_webhook_deliveries.sql",
+        webhook_id,
+        status,
+        limit,
+        offset
+    )
+    .fetch_all(&state.database)
+    .await?;
+    let total = sqlx::query_file!("sql/select_webhook_delivery_count.sql", webhook_id, status)
+        .fetch_one(&state.database)
+        .await?
+        .count;
+
+    let response = serde_json::to_string(&Page::new(deliveries, &page, total))?;
+    Ok(response.into_response())
+}
+
+fn sign_payload(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!(
+        "sha256={}",
+        general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    )
+}
+
+async fn send_pending_deliveries(state: &WaterOfLifeState) -> sqlx::Result<()> {
+    let pending = sqlx::query_file!(
+        "sql/select_pending_webhook_deliveries.sql",
+        SENDER_BATCH_SIZE
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    for delivery in pending {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let result = state
+            .client
+            .post(&delivery.url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, delivery.id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                sign_payload(&delivery.secret, timestamp, &delivery.payload),
+            )
+            .body(delivery.payload)
+            .send()
+            .await;
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                let response_status = response.status().as_u16() as i64;
+                sqlx::query_file!(
+                    "sql/update_webhook_delivered.sql",
+                    delivery.id,
+                    response_status
+                )
+                .execute(&state.database)
+                .await?;
+                continue;
+            }
+            Ok(response) => (
+                Some(response.status().as_u16() as i64),
+                format!("Responded with {}", response.status()),
+            ),
+            Err(e) => (None, e.to_string()),
+        };
+        tracing::warn!("Could not deliver webhook {}: {}", delivery.id, error);
+        let backoff = RETRY_BACKOFF_SECS << delivery.attempts.min(16);
+        sqlx::query_file!(
+            "sql/update_webhook_failed.sql",
+            delivery.id,
+            response_status,
+            error,
+            MAX_ATTEMPTS,
+            backoff
+        )
+        .execute(&state.database)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Periodically delivers queued webhook events, retrying failures with backoff.
+pub async fn webhook_sender(state: WaterOfLifeState) {
+    let mut interval = tokio::time::interval(SENDER_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_pending_deliveries(&state).await {
+            tracing::warn!("webhook_sender: {}", e);
+        }
+    }
+}
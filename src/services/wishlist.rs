@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+
+use crate::{json_web::User, WaterOfLifeState};
+
+use super::{api::ensure_spirit_exists, WebError, WebResult};
+
+fn default_notify() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WishlistDetails {
+    /// Whether to be notified when new price or availability data shows up for the spirit.
+    #[serde(default = "default_notify")]
+    notify: bool,
+    #[serde(default)]
+    note: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WishlistPayload {
+    spirit_id: String,
+    #[serde(flatten)]
+    details: WishlistDetails,
+}
+
+#[derive(Debug, Serialize)]
+struct WishlistEntryResponse {
+    spirit_id: String,
+    spirit_name: String,
+    notify: bool,
+    note: String,
+    created_at: String,
+}
+
+/// Notifies everyone wishing for a spirit, other than the user who triggered the update, that
+/// new price or availability data was submitted for it.
+pub async fn notify_wishlist<'e, E>(
+    executor: E,
+    spirit_id: &str,
+    submitted_by: &str,
+    message: &str,
+) -> sqlx::Result<()>
+where
+    E: SqliteExecutor<'e>,
+{
+    sqlx::query_file!(
+        "sql/insert_wishlist_notifications.sql",
+        spirit_id,
+        submitted_by,
+        message
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_wishlist(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+) -> WebResult<Response> {
+    let entries = sqlx::query_file_as!(
+        WishlistEntryResponse,
+        "sql/select_wishlist.sql",
+        user.user_id
+    )
+    .fetch_all(&state.database)
+    .await?;
+
+    let response = serde_json::to_string(&entries)?;
+    Ok(response.into_response())
+}
+
+pub async fn add_wishlist_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Json(payload): Json<WishlistPayload>,
+) -> WebResult<Response> {
+    ensure_spirit_exists(&state.database, &payload.spirit_id).await?;
+
+    let result = sqlx::query_file!(
+        "sql/insert_wishlist_entry.sql",
+        user.user_id,
+        payload.spirit_id,
+        payload.details.notify,
+        payload.details.note
+    )
+    .execute(&state.database)
+    .await;
+    match result {
+        Ok(_) => Ok("".into_response()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(WebError::Conflict(
+            "This spirit is already on your wishlist.".into(),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn edit_wishlist_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+    Json(details): Json<WishlistDetails>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!(
+        "sql/update_wishlist_entry.sql",
+        user.user_id,
+        spirit_id,
+        details.notify,
+        details.note
+    )
+    .execute(&state.database)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
+
+pub async fn delete_wishlist_entry(
+    Extension(user): Extension<User>,
+    State(state): State<WaterOfLifeState>,
+    Path(spirit_id): Path<String>,
+) -> WebResult<Response> {
+    let result = sqlx::query_file!("sql/delete_wishlist_entry.sql", user.user_id, spirit_id)
+        .execute(&state.database)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(WebError::NotFound);
+    }
+
+    Ok("".into_response())
+}
@@ -0,0 +1,225 @@
+//! Logging, and span export to an OpenTelemetry collector when `telemetry.otlp_enabled` is set.
+//! Slow requests and database statements are also counted per route, for spotting regressions
+//! without a metrics stack.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use opentelemetry::{global, propagation::Injector, trace::TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Subscriber,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::config::{LogFormat, TelemetryConfig};
+
+/// Flushes exported spans when dropped, so shut down by letting it go out of scope.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Could not flush spans: {}", e);
+            }
+        }
+    }
+}
+
+fn tracer_provider(config: &TelemetryConfig) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&config.otlp_endpoint)
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build())
+}
+
+/// The span `middleware::create_span` opens for each request.
+const REQUEST_SPAN: &str = "request";
+/// sqlx logs every statement under this target, and slow ones with a `slow_threshold` field.
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+const SLOW_THRESHOLD_FIELD: &str = "slow_threshold";
+/// Counted against statements run outside a request, such as by the scheduler or job workers.
+const BACKGROUND_ROUTE: &str = "<background>";
+
+/// How often a route has been slow since startup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SlowRoute {
+    pub slow_requests: u64,
+    pub slow_queries: u64,
+    pub slowest_request_ms: u64,
+}
+
+/// Slow requests and statements counted per matched path, kept in memory for the admin
+/// endpoint.
+#[derive(Clone, Default)]
+pub struct SlowOperations {
+    routes: Arc<Mutex<HashMap<String, SlowRoute>>>,
+}
+
+impl SlowOperations {
+    pub fn record_request(&self, route: &str, elapsed: Duration) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let counts = routes.entry(route.to_owned()).or_default();
+        counts.slow_requests += 1;
+        counts.slowest_request_ms = counts.slowest_request_ms.max(elapsed.as_millis() as u64);
+    }
+
+    fn record_query(&self, route: &str) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.entry(route.to_owned()).or_default().slow_queries += 1;
+    }
+
+    pub fn routes(&self) -> HashMap<String, SlowRoute> {
+        self.routes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// The matched path of a request span, kept so slow statements run while serving it can be
+/// counted against its route.
+struct RequestRoute(String);
+
+struct MatchedPathVisitor(Option<String>);
+
+impl Visit for MatchedPathVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "matched_path" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+/// Counts the slow statements sqlx logs. The log line itself comes from the fmt layer, which
+/// already includes the request span's matched path and user id.
+struct SlowQueryCounter {
+    slow_operations: SlowOperations,
+}
+
+impl<S> Layer<S> for SlowQueryCounter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attributes.metadata().name() != REQUEST_SPAN {
+            return;
+        }
+        let mut visitor = MatchedPathVisitor(None);
+        attributes.record(&mut visitor);
+        if let (Some(route), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(RequestRoute(route));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if metadata.target() != SQLX_QUERY_TARGET
+            || metadata.fields().field(SLOW_THRESHOLD_FIELD).is_none()
+        {
+            return;
+        }
+        let route = ctx.event_scope(event).and_then(|scope| {
+            scope.from_root().find_map(|span| {
+                span.extensions()
+                    .get::<RequestRoute>()
+                    .map(|RequestRoute(route)| route.clone())
+            })
+        });
+        self.slow_operations
+            .record_query(route.as_deref().unwrap_or(BACKGROUND_ROUTE));
+    }
+}
+
+/// Installs the global subscriber, logging to stderr as text or JSON lines depending on
+/// `log_format`. Stdout is left for command output such as `export`.
+/// Spans are only exported if OTLP is enabled, in which case W3C trace context is also
+/// propagated on outbound requests. Slow statements are counted into `slow_operations`.
+pub fn init(config: &TelemetryConfig, slow_operations: &SlowOperations) -> Telemetry {
+    let provider = config.otlp_enabled.then(|| {
+        tracer_provider(config).unwrap_or_else(|e| panic!("Could not set up OTLP export: {}", e))
+    });
+    let otel_layer = provider.as_ref().map(|provider| {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(config.service_name.clone()))
+    });
+
+    let json = config.log_format == LogFormat::Json;
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(&config.log_filter))
+        .with(json.then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(io::stderr)
+        }))
+        .with((!json).then(|| tracing_subscriber::fmt::layer().with_writer(io::stderr)))
+        .with(otel_layer)
+        .with(SlowQueryCounter {
+            slow_operations: slow_operations.clone(),
+        })
+        .init();
+
+    Telemetry { provider }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Headers carrying `span`'s trace context, such as `traceparent`. Empty unless OTLP export
+/// is enabled.
+pub fn trace_context_headers(span: &tracing::Span) -> HeaderMap {
+    let context = span.context();
+    let mut headers = HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+/// Stands in for a secret, like a token, in `Debug` output so it never reaches the logs.
+pub struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
@@ -0,0 +1,265 @@
+//! Setup for handler tests, enabled with the `testing` feature.
+//!
+//! ```ignore
+//! let app = testing::app().await;
+//! let user = testing::create_user(&app.state.database, APP_USER_ROLE).await;
+//! let spirit_id = testing::create_spirit(&app.state.database, "Test Bourbon").await;
+//! let request = Request::get(format!("/api/spirit/{}", spirit_id))
+//!     .header(COOKIE, testing::auth_cookie(&app.state, &user))
+//!     .body(Body::empty())
+//!     .unwrap();
+//! let response = app.router.oneshot(request).await.unwrap();
+//! ```
+// Not every test needs every helper.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use axum::Router;
+use reqwest::Client;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    config::AppConfig,
+    infra::{ImageStore, StorageBackend, Stores},
+    json_web::{generate_access_and_refresh_tokens, User},
+    mailer::Mailer,
+    repositories::Repositories,
+    router,
+    security::SecurityMonitor,
+    services::{
+        IdentityProviderHealth, OpenidConfiguration, RoleCheckLocks, SchedulerStatus,
+        SettingsService, APP_ADMIN_ROLE, APP_MODERATOR_ROLE,
+    },
+    telemetry::SlowOperations,
+    WaterOfLifeState,
+};
+
+const TEST_CLIENT_ID: &str = "water-of-life-test";
+const TEST_ACCESS_TOKEN_SECRET: &str = "test-access-token-secret";
+const TEST_REFRESH_TOKEN_SECRET: &str = "test-refresh-token-secret";
+const TEST_SPIRIT_TYPE: &str = "Bourbon";
+
+/// A clock that stands still until it's moved, for tests.
+pub struct MockClock {
+    now: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// Starts at the current system time, so timestamps the database adds still line up.
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(SystemClock.now()),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct TestApp {
+    pub router: Router,
+    /// The state behind `router`, for seeding data and inspecting the database.
+    pub state: WaterOfLifeState,
+    /// The app's clock, for moving time forward past token expiry.
+    pub clock: Arc<MockClock>,
+}
+
+/// A fresh in-memory database with every migration applied.
+pub async fn database() -> SqlitePool {
+    // Each connection to `:memory:` gets its own database, so keep exactly one open. The
+    // filename is passed straight through rather than using `sqlite::memory:`, which would
+    // also open the attached spirit import database in memory and break the migrations.
+    // Waiting for that connection gives up after a second rather than sqlx's default 30, so
+    // tests that hold it to simulate an unavailable database stay quick.
+    let database = SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_secs(1))
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().filename(":memory:"))
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&database).await.unwrap();
+    database
+}
+
+/// Builds the full router on an in-memory database and in-memory stores. Nothing talks to
+/// Keycloak, so authenticate requests with [`auth_cookie`] rather than the `/oidc` routes.
+pub async fn app() -> TestApp {
+    let database = database().await;
+    let client = Client::new();
+    let stores = Stores::new(&StorageBackend::Memory, &database)
+        .await
+        .unwrap();
+    let security = SecurityMonitor::new(client.clone(), None, stores.rate_limits.clone());
+
+    let files_path = env::temp_dir().join(format!("water-of-life-test-{}", Uuid::new_v4()));
+    let mut config = AppConfig::default();
+    config.storage.images_path = files_path.join("spirit_images");
+    fs::create_dir_all(&config.storage.images_path).unwrap();
+    config.storage.uploads_path = files_path.join("spirit_uploads");
+    fs::create_dir_all(&config.storage.uploads_path).unwrap();
+    config.oidc.client_id = TEST_CLIENT_ID.to_owned();
+    config.tokens.access_token_hmac_secret = TEST_ACCESS_TOKEN_SECRET.to_owned();
+    config.tokens.refresh_token_hmac_secret = TEST_REFRESH_TOKEN_SECRET.to_owned();
+
+    let (message_events, _) = broadcast::channel(256);
+    let (tasting_events, _) = broadcast::channel(256);
+    let repositories = Repositories::sqlite(&database);
+    let settings = SettingsService::new(database.clone());
+    let mailer = Mailer::new(&config.mail);
+    let images = ImageStore::new(&config.storage.images_path);
+    let clock = Arc::new(MockClock::new());
+    let state = WaterOfLifeState {
+        client,
+        database,
+        oidc_configuration: OpenidConfiguration::default(),
+        config: Arc::new(config),
+        jwks: Arc::new(RwLock::new(HashMap::new())),
+        message_events,
+        tasting_events,
+        stores,
+        repositories,
+        security,
+        idp_health: IdentityProviderHealth::default(),
+        role_checks: RoleCheckLocks::default(),
+        scheduler: SchedulerStatus::default(),
+        settings,
+        images,
+        mailer,
+        slow_operations: SlowOperations::default(),
+        clock: clock.clone(),
+    };
+
+    TestApp {
+        router: router(state.clone()),
+        state,
+        clock,
+    }
+}
+
+/// Inserts a user with a unique id, username and email.
+pub async fn create_user(database: &SqlitePool, role: &str) -> User {
+    let preferred_username = format!("user-{}", &Uuid::new_v4().simple().to_string()[..8]);
+    let user = User {
+        user_id: Uuid::new_v4().to_string(),
+        email: format!("{}@example.com", preferred_username),
+        preferred_username,
+        refresh_token_version: 1,
+        role: role.to_owned(),
+        email_verified: true,
+    };
+    sqlx::query_file!(
+        "sql/insert_user.sql",
+        user.user_id,
+        user.preferred_username,
+        user.email,
+        user.refresh_token_version,
+        user.role,
+        user.email_verified
+    )
+    .execute(database)
+    .await
+    .unwrap();
+
+    user
+}
+
+pub async fn create_admin(database: &SqlitePool) -> User {
+    create_user(database, APP_ADMIN_ROLE).await
+}
+
+pub async fn create_moderator(database: &SqlitePool) -> User {
+    create_user(database, APP_MODERATOR_ROLE).await
+}
+
+/// Inserts a searchable bourbon with the given name and returns its id.
+pub async fn create_spirit(database: &SqlitePool, name: &str) -> String {
+    let id = Uuid::new_v4().to_string();
+    let spirit_type = sqlx::query_file!("sql/select_spirit_type.sql", TEST_SPIRIT_TYPE)
+        .fetch_one(database)
+        .await
+        .unwrap();
+    let region_id: Option<i64> = None;
+    let abv = 45.0;
+    let created_by: Option<&str> = None;
+    sqlx::query_file!(
+        "sql/insert_spirit.sql",
+        id,
+        name,
+        "",
+        "Test Distillery",
+        spirit_type.name,
+        spirit_type.id,
+        region_id,
+        abv,
+        created_by
+    )
+    .execute(database)
+    .await
+    .unwrap();
+    sqlx::query_file!(
+        "sql/insert_spirit_fts.sql",
+        id,
+        name,
+        "Test Distillery",
+        spirit_type.name
+    )
+    .execute(database)
+    .await
+    .unwrap();
+
+    id
+}
+
+/// Inserts a review by `user` and returns its id.
+pub async fn create_review(database: &SqlitePool, user: &User, spirit_id: &str, body: &str) -> i64 {
+    sqlx::query_file!("sql/insert_review.sql", user.user_id, spirit_id, body)
+        .fetch_one(database)
+        .await
+        .unwrap()
+        .id
+}
+
+/// Access and refresh tokens for `user`, signed with the test app's secrets.
+pub fn signed_tokens(state: &WaterOfLifeState, user: &User) -> (String, String) {
+    generate_access_and_refresh_tokens(
+        &*state.clock,
+        &state.config.tokens,
+        &state.config.oidc.client_id,
+        &user.user_id,
+        &user.role,
+        user.refresh_token_version,
+        false,
+    )
+    .unwrap()
+}
+
+/// A `Cookie` header value that signs requests in as `user`.
+pub fn auth_cookie(state: &WaterOfLifeState, user: &User) -> String {
+    let (access_token, refresh_token) = signed_tokens(state, user);
+    format!("wl_id={}; wl_rid={}", access_token, refresh_token)
+}
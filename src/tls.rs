@@ -0,0 +1,51 @@
+//! HTTPS serving with rustls, for running without a reverse proxy.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Serves `app` over HTTPS on `bind_address`.
+pub async fn serve(
+    bind_address: SocketAddr,
+    app: Router,
+    cert_path: &Path,
+    key_path: &Path,
+) -> io::Result<()> {
+    // Errors only if a provider is already installed, which is just as good.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
+    tokio::spawn(reload_on_hangup(
+        config.clone(),
+        cert_path.to_owned(),
+        key_path.to_owned(),
+    ));
+
+    tracing::debug!("listening on https://{}", bind_address);
+    axum_server::bind_rustls(bind_address, config)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+/// Rereads the certificate and key on SIGHUP, so a renewed certificate is picked up without
+/// a restart. If the new files can't be loaded the old certificate stays in use.
+async fn reload_on_hangup(config: RustlsConfig, cert_path: PathBuf, key_path: PathBuf) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("reload_on_hangup: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match config.reload_from_pem_file(&cert_path, &key_path).await {
+            Ok(()) => tracing::info!("Reloaded the TLS certificate"),
+            Err(e) => tracing::warn!("reload_on_hangup: {}", e),
+        }
+    }
+}